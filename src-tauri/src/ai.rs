@@ -0,0 +1,580 @@
+//! Server-side AI generation.
+//!
+//! Providers implement [`AiProvider`]; [`AiService`] builds the prompts,
+//! enforces the daily generation quota and parses model output. API keys are
+//! read from [`SecurityService`] and never leave the backend.
+//!
+//! Each workspace picks its provider through a [`ProviderConfig`] stored in
+//! the vault settings, so on-prem workspaces can use a local model while
+//! others keep Gemini.
+
+mod cache;
+mod context;
+mod gemini;
+mod lines;
+#[cfg(test)]
+mod mock;
+mod offline;
+mod ollama;
+mod openai;
+mod output;
+mod prompts;
+mod streaming;
+
+pub use context::{ContextOptions, ConversationContext};
+pub use gemini::GeminiProvider;
+#[cfg(test)]
+pub use mock::MockProvider;
+pub use offline::generate_offline;
+pub use ollama::OllamaProvider;
+pub use openai::OpenAiCompatibleProvider;
+pub use output::Severity;
+pub use prompts::PromptTemplate;
+pub use streaming::TokenUsage;
+
+use crate::security::SecurityService;
+use crate::vault::{QuotaGrant, UsageOutcome, UsageRecord, VaultService};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio::sync::Notify;
+
+/// Workspace used when the caller doesn't name one
+pub const DEFAULT_WORKSPACE: &str = "default";
+
+const PROVIDER_SETTING_PREFIX: &str = "ai.provider.";
+
+/// Follow-up attempts allowed when a reply fails validation
+const MAX_CORRECTIVE_RETRIES: usize = 2;
+
+/// Which backend a workspace generates with
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ProviderConfig {
+    Gemini {
+        #[serde(default)]
+        model: Option<String>,
+    },
+    /// Any `/chat/completions` endpoint; the API key, if one is needed, is
+    /// kept in the secret store under the workspace
+    #[serde(rename = "openai_compatible")]
+    OpenAiCompatible { base_url: String, model: String },
+    Ollama {
+        #[serde(default)]
+        base_url: Option<String>,
+        model: String,
+    },
+}
+
+impl Default for ProviderConfig {
+    fn default() -> Self {
+        ProviderConfig::Gemini { model: None }
+    }
+}
+
+impl ProviderConfig {
+    fn validate(&self) -> Result<()> {
+        let (base_url, model) = match self {
+            ProviderConfig::Gemini { model } => (None, model.as_deref()),
+            ProviderConfig::OpenAiCompatible { base_url, model } => (Some(base_url.as_str()), Some(model.as_str())),
+            ProviderConfig::Ollama { base_url, model } => (base_url.as_deref(), Some(model.as_str())),
+        };
+
+        if let Some(url) = base_url {
+            if !(url.starts_with("http://") || url.starts_with("https://")) {
+                anyhow::bail!("Provider URL must start with http:// or https://");
+            }
+        }
+        if model.is_some_and(|m| m.trim().is_empty()) {
+            anyhow::bail!("Provider model must not be empty");
+        }
+
+        Ok(())
+    }
+}
+
+/// Workspace ids double as secret-store key names, so keep them simple
+pub fn validate_workspace(workspace: &str) -> Result<()> {
+    let valid = !workspace.is_empty()
+        && workspace.len() <= 64
+        && workspace.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+    if !valid {
+        anyhow::bail!("Invalid workspace id: {}", workspace);
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct GenerationRequest {
+    pub prompt: String,
+    pub system: Option<String>,
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u32>,
+}
+
+impl GenerationRequest {
+    pub fn new(prompt: impl Into<String>) -> Self {
+        Self {
+            prompt: prompt.into(),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenerationResponse {
+    pub text: String,
+    pub model: String,
+    pub prompt_tokens: Option<u32>,
+    pub completion_tokens: Option<u32>,
+}
+
+/// Receives each text fragment of a streamed reply
+pub type ChunkSink<'a> = &'a mut (dyn FnMut(&str) + Send);
+
+/// A text generation backend
+#[async_trait]
+pub trait AiProvider: Send + Sync {
+    /// Short identifier, e.g. "gemini"
+    fn name(&self) -> &str;
+
+    fn model(&self) -> &str;
+
+    async fn generate(&self, request: &GenerationRequest) -> Result<GenerationResponse>;
+
+    /// Generate while passing fragments to `on_chunk` as they arrive. The
+    /// returned response holds the full text. Providers without streaming
+    /// report the whole reply as one fragment.
+    async fn generate_stream(
+        &self,
+        request: &GenerationRequest,
+        on_chunk: ChunkSink<'_>,
+    ) -> Result<GenerationResponse> {
+        let response = self.generate(request).await?;
+        on_chunk(&response.text);
+        Ok(response)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DorkGenerationRequest {
+    pub description: String,
+    pub search_engine: Option<String>,
+    pub category: Option<String>,
+    #[serde(default)]
+    pub advanced: bool,
+    /// Skip the response cache and always ask the provider
+    #[serde(default)]
+    pub bypass_cache: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DorkGenerationResult {
+    pub query: String,
+    pub explanation: String,
+    pub category: String,
+    #[serde(default)]
+    pub severity: Severity,
+    pub tags: Vec<String>,
+    pub alternatives: Option<Vec<String>>,
+    /// Lint findings and repairs that didn't make the result unusable
+    #[serde(default)]
+    pub warnings: Vec<String>,
+    /// Id of the prompt template version that produced this result
+    #[serde(default)]
+    pub prompt_version: String,
+    /// Served from the response cache rather than the provider
+    #[serde(default)]
+    pub cached: bool,
+}
+
+pub struct AiService {
+    security: Arc<SecurityService>,
+    vault: Arc<VaultService>,
+    http_client: reqwest::Client,
+    /// Cancel handles for in-flight streams, by request id
+    active_streams: Mutex<HashMap<String, Arc<Notify>>>,
+}
+
+impl AiService {
+    pub fn new(security: Arc<SecurityService>, vault: Arc<VaultService>) -> Result<Self> {
+        let http_client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(120))
+            .user_agent(format!("Parallax/{}", env!("CARGO_PKG_VERSION")))
+            .build()
+            .context("Failed to create HTTP client")?;
+
+        Ok(Self {
+            security,
+            vault,
+            http_client,
+            active_streams: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Generate a dork from a natural language description with the
+    /// workspace's provider, counting it against the tier's daily quota
+    pub async fn generate_dork(
+        &self,
+        request: &DorkGenerationRequest,
+        workspace: &str,
+        license_tier: &str,
+    ) -> Result<DorkGenerationResult> {
+        let provider = self.provider(workspace).await?;
+        self.generate_dork_with(provider.as_ref(), request, license_tier).await
+    }
+
+    /// The workspace's provider settings, Gemini if none were saved
+    pub async fn provider_config(&self, workspace: &str) -> Result<ProviderConfig> {
+        validate_workspace(workspace)?;
+
+        match self.vault.get_setting(&provider_setting_key(workspace)).await? {
+            Some(value) => serde_json::from_value(value).context("Failed to parse provider config"),
+            None => Ok(ProviderConfig::default()),
+        }
+    }
+
+    pub async fn set_provider_config(&self, workspace: &str, config: &ProviderConfig) -> Result<()> {
+        validate_workspace(workspace)?;
+        config.validate()?;
+
+        let value = serde_json::to_value(config).context("Failed to serialize provider config")?;
+        self.vault.set_setting(&provider_setting_key(workspace), &value).await?;
+
+        tracing::info!("AI provider for workspace {} set to {:?}", workspace, config);
+        Ok(())
+    }
+
+    async fn provider(&self, workspace: &str) -> Result<Box<dyn AiProvider>> {
+        let client = self.http_client.clone();
+
+        let provider: Box<dyn AiProvider> = match self.provider_config(workspace).await? {
+            ProviderConfig::Gemini { model } => {
+                let api_key = self.security.get_gemini_api_key().await
+                    .context("Gemini API key not configured. Please add your API key in settings.")?;
+                let provider = GeminiProvider::new(client, api_key);
+                match model {
+                    Some(model) => Box::new(provider.with_model(model)),
+                    None => Box::new(provider),
+                }
+            }
+            ProviderConfig::OpenAiCompatible { base_url, model } => {
+                // Local gateways often run without authentication
+                let api_key = self.security.get_ai_provider_key(workspace).await?;
+                Box::new(OpenAiCompatibleProvider::new(client, &base_url, &model, api_key))
+            }
+            ProviderConfig::Ollama { base_url, model } => Box::new(OllamaProvider::new(
+                client,
+                base_url.as_deref().unwrap_or(ollama::DEFAULT_BASE_URL),
+                &model,
+            )),
+        };
+
+        Ok(provider)
+    }
+
+    async fn generate_dork_with(
+        &self,
+        provider: &dyn AiProvider,
+        request: &DorkGenerationRequest,
+        license_tier: &str,
+    ) -> Result<DorkGenerationResult> {
+        if request.description.trim().is_empty() {
+            anyhow::bail!("Describe what the dork should find");
+        }
+
+        let (generation, prompt_version) = self.dork_generation_request(request).await?;
+        let key = cache::cache_key(provider, &prompt_version, &generation);
+        if !request.bypass_cache {
+            if let Some(result) = self.cached_result(&key).await {
+                return Ok(result);
+            }
+        }
+
+        // One generation covers any corrective retries
+        let Some(grant) = self.vault.try_consume_ai_generation(license_tier).await? else {
+            anyhow::bail!("Daily AI generation limit reached. Upgrade for unlimited generations.");
+        };
+
+        let result = match self.generate_recorded(provider, prompts::DORK_GENERATION, &generation).await {
+            Ok(response) => {
+                self.validate_or_retry(provider, request, &generation, &prompt_version, &response.text)
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+
+        match &result {
+            Ok(result) => self.store_cached_quietly(provider, &key, result).await,
+            Err(_) => self.refund_quietly(grant).await,
+        }
+        result
+    }
+
+    /// Render the active dork generation prompt, returning it with the id
+    /// of the template version used
+    async fn dork_generation_request(&self, request: &DorkGenerationRequest) -> Result<(GenerationRequest, String)> {
+        let template = self.prompt_template(prompts::DORK_GENERATION, None).await?;
+        let generation = template.render(&[
+            ("description", request.description.trim()),
+            ("engine", request.search_engine.as_deref().unwrap_or("google")),
+            ("category", request.category.as_deref().unwrap_or("General")),
+            ("advanced", if request.advanced { "Yes" } else { "No" }),
+            ("schema", output::DORK_OUTPUT_SCHEMA),
+        ])?;
+        Ok((generation, template.id()))
+    }
+
+    /// Validate a reply to `generation`, asking the model to correct it up
+    /// to [`MAX_CORRECTIVE_RETRIES`] times
+    async fn validate_or_retry(
+        &self,
+        provider: &dyn AiProvider,
+        request: &DorkGenerationRequest,
+        generation: &GenerationRequest,
+        prompt_version: &str,
+        reply: &str,
+    ) -> Result<DorkGenerationResult> {
+        let mut reply = reply.to_string();
+        let mut attempt = 0;
+
+        loop {
+            let problems = match output::parse_dork_output(&reply, request) {
+                Ok(mut result) => {
+                    result.prompt_version = prompt_version.to_string();
+                    return Ok(result);
+                }
+                Err(problems) => problems,
+            };
+
+            tracing::warn!("{} reply failed validation (attempt {}): {}", provider.name(), attempt + 1, problems.join("; "));
+            if attempt == MAX_CORRECTIVE_RETRIES {
+                anyhow::bail!("The AI reply was not a usable dork: {}", problems.join("; "));
+            }
+            attempt += 1;
+
+            let correction = GenerationRequest {
+                prompt: output::corrective_prompt(&generation.prompt, &reply, &problems),
+                ..generation.clone()
+            };
+            reply = self.generate_recorded(provider, prompts::DORK_GENERATION, &correction).await?.text;
+        }
+    }
+
+    /// Call the provider and record the request in the usage ledger
+    async fn generate_recorded(
+        &self,
+        provider: &dyn AiProvider,
+        feature: &str,
+        request: &GenerationRequest,
+    ) -> Result<GenerationResponse> {
+        let started = Instant::now();
+
+        match provider.generate(request).await {
+            Ok(response) => {
+                let usage = response_usage(request, &response);
+                self.record_usage_quietly(provider, feature, usage, started, UsageOutcome::Success).await;
+                Ok(response)
+            }
+            Err(e) => {
+                tracing::error!("{} ({}) generation failed: {}", provider.name(), provider.model(), e);
+                self.record_usage_quietly(provider, feature, TokenUsage::default(), started, UsageOutcome::Error)
+                    .await;
+                Err(e)
+            }
+        }
+    }
+
+    async fn record_usage_quietly(
+        &self,
+        provider: &dyn AiProvider,
+        feature: &str,
+        usage: TokenUsage,
+        started: Instant,
+        outcome: UsageOutcome,
+    ) {
+        let record = UsageRecord {
+            id: uuid::Uuid::new_v4().to_string(),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            provider: provider.name().to_string(),
+            model: provider.model().to_string(),
+            feature: feature.to_string(),
+            prompt_tokens: usage.prompt_tokens,
+            completion_tokens: usage.completion_tokens,
+            estimated: usage.estimated,
+            latency_ms: started.elapsed().as_millis() as u64,
+            outcome,
+        };
+
+        if let Err(e) = self.vault.record_ai_usage(&record).await {
+            tracing::warn!("Failed to record AI usage: {}", e);
+        }
+    }
+
+    async fn refund_quietly(&self, grant: QuotaGrant) {
+        if let Err(e) = self.vault.refund_ai_generation(grant).await {
+            tracing::warn!("Failed to refund AI generation: {}", e);
+        }
+    }
+}
+
+/// Rough token count for providers that don't report usage, at about four
+/// characters per token
+pub fn estimate_tokens(text: &str) -> u32 {
+    let chars = text.chars().count() as u32;
+    (chars + 3) / 4
+}
+
+/// Token counts for a response, estimating whatever the provider didn't report
+fn response_usage(request: &GenerationRequest, response: &GenerationResponse) -> TokenUsage {
+    TokenUsage {
+        prompt_tokens: response.prompt_tokens.unwrap_or_else(|| estimate_tokens(&request.prompt)),
+        completion_tokens: response.completion_tokens.unwrap_or_else(|| estimate_tokens(&response.text)),
+        estimated: response.prompt_tokens.is_none() || response.completion_tokens.is_none(),
+    }
+}
+
+fn provider_setting_key(workspace: &str) -> String {
+    format!("{}{}", PROVIDER_SETTING_PREFIX, workspace)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{StubResponse, StubServer};
+
+    const REPLY: &str =
+        "Sure!\n```json\n{\"query\": \"site:example.com filetype:env\", \"tags\": [\"env\"]}\n```";
+
+    fn request(description: &str) -> DorkGenerationRequest {
+        DorkGenerationRequest {
+            description: description.to_string(),
+            search_engine: None,
+            category: None,
+            advanced: false,
+            bypass_cache: false,
+        }
+    }
+
+    fn service() -> (AiService, Arc<VaultService>) {
+        let vault = Arc::new(VaultService::open_in_memory().unwrap());
+        let service = AiService::new(Arc::new(SecurityService::new()), vault.clone()).unwrap();
+        (service, vault)
+    }
+
+    #[tokio::test]
+    async fn test_generation_is_metered_against_quota() {
+        let (service, vault) = service();
+
+        let provider = MockProvider::new().reply(REPLY).fail("provider unavailable");
+        let result = service.generate_dork_with(&provider, &request("env files"), "free").await.unwrap();
+        assert_eq!(result.query, "site:example.com filetype:env");
+        assert_eq!(result.category, "General");
+        assert_eq!(result.tags, vec!["env"]);
+        assert!(provider.requests()[0].prompt.contains("User Request: \"env files\""));
+        assert_eq!(provider.requests()[0].temperature, Some(0.4));
+        assert_eq!(result.prompt_version, "dork_generation@1");
+        assert_eq!(vault.get_usage_stats().await.unwrap().ai_generations_today, 1);
+
+        // Failed calls are refunded
+        assert!(service.generate_dork_with(&provider, &request("env backups"), "free").await.is_err());
+        assert_eq!(vault.get_usage_stats().await.unwrap().ai_generations_today, 1);
+
+        // Both calls are in the ledger
+        let report = vault.usage_report(crate::vault::UsagePeriod::Day, None, None).await.unwrap();
+        assert_eq!(report.total_requests, 2);
+        assert_eq!(report.rollups[0].failed_requests, 1);
+        assert_eq!(report.rollups[0].model, "mock-1");
+
+        let mut provider = MockProvider::new();
+        for _ in 0..crate::vault::FREE_TIER_DAILY_AI_LIMIT {
+            provider = provider.reply(REPLY);
+        }
+        for i in 1..crate::vault::FREE_TIER_DAILY_AI_LIMIT {
+            service.generate_dork_with(&provider, &request(&format!("env files {}", i)), "free").await.unwrap();
+        }
+        let exhausted = service.generate_dork_with(&provider, &request("config files"), "free").await;
+        assert!(exhausted.unwrap_err().to_string().contains("limit reached"));
+        assert!(service.generate_dork_with(&provider, &request("config files"), "professional").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_workspace_provider_selection() {
+        let (service, _vault) = service();
+        let server = StubServer::start(vec![
+            ("/api/chat", StubResponse::json(serde_json::json!({
+                "model": "llama3.1",
+                "message": {"role": "assistant", "content": REPLY},
+                "done": true
+            }).to_string())),
+        ]).await;
+
+        assert_eq!(service.provider_config("onprem").await.unwrap(), ProviderConfig::default());
+        assert!(service.provider_config("../etc").await.is_err());
+        assert!(service.set_provider_config("onprem", &ProviderConfig::Ollama {
+            base_url: Some("file:///tmp".to_string()),
+            model: "llama3.1".to_string(),
+        }).await.is_err());
+
+        let config = ProviderConfig::Ollama { base_url: Some(server.url()), model: "llama3.1".to_string() };
+        service.set_provider_config("onprem", &config).await.unwrap();
+        assert_eq!(service.provider_config("onprem").await.unwrap(), config);
+        assert_eq!(service.provider_config(DEFAULT_WORKSPACE).await.unwrap(), ProviderConfig::default());
+
+        let result = service.generate_dork(&request("env files"), "onprem", "free").await.unwrap();
+        assert_eq!(result.query, "site:example.com filetype:env");
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_reply_is_retried_with_correction() {
+        let (service, vault) = service();
+
+        let provider = MockProvider::new()
+            .reply(r#"{"query": "intitle:\"index of backup", "severity": "High"}"#)
+            .reply(r#"{"query": "intitle:\"index of\" backup", "explanation": "Open listings", "severity": "High"}"#);
+        let result = service.generate_dork_with(&provider, &request("backups"), "free").await.unwrap();
+        assert_eq!(result.query, "intitle:\"index of\" backup");
+        assert_eq!(result.severity, Severity::High);
+
+        let correction = &provider.requests()[1].prompt;
+        assert!(correction.contains("- Unbalanced double quotes"));
+        assert_eq!(vault.get_usage_stats().await.unwrap().ai_generations_today, 1);
+
+        // Giving up after the bounded retries refunds the generation
+        let mut provider = MockProvider::new();
+        for _ in 0..=MAX_CORRECTIVE_RETRIES {
+            provider = provider.reply("I can't help with that.\nSorry.");
+        }
+        let error = service.generate_dork_with(&provider, &request("old backups"), "free").await.unwrap_err();
+        assert!(error.to_string().contains("not a usable dork"));
+        assert_eq!(provider.requests().len(), MAX_CORRECTIVE_RETRIES + 1);
+        assert_eq!(vault.get_usage_stats().await.unwrap().ai_generations_today, 1);
+    }
+
+    #[tokio::test]
+    async fn test_repeated_requests_are_served_from_cache() {
+        let (service, vault) = service();
+        let provider = MockProvider::new().reply(REPLY).reply(REPLY);
+
+        let first = service.generate_dork_with(&provider, &request("env files"), "free").await.unwrap();
+        let second = service.generate_dork_with(&provider, &request("  env files "), "free").await.unwrap();
+        assert!(!first.cached);
+        assert!(second.cached);
+        assert_eq!(second.query, first.query);
+        assert_eq!(provider.requests().len(), 1);
+        assert_eq!(vault.get_usage_stats().await.unwrap().ai_generations_today, 1);
+
+        let bypass = DorkGenerationRequest { bypass_cache: true, ..request("env files") };
+        assert!(!service.generate_dork_with(&provider, &bypass, "free").await.unwrap().cached);
+        assert_eq!(provider.requests().len(), 2);
+
+        let stats = vault.cache_stats().await.unwrap();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (1, 1, 1));
+    }
+}
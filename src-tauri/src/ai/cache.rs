@@ -0,0 +1,74 @@
+//! Reuse of earlier AI results.
+//!
+//! A validated dork is cached under a hash of the provider, model, prompt
+//! version and the rendered request, so changing any of them (including a
+//! prompt override) misses the cache. Hits don't reach the provider and
+//! aren't counted against the daily quota.
+
+use super::{AiProvider, AiService, DorkGenerationResult, GenerationRequest};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+/// Content hash of everything that determines a reply
+pub(super) fn cache_key(provider: &dyn AiProvider, prompt_version: &str, generation: &GenerationRequest) -> String {
+    let material = json!({
+        "provider": provider.name(),
+        "model": provider.model(),
+        "prompt_version": prompt_version,
+        "prompt": generation.prompt,
+        "system": generation.system,
+        "temperature": generation.temperature,
+        "max_tokens": generation.max_tokens,
+    });
+
+    let mut hasher = Sha256::new();
+    hasher.update(material.to_string().as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+impl AiService {
+    /// The cached result for `key`, if any. Cache failures count as misses.
+    pub(super) async fn cached_result(&self, key: &str) -> Option<DorkGenerationResult> {
+        let value = match self.vault.cache_lookup(key).await {
+            Ok(value) => value?,
+            Err(e) => {
+                tracing::warn!("AI cache lookup failed: {}", e);
+                return None;
+            }
+        };
+
+        match serde_json::from_str::<DorkGenerationResult>(&value) {
+            Ok(mut result) => {
+                result.cached = true;
+                Some(result)
+            }
+            Err(e) => {
+                tracing::warn!("Ignoring unreadable AI cache entry {}: {}", key, e);
+                None
+            }
+        }
+    }
+
+    pub(super) async fn store_cached_quietly(
+        &self,
+        provider: &dyn AiProvider,
+        key: &str,
+        result: &DorkGenerationResult,
+    ) {
+        let value = match serde_json::to_string(result) {
+            Ok(value) => value,
+            Err(e) => {
+                tracing::warn!("Failed to serialize AI result for caching: {}", e);
+                return;
+            }
+        };
+
+        if let Err(e) = self
+            .vault
+            .cache_store(key, provider.name(), provider.model(), &result.prompt_version, &value)
+            .await
+        {
+            tracing::warn!("Failed to cache AI result: {}", e);
+        }
+    }
+}
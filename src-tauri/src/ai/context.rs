@@ -0,0 +1,431 @@
+//! Conversation context assembly.
+//!
+//! Long conversations are replayed to a model as a token-bounded window: the
+//! latest summary of older turns, every pinned message, and as many recent
+//! messages as fit. Turns that fall out of the window are folded into a new
+//! summary, stored in the conversation as a [`SUMMARY_ROLE`] message, so
+//! each turn is only summarized once. A long backlog is folded in bounded
+//! chunks, each one on top of the summary before it, so no summarization
+//! request outgrows the model's own context.
+
+use super::{estimate_tokens, prompts, AiProvider, AiService};
+use crate::vault::{Message, SUMMARY_ROLE};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// Allowance for the role label and separators around each message
+const MESSAGE_OVERHEAD_TOKENS: u32 = 4;
+
+/// Most transcript tokens sent in one summarization request
+const SUMMARY_CHUNK_TOKENS: u32 = 3000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ContextOptions {
+    /// Token budget for the assembled context
+    pub max_tokens: u32,
+    /// Summarize turns that no longer fit instead of dropping them
+    pub summarize: bool,
+}
+
+impl Default for ContextOptions {
+    fn default() -> Self {
+        Self {
+            max_tokens: 3000,
+            summarize: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversationContext {
+    /// The summary (if any) first, then pinned and recent messages in order
+    pub messages: Vec<Message>,
+    pub estimated_tokens: u32,
+    /// Turns left out without being covered by a summary
+    pub omitted: usize,
+    /// True when a new summary was written while assembling
+    pub summarized: bool,
+}
+
+struct Window<'a> {
+    summary: Option<&'a Message>,
+    included: Vec<&'a Message>,
+    /// Turns older than the window that no summary covers yet
+    overflow: Vec<&'a Message>,
+}
+
+fn message_tokens(message: &Message) -> u32 {
+    estimate_tokens(&message.content) + MESSAGE_OVERHEAD_TOKENS
+}
+
+/// Pick the summary, pinned messages and the newest turns that fit `max_tokens`
+fn select_window(messages: &[Message], max_tokens: u32) -> Window<'_> {
+    // The latest summary covers everything up to the message it names
+    let (summary, covered) = messages
+        .iter()
+        .enumerate()
+        .rev()
+        .filter(|(_, m)| m.role == SUMMARY_ROLE)
+        .find_map(|(index, summary)| {
+            let through = summary.summarizes_through.as_deref()?;
+            let covered = messages[..index].iter().position(|m| m.id == through)?;
+            Some((Some(summary), covered + 1))
+        })
+        .unwrap_or((None, 0));
+
+    let turns: Vec<(usize, &Message)> = messages
+        .iter()
+        .enumerate()
+        .filter(|(_, m)| m.role != SUMMARY_ROLE)
+        .collect();
+
+    let mut budget = max_tokens.saturating_sub(summary.map_or(0, message_tokens));
+    let mut keep: Vec<usize> = Vec::new();
+    for (index, message) in &turns {
+        if message.pinned {
+            budget = budget.saturating_sub(message_tokens(message));
+            keep.push(*index);
+        }
+    }
+
+    // Newest first, stopping at the first turn that doesn't fit. The latest
+    // turn is always kept.
+    let mut window_start = messages.len();
+    for (index, message) in turns.iter().rev().filter(|(index, m)| *index >= covered && !m.pinned) {
+        let tokens = message_tokens(message);
+        if tokens > budget && window_start < messages.len() {
+            break;
+        }
+        budget = budget.saturating_sub(tokens);
+        keep.push(*index);
+        window_start = *index;
+    }
+    keep.sort_unstable();
+
+    Window {
+        summary,
+        included: keep.iter().map(|&index| &messages[index]).collect(),
+        overflow: turns
+            .iter()
+            .filter(|(index, m)| *index >= covered && *index < window_start && !m.pinned)
+            .map(|(_, m)| *m)
+            .collect(),
+    }
+}
+
+/// `role: content` lines for the model
+fn transcript(messages: &[&Message]) -> String {
+    messages
+        .iter()
+        .map(|m| match &m.dork {
+            Some(dork) => format!("{}: {}\n(dork: {})", m.role, m.content, dork),
+            None => format!("{}: {}", m.role, m.content),
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Split turns into runs of at most `max_tokens`. A turn over the limit on
+/// its own gets a run to itself, clipped to fit.
+fn summary_chunks(messages: &[&Message], max_tokens: u32) -> Vec<Vec<Message>> {
+    let mut chunks: Vec<Vec<Message>> = Vec::new();
+    let mut used = 0;
+    for &message in messages {
+        let mut message = message.clone();
+        if message_tokens(&message) > max_tokens {
+            // Four characters per token, as in `estimate_tokens`
+            let keep = (max_tokens.saturating_sub(MESSAGE_OVERHEAD_TOKENS) * 4) as usize;
+            message.content = message.content.chars().take(keep).collect();
+        }
+        let tokens = message_tokens(&message);
+        match chunks.last_mut() {
+            Some(chunk) if used + tokens <= max_tokens => {
+                chunk.push(message);
+                used += tokens;
+            }
+            _ => {
+                chunks.push(vec![message]);
+                used = tokens;
+            }
+        }
+    }
+    chunks
+}
+
+impl AiService {
+    /// Assemble the model context for a conversation with the workspace's
+    /// provider, summarizing older turns when they no longer fit
+    pub async fn conversation_context(
+        &self,
+        conversation_id: &str,
+        workspace: &str,
+        options: &ContextOptions,
+        license_tier: &str,
+    ) -> Result<ConversationContext> {
+        let provider = self.provider(workspace).await?;
+        self.metered_conversation_context(provider.as_ref(), conversation_id, options, license_tier).await
+    }
+
+    /// Summarizing calls the provider, so it counts as a generation like any
+    /// other. Without quota left the window just slides.
+    pub(super) async fn metered_conversation_context(
+        &self,
+        provider: &dyn AiProvider,
+        conversation_id: &str,
+        options: &ContextOptions,
+        license_tier: &str,
+    ) -> Result<ConversationContext> {
+        let mut options = options.clone();
+        let mut grant = None;
+        if options.summarize {
+            let conversation = self.vault.get_conversation(conversation_id).await?;
+            if !select_window(&conversation.messages, options.max_tokens).overflow.is_empty() {
+                grant = self.vault.try_consume_ai_generation(license_tier).await?;
+                options.summarize = grant.is_some();
+            }
+        }
+
+        let context = self.conversation_context_with(provider, conversation_id, &options).await;
+        if let Some(grant) = grant {
+            if !context.as_ref().is_ok_and(|context| context.summarized) {
+                self.refund_quietly(grant).await;
+            }
+        }
+        context
+    }
+
+    pub(super) async fn conversation_context_with(
+        &self,
+        provider: &dyn AiProvider,
+        conversation_id: &str,
+        options: &ContextOptions,
+    ) -> Result<ConversationContext> {
+        let mut conversation = self.vault.get_conversation(conversation_id).await?;
+        let mut summarized = false;
+
+        let overflow = select_window(&conversation.messages, options.max_tokens).overflow.len();
+        if overflow > 0 && options.summarize {
+            match self.summarize(provider, conversation_id, &conversation.messages, options.max_tokens).await {
+                Ok(()) => {
+                    conversation = self.vault.get_conversation(conversation_id).await?;
+                    summarized = true;
+                }
+                // Fall back to a plain sliding window
+                Err(e) => tracing::warn!("Failed to summarize conversation {}: {}", conversation_id, e),
+            }
+        }
+
+        let window = select_window(&conversation.messages, options.max_tokens);
+        let messages: Vec<Message> = window.summary.into_iter().chain(window.included).cloned().collect();
+
+        Ok(ConversationContext {
+            estimated_tokens: messages.iter().map(message_tokens).sum(),
+            omitted: window.overflow.len(),
+            summarized,
+            messages,
+        })
+    }
+
+    /// System prompt replaying the conversation so far, or `None` if it's
+    /// empty or can't be loaded
+    pub(super) async fn context_prompt(&self, provider: &dyn AiProvider, conversation_id: &str) -> Option<String> {
+        let context = match self
+            .conversation_context_with(provider, conversation_id, &ContextOptions::default())
+            .await
+        {
+            Ok(context) => context,
+            Err(e) => {
+                tracing::warn!("Failed to assemble context for conversation {}: {}", conversation_id, e);
+                return None;
+            }
+        };
+
+        if context.messages.is_empty() {
+            return None;
+        }
+        let messages: Vec<&Message> = context.messages.iter().collect();
+        Some(format!("The conversation so far:\n\n{}", transcript(&messages)))
+    }
+
+    /// Fold the turns that fell out of the window into a new summary message
+    async fn summarize(
+        &self,
+        provider: &dyn AiProvider,
+        conversation_id: &str,
+        messages: &[Message],
+        max_tokens: u32,
+    ) -> Result<()> {
+        let window = select_window(messages, max_tokens);
+        if window.overflow.is_empty() {
+            return Ok(());
+        }
+
+        let template = self.prompt_template(prompts::CONVERSATION_SUMMARY, None).await?;
+        let mut previous = window.summary.map(|summary| summary.content.clone());
+        // Each chunk's summary is stored as it's made, so a failure part way
+        // keeps the turns already folded in
+        for chunk in summary_chunks(&window.overflow, SUMMARY_CHUNK_TOKENS) {
+            let turns: Vec<&Message> = chunk.iter().collect();
+            let request = template.render(&[
+                ("summary", previous.as_deref().unwrap_or("None")),
+                ("transcript", &transcript(&turns)),
+            ])?;
+
+            let response = self.generate_recorded(provider, prompts::CONVERSATION_SUMMARY, &request).await?;
+            let content = response.text.trim();
+            if content.is_empty() {
+                anyhow::bail!("The summary was empty");
+            }
+
+            let summary = Message {
+                id: uuid::Uuid::new_v4().to_string(),
+                role: SUMMARY_ROLE.to_string(),
+                content: content.to_string(),
+                timestamp: chrono::Utc::now().to_rfc3339(),
+                dork: None,
+                dork_id: None,
+                prompt_version: Some(template.id()),
+                pinned: false,
+                summarizes_through: chunk.last().map(|last| last.id.clone()),
+            };
+            self.vault.append_message(conversation_id, &summary).await?;
+            previous = Some(summary.content);
+        }
+
+        tracing::info!("Summarized {} turns of conversation {}", window.overflow.len(), conversation_id);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::MockProvider;
+    use crate::security::SecurityService;
+    use crate::vault::{Conversation, VaultService};
+    use std::sync::Arc;
+
+    fn message(id: &str, words: usize) -> Message {
+        Message {
+            id: id.to_string(),
+            role: "user".to_string(),
+            content: vec!["word"; words].join(" "),
+            timestamp: String::new(),
+            dork: None,
+            dork_id: None,
+            prompt_version: None,
+            pinned: false,
+            summarizes_through: None,
+        }
+    }
+
+    fn ids(messages: &[&Message]) -> Vec<String> {
+        messages.iter().map(|m| m.id.clone()).collect()
+    }
+
+    #[test]
+    fn test_window_keeps_pinned_and_recent_turns() {
+        // Each message is 100 characters: 25 + 4 = 29 tokens
+        let mut messages: Vec<Message> = (0..6).map(|i| message(&format!("m{}", i), 20)).collect();
+        messages[1].pinned = true;
+
+        let window = select_window(&messages, 90);
+        assert_eq!(ids(&window.included), vec!["m1", "m4", "m5"]);
+        assert_eq!(ids(&window.overflow), vec!["m0", "m2", "m3"]);
+
+        // A summary through m3 covers the overflow
+        let mut summary = message("s", 5);
+        summary.role = SUMMARY_ROLE.to_string();
+        summary.summarizes_through = Some("m3".to_string());
+        messages.push(summary);
+        let window = select_window(&messages, 100);
+        assert_eq!(window.summary.map(|s| s.id.as_str()), Some("s"));
+        assert!(window.overflow.is_empty());
+
+        // The latest turn is kept even when it alone is over budget
+        assert_eq!(ids(&select_window(&messages[4..6], 10).included), vec!["m5"]);
+    }
+
+    #[tokio::test]
+    async fn test_overflow_is_summarized_once() {
+        let vault = Arc::new(VaultService::open_in_memory().unwrap());
+        let service = AiService::new(Arc::new(SecurityService::new()), vault.clone()).unwrap();
+        let client_copy = Conversation {
+            id: "c1".to_string(),
+            title: "Long".to_string(),
+            messages: (0..6).map(|i| message(&format!("m{}", i), 20)).collect(),
+            created_at: String::new(),
+            updated_at: String::new(),
+        };
+        vault.save_conversation(&client_copy).await.unwrap();
+
+        let provider = MockProvider::new().reply("Looked for env files on acme.io.");
+        let options = ContextOptions { max_tokens: 100, summarize: true };
+        let context = service.conversation_context_with(&provider, "c1", &options).await.unwrap();
+        assert!(context.summarized);
+        assert_eq!(context.omitted, 0);
+        assert_eq!(context.messages[0].role, SUMMARY_ROLE);
+        assert_eq!(context.messages[0].content, "Looked for env files on acme.io.");
+        assert!(context.estimated_tokens <= options.max_tokens);
+        assert!(provider.requests()[0].prompt.contains("user: word word"));
+
+        // Nothing new has overflowed, so the provider isn't called again
+        let again = service.conversation_context_with(&provider, "c1", &options).await.unwrap();
+        assert!(!again.summarized);
+        assert_eq!(provider.requests().len(), 1);
+
+        // Saving the client's list, which has no summary, keeps it
+        vault.save_conversation(&client_copy).await.unwrap();
+        let stored = vault.get_conversation("c1").await.unwrap();
+        assert_eq!(stored.messages.iter().filter(|m| m.role == SUMMARY_ROLE).count(), 1);
+        let again = service.conversation_context_with(&provider, "c1", &options).await.unwrap();
+        assert!(!again.summarized);
+        assert_eq!(provider.requests().len(), 1);
+
+        // Without a provider the window just slides
+        let offline = ContextOptions { max_tokens: 40, summarize: false };
+        let context = service.conversation_context_with(&provider, "c1", &offline).await.unwrap();
+        assert!(context.omitted > 0);
+    }
+
+    #[tokio::test]
+    async fn test_long_backlog_is_summarized_in_chunks() {
+        let vault = Arc::new(VaultService::open_in_memory().unwrap());
+        let service = AiService::new(Arc::new(SecurityService::new()), vault.clone()).unwrap();
+        // About 1200 tokens each, and one far past the chunk limit on its own
+        let mut messages: Vec<Message> = (0..8).map(|i| message(&format!("m{}", i), 960)).collect();
+        messages[2] = message("m2", 50_000);
+        vault.save_conversation(&Conversation {
+            id: "c1".to_string(),
+            title: "Migrated".to_string(),
+            messages,
+            created_at: String::new(),
+            updated_at: String::new(),
+        }).await.unwrap();
+
+        let provider = (0..8).fold(MockProvider::new(), |provider, i| provider.reply(format!("Summary {}", i)));
+        let options = ContextOptions { max_tokens: 1500, summarize: true };
+        let context = service.metered_conversation_context(&provider, "c1", &options, "free").await.unwrap();
+        assert!(context.summarized);
+        assert_eq!(context.omitted, 0);
+
+        let requests = provider.requests();
+        assert_eq!(requests.len(), 4);
+        for request in &requests {
+            assert!(estimate_tokens(&request.prompt) < SUMMARY_CHUNK_TOKENS + 200);
+        }
+        // Each chunk is folded into the summary before it
+        assert!(requests[1].prompt.contains("Summary 0"));
+        assert_eq!(context.messages[0].content, "Summary 3");
+        assert_eq!(vault.quota_status("free").await.unwrap().used, 1);
+
+        // With the quota spent, new overflow slides out without a provider call
+        while vault.try_consume_ai_generation("free").await.unwrap().is_some() {}
+        vault.append_message("c1", &message("m8", 960)).await.unwrap();
+        let context = service.metered_conversation_context(&provider, "c1", &options, "free").await.unwrap();
+        assert!(!context.summarized);
+        assert!(context.omitted > 0);
+        assert_eq!(provider.requests().len(), 4);
+    }
+}
@@ -0,0 +1,266 @@
+//! Google Gemini provider using the `generateContent` REST endpoint.
+
+use super::lines::{read_lines, sse_data};
+use super::{AiProvider, ChunkSink, GenerationRequest, GenerationResponse};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_MODEL: &str = "gemini-2.0-flash-exp";
+const BASE_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
+
+pub struct GeminiProvider {
+    http_client: reqwest::Client,
+    api_key: String,
+    model: String,
+}
+
+impl GeminiProvider {
+    pub fn new(http_client: reqwest::Client, api_key: String) -> Self {
+        Self {
+            http_client,
+            api_key,
+            model: DEFAULT_MODEL.to_string(),
+        }
+    }
+
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = model.into();
+        self
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerateContentRequest<'a> {
+    contents: Vec<Content<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system_instruction: Option<Content<'a>>,
+    generation_config: GenerationConfig,
+}
+
+#[derive(Serialize)]
+struct Content<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    role: Option<&'a str>,
+    parts: Vec<Part<'a>>,
+}
+
+#[derive(Serialize)]
+struct Part<'a> {
+    text: &'a str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerationConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_output_tokens: Option<u32>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerateContentResponse {
+    #[serde(default)]
+    candidates: Vec<Candidate>,
+    usage_metadata: Option<UsageMetadata>,
+    prompt_feedback: Option<PromptFeedback>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Candidate {
+    content: Option<CandidateContent>,
+    finish_reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CandidateContent {
+    #[serde(default)]
+    parts: Vec<CandidatePart>,
+}
+
+#[derive(Deserialize)]
+struct CandidatePart {
+    text: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UsageMetadata {
+    prompt_token_count: Option<u32>,
+    candidates_token_count: Option<u32>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PromptFeedback {
+    block_reason: Option<String>,
+}
+
+impl GeminiProvider {
+    async fn send(&self, request: &GenerationRequest, method: &str) -> Result<reqwest::Response> {
+        let body = GenerateContentRequest {
+            contents: vec![Content {
+                role: Some("user"),
+                parts: vec![Part { text: &request.prompt }],
+            }],
+            system_instruction: request.system.as_deref().map(|system| Content {
+                role: None,
+                parts: vec![Part { text: system }],
+            }),
+            generation_config: GenerationConfig {
+                temperature: request.temperature,
+                max_output_tokens: request.max_tokens,
+            },
+        };
+
+        let url = format!("{}/models/{}:{}", BASE_URL, self.model, method);
+        let response = self
+            .http_client
+            .post(&url)
+            .header("x-goog-api-key", &self.api_key)
+            .json(&body)
+            .send()
+            .await
+            .context("Failed to reach Gemini API")?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            let message = serde_json::from_str::<serde_json::Value>(&error_text)
+                .ok()
+                .and_then(|v| v["error"]["message"].as_str().map(str::to_string))
+                .unwrap_or(error_text);
+            anyhow::bail!("Gemini API error ({}): {}", status, message);
+        }
+
+        Ok(response)
+    }
+}
+
+#[async_trait]
+impl AiProvider for GeminiProvider {
+    fn name(&self) -> &str {
+        "gemini"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn generate(&self, request: &GenerationRequest) -> Result<GenerationResponse> {
+        let parsed: GenerateContentResponse = self
+            .send(request, "generateContent")
+            .await?
+            .json()
+            .await
+            .context("Failed to parse Gemini response")?;
+
+        into_generation_response(parsed, &self.model)
+    }
+
+    async fn generate_stream(
+        &self,
+        request: &GenerationRequest,
+        on_chunk: ChunkSink<'_>,
+    ) -> Result<GenerationResponse> {
+        let response = self.send(request, "streamGenerateContent?alt=sse").await?;
+
+        let mut text = String::new();
+        let mut usage = None;
+        read_lines(response, |line| {
+            let Some(data) = sse_data(line) else {
+                return Ok(());
+            };
+            let mut event: GenerateContentResponse =
+                serde_json::from_str(data).context("Failed to parse Gemini stream event")?;
+            if let Some(metadata) = event.usage_metadata.take() {
+                usage = Some(metadata);
+            }
+
+            let event = into_generation_response(event, &self.model);
+            if let Ok(fragment) = &event {
+                on_chunk(&fragment.text);
+                text.push_str(&fragment.text);
+            } else if text.is_empty() {
+                // Only fatal before any text arrived; the final event of a
+                // stream commonly carries no parts
+                event?;
+            }
+            Ok(())
+        })
+        .await?;
+
+        if text.is_empty() {
+            anyhow::bail!("Gemini returned an empty response");
+        }
+
+        Ok(GenerationResponse {
+            text,
+            model: self.model.clone(),
+            prompt_tokens: usage.as_ref().and_then(|u| u.prompt_token_count),
+            completion_tokens: usage.as_ref().and_then(|u| u.candidates_token_count),
+        })
+    }
+}
+
+fn into_generation_response(response: GenerateContentResponse, model: &str) -> Result<GenerationResponse> {
+    if let Some(reason) = response.prompt_feedback.and_then(|f| f.block_reason) {
+        anyhow::bail!("Gemini blocked the prompt: {}", reason);
+    }
+
+    let candidate = response
+        .candidates
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("Gemini returned no candidates"))?;
+
+    let text: String = candidate
+        .content
+        .map(|c| c.parts.into_iter().filter_map(|p| p.text).collect())
+        .unwrap_or_default();
+
+    if text.is_empty() {
+        anyhow::bail!(
+            "Gemini returned an empty response (finish reason: {})",
+            candidate.finish_reason.as_deref().unwrap_or("unknown")
+        );
+    }
+
+    let usage = response.usage_metadata;
+    Ok(GenerationResponse {
+        text,
+        model: model.to_string(),
+        prompt_tokens: usage.as_ref().and_then(|u| u.prompt_token_count),
+        completion_tokens: usage.as_ref().and_then(|u| u.candidates_token_count),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_generate_content_response() {
+        let response: GenerateContentResponse = serde_json::from_str(r#"{
+            "candidates": [{
+                "content": {"role": "model", "parts": [{"text": "{\"query\": "}, {"text": "\"inurl:admin\"}"}]},
+                "finishReason": "STOP"
+            }],
+            "usageMetadata": {"promptTokenCount": 42, "candidatesTokenCount": 7, "totalTokenCount": 49}
+        }"#).unwrap();
+
+        let parsed = into_generation_response(response, DEFAULT_MODEL).unwrap();
+        assert_eq!(parsed.text, "{\"query\": \"inurl:admin\"}");
+        assert_eq!(parsed.prompt_tokens, Some(42));
+        assert_eq!(parsed.completion_tokens, Some(7));
+
+        let blocked: GenerateContentResponse = serde_json::from_str(
+            r#"{"promptFeedback": {"blockReason": "SAFETY"}}"#
+        ).unwrap();
+        assert!(into_generation_response(blocked, DEFAULT_MODEL).is_err());
+    }
+}
@@ -0,0 +1,38 @@
+//! Helpers for reading streamed HTTP response bodies line by line.
+
+use anyhow::{Context, Result};
+
+/// Feed each non-empty line of a streamed body to `on_line` as it arrives
+pub(super) async fn read_lines(
+    mut response: reqwest::Response,
+    mut on_line: impl FnMut(&str) -> Result<()> + Send,
+) -> Result<()> {
+    let mut pending: Vec<u8> = Vec::new();
+
+    while let Some(chunk) = response.chunk().await.context("Failed to read response stream")? {
+        pending.extend_from_slice(&chunk);
+
+        while let Some(newline) = pending.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = pending.drain(..=newline).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end();
+            if !line.is_empty() {
+                on_line(line)?;
+            }
+        }
+    }
+
+    let rest = String::from_utf8_lossy(&pending);
+    if !rest.trim().is_empty() {
+        on_line(rest.trim())?;
+    }
+
+    Ok(())
+}
+
+/// Payload of a server-sent event `data:` line. Comments, other fields and
+/// the OpenAI `[DONE]` sentinel yield `None`.
+pub(super) fn sse_data(line: &str) -> Option<&str> {
+    let data = line.strip_prefix("data:")?.trim_start();
+    (data != "[DONE]").then_some(data)
+}
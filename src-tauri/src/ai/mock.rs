@@ -0,0 +1,86 @@
+//! Scripted provider for tests.
+
+use super::{AiProvider, ChunkSink, GenerationRequest, GenerationResponse};
+use anyhow::Result;
+use async_trait::async_trait;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Replies with queued responses in order and records every request.
+/// A queued `Err` makes that call fail; an empty queue fails too. Streaming
+/// emits the reply word by word, optionally pausing between words.
+#[derive(Default)]
+pub struct MockProvider {
+    replies: Mutex<VecDeque<Result<String, String>>>,
+    requests: Mutex<Vec<GenerationRequest>>,
+    chunk_delay: Option<Duration>,
+}
+
+impl MockProvider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn reply(self, text: impl Into<String>) -> Self {
+        self.replies.lock().unwrap().push_back(Ok(text.into()));
+        self
+    }
+
+    pub fn fail(self, error: impl Into<String>) -> Self {
+        self.replies.lock().unwrap().push_back(Err(error.into()));
+        self
+    }
+
+    pub fn with_chunk_delay(mut self, delay: Duration) -> Self {
+        self.chunk_delay = Some(delay);
+        self
+    }
+
+    pub fn requests(&self) -> Vec<GenerationRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl AiProvider for MockProvider {
+    fn name(&self) -> &str {
+        "mock"
+    }
+
+    fn model(&self) -> &str {
+        "mock-1"
+    }
+
+    async fn generate(&self, request: &GenerationRequest) -> Result<GenerationResponse> {
+        self.requests.lock().unwrap().push(request.clone());
+
+        match self.replies.lock().unwrap().pop_front() {
+            Some(Ok(text)) => Ok(GenerationResponse {
+                prompt_tokens: Some(request.prompt.split_whitespace().count() as u32),
+                completion_tokens: Some(text.split_whitespace().count() as u32),
+                text,
+                model: self.model().to_string(),
+            }),
+            Some(Err(error)) => Err(anyhow::anyhow!(error)),
+            None => anyhow::bail!("MockProvider has no reply queued"),
+        }
+    }
+
+    async fn generate_stream(
+        &self,
+        request: &GenerationRequest,
+        on_chunk: ChunkSink<'_>,
+    ) -> Result<GenerationResponse> {
+        let response = self.generate(request).await?;
+
+        for word in response.text.split_inclusive(' ') {
+            if let Some(delay) = self.chunk_delay {
+                tokio::time::sleep(delay).await;
+            }
+            on_chunk(word);
+        }
+
+        Ok(response)
+    }
+}
@@ -0,0 +1,313 @@
+//! Rule-based dork generation for when no model can be reached.
+//!
+//! The description is reduced to an intent (target domain, asset categories,
+//! file types and keywords) and matched against the template library.
+//! Matches are ranked by severity, then by how well they fit, and the target
+//! domain is written into each query. The same request always produces the
+//! same result, and nothing is counted against the AI quota.
+
+use super::{DorkGenerationRequest, DorkGenerationResult, Severity};
+use crate::dorks::{lint_query, template_library, DorkTemplate, LintLevel, PLACEHOLDER_DOMAIN};
+use anyhow::Result;
+use std::cmp::Reverse;
+use std::collections::BTreeSet;
+
+/// Recorded as the result's `prompt_version`
+pub const OFFLINE_GENERATOR_VERSION: &str = "offline_rules@1";
+
+const MAX_ALTERNATIVES: usize = 5;
+
+/// Words and phrases that point at a library category
+const ASSET_KEYWORDS: &[(&str, &[&str])] = &[
+    ("Cloud Storage", &["bucket", "buckets", "s3", "blob", "cloud", "storage"]),
+    ("Databases", &["database", "databases", "sql", "mongo", "mongodb", "mysql", "postgres", "elasticsearch", "redis"]),
+    ("Login Panels", &["login", "logins", "admin", "panel", "panels", "portal", "dashboard", "sign in"]),
+    ("Configuration Files", &["config", "configs", "configuration", "env", "settings"]),
+    ("API Keys & Secrets", &["api key", "api keys", "secret", "secrets", "token", "tokens", "credential", "credentials", "password", "passwords"]),
+    ("Exposed Directories", &["directory", "directories", "index of", "listing", "listings", "open dir"]),
+    ("Network Devices", &["router", "routers", "switch", "firewall", "vpn", "network"]),
+    ("IoT & Cameras", &["camera", "cameras", "webcam", "webcams", "cctv", "iot", "scada"]),
+    ("Development Files", &["git", "source code", "repository", "repositories", "svn", "debug"]),
+    ("Backup Files", &["backup", "backups", "dump", "dumps", "archive", "archives"]),
+    ("Email & Documents", &["email", "emails", "document", "documents", "pdf", "spreadsheet", "spreadsheets"]),
+    ("Social Engineering", &["employee", "employees", "linkedin", "staff"]),
+    ("Government & Public Records", &["government", "public record", "public records"]),
+    ("E-Commerce", &["shop", "store", "order", "orders", "invoice", "invoices", "payment", "payments", "ecommerce"]),
+];
+
+/// Extensions recognised as a requested file type
+const FILE_TYPES: &[&str] = &[
+    "bak", "cfg", "conf", "csv", "db", "doc", "docx", "env", "gz", "ini", "json", "key", "log", "pdf", "pem",
+    "php", "ppk", "pst", "sql", "sqlite", "tar", "txt", "xls", "xlsx", "xml", "yaml", "yml", "zip",
+];
+
+/// Words too common to say anything about the intent
+const STOP_WORDS: &[&str] = &[
+    "all", "and", "any", "are", "exposed", "file", "files", "find", "for", "from", "look", "open", "page",
+    "pages", "public", "search", "show", "site", "sites", "that", "the", "with",
+];
+
+#[derive(Debug, Default)]
+struct Intent {
+    domain: Option<String>,
+    categories: BTreeSet<&'static str>,
+    file_types: BTreeSet<&'static str>,
+    keywords: BTreeSet<String>,
+}
+
+struct Candidate {
+    query: String,
+    severity: Severity,
+    score: u32,
+    id: String,
+    template: Option<&'static DorkTemplate>,
+}
+
+/// Build dorks for `request` from the template library
+pub fn generate_offline(request: &DorkGenerationRequest) -> Result<DorkGenerationResult> {
+    if request.description.trim().is_empty() {
+        anyhow::bail!("Describe what the dork should find");
+    }
+
+    let mut intent = parse_intent(&request.description);
+    if let Some(category) = request.category.as_deref() {
+        if let Some((name, _)) = ASSET_KEYWORDS.iter().find(|(name, _)| name.eq_ignore_ascii_case(category)) {
+            intent.categories.insert(name);
+        }
+    }
+
+    let mut candidates: Vec<Candidate> = template_library()
+        .iter()
+        .filter(|template| intent.domain.is_some() || !template.needs_target())
+        .filter_map(|template| {
+            let score = score(template, &intent);
+            (score > 0).then(|| Candidate {
+                query: apply_domain(&template.query, intent.domain.as_deref()),
+                severity: template.severity,
+                score,
+                id: template.id.clone(),
+                template: Some(template),
+            })
+        })
+        .collect();
+
+    if !intent.file_types.is_empty() {
+        let file_types: Vec<String> = intent.file_types.iter().map(|t| format!("filetype:{}", t)).collect();
+        candidates.push(Candidate {
+            query: apply_domain(&file_types.join(" | "), intent.domain.as_deref()),
+            severity: Severity::Medium,
+            score: 1,
+            id: "filetype".to_string(),
+            template: None,
+        });
+    }
+
+    candidates.retain(|candidate| {
+        lint_query(&candidate.query).iter().all(|issue| issue.level != LintLevel::Error)
+    });
+    candidates.sort_by(|a, b| {
+        (Reverse(a.severity), Reverse(a.score), &a.id).cmp(&(Reverse(b.severity), Reverse(b.score), &b.id))
+    });
+
+    let mut seen = BTreeSet::new();
+    candidates.retain(|candidate| seen.insert(candidate.query.clone()));
+
+    let Some(best) = candidates.first() else {
+        anyhow::bail!(
+            "No templates match \"{}\". Name an asset type such as login panels, config files or backups.",
+            request.description.trim()
+        );
+    };
+
+    let mut warnings = vec!["Generated offline from the template library".to_string()];
+    if best.template.is_some_and(|t| t.legal_warning) {
+        warnings.push("Results may contain sensitive data; only use this on systems you're authorized to test".to_string());
+    }
+
+    let (explanation, category, tags) = match best.template {
+        Some(template) => (
+            format!("{}: {}", template.name, template.description),
+            template.category.clone(),
+            template.tags.iter().map(|tag| tag.to_lowercase()).collect(),
+        ),
+        None => (
+            "Documents of the requested file types".to_string(),
+            request.category.clone().unwrap_or_else(|| "Files".to_string()),
+            intent.file_types.iter().map(|t| t.to_string()).collect(),
+        ),
+    };
+
+    Ok(DorkGenerationResult {
+        query: best.query.clone(),
+        explanation,
+        category,
+        severity: best.severity,
+        tags,
+        alternatives: Some(
+            candidates
+                .iter()
+                .skip(1)
+                .take(MAX_ALTERNATIVES)
+                .map(|candidate| candidate.query.clone())
+                .collect(),
+        ),
+        warnings,
+        prompt_version: OFFLINE_GENERATOR_VERSION.to_string(),
+        cached: false,
+    })
+}
+
+fn parse_intent(description: &str) -> Intent {
+    let mut intent = Intent::default();
+    let mut rest = Vec::new();
+    for token in description.split_whitespace() {
+        match domain_in(token) {
+            Some(domain) if intent.domain.is_none() => intent.domain = Some(domain),
+            Some(_) => {}
+            None => rest.push(token),
+        }
+    }
+
+    // Pad with spaces so phrases only match on word boundaries
+    let words: String = rest
+        .join(" ")
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect();
+    let padded = format!(" {} ", words.split_whitespace().collect::<Vec<_>>().join(" "));
+
+    for (category, phrases) in ASSET_KEYWORDS {
+        if phrases.iter().any(|phrase| padded.contains(&format!(" {} ", phrase))) {
+            intent.categories.insert(category);
+        }
+    }
+
+    for word in padded.split_whitespace() {
+        if let Some(file_type) = FILE_TYPES.iter().find(|t| **t == word) {
+            intent.file_types.insert(file_type);
+        }
+        if word.len() >= 3 && !STOP_WORDS.contains(&word) {
+            intent.keywords.insert(word.to_string());
+        }
+    }
+
+    intent
+}
+
+/// A domain name written in the description, without scheme or path
+fn domain_in(token: &str) -> Option<String> {
+    let token = token.trim_matches(|c: char| !c.is_alphanumeric());
+    let token = token.split_once("://").map_or(token, |(_, rest)| rest);
+    let host = token.split(['/', '?', '#']).next()?.to_lowercase();
+
+    let labels: Vec<&str> = host.split('.').collect();
+    let tld = labels.last()?;
+    let valid = labels.len() >= 2
+        && labels.iter().all(|label| {
+            !label.is_empty() && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+        && tld.len() >= 2
+        && tld.chars().all(|c| c.is_ascii_alphabetic())
+        && !FILE_TYPES.contains(tld);
+
+    valid.then_some(host)
+}
+
+fn score(template: &DorkTemplate, intent: &Intent) -> u32 {
+    let mut score = 0;
+
+    if intent.categories.contains(template.category.as_str()) {
+        score += 3;
+    }
+
+    let query = template.query.to_lowercase();
+    for file_type in &intent.file_types {
+        let operators = [format!("filetype:{}", file_type), format!("ext:{}", file_type), format!(".{}", file_type)];
+        if operators.iter().any(|operator| query.contains(operator.as_str())) {
+            score += 3;
+        }
+    }
+
+    let text = format!("{} {}", template.name, template.description).to_lowercase();
+    for keyword in &intent.keywords {
+        if template.tags.iter().any(|tag| tag.eq_ignore_ascii_case(keyword)) {
+            score += 2;
+        } else if text.split(|c: char| !c.is_alphanumeric()).any(|word| word == keyword) {
+            score += 1;
+        }
+    }
+
+    score
+}
+
+/// Point a query at `domain`: fill in the placeholder, or scope it with
+/// `site:` unless it already targets a hosting platform
+fn apply_domain(query: &str, domain: Option<&str>) -> String {
+    let Some(domain) = domain else {
+        return query.to_string();
+    };
+
+    if query.contains(PLACEHOLDER_DOMAIN) {
+        query.replace(PLACEHOLDER_DOMAIN, domain)
+    } else if query.to_lowercase().contains("site:") {
+        format!("{} \"{}\"", query, domain)
+    } else if query.contains(" | ") || query.contains(" OR ") {
+        format!("site:{} ({})", domain, query)
+    } else {
+        format!("site:{} {}", domain, query)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(description: &str) -> DorkGenerationRequest {
+        DorkGenerationRequest {
+            description: description.to_string(),
+            search_engine: None,
+            category: None,
+            advanced: false,
+            bypass_cache: false,
+        }
+    }
+
+    #[test]
+    fn test_intent_targets_domain_and_ranks_by_severity() {
+        let result = generate_offline(&request("Find exposed config files and .env on https://Acme.io/login")).unwrap();
+        assert!(result.query.contains("acme.io"));
+        assert_eq!(result.prompt_version, OFFLINE_GENERATOR_VERSION);
+
+        let library = template_library();
+        let severity_of = |query: &str| {
+            library
+                .iter()
+                .find(|t| apply_domain(&t.query, Some("acme.io")) == query)
+                .map_or(Severity::Medium, |t| t.severity)
+        };
+        let mut severities = vec![result.severity];
+        severities.extend(result.alternatives.as_ref().unwrap().iter().map(|q| severity_of(q)));
+        assert!(severities.windows(2).all(|pair| pair[0] >= pair[1]));
+
+        // Deterministic, and placeholders are only used with a target
+        let again = generate_offline(&request("Find exposed config files and .env on https://Acme.io/login")).unwrap();
+        assert_eq!(again.query, result.query);
+        let untargeted = generate_offline(&request("wordpress sites")).unwrap();
+        assert!(!untargeted.query.contains(PLACEHOLDER_DOMAIN));
+        assert!(untargeted.alternatives.unwrap().iter().all(|q| !q.contains(PLACEHOLDER_DOMAIN)));
+    }
+
+    #[test]
+    fn test_parse_intent() {
+        let intent = parse_intent("admin login panels and SQL backups for shop.example.org, not index.php");
+        assert_eq!(intent.domain.as_deref(), Some("shop.example.org"));
+        assert!(intent.categories.contains("Login Panels"));
+        assert!(intent.categories.contains("Backup Files"));
+        assert!(intent.file_types.contains("sql"));
+        assert!(!intent.categories.contains("E-Commerce"));
+
+        assert_eq!(apply_domain("filetype:sql | filetype:bak", Some("a.com")), "site:a.com (filetype:sql | filetype:bak)");
+        assert!(generate_offline(&request("something vague")).is_err());
+    }
+}
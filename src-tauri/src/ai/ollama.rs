@@ -0,0 +1,209 @@
+//! Provider for an Ollama-style local `/api/chat` endpoint, so generation
+//! can run entirely on-prem.
+
+use super::lines::read_lines;
+use super::{AiProvider, ChunkSink, GenerationRequest, GenerationResponse};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_BASE_URL: &str = "http://localhost:11434";
+
+pub struct OllamaProvider {
+    http_client: reqwest::Client,
+    base_url: String,
+    model: String,
+}
+
+impl OllamaProvider {
+    pub fn new(http_client: reqwest::Client, base_url: &str, model: &str) -> Self {
+        Self {
+            http_client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            model: model.to_string(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ChatRequest<'a> {
+    model: &'a str,
+    messages: Vec<ChatMessage<'a>>,
+    stream: bool,
+    options: ChatOptions,
+}
+
+#[derive(Serialize)]
+struct ChatMessage<'a> {
+    role: &'a str,
+    content: &'a str,
+}
+
+#[derive(Serialize)]
+struct ChatOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_predict: Option<u32>,
+}
+
+/// One response object, or one line of a streamed response
+#[derive(Deserialize)]
+struct ChatResponse {
+    model: Option<String>,
+    message: Option<ResponseMessage>,
+    prompt_eval_count: Option<u32>,
+    eval_count: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct ResponseMessage {
+    content: String,
+}
+
+impl OllamaProvider {
+    async fn send(&self, request: &GenerationRequest, stream: bool) -> Result<reqwest::Response> {
+        let mut messages = Vec::new();
+        if let Some(system) = &request.system {
+            messages.push(ChatMessage { role: "system", content: system });
+        }
+        messages.push(ChatMessage { role: "user", content: &request.prompt });
+
+        let body = ChatRequest {
+            model: &self.model,
+            messages,
+            stream,
+            options: ChatOptions {
+                temperature: request.temperature,
+                num_predict: request.max_tokens,
+            },
+        };
+
+        let response = self
+            .http_client
+            .post(format!("{}/api/chat", self.base_url))
+            .json(&body)
+            .send()
+            .await
+            .with_context(|| format!("Failed to reach Ollama at {}", self.base_url))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            let message = serde_json::from_str::<serde_json::Value>(&error_text)
+                .ok()
+                .and_then(|v| v["error"].as_str().map(str::to_string))
+                .unwrap_or(error_text);
+            anyhow::bail!("Ollama error ({}): {}", status, message);
+        }
+
+        Ok(response)
+    }
+}
+
+#[async_trait]
+impl AiProvider for OllamaProvider {
+    fn name(&self) -> &str {
+        "ollama"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn generate(&self, request: &GenerationRequest) -> Result<GenerationResponse> {
+        let parsed: ChatResponse = self
+            .send(request, false)
+            .await?
+            .json()
+            .await
+            .context("Failed to parse Ollama response")?;
+
+        let text = parsed
+            .message
+            .map(|m| m.content)
+            .filter(|text| !text.is_empty())
+            .ok_or_else(|| anyhow::anyhow!("Ollama returned no content"))?;
+
+        Ok(GenerationResponse {
+            text,
+            model: parsed.model.unwrap_or_else(|| self.model.clone()),
+            prompt_tokens: parsed.prompt_eval_count,
+            completion_tokens: parsed.eval_count,
+        })
+    }
+
+    /// Ollama streams newline-delimited JSON; token counts arrive on the
+    /// final `done` line
+    async fn generate_stream(
+        &self,
+        request: &GenerationRequest,
+        on_chunk: ChunkSink<'_>,
+    ) -> Result<GenerationResponse> {
+        let response = self.send(request, true).await?;
+
+        let mut text = String::new();
+        let mut model = None;
+        let (mut prompt_tokens, mut completion_tokens) = (None, None);
+        read_lines(response, |line| {
+            let chunk: ChatResponse =
+                serde_json::from_str(line).context("Failed to parse Ollama stream line")?;
+            if let Some(message) = chunk.message.filter(|m| !m.content.is_empty()) {
+                on_chunk(&message.content);
+                text.push_str(&message.content);
+            }
+            model = chunk.model.or(model.take());
+            prompt_tokens = chunk.prompt_eval_count.or(prompt_tokens);
+            completion_tokens = chunk.eval_count.or(completion_tokens);
+            Ok(())
+        })
+        .await?;
+
+        if text.is_empty() {
+            anyhow::bail!("Ollama returned no content");
+        }
+
+        Ok(GenerationResponse {
+            text,
+            model: model.unwrap_or_else(|| self.model.clone()),
+            prompt_tokens,
+            completion_tokens,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{StubResponse, StubServer};
+
+    #[tokio::test]
+    async fn test_chat_against_stub() {
+        let server = StubServer::start(vec![
+            ("/api/chat", StubResponse::json(r#"{
+                "model": "llama3.1",
+                "message": {"role": "assistant", "content": "intitle:\"index of\""},
+                "done": true,
+                "prompt_eval_count": 20,
+                "eval_count": 5
+            }"#)),
+        ]).await;
+
+        let provider = OllamaProvider::new(reqwest::Client::new(), &server.url(), "llama3.1");
+        let mut request = GenerationRequest::new("open directories");
+        request.temperature = Some(0.2);
+
+        let response = provider.generate(&request).await.unwrap();
+        assert_eq!(response.text, "intitle:\"index of\"");
+        assert_eq!(response.prompt_tokens, Some(20));
+        assert_eq!(response.completion_tokens, Some(5));
+
+        let body = server.requests()[0].json();
+        assert_eq!(body["stream"], false);
+        assert_eq!(body["model"], "llama3.1");
+        assert!((body["options"]["temperature"].as_f64().unwrap() - 0.2).abs() < 1e-6);
+
+        let wrong_root = OllamaProvider::new(reqwest::Client::new(), &format!("{}/v1", server.url()), "llama3.1");
+        assert!(wrong_root.generate(&request).await.is_err());
+    }
+}
@@ -0,0 +1,280 @@
+//! Provider for any OpenAI-compatible `/chat/completions` endpoint
+//! (OpenAI, Azure-style gateways, vLLM, LM Studio, llama.cpp server, ...).
+
+use super::lines::{read_lines, sse_data};
+use super::{AiProvider, ChunkSink, GenerationRequest, GenerationResponse};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+pub struct OpenAiCompatibleProvider {
+    http_client: reqwest::Client,
+    base_url: String,
+    model: String,
+    api_key: Option<String>,
+}
+
+impl OpenAiCompatibleProvider {
+    /// `base_url` is the API root, e.g. `https://api.openai.com/v1`
+    pub fn new(http_client: reqwest::Client, base_url: &str, model: &str, api_key: Option<String>) -> Self {
+        Self {
+            http_client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            model: model.to_string(),
+            api_key,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ChatCompletionRequest<'a> {
+    model: &'a str,
+    messages: Vec<ChatMessage<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_options: Option<StreamOptions>,
+}
+
+#[derive(Serialize)]
+struct StreamOptions {
+    include_usage: bool,
+}
+
+#[derive(Serialize)]
+struct ChatMessage<'a> {
+    role: &'a str,
+    content: &'a str,
+}
+
+#[derive(Deserialize)]
+struct ChatCompletionResponse {
+    model: Option<String>,
+    #[serde(default)]
+    choices: Vec<Choice>,
+    usage: Option<Usage>,
+}
+
+#[derive(Deserialize)]
+struct Choice {
+    message: ChoiceMessage,
+}
+
+#[derive(Deserialize)]
+struct ChoiceMessage {
+    content: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ChatCompletionChunk {
+    model: Option<String>,
+    #[serde(default)]
+    choices: Vec<ChunkChoice>,
+    usage: Option<Usage>,
+}
+
+#[derive(Deserialize)]
+struct ChunkChoice {
+    delta: ChoiceMessage,
+}
+
+#[derive(Deserialize)]
+struct Usage {
+    prompt_tokens: Option<u32>,
+    completion_tokens: Option<u32>,
+}
+
+impl OpenAiCompatibleProvider {
+    async fn send(&self, request: &GenerationRequest, stream: bool) -> Result<reqwest::Response> {
+        let mut messages = Vec::new();
+        if let Some(system) = &request.system {
+            messages.push(ChatMessage { role: "system", content: system });
+        }
+        messages.push(ChatMessage { role: "user", content: &request.prompt });
+
+        let body = ChatCompletionRequest {
+            model: &self.model,
+            messages,
+            temperature: request.temperature,
+            max_tokens: request.max_tokens,
+            stream,
+            stream_options: stream.then_some(StreamOptions { include_usage: true }),
+        };
+
+        let mut http_request = self
+            .http_client
+            .post(format!("{}/chat/completions", self.base_url))
+            .json(&body);
+        if let Some(api_key) = &self.api_key {
+            http_request = http_request.bearer_auth(api_key);
+        }
+
+        let response = http_request
+            .send()
+            .await
+            .with_context(|| format!("Failed to reach {}", self.base_url))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            let message = serde_json::from_str::<serde_json::Value>(&error_text)
+                .ok()
+                .and_then(|v| v["error"]["message"].as_str().map(str::to_string))
+                .unwrap_or(error_text);
+            anyhow::bail!("Chat completion error ({}): {}", status, message);
+        }
+
+        Ok(response)
+    }
+}
+
+#[async_trait]
+impl AiProvider for OpenAiCompatibleProvider {
+    fn name(&self) -> &str {
+        "openai_compatible"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn generate(&self, request: &GenerationRequest) -> Result<GenerationResponse> {
+        let parsed: ChatCompletionResponse = self
+            .send(request, false)
+            .await?
+            .json()
+            .await
+            .context("Failed to parse chat completion response")?;
+
+        let text = parsed
+            .choices
+            .into_iter()
+            .next()
+            .and_then(|choice| choice.message.content)
+            .filter(|text| !text.is_empty())
+            .ok_or_else(|| anyhow::anyhow!("Chat completion returned no content"))?;
+
+        Ok(GenerationResponse {
+            text,
+            model: parsed.model.unwrap_or_else(|| self.model.clone()),
+            prompt_tokens: parsed.usage.as_ref().and_then(|u| u.prompt_tokens),
+            completion_tokens: parsed.usage.as_ref().and_then(|u| u.completion_tokens),
+        })
+    }
+
+    async fn generate_stream(
+        &self,
+        request: &GenerationRequest,
+        on_chunk: ChunkSink<'_>,
+    ) -> Result<GenerationResponse> {
+        let response = self.send(request, true).await?;
+
+        let mut text = String::new();
+        let mut model = None;
+        let mut usage = None;
+        read_lines(response, |line| {
+            let Some(data) = sse_data(line) else {
+                return Ok(());
+            };
+            let chunk: ChatCompletionChunk =
+                serde_json::from_str(data).context("Failed to parse chat completion chunk")?;
+
+            model = chunk.model.or(model.take());
+            if chunk.usage.is_some() {
+                usage = chunk.usage;
+            }
+            for delta in chunk.choices.into_iter().filter_map(|c| c.delta.content) {
+                on_chunk(&delta);
+                text.push_str(&delta);
+            }
+            Ok(())
+        })
+        .await?;
+
+        if text.is_empty() {
+            anyhow::bail!("Chat completion returned no content");
+        }
+
+        Ok(GenerationResponse {
+            text,
+            model: model.unwrap_or_else(|| self.model.clone()),
+            prompt_tokens: usage.as_ref().and_then(|u| u.prompt_tokens),
+            completion_tokens: usage.as_ref().and_then(|u| u.completion_tokens),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{StubResponse, StubServer};
+
+    #[tokio::test]
+    async fn test_chat_completion_against_stub() {
+        let server = StubServer::start(vec![
+            ("/v1/chat/completions", StubResponse::json(r#"{
+                "id": "chatcmpl-1",
+                "model": "llama-3.1-8b",
+                "choices": [{"index": 0, "message": {"role": "assistant", "content": "inurl:admin"}}],
+                "usage": {"prompt_tokens": 12, "completion_tokens": 3, "total_tokens": 15}
+            }"#)),
+        ]).await;
+
+        let provider = OpenAiCompatibleProvider::new(
+            reqwest::Client::new(),
+            &format!("{}/v1/", server.url()),
+            "llama-3.1-8b",
+            Some("sk-test".to_string()),
+        );
+        let mut request = GenerationRequest::new("find admin panels");
+        request.system = Some("be brief".to_string());
+        request.max_tokens = Some(64);
+
+        let response = provider.generate(&request).await.unwrap();
+        assert_eq!(response.text, "inurl:admin");
+        assert_eq!(response.prompt_tokens, Some(12));
+        assert_eq!(response.completion_tokens, Some(3));
+
+        let received = &server.requests()[0];
+        assert_eq!(received.method, "POST");
+        assert_eq!(received.headers["authorization"], "Bearer sk-test");
+        let body = received.json();
+        assert_eq!(body["model"], "llama-3.1-8b");
+        assert_eq!(body["messages"][0]["role"], "system");
+        assert_eq!(body["messages"][1]["content"], "find admin panels");
+        assert_eq!(body["max_tokens"], 64);
+        assert_eq!(body["stream"], false);
+    }
+
+    #[tokio::test]
+    async fn test_streamed_chat_completion() {
+        let events = [
+            r#"{"model":"m","choices":[{"index":0,"delta":{"role":"assistant"}}]}"#,
+            r#"{"model":"m","choices":[{"index":0,"delta":{"content":"inurl:"}}]}"#,
+            r#"{"model":"m","choices":[{"index":0,"delta":{"content":"admin"}}]}"#,
+            r#"{"model":"m","choices":[],"usage":{"prompt_tokens":9,"completion_tokens":2}}"#,
+            "[DONE]",
+        ];
+        let body: String = events.iter().map(|e| format!("data: {}\n\n", e)).collect();
+        let server = StubServer::start(vec![
+            ("/chat/completions", StubResponse::json(body).with_content_type("text/event-stream")),
+        ]).await;
+
+        let provider = OpenAiCompatibleProvider::new(reqwest::Client::new(), &server.url(), "m", None);
+        let mut chunks = Vec::new();
+        let response = provider
+            .generate_stream(&GenerationRequest::new("admin"), &mut |delta: &str| chunks.push(delta.to_string()))
+            .await
+            .unwrap();
+
+        assert_eq!(chunks, vec!["inurl:", "admin"]);
+        assert_eq!(response.text, "inurl:admin");
+        assert_eq!(response.completion_tokens, Some(2));
+        assert_eq!(server.requests()[0].path, "/chat/completions");
+        assert!(!server.requests()[0].headers.contains_key("authorization"));
+        assert_eq!(server.requests()[0].json()["stream_options"]["include_usage"], true);
+    }
+}
@@ -0,0 +1,293 @@
+//! Structured dork output: the JSON schema models are asked to follow, and
+//! the repair and validation applied to what they actually return.
+//!
+//! Replies are repaired where the intent is clear (code fences, surrounding
+//! prose, trailing commas, truncated objects) and every query is linted.
+//! Problems that make the result unusable are returned so the caller can
+//! retry with a corrective prompt; minor ones are kept as warnings.
+
+use super::{DorkGenerationRequest, DorkGenerationResult};
+use crate::dorks::{lint_query, LintLevel};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+
+/// JSON Schema for a generated dork, embedded in the prompt
+pub const DORK_OUTPUT_SCHEMA: &str = r#"{
+  "type": "object",
+  "required": ["query", "explanation", "category", "severity", "tags"],
+  "properties": {
+    "query": {"type": "string", "description": "the search dork, on one line"},
+    "explanation": {"type": "string"},
+    "category": {"type": "string"},
+    "severity": {"type": "string", "enum": ["Low", "Medium", "High", "Critical"]},
+    "tags": {"type": "array", "items": {"type": "string"}},
+    "alternatives": {"type": "array", "items": {"type": "string"}}
+  }
+}"#;
+
+/// How sensitive the results of a dork are likely to be
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Severity {
+    Low,
+    #[default]
+    Medium,
+    High,
+    Critical,
+}
+
+impl Severity {
+    pub(crate) fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "low" => Some(Severity::Low),
+            "medium" | "moderate" => Some(Severity::Medium),
+            "high" => Some(Severity::High),
+            "critical" => Some(Severity::Critical),
+            _ => None,
+        }
+    }
+}
+
+/// Parse, repair and lint a model reply. `Err` lists the problems that make
+/// the reply unusable.
+pub fn parse_dork_output(text: &str, request: &DorkGenerationRequest) -> Result<DorkGenerationResult, Vec<String>> {
+    let default_category = request.category.clone().unwrap_or_else(|| "General".to_string());
+
+    let Some(value) = repair_json(text) else {
+        // A bare one-line query with an operator is usable as long as it lints clean
+        let query = text.trim();
+        let problems = query_errors(query);
+        if query.lines().count() == 1 && query.contains(':') && problems.is_empty() {
+            return Ok(DorkGenerationResult {
+                query: query.to_string(),
+                category: default_category,
+                warnings: vec!["Reply was not structured; only the query was recovered".to_string()],
+                ..Default::default()
+            });
+        }
+        return Err(vec!["Reply was not a JSON object".to_string()]);
+    };
+
+    let mut problems = Vec::new();
+    let mut warnings = Vec::new();
+    let string = |key: &str| {
+        value
+            .get(key)
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+    };
+
+    let query = string("query").unwrap_or_default();
+    if query.is_empty() {
+        problems.push("\"query\" is missing or not a string".to_string());
+    } else {
+        problems.extend(query_errors(&query));
+        warnings.extend(query_warnings(&query));
+    }
+
+    let explanation = string("explanation").unwrap_or_else(|| {
+        warnings.push("No explanation was given".to_string());
+        String::new()
+    });
+
+    let severity = match string("severity") {
+        Some(raw) => Severity::parse(&raw).unwrap_or_else(|| {
+            warnings.push(format!("Unknown severity \"{}\"; using Medium", raw));
+            Severity::Medium
+        }),
+        None => {
+            warnings.push("No severity was given; using Medium".to_string());
+            Severity::Medium
+        }
+    };
+
+    let alternatives = string_list(value.get("alternatives")).map(|alternatives| {
+        alternatives
+            .into_iter()
+            .filter(|alternative| {
+                let errors = query_errors(alternative);
+                if !errors.is_empty() {
+                    warnings.push(format!("Dropped alternative {}: {}", alternative, errors.join(", ")));
+                }
+                errors.is_empty() && *alternative != query
+            })
+            .collect()
+    });
+
+    if !problems.is_empty() {
+        return Err(problems);
+    }
+
+    let mut seen = HashSet::new();
+    let tags: Vec<String> = string_list(value.get("tags"))
+        .unwrap_or_default()
+        .into_iter()
+        .map(|tag| tag.to_lowercase())
+        .filter(|tag| seen.insert(tag.clone()))
+        .collect();
+
+    Ok(DorkGenerationResult {
+        query,
+        explanation,
+        category: string("category").unwrap_or(default_category),
+        severity,
+        tags,
+        alternatives,
+        warnings,
+        ..Default::default()
+    })
+}
+
+/// Prompt asking the model to fix a reply that failed validation
+pub fn corrective_prompt(original_prompt: &str, reply: &str, problems: &[String]) -> String {
+    let problems: String = problems.iter().map(|p| format!("- {}\n", p)).collect();
+    format!(
+        "{original_prompt}\n\nYour previous reply was:\n\n{reply}\n\nIt was rejected because:\n{problems}\nReply again with ONLY a corrected JSON object that matches the schema."
+    )
+}
+
+/// Find the JSON object in a reply, fixing common damage along the way
+fn repair_json(text: &str) -> Option<Value> {
+    let start = text.find('{')?;
+    let candidate = match text.rfind('}') {
+        Some(end) if end > start => &text[start..=end],
+        _ => &text[start..],
+    };
+
+    if let Ok(value @ Value::Object(_)) = serde_json::from_str(candidate) {
+        return Some(value);
+    }
+
+    match serde_json::from_str(&close_and_trim(candidate)) {
+        Ok(value @ Value::Object(_)) => Some(value),
+        _ => None,
+    }
+}
+
+/// Drop trailing commas and close whatever strings, arrays and objects a
+/// truncated reply left open
+fn close_and_trim(json: &str) -> String {
+    let mut out = String::with_capacity(json.len() + 8);
+    let mut open = Vec::new();
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for c in json.chars() {
+        if in_string {
+            out.push(c);
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+
+        match c {
+            '"' => in_string = true,
+            '{' => open.push('}'),
+            '[' => open.push(']'),
+            '}' | ']' => {
+                open.pop();
+                trim_trailing_comma(&mut out);
+            }
+            _ => {}
+        }
+        out.push(c);
+    }
+
+    if in_string {
+        out.push('"');
+    }
+    while let Some(closer) = open.pop() {
+        trim_trailing_comma(&mut out);
+        out.push(closer);
+    }
+    out
+}
+
+fn trim_trailing_comma(out: &mut String) {
+    let trimmed = out.trim_end().len();
+    if out[..trimmed].ends_with(',') {
+        out.truncate(trimmed - 1);
+    }
+}
+
+/// Accept either a JSON array of strings or one comma-separated string
+fn string_list(value: Option<&Value>) -> Option<Vec<String>> {
+    let items: Vec<String> = match value? {
+        Value::Array(items) => items.iter().filter_map(Value::as_str).map(str::to_string).collect(),
+        Value::String(list) => list.split(',').map(str::to_string).collect(),
+        _ => return None,
+    };
+
+    Some(
+        items
+            .into_iter()
+            .map(|item| item.trim().to_string())
+            .filter(|item| !item.is_empty())
+            .collect(),
+    )
+}
+
+fn query_errors(query: &str) -> Vec<String> {
+    lint_query(query)
+        .into_iter()
+        .filter(|issue| issue.level == LintLevel::Error)
+        .map(|issue| issue.message)
+        .collect()
+}
+
+fn query_warnings(query: &str) -> Vec<String> {
+    lint_query(query)
+        .into_iter()
+        .filter(|issue| issue.level == LintLevel::Warning)
+        .map(|issue| issue.message)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request() -> DorkGenerationRequest {
+        DorkGenerationRequest {
+            description: "exposed env files".to_string(),
+            search_engine: None,
+            category: Some("Files".to_string()),
+            advanced: false,
+            bypass_cache: false,
+        }
+    }
+
+    #[test]
+    fn test_repairs_fenced_truncated_reply() {
+        let reply = "Here you go:\n```json\n{\"query\": \"site:example.com ext:env\", \"explanation\": \"Env files\", \
+                     \"severity\": \"critical\", \"tags\": [\"Env\", \"secrets\",], \"alternatives\": [\"ext:env \\\"DB_\"";
+        let result = parse_dork_output(reply, &request()).unwrap();
+
+        assert_eq!(result.query, "site:example.com ext:env");
+        assert_eq!(result.severity, Severity::Critical);
+        assert_eq!(result.category, "Files");
+        assert_eq!(result.tags, vec!["env", "secrets"]);
+        // The truncated alternative has an unbalanced quote
+        assert_eq!(result.alternatives, Some(vec![]));
+        assert!(result.warnings.iter().any(|w| w.starts_with("Dropped alternative")));
+    }
+
+    #[test]
+    fn test_rejects_invalid_queries() {
+        let problems = parse_dork_output(r#"{"query": "intitle:\"index of", "severity": "High"}"#, &request())
+            .unwrap_err();
+        assert_eq!(problems, vec!["Unbalanced double quotes"]);
+
+        assert!(parse_dork_output(r#"{"explanation": "no query"}"#, &request()).is_err());
+        assert!(parse_dork_output("I can't help with that.\nSorry.", &request()).is_err());
+
+        let prompt = corrective_prompt("Make a dork", "{}", &problems);
+        assert!(prompt.contains("- Unbalanced double quotes\n"));
+    }
+}
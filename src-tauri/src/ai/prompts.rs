@@ -0,0 +1,278 @@
+//! Prompt template registry.
+//!
+//! Every prompt the backend sends is a named, versioned template with
+//! `{{variable}}` placeholders and its own model parameters. Users can
+//! override a template's text and parameters; overrides are stored in the
+//! vault as numbered revisions. Messages record the `<name>@<version>` id of
+//! the template that produced them.
+
+use super::{AiService, GenerationRequest};
+use crate::vault::PromptOverride;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+pub const DORK_GENERATION: &str = "dork_generation";
+pub const CONVERSATION_SUMMARY: &str = "conversation_summary";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PromptTemplate {
+    pub name: String,
+    /// The built-in version number, or `user-<revision>` for an override
+    pub version: String,
+    pub description: String,
+    /// Variables the template may use
+    pub variables: Vec<String>,
+    pub template: String,
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u32>,
+}
+
+impl PromptTemplate {
+    /// `<name>@<version>`, as recorded on messages
+    pub fn id(&self) -> String {
+        format!("{}@{}", self.name, self.version)
+    }
+
+    /// Fill in the placeholders and attach the template's model parameters
+    pub fn render(&self, values: &[(&str, &str)]) -> Result<GenerationRequest> {
+        let mut prompt = String::with_capacity(self.template.len());
+        let mut rest = self.template.as_str();
+
+        while let Some(start) = rest.find("{{") {
+            let end = rest[start..]
+                .find("}}")
+                .map(|end| start + end)
+                .with_context(|| format!("Unclosed placeholder in prompt {}", self.id()))?;
+            let name = rest[start + 2..end].trim();
+            let value = values
+                .iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| *value)
+                .with_context(|| format!("No value for {{{{{}}}}} in prompt {}", name, self.id()))?;
+
+            prompt.push_str(&rest[..start]);
+            prompt.push_str(value);
+            rest = &rest[end + 2..];
+        }
+        prompt.push_str(rest);
+
+        Ok(GenerationRequest {
+            prompt,
+            system: None,
+            temperature: self.temperature,
+            max_tokens: self.max_tokens,
+        })
+    }
+
+    /// Apply a user override's text and parameters
+    fn with_override(&self, saved: PromptOverride) -> Self {
+        Self {
+            version: format!("user-{}", saved.revision),
+            template: saved.template,
+            temperature: saved.temperature,
+            max_tokens: saved.max_tokens,
+            ..self.clone()
+        }
+    }
+}
+
+struct BuiltinPrompt {
+    name: &'static str,
+    version: u32,
+    description: &'static str,
+    variables: &'static [&'static str],
+    template: &'static str,
+    temperature: Option<f32>,
+    max_tokens: Option<u32>,
+}
+
+/// Bump a template's version whenever its text or parameters change
+const BUILTIN_PROMPTS: &[BuiltinPrompt] = &[
+    BuiltinPrompt {
+        name: DORK_GENERATION,
+        version: 1,
+        description: "Turn a plain-language request into a search dork",
+        variables: &["description", "engine", "category", "advanced", "schema"],
+        template: r#"You are an expert in creating search engine dorks (advanced search queries) for OSINT and security research.
+
+User Request: "{{description}}"
+Search Engine: {{engine}}
+Category: {{category}}
+Advanced Mode: {{advanced}}
+
+Generate a search dork query that accomplishes the user's goal. Return ONLY a JSON object matching this JSON Schema:
+
+{{schema}}
+
+Important guidelines:
+- For Google, use operators like: site:, filetype:, inurl:, intitle:, intext:, ext:, cache:
+- For Bing, use similar operators with some variations
+- Focus on ethical OSINT and authorized security testing
+- Make queries specific and effective
+- Choose the category from e.g. Files, Login Pages, Vulnerable Sites, Exposed Data
+- Rate severity by how sensitive the exposed results would be
+- Provide 2-3 alternative queries with different approaches
+- Keep explanations concise but informative
+
+Return ONLY the JSON object, no additional text."#,
+        temperature: Some(0.4),
+        max_tokens: Some(1024),
+    },
+    BuiltinPrompt {
+        name: CONVERSATION_SUMMARY,
+        version: 1,
+        description: "Condense older conversation turns so long chats fit the context window",
+        variables: &["summary", "transcript"],
+        template: r#"Summarize this OSINT research conversation so it can stand in for the original messages.
+
+Summary of earlier turns:
+{{summary}}
+
+Messages to add:
+{{transcript}}
+
+Keep the targets, goals, constraints and every dork that was produced or rejected. Write plain prose of at most 200 words. Return ONLY the summary."#,
+        temperature: Some(0.2),
+        max_tokens: Some(400),
+    },
+];
+
+fn builtin(name: &str) -> Result<PromptTemplate> {
+    BUILTIN_PROMPTS
+        .iter()
+        .find(|prompt| prompt.name == name)
+        .map(|prompt| PromptTemplate {
+            name: prompt.name.to_string(),
+            version: prompt.version.to_string(),
+            description: prompt.description.to_string(),
+            variables: prompt.variables.iter().map(|v| v.to_string()).collect(),
+            template: prompt.template.to_string(),
+            temperature: prompt.temperature,
+            max_tokens: prompt.max_tokens,
+        })
+        .with_context(|| format!("Unknown prompt template: {}", name))
+}
+
+/// Reject overrides that use variables the template doesn't provide
+fn validate_override(base: &PromptTemplate, template: &str, temperature: Option<f32>) -> Result<()> {
+    if template.trim().is_empty() {
+        anyhow::bail!("Prompt template must not be empty");
+    }
+    if temperature.is_some_and(|t| !(0.0..=2.0).contains(&t)) {
+        anyhow::bail!("Temperature must be between 0 and 2");
+    }
+
+    let placeholder_values: Vec<(&str, &str)> = base.variables.iter().map(|v| (v.as_str(), "")).collect();
+    PromptTemplate {
+        template: template.to_string(),
+        ..base.clone()
+    }
+    .render(&placeholder_values)
+    .map(|_| ())
+}
+
+impl AiService {
+    /// Every template as currently in effect, overrides applied
+    pub async fn prompt_templates(&self) -> Result<Vec<PromptTemplate>> {
+        let mut templates = Vec::with_capacity(BUILTIN_PROMPTS.len());
+        for prompt in BUILTIN_PROMPTS {
+            templates.push(self.prompt_template(prompt.name, None).await?);
+        }
+        Ok(templates)
+    }
+
+    /// The active template for `name`, or a specific `version` of it
+    pub async fn prompt_template(&self, name: &str, version: Option<&str>) -> Result<PromptTemplate> {
+        let base = builtin(name)?;
+
+        match version {
+            None => Ok(match self.vault.active_prompt_override(name).await? {
+                Some(saved) => base.with_override(saved),
+                None => base,
+            }),
+            Some(version) if version == base.version => Ok(base),
+            Some(version) => {
+                let revision = version
+                    .strip_prefix("user-")
+                    .and_then(|r| r.parse().ok())
+                    .with_context(|| format!("Unknown version {} of prompt {}", version, name))?;
+                let saved = self
+                    .vault
+                    .prompt_override_revision(name, revision)
+                    .await?
+                    .with_context(|| format!("Unknown version {} of prompt {}", version, name))?;
+                Ok(base.with_override(saved))
+            }
+        }
+    }
+
+    pub async fn set_prompt_override(
+        &self,
+        name: &str,
+        template: &str,
+        temperature: Option<f32>,
+        max_tokens: Option<u32>,
+    ) -> Result<PromptTemplate> {
+        let base = builtin(name)?;
+        validate_override(&base, template, temperature)?;
+
+        let saved = self.vault.save_prompt_override(name, template, temperature, max_tokens).await?;
+        Ok(base.with_override(saved))
+    }
+
+    /// Return to the built-in template
+    pub async fn reset_prompt_override(&self, name: &str) -> Result<()> {
+        builtin(name)?;
+        self.vault.reset_prompt_override(name).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::security::SecurityService;
+    use crate::vault::VaultService;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_render_template() {
+        let template = builtin(CONVERSATION_SUMMARY).unwrap();
+        let request = template.render(&[("summary", "None"), ("transcript", "user: hi")]).unwrap();
+        assert!(request.prompt.contains("Summary of earlier turns:\nNone\n\nMessages to add:\nuser: hi\n"));
+        assert_eq!(request.temperature, Some(0.2));
+        assert_eq!(template.id(), "conversation_summary@1");
+
+        assert!(template.render(&[("summary", "None")]).is_err());
+        assert!(builtin("missing").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_overrides_are_versioned() {
+        let vault = Arc::new(VaultService::open_in_memory().unwrap());
+        let service = AiService::new(Arc::new(SecurityService::new()), vault).unwrap();
+
+        assert!(service.set_prompt_override(CONVERSATION_SUMMARY, "Summarize {{topic}}", None, None).await.is_err());
+
+        let first = service
+            .set_prompt_override(CONVERSATION_SUMMARY, "Summarize {{transcript}}", Some(0.1), None)
+            .await
+            .unwrap();
+        let second = service
+            .set_prompt_override(CONVERSATION_SUMMARY, "Briefly: {{transcript}}", None, Some(200))
+            .await
+            .unwrap();
+        assert_eq!(first.id(), "conversation_summary@user-1");
+        assert_eq!(service.prompt_template(CONVERSATION_SUMMARY, None).await.unwrap(), second);
+        assert_eq!(service.prompt_template(CONVERSATION_SUMMARY, Some("user-1")).await.unwrap(), first);
+
+        service.reset_prompt_override(CONVERSATION_SUMMARY).await.unwrap();
+        let active = service.prompt_template(CONVERSATION_SUMMARY, None).await.unwrap();
+        assert_eq!(active.version, "1");
+        assert_eq!(service.prompt_templates().await.unwrap().len(), BUILTIN_PROMPTS.len());
+
+        // Revision numbers keep counting after a reset
+        let third = service.set_prompt_override(CONVERSATION_SUMMARY, "{{transcript}}", None, None).await.unwrap();
+        assert_eq!(third.version, "user-3");
+    }
+}
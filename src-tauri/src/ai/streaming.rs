@@ -0,0 +1,506 @@
+//! Streamed dork generation.
+//!
+//! Fragments are reported through [`StreamEvent`]s tagged with the caller's
+//! request id, and the assistant message is written into the conversation as
+//! it grows so a crash or cancellation keeps whatever had arrived.
+
+use super::{
+    cache, estimate_tokens, prompts, response_usage, AiProvider, AiService, DorkGenerationRequest,
+    DorkGenerationResult,
+};
+use crate::indicators;
+use crate::vault::{GraphSource, Message, SourceKind, UsageOutcome};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, Notify};
+
+/// How often a growing reply is written back to the vault
+const PERSIST_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    /// True when the provider didn't report counts and they were estimated
+    pub estimated: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum StreamEvent {
+    Started {
+        request_id: String,
+        message_id: String,
+    },
+    Chunk {
+        request_id: String,
+        delta: String,
+    },
+    Done {
+        request_id: String,
+        message_id: String,
+        result: DorkGenerationResult,
+        usage: TokenUsage,
+    },
+    Cancelled {
+        request_id: String,
+        message_id: String,
+        usage: TokenUsage,
+    },
+    Error {
+        request_id: String,
+        message: String,
+    },
+}
+
+/// Removes a stream's cancel handle when the stream ends, however it ends
+struct StreamRegistration<'a> {
+    service: &'a AiService,
+    request_id: String,
+}
+
+impl Drop for StreamRegistration<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut streams) = self.service.active_streams.lock() {
+            streams.remove(&self.request_id);
+        }
+    }
+}
+
+impl AiService {
+    /// Stream a dork generation into `conversation_id`, reporting progress
+    /// through `emit`. Returns `None` if the stream was cancelled.
+    pub async fn stream_dork(
+        &self,
+        request_id: &str,
+        conversation_id: &str,
+        request: &DorkGenerationRequest,
+        workspace: &str,
+        license_tier: &str,
+        emit: impl Fn(StreamEvent) + Send + Sync,
+    ) -> Result<Option<DorkGenerationResult>> {
+        let provider = self.provider(workspace).await?;
+        let result = self
+            .stream_dork_with(provider.as_ref(), request_id, conversation_id, request, license_tier, emit)
+            .await?;
+        if let Some(result) = &result {
+            self.record_indicators_quietly(workspace, conversation_id, request, result).await;
+        }
+        Ok(result)
+    }
+
+    /// Ask a running stream to stop. Returns false if no stream has that id.
+    pub fn cancel_generation(&self, request_id: &str) -> bool {
+        let streams = match self.active_streams.lock() {
+            Ok(streams) => streams,
+            Err(_) => return false,
+        };
+
+        match streams.get(request_id) {
+            Some(cancel) => {
+                cancel.notify_one();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub(super) async fn stream_dork_with(
+        &self,
+        provider: &dyn AiProvider,
+        request_id: &str,
+        conversation_id: &str,
+        request: &DorkGenerationRequest,
+        license_tier: &str,
+        emit: impl Fn(StreamEvent) + Send + Sync,
+    ) -> Result<Option<DorkGenerationResult>> {
+        if request.description.trim().is_empty() {
+            anyhow::bail!("Describe what the dork should find");
+        }
+
+        let cancel = Arc::new(Notify::new());
+        let _registration = {
+            let mut streams = self
+                .active_streams
+                .lock()
+                .map_err(|_| anyhow::anyhow!("Stream registry is poisoned"))?;
+            if streams.contains_key(request_id) {
+                anyhow::bail!("A generation with request id {} is already running", request_id);
+            }
+            streams.insert(request_id.to_string(), cancel.clone());
+            StreamRegistration {
+                service: self,
+                request_id: request_id.to_string(),
+            }
+        };
+
+        // The key leaves out the conversation context, so a description
+        // shares its entry with one-off generations
+        let (mut generation_request, prompt_version) = self.dork_generation_request(request).await?;
+        let key = cache::cache_key(provider, &prompt_version, &generation_request);
+        if !request.bypass_cache {
+            if let Some(result) = self.cached_result(&key).await {
+                return self.replay_cached(request_id, conversation_id, result, emit).await.map(Some);
+            }
+        }
+
+        let Some(grant) = self.vault.try_consume_ai_generation(license_tier).await? else {
+            anyhow::bail!("Daily AI generation limit reached. Upgrade for unlimited generations.");
+        };
+        // May call the provider to summarize, so only once the quota allows
+        generation_request.system = self.context_prompt(provider, conversation_id).await;
+
+        let message = Message {
+            id: uuid::Uuid::new_v4().to_string(),
+            role: "assistant".to_string(),
+            content: String::new(),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            dork: None,
+            dork_id: None,
+            prompt_version: Some(prompt_version.clone()),
+            pinned: false,
+            summarizes_through: None,
+        };
+        if let Err(e) = self.vault.append_message(conversation_id, &message).await {
+            self.refund_quietly(grant).await;
+            return Err(e);
+        }
+        emit(StreamEvent::Started {
+            request_id: request_id.to_string(),
+            message_id: message.id.clone(),
+        });
+
+        let (sender, mut receiver) = mpsc::unbounded_channel::<String>();
+        let mut on_chunk = move |delta: &str| {
+            let _ = sender.send(delta.to_string());
+        };
+
+        let mut text = String::new();
+        let started = Instant::now();
+        let mut last_saved = started;
+        let outcome = {
+            let generation = provider.generate_stream(&generation_request, &mut on_chunk);
+            tokio::pin!(generation);
+
+            loop {
+                tokio::select! {
+                    biased;
+                    _ = cancel.notified() => break None,
+                    Some(delta) = receiver.recv() => {
+                        text.push_str(&delta);
+                        emit(StreamEvent::Chunk { request_id: request_id.to_string(), delta });
+
+                        if last_saved.elapsed() >= PERSIST_INTERVAL {
+                            self.persist_partial(conversation_id, &message.id, &text).await;
+                            last_saved = Instant::now();
+                        }
+                    }
+                    result = &mut generation => break Some(result),
+                }
+            }
+        };
+
+        // Fragments sent just before the provider returned
+        while let Ok(delta) = receiver.try_recv() {
+            text.push_str(&delta);
+            emit(StreamEvent::Chunk { request_id: request_id.to_string(), delta });
+        }
+
+        let prompt_estimate = estimate_tokens(&generation_request.prompt);
+
+        match outcome {
+            None => {
+                self.persist_partial(conversation_id, &message.id, &text).await;
+                let usage = TokenUsage {
+                    prompt_tokens: prompt_estimate,
+                    completion_tokens: estimate_tokens(&text),
+                    estimated: true,
+                };
+                self.record_usage_quietly(provider, prompts::DORK_GENERATION, usage, started, UsageOutcome::Cancelled)
+                    .await;
+
+                tracing::info!("Generation {} cancelled after {} characters", request_id, text.len());
+                emit(StreamEvent::Cancelled {
+                    request_id: request_id.to_string(),
+                    message_id: message.id,
+                    usage,
+                });
+                Ok(None)
+            }
+            Some(Err(e)) => {
+                tracing::error!("{} ({}) stream failed: {}", provider.name(), provider.model(), e);
+
+                let usage = if text.is_empty() {
+                    self.refund_quietly(grant).await;
+                    self.persist_partial(conversation_id, &message.id, &format!("Sorry, I encountered an error: {}", e))
+                        .await;
+                    TokenUsage::default()
+                } else {
+                    self.persist_partial(conversation_id, &message.id, &text).await;
+                    TokenUsage {
+                        prompt_tokens: prompt_estimate,
+                        completion_tokens: estimate_tokens(&text),
+                        estimated: true,
+                    }
+                };
+                self.record_usage_quietly(provider, prompts::DORK_GENERATION, usage, started, UsageOutcome::Error)
+                    .await;
+
+                emit(StreamEvent::Error {
+                    request_id: request_id.to_string(),
+                    message: e.to_string(),
+                });
+                Err(e)
+            }
+            Some(Ok(response)) => {
+                let usage = response_usage(&generation_request, &response);
+                self.record_usage_quietly(provider, prompts::DORK_GENERATION, usage, started, UsageOutcome::Success)
+                    .await;
+
+                // Corrective retries aren't streamed; the final result replaces
+                // whatever was shown
+                let result = match self
+                    .validate_or_retry(provider, request, &generation_request, &prompt_version, &response.text)
+                    .await
+                {
+                    Ok(result) => result,
+                    Err(e) => {
+                        self.refund_quietly(grant).await;
+                        self.persist_partial(conversation_id, &message.id, &format!("Sorry, I encountered an error: {}", e))
+                            .await;
+                        emit(StreamEvent::Error {
+                            request_id: request_id.to_string(),
+                            message: e.to_string(),
+                        });
+                        return Err(e);
+                    }
+                };
+
+                self.vault
+                    .update_message_content(conversation_id, &message.id, &reply_content(&result), Some(&result.query))
+                    .await?;
+                self.store_cached_quietly(provider, &key, &result).await;
+
+                emit(StreamEvent::Done {
+                    request_id: request_id.to_string(),
+                    message_id: message.id,
+                    result: result.clone(),
+                    usage,
+                });
+                Ok(Some(result))
+            }
+        }
+    }
+
+    /// Report a cached result as a stream that finished at once
+    async fn replay_cached(
+        &self,
+        request_id: &str,
+        conversation_id: &str,
+        result: DorkGenerationResult,
+        emit: impl Fn(StreamEvent) + Send + Sync,
+    ) -> Result<DorkGenerationResult> {
+        let message = Message {
+            id: uuid::Uuid::new_v4().to_string(),
+            role: "assistant".to_string(),
+            content: reply_content(&result),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            dork: Some(result.query.clone()),
+            dork_id: None,
+            prompt_version: Some(result.prompt_version.clone()),
+            pinned: false,
+            summarizes_through: None,
+        };
+        self.vault.append_message(conversation_id, &message).await?;
+
+        emit(StreamEvent::Started {
+            request_id: request_id.to_string(),
+            message_id: message.id.clone(),
+        });
+        emit(StreamEvent::Done {
+            request_id: request_id.to_string(),
+            message_id: message.id,
+            result: result.clone(),
+            usage: TokenUsage::default(),
+        });
+        Ok(result)
+    }
+
+    /// Add the indicators named in a request and its reply to the entity graph
+    async fn record_indicators_quietly(
+        &self,
+        workspace: &str,
+        conversation_id: &str,
+        request: &DorkGenerationRequest,
+        result: &DorkGenerationResult,
+    ) {
+        let text = format!("{}\n{}\n{}", request.description, result.query, result.explanation);
+        let indicators = indicators::extract_indicators(&text);
+        if indicators.is_empty() {
+            return;
+        }
+
+        let source = GraphSource {
+            kind: SourceKind::Ai,
+            reference: conversation_id.to_string(),
+        };
+        if let Err(e) = self.vault.upsert_graph(workspace, &indicators::indicator_batch(&indicators, source)).await {
+            tracing::warn!("Failed to record indicators from conversation {}: {}", conversation_id, e);
+        }
+    }
+
+    async fn persist_partial(&self, conversation_id: &str, message_id: &str, content: &str) {
+        if let Err(e) = self.vault.update_message_content(conversation_id, message_id, content, None).await {
+            tracing::warn!("Failed to persist streamed message {}: {}", message_id, e);
+        }
+    }
+}
+
+/// The assistant message text for a finished generation
+fn reply_content(result: &DorkGenerationResult) -> String {
+    if result.explanation.is_empty() {
+        format!("Here's a dork for that:\n\n{}", result.query)
+    } else {
+        result.explanation.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::MockProvider;
+    use crate::security::SecurityService;
+    use crate::vault::{Conversation, VaultService};
+
+    async fn setup() -> (Arc<AiService>, Arc<VaultService>) {
+        let vault = Arc::new(VaultService::open_in_memory().unwrap());
+        let now = chrono::Utc::now().to_rfc3339();
+        vault.save_conversation(&Conversation {
+            id: "c1".to_string(),
+            title: "Streaming".to_string(),
+            messages: vec![],
+            created_at: now.clone(),
+            updated_at: now,
+        }).await.unwrap();
+
+        let service = Arc::new(AiService::new(Arc::new(SecurityService::new()), vault.clone()).unwrap());
+        (service, vault)
+    }
+
+    fn request() -> DorkGenerationRequest {
+        DorkGenerationRequest {
+            description: "exposed env files".to_string(),
+            search_engine: None,
+            category: None,
+            advanced: false,
+            bypass_cache: false,
+        }
+    }
+
+    const REPLY: &str = r#"{"query": "site:example.com ext:env", "explanation": "Finds env files"}"#;
+
+    #[tokio::test]
+    async fn test_stream_persists_and_records_usage() {
+        let (service, vault) = setup().await;
+        let provider = MockProvider::new().reply(REPLY);
+
+        let events = std::sync::Mutex::new(Vec::new());
+        let result = service
+            .stream_dork_with(&provider, "r1", "c1", &request(), "free", |event| events.lock().unwrap().push(event))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(result.query, "site:example.com ext:env");
+
+        let events = events.into_inner().unwrap();
+        assert!(matches!(events.first(), Some(StreamEvent::Started { .. })));
+        let streamed: String = events
+            .iter()
+            .filter_map(|e| match e {
+                StreamEvent::Chunk { delta, .. } => Some(delta.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(streamed, REPLY);
+        let Some(StreamEvent::Done { usage, .. }) = events.last() else {
+            panic!("stream did not finish with Done");
+        };
+
+        let message = &vault.get_conversation("c1").await.unwrap().messages[0];
+        assert_eq!(message.content, "Finds env files");
+        assert_eq!(message.dork.as_deref(), Some("site:example.com ext:env"));
+        assert_eq!(message.prompt_version.as_deref(), Some("dork_generation@1"));
+
+        let stats = vault.get_usage_stats().await.unwrap();
+        assert_eq!(stats.ai_generations_today, 1);
+        assert_eq!(stats.completion_tokens_today, i64::from(usage.completion_tokens));
+        assert_eq!(stats.total_prompt_tokens, i64::from(usage.prompt_tokens));
+    }
+
+    #[tokio::test]
+    async fn test_cache_hit_skips_context_summary() {
+        let (service, vault) = setup().await;
+        let provider = MockProvider::new().reply(REPLY);
+        service.generate_dork_with(&provider, &request(), "free").await.unwrap();
+
+        // Long enough that assembling context would summarize
+        let mut conversation = vault.get_conversation("c1").await.unwrap();
+        conversation.messages = (0..40)
+            .map(|i| Message {
+                id: format!("m{}", i),
+                role: "user".to_string(),
+                content: "word ".repeat(100),
+                timestamp: String::new(),
+                dork: None,
+                dork_id: None,
+                prompt_version: None,
+                pinned: false,
+                summarizes_through: None,
+            })
+            .collect();
+        vault.save_conversation(&conversation).await.unwrap();
+
+        let result = service
+            .stream_dork_with(&provider, "r3", "c1", &request(), "free", |_| {})
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(result.query, "site:example.com ext:env");
+        assert_eq!(provider.requests().len(), 1);
+        assert_eq!(vault.get_usage_stats().await.unwrap().ai_generations_today, 1);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_keeps_partial_message() {
+        let (service, vault) = setup().await;
+        let provider = MockProvider::new()
+            .reply("one two three four five six seven eight")
+            .with_chunk_delay(Duration::from_millis(20));
+
+        let (first_chunk, mut chunk_seen) = mpsc::unbounded_channel();
+        let stream = {
+            let service = service.clone();
+            tokio::spawn(async move {
+                service
+                    .stream_dork_with(&provider, "r2", "c1", &request(), "free", move |event| {
+                        if matches!(event, StreamEvent::Chunk { .. }) {
+                            let _ = first_chunk.send(());
+                        }
+                    })
+                    .await
+            })
+        };
+
+        chunk_seen.recv().await.unwrap();
+        assert!(service.cancel_generation("r2"));
+        assert!(stream.await.unwrap().unwrap().is_none());
+        assert!(!service.cancel_generation("r2"));
+
+        let message = &vault.get_conversation("c1").await.unwrap().messages[0];
+        assert!(message.content.starts_with("one "));
+        assert!(message.content.len() < "one two three four five six seven eight".len());
+        assert!(vault.get_usage_stats().await.unwrap().completion_tokens_today > 0);
+    }
+}
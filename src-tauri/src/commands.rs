@@ -1,8 +1,27 @@
+use crate::ai::{
+    AiService, ContextOptions, ConversationContext, DorkGenerationRequest, DorkGenerationResult, PromptTemplate,
+    ProviderConfig, DEFAULT_WORKSPACE,
+};
 use crate::security::SecurityService;
 use crate::licensing::LicenseService;
-use crate::vault::{VaultService, DorkQuery};
+use crate::vault::{
+    VaultService, DorkQuery, BackupArchive, RestoreMode, RestoreReport,
+    ImportFormat, ImportOptions, ImportReport, DorkMergeRecord, DorkProvenance,
+    Entity, EntityKey, EntityType, GraphBatch, GraphSource, GraphUpsertReport, RelationType, SourceKind,
+    Subgraph,
+};
+use crate::graph::{
+    self, GraphDocument, GraphFilter, GraphFormat, GraphImportReport, LayoutOptions, NodePosition,
+};
+use crate::dorks::DuplicateCluster;
+use crate::enrichment::{
+    self, CertTransparencyConfig, ConnectorInfo, ConnectorSettings, CtReport, DnsConfig, DnsReport, EnrichmentJob,
+    EnrichmentService, JobReport,
+};
+use crate::imaging::{self, ImageMetadata, ScrubOptions, ScrubReport};
+use crate::indicators::{self, Indicator};
 use serde::{Deserialize, Serialize};
-use tauri::State;
+use tauri::{Emitter, State};
 use std::sync::Arc;
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -52,14 +71,6 @@ pub async fn store_gemini_api_key(
         .map_err(|e| format!("Failed to store API key: {}", e))
 }
 
-#[tauri::command]
-pub async fn get_gemini_api_key(
-    security: State<'_, Arc<SecurityService>>,
-) -> Result<String, String> {
-    security.get_gemini_api_key().await
-        .map_err(|e| format!("Failed to retrieve API key: {}", e))
-}
-
 #[tauri::command]
 pub async fn delete_gemini_api_key(
     security: State<'_, Arc<SecurityService>>,
@@ -121,6 +132,68 @@ pub async fn delete_dork(
         .map_err(|e| format!("Failed to delete dork: {}", e))
 }
 
+// Save a dork generated in an AI conversation, recording its provenance
+#[tauri::command]
+pub async fn save_dork_from_message(
+    conversation_id: String,
+    message_id: String,
+    dork: DorkQuery,
+    vault: State<'_, Arc<VaultService>>,
+) -> Result<DorkProvenance, String> {
+    vault.save_dork_from_message(&conversation_id, &message_id, dork).await
+        .map_err(|e| format!("Failed to save dork: {}", e))
+}
+
+#[tauri::command]
+pub async fn list_dork_provenance(
+    dork_id: Option<String>,
+    vault: State<'_, Arc<VaultService>>,
+) -> Result<Vec<DorkProvenance>, String> {
+    vault.list_dork_provenance(dork_id.as_deref()).await
+        .map_err(|e| format!("Failed to get dork provenance: {}", e))
+}
+
+// Duplicate detection: clusters of dorks at or above `threshold` similarity
+#[tauri::command]
+pub async fn find_duplicate_dorks(
+    threshold: Option<f64>,
+    vault: State<'_, Arc<VaultService>>,
+) -> Result<Vec<DuplicateCluster>, String> {
+    vault.find_duplicate_dorks(threshold.unwrap_or(0.75)).await
+        .map_err(|e| format!("Failed to find duplicate dorks: {}", e))
+}
+
+#[tauri::command]
+pub async fn merge_dorks(
+    keep_id: String,
+    merge_ids: Vec<String>,
+    vault: State<'_, Arc<VaultService>>,
+) -> Result<DorkQuery, String> {
+    vault.merge_dorks(&keep_id, &merge_ids).await
+        .map_err(|e| format!("Failed to merge dorks: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_dork_merge_history(
+    id: String,
+    vault: State<'_, Arc<VaultService>>,
+) -> Result<Vec<DorkMergeRecord>, String> {
+    vault.get_dork_merge_history(&id).await
+        .map_err(|e| format!("Failed to get merge history: {}", e))
+}
+
+// Import dorks from GHDB dumps, mapped CSV or plain text lists
+#[tauri::command]
+pub async fn import_dorks(
+    content: String,
+    format: ImportFormat,
+    options: ImportOptions,
+    vault: State<'_, Arc<VaultService>>,
+) -> Result<ImportReport, String> {
+    vault.import_dorks(&content, &format, &options).await
+        .map_err(|e| format!("Failed to import dorks: {}", e))
+}
+
 // Export functionality
 #[tauri::command]
 pub async fn export_data(
@@ -214,14 +287,6 @@ pub async fn get_usage_stats(
         .map_err(|e| format!("Failed to get usage stats: {}", e))
 }
 
-#[tauri::command]
-pub async fn increment_ai_usage(
-    vault: State<'_, Arc<VaultService>>,
-) -> Result<i32, String> {
-    vault.increment_ai_usage().await
-        .map_err(|e| format!("Failed to increment AI usage: {}", e))
-}
-
 #[tauri::command]
 pub async fn can_generate_ai(
     vault: State<'_, Arc<VaultService>>,
@@ -272,16 +337,310 @@ pub async fn get_remaining_ai_generations(
         "free".to_string()
     };
 
+    let status = vault.quota_status(&tier).await
+        .map_err(|e| format!("Failed to get AI quota: {}", e))?;
+
     // Pro/Team/Enterprise: return -1 (unlimited)
-    if matches!(tier.as_str(), "professional" | "team" | "enterprise") {
-        return Ok(-1);
-    }
+    Ok(status.remaining.unwrap_or(-1))
+}
+
+#[tauri::command]
+pub async fn get_ai_quota_status(
+    vault: State<'_, Arc<VaultService>>,
+    license: State<'_, Arc<LicenseService>>,
+) -> Result<crate::vault::QuotaStatus, String> {
+    let license_info = license.get_license_info().await
+        .map_err(|e| format!("Failed to get license info: {}", e))?;
+
+    let tier = if license_info.activated && license_info.status == "active" {
+        license_info.tier
+    } else {
+        "free".to_string()
+    };
+
+    vault.quota_status(&tier).await
+        .map_err(|e| format!("Failed to get AI quota: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_ai_quota_config(
+    vault: State<'_, Arc<VaultService>>,
+) -> Result<crate::vault::QuotaConfig, String> {
+    vault.quota_config().await
+        .map_err(|e| format!("Failed to get AI quota config: {}", e))
+}
+
+#[tauri::command]
+pub async fn set_ai_quota_config(
+    config: crate::vault::QuotaConfig,
+    vault: State<'_, Arc<VaultService>>,
+) -> Result<(), String> {
+    vault.set_quota_config(&config).await
+        .map_err(|e| format!("Failed to save AI quota config: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_ai_cache_stats(
+    vault: State<'_, Arc<VaultService>>,
+) -> Result<crate::vault::CacheStats, String> {
+    vault.cache_stats().await
+        .map_err(|e| format!("Failed to get AI cache stats: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_ai_cache_config(
+    vault: State<'_, Arc<VaultService>>,
+) -> Result<crate::vault::CacheConfig, String> {
+    vault.cache_config().await
+        .map_err(|e| format!("Failed to get AI cache config: {}", e))
+}
+
+#[tauri::command]
+pub async fn set_ai_cache_config(
+    config: crate::vault::CacheConfig,
+    vault: State<'_, Arc<VaultService>>,
+) -> Result<(), String> {
+    vault.set_cache_config(&config).await
+        .map_err(|e| format!("Failed to save AI cache config: {}", e))
+}
+
+/// Drop every cached AI response; returns how many were removed
+#[tauri::command]
+pub async fn clear_ai_cache(
+    vault: State<'_, Arc<VaultService>>,
+) -> Result<u64, String> {
+    vault.clear_cache().await
+        .map_err(|e| format!("Failed to clear AI cache: {}", e))
+}
+
+/// AI usage rolled up by day or month, priced with the model cost table.
+/// `since` and `until` are inclusive `YYYY-MM-DD` dates.
+#[tauri::command]
+pub async fn get_usage_report(
+    period: crate::vault::UsagePeriod,
+    since: Option<String>,
+    until: Option<String>,
+    vault: State<'_, Arc<VaultService>>,
+) -> Result<crate::vault::UsageReport, String> {
+    vault.usage_report(period, since.as_deref(), until.as_deref()).await
+        .map_err(|e| format!("Failed to build usage report: {}", e))
+}
 
-    // Free tier: calculate remaining
-    let stats = vault.get_usage_stats().await
-        .map_err(|e| format!("Failed to get usage stats: {}", e))?;
+#[tauri::command]
+pub async fn get_model_costs(
+    vault: State<'_, Arc<VaultService>>,
+) -> Result<Vec<crate::vault::ModelCost>, String> {
+    vault.model_costs().await
+        .map_err(|e| format!("Failed to get model costs: {}", e))
+}
 
-    Ok(10 - stats.ai_generations_today)
+#[tauri::command]
+pub async fn set_model_costs(
+    costs: Vec<crate::vault::ModelCost>,
+    vault: State<'_, Arc<VaultService>>,
+) -> Result<(), String> {
+    vault.set_model_costs(&costs).await
+        .map_err(|e| format!("Failed to save model costs: {}", e))
+}
+
+// ========================================================================
+// AI GENERATION COMMANDS
+// ========================================================================
+
+#[tauri::command]
+pub async fn generate_dork(
+    request: DorkGenerationRequest,
+    workspace: Option<String>,
+    ai: State<'_, Arc<AiService>>,
+    license: State<'_, Arc<LicenseService>>,
+) -> Result<DorkGenerationResult, String> {
+    let license_info = license.get_license_info().await
+        .map_err(|e| format!("Failed to get license info: {}", e))?;
+
+    let tier = if license_info.activated && license_info.status == "active" {
+        license_info.tier
+    } else {
+        "free".to_string()
+    };
+
+    let workspace = workspace.as_deref().unwrap_or(DEFAULT_WORKSPACE);
+    ai.generate_dork(&request, workspace, &tier).await
+        .map_err(|e| format!("Failed to generate dork: {}", e))
+}
+
+/// Build a dork from the template library without an AI provider. Works
+/// offline and doesn't count against the AI quota.
+#[tauri::command]
+pub async fn generate_dork_offline(
+    request: DorkGenerationRequest,
+) -> Result<DorkGenerationResult, String> {
+    crate::ai::generate_offline(&request)
+        .map_err(|e| format!("Failed to generate dork: {}", e))
+}
+
+/// The token-bounded context a conversation is replayed to the model with.
+/// Older turns that no longer fit are summarized with the workspace's
+/// provider, which counts against the AI quota.
+#[tauri::command]
+pub async fn get_conversation_context(
+    conversation_id: String,
+    options: Option<ContextOptions>,
+    workspace: Option<String>,
+    ai: State<'_, Arc<AiService>>,
+    license: State<'_, Arc<LicenseService>>,
+) -> Result<ConversationContext, String> {
+    let license_info = license.get_license_info().await
+        .map_err(|e| format!("Failed to get license info: {}", e))?;
+
+    let tier = if license_info.activated && license_info.status == "active" {
+        license_info.tier
+    } else {
+        "free".to_string()
+    };
+
+    let workspace = workspace.as_deref().unwrap_or(DEFAULT_WORKSPACE);
+    ai.conversation_context(&conversation_id, workspace, &options.unwrap_or_default(), &tier).await
+        .map_err(|e| format!("Failed to assemble conversation context: {}", e))
+}
+
+/// Pin a message so it is always part of the conversation context
+#[tauri::command]
+pub async fn set_message_pinned(
+    conversation_id: String,
+    message_id: String,
+    pinned: bool,
+    vault: State<'_, Arc<VaultService>>,
+) -> Result<(), String> {
+    vault.set_message_pinned(&conversation_id, &message_id, pinned).await
+        .map_err(|e| format!("Failed to pin message: {}", e))
+}
+
+/// Event carrying `StreamEvent`s for streamed generations
+const AI_STREAM_EVENT: &str = "ai-stream";
+
+// Stream a dork generation into a conversation. Progress arrives as
+// `ai-stream` events tagged with `request_id`; resolves to `None` if cancelled.
+#[tauri::command]
+pub async fn stream_generate_dork(
+    request_id: String,
+    conversation_id: String,
+    request: DorkGenerationRequest,
+    workspace: Option<String>,
+    app: tauri::AppHandle,
+    ai: State<'_, Arc<AiService>>,
+    license: State<'_, Arc<LicenseService>>,
+) -> Result<Option<DorkGenerationResult>, String> {
+    let license_info = license.get_license_info().await
+        .map_err(|e| format!("Failed to get license info: {}", e))?;
+
+    let tier = if license_info.activated && license_info.status == "active" {
+        license_info.tier
+    } else {
+        "free".to_string()
+    };
+
+    let workspace = workspace.as_deref().unwrap_or(DEFAULT_WORKSPACE);
+    ai.stream_dork(&request_id, &conversation_id, &request, workspace, &tier, |event| {
+        if let Err(e) = app.emit(AI_STREAM_EVENT, &event) {
+            tracing::warn!("Failed to emit stream event: {}", e);
+        }
+    })
+    .await
+    .map_err(|e| format!("Failed to generate dork: {}", e))
+}
+
+#[tauri::command]
+pub async fn cancel_generation(
+    request_id: String,
+    ai: State<'_, Arc<AiService>>,
+) -> Result<bool, String> {
+    Ok(ai.cancel_generation(&request_id))
+}
+
+#[tauri::command]
+pub async fn get_ai_provider_config(
+    workspace: Option<String>,
+    ai: State<'_, Arc<AiService>>,
+) -> Result<ProviderConfig, String> {
+    ai.provider_config(workspace.as_deref().unwrap_or(DEFAULT_WORKSPACE)).await
+        .map_err(|e| format!("Failed to get AI provider: {}", e))
+}
+
+#[tauri::command]
+pub async fn set_ai_provider_config(
+    workspace: Option<String>,
+    config: ProviderConfig,
+    ai: State<'_, Arc<AiService>>,
+) -> Result<(), String> {
+    ai.set_provider_config(workspace.as_deref().unwrap_or(DEFAULT_WORKSPACE), &config).await
+        .map_err(|e| format!("Failed to set AI provider: {}", e))
+}
+
+// API key for a workspace's OpenAI-compatible endpoint; write-only like the
+// Gemini key
+#[tauri::command]
+pub async fn store_ai_provider_key(
+    workspace: Option<String>,
+    api_key: String,
+    security: State<'_, Arc<SecurityService>>,
+) -> Result<(), String> {
+    let workspace = workspace.as_deref().unwrap_or(DEFAULT_WORKSPACE);
+    crate::ai::validate_workspace(workspace).map_err(|e| e.to_string())?;
+
+    security.store_ai_provider_key(workspace, &api_key).await
+        .map_err(|e| format!("Failed to store API key: {}", e))
+}
+
+#[tauri::command]
+pub async fn delete_ai_provider_key(
+    workspace: Option<String>,
+    security: State<'_, Arc<SecurityService>>,
+) -> Result<(), String> {
+    let workspace = workspace.as_deref().unwrap_or(DEFAULT_WORKSPACE);
+    crate::ai::validate_workspace(workspace).map_err(|e| e.to_string())?;
+
+    security.delete_ai_provider_key(workspace).await
+        .map_err(|e| format!("Failed to delete API key: {}", e))
+}
+
+#[tauri::command]
+pub async fn list_prompt_templates(
+    ai: State<'_, Arc<AiService>>,
+) -> Result<Vec<PromptTemplate>, String> {
+    ai.prompt_templates().await
+        .map_err(|e| format!("Failed to list prompt templates: {}", e))
+}
+
+/// Get the active template, or a specific version to reproduce a past output
+#[tauri::command]
+pub async fn get_prompt_template(
+    name: String,
+    version: Option<String>,
+    ai: State<'_, Arc<AiService>>,
+) -> Result<PromptTemplate, String> {
+    ai.prompt_template(&name, version.as_deref()).await
+        .map_err(|e| format!("Failed to get prompt template: {}", e))
+}
+
+#[tauri::command]
+pub async fn set_prompt_override(
+    name: String,
+    template: String,
+    temperature: Option<f32>,
+    max_tokens: Option<u32>,
+    ai: State<'_, Arc<AiService>>,
+) -> Result<PromptTemplate, String> {
+    ai.set_prompt_override(&name, &template, temperature, max_tokens).await
+        .map_err(|e| format!("Failed to save prompt override: {}", e))
+}
+
+#[tauri::command]
+pub async fn reset_prompt_override(
+    name: String,
+    ai: State<'_, Arc<AiService>>,
+) -> Result<(), String> {
+    ai.reset_prompt_override(&name).await
+        .map_err(|e| format!("Failed to reset prompt override: {}", e))
 }
 
 // ========================================================================
@@ -315,6 +674,25 @@ pub async fn list_conversations(
         .map_err(|e| format!("Failed to list conversations: {}", e))
 }
 
+#[tauri::command]
+pub async fn append_message(
+    conversation_id: String,
+    message: crate::vault::Message,
+    vault: State<'_, Arc<VaultService>>,
+) -> Result<(), String> {
+    vault.append_message(&conversation_id, &message).await
+        .map_err(|e| format!("Failed to append message: {}", e))
+}
+
+#[tauri::command]
+pub async fn list_conversation_summaries(
+    limit: Option<i32>,
+    vault: State<'_, Arc<VaultService>>,
+) -> Result<Vec<crate::vault::ConversationSummary>, String> {
+    vault.list_conversation_summaries(limit).await
+        .map_err(|e| format!("Failed to list conversations: {}", e))
+}
+
 #[tauri::command]
 pub async fn delete_conversation(
     id: String,
@@ -324,6 +702,522 @@ pub async fn delete_conversation(
         .map_err(|e| format!("Failed to delete conversation: {}", e))
 }
 
+// ========================================================================
+// ENTITY GRAPH COMMANDS
+// ========================================================================
+
+/// Merge entities and relationships observed by one source into the graph
+#[tauri::command]
+pub async fn upsert_graph(
+    batch: GraphBatch,
+    workspace: Option<String>,
+    vault: State<'_, Arc<VaultService>>,
+) -> Result<GraphUpsertReport, String> {
+    let workspace = workspace.as_deref().unwrap_or(DEFAULT_WORKSPACE);
+    crate::ai::validate_workspace(workspace).map_err(|e| e.to_string())?;
+
+    vault.upsert_graph(workspace, &batch).await
+        .map_err(|e| format!("Failed to update graph: {}", e))
+}
+
+#[tauri::command]
+pub async fn load_graph(
+    filter: Option<GraphFilter>,
+    workspace: Option<String>,
+    vault: State<'_, Arc<VaultService>>,
+) -> Result<Subgraph, String> {
+    let stored = load_workspace_graph(&vault, workspace.as_deref()).await?;
+    match filter {
+        Some(filter) => graph::filter_graph(&stored, &filter)
+            .map_err(|e| format!("Failed to filter graph: {}", e)),
+        None => Ok(stored),
+    }
+}
+
+/// Entities within `hops` of an entity, optionally only of some types
+#[tauri::command]
+pub async fn get_graph_neighborhood(
+    entity_id: String,
+    hops: usize,
+    filter: Option<GraphFilter>,
+    result_types: Option<Vec<EntityType>>,
+    workspace: Option<String>,
+    vault: State<'_, Arc<VaultService>>,
+) -> Result<Subgraph, String> {
+    let stored = load_workspace_graph(&vault, workspace.as_deref()).await?;
+    graph::neighborhood(
+        &stored,
+        &entity_id,
+        hops,
+        &filter.unwrap_or_default(),
+        &result_types.unwrap_or_default(),
+    )
+    .map_err(|e| format!("Failed to get neighborhood: {}", e))
+}
+
+/// The fewest-hop path between two entities, or null if they aren't connected
+#[tauri::command]
+pub async fn find_graph_path(
+    from_id: String,
+    to_id: String,
+    filter: Option<GraphFilter>,
+    workspace: Option<String>,
+    vault: State<'_, Arc<VaultService>>,
+) -> Result<Option<Subgraph>, String> {
+    let stored = load_workspace_graph(&vault, workspace.as_deref()).await?;
+    graph::shortest_path(&stored, &from_id, &to_id, &filter.unwrap_or_default())
+        .map_err(|e| format!("Failed to find path: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_graph_components(
+    filter: Option<GraphFilter>,
+    workspace: Option<String>,
+    vault: State<'_, Arc<VaultService>>,
+) -> Result<Vec<Subgraph>, String> {
+    let stored = load_workspace_graph(&vault, workspace.as_deref()).await?;
+    graph::connected_components(&stored, &filter.unwrap_or_default())
+        .map_err(|e| format!("Failed to find components: {}", e))
+}
+
+/// Node positions for the workspace graph, computed off the async runtime
+#[tauri::command]
+pub async fn compute_graph_layout(
+    options: Option<LayoutOptions>,
+    filter: Option<GraphFilter>,
+    workspace: Option<String>,
+    vault: State<'_, Arc<VaultService>>,
+) -> Result<Vec<NodePosition>, String> {
+    let mut stored = load_workspace_graph(&vault, workspace.as_deref()).await?;
+    if let Some(filter) = filter {
+        stored = graph::filter_graph(&stored, &filter).map_err(|e| format!("Failed to filter graph: {}", e))?;
+    }
+
+    let options = options.unwrap_or_default();
+    tokio::task::spawn_blocking(move || graph::compute_layout(&stored, &options))
+        .await
+        .map_err(|e| format!("Layout task failed: {}", e))?
+        .map_err(|e| format!("Failed to compute layout: {}", e))
+}
+
+/// Write the workspace graph as GraphML, GEXF or Maltego entity/link tables
+#[tauri::command]
+pub async fn export_graph(
+    format: GraphFormat,
+    filter: Option<GraphFilter>,
+    workspace: Option<String>,
+    vault: State<'_, Arc<VaultService>>,
+) -> Result<GraphDocument, String> {
+    let mut stored = load_workspace_graph(&vault, workspace.as_deref()).await?;
+    if let Some(filter) = filter {
+        stored = graph::filter_graph(&stored, &filter).map_err(|e| format!("Failed to filter graph: {}", e))?;
+    }
+
+    graph::export_graph(&stored, format)
+        .map_err(|e| format!("Failed to export graph: {}", e))
+}
+
+/// Merge a GraphML, GEXF or Maltego export into the workspace graph, with
+/// `name` (usually the file name) recorded as the source
+#[tauri::command]
+pub async fn import_graph(
+    document: GraphDocument,
+    name: String,
+    workspace: Option<String>,
+    vault: State<'_, Arc<VaultService>>,
+) -> Result<GraphImportReport, String> {
+    let workspace = workspace.as_deref().unwrap_or(DEFAULT_WORKSPACE);
+    crate::ai::validate_workspace(workspace).map_err(|e| e.to_string())?;
+
+    let source = GraphSource { kind: SourceKind::Import, reference: name };
+    let imported = graph::import_graph(&document, source)
+        .map_err(|e| format!("Failed to read graph: {}", e))?;
+    let merged = vault.upsert_graph(workspace, &imported.batch).await
+        .map_err(|e| format!("Failed to import graph: {}", e))?;
+
+    Ok(GraphImportReport { merged, warnings: imported.warnings })
+}
+
+async fn load_workspace_graph(vault: &VaultService, workspace: Option<&str>) -> Result<Subgraph, String> {
+    let workspace = workspace.unwrap_or(DEFAULT_WORKSPACE);
+    crate::ai::validate_workspace(workspace).map_err(|e| e.to_string())?;
+
+    vault.load_graph(workspace).await
+        .map_err(|e| format!("Failed to load graph: {}", e))
+}
+
+/// An entity and its direct neighbors, optionally along some relationship types
+#[tauri::command]
+pub async fn get_entity_neighbors(
+    entity_id: String,
+    relation_types: Option<Vec<RelationType>>,
+    workspace: Option<String>,
+    vault: State<'_, Arc<VaultService>>,
+) -> Result<Subgraph, String> {
+    let workspace = workspace.as_deref().unwrap_or(DEFAULT_WORKSPACE);
+    crate::ai::validate_workspace(workspace).map_err(|e| e.to_string())?;
+
+    vault.entity_neighbors(workspace, &entity_id, &relation_types.unwrap_or_default()).await
+        .map_err(|e| format!("Failed to get entity neighbors: {}", e))
+}
+
+#[tauri::command]
+pub async fn find_graph_entity(
+    key: EntityKey,
+    workspace: Option<String>,
+    vault: State<'_, Arc<VaultService>>,
+) -> Result<Option<Entity>, String> {
+    let workspace = workspace.as_deref().unwrap_or(DEFAULT_WORKSPACE);
+    crate::ai::validate_workspace(workspace).map_err(|e| e.to_string())?;
+
+    vault.find_entity(workspace, &key).await
+        .map_err(|e| format!("Failed to find entity: {}", e))
+}
+
+#[tauri::command]
+pub async fn clear_graph(
+    workspace: Option<String>,
+    vault: State<'_, Arc<VaultService>>,
+) -> Result<usize, String> {
+    let workspace = workspace.as_deref().unwrap_or(DEFAULT_WORKSPACE);
+    crate::ai::validate_workspace(workspace).map_err(|e| e.to_string())?;
+
+    vault.clear_graph(workspace).await
+        .map_err(|e| format!("Failed to clear graph: {}", e))
+}
+
+// ========================================================================
+// INDICATOR EXTRACTION COMMANDS
+// ========================================================================
+
+/// Extract domains, URLs, IPs, emails and hashes from free text
+#[tauri::command]
+pub async fn extract_indicators(text: String) -> Result<Vec<Indicator>, String> {
+    Ok(indicators::extract_indicators(&text))
+}
+
+/// Add the indicators found in a message to the entity graph
+#[tauri::command]
+pub async fn record_indicators(
+    text: String,
+    conversation_id: String,
+    workspace: Option<String>,
+    vault: State<'_, Arc<VaultService>>,
+) -> Result<GraphUpsertReport, String> {
+    let workspace = workspace.as_deref().unwrap_or(DEFAULT_WORKSPACE);
+    crate::ai::validate_workspace(workspace).map_err(|e| e.to_string())?;
+
+    let source = GraphSource {
+        kind: SourceKind::Ai,
+        reference: conversation_id,
+    };
+    let batch = indicators::indicator_batch(&indicators::extract_indicators(&text), source);
+    vault.upsert_graph(workspace, &batch).await
+        .map_err(|e| format!("Failed to record indicators: {}", e))
+}
+
+// ========================================================================
+// ENRICHMENT COMMANDS
+// ========================================================================
+
+#[tauri::command]
+pub async fn get_dns_config(vault: State<'_, Arc<VaultService>>) -> Result<DnsConfig, String> {
+    enrichment::dns_config(&vault).await
+        .map_err(|e| format!("Failed to get DNS settings: {}", e))
+}
+
+#[tauri::command]
+pub async fn set_dns_config(config: DnsConfig, vault: State<'_, Arc<VaultService>>) -> Result<(), String> {
+    enrichment::set_dns_config(&vault, &config).await
+        .map_err(|e| format!("Failed to save DNS settings: {}", e))
+}
+
+/// Resolve domains and record their addresses, aliases, mail and name
+/// servers in the workspace graph
+#[tauri::command]
+pub async fn enrich_dns(
+    names: Vec<String>,
+    workspace: Option<String>,
+    vault: State<'_, Arc<VaultService>>,
+) -> Result<DnsReport, String> {
+    let workspace = workspace.as_deref().unwrap_or(DEFAULT_WORKSPACE);
+    crate::ai::validate_workspace(workspace).map_err(|e| e.to_string())?;
+
+    enrichment::enrich_dns(&vault, workspace, &names).await
+        .map_err(|e| format!("Failed to resolve domains: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_ct_config(vault: State<'_, Arc<VaultService>>) -> Result<CertTransparencyConfig, String> {
+    enrichment::ct_config(&vault).await
+        .map_err(|e| format!("Failed to get certificate transparency settings: {}", e))
+}
+
+#[tauri::command]
+pub async fn set_ct_config(
+    config: CertTransparencyConfig,
+    vault: State<'_, Arc<VaultService>>,
+) -> Result<(), String> {
+    enrichment::set_ct_config(&vault, &config).await
+        .map_err(|e| format!("Failed to save certificate transparency settings: {}", e))
+}
+
+/// Discover subdomains from certificate transparency logs and record them
+/// in the workspace graph
+#[tauri::command]
+pub async fn enrich_certificate_transparency(
+    domain: String,
+    workspace: Option<String>,
+    vault: State<'_, Arc<VaultService>>,
+) -> Result<CtReport, String> {
+    let workspace = workspace.as_deref().unwrap_or(DEFAULT_WORKSPACE);
+    crate::ai::validate_workspace(workspace).map_err(|e| e.to_string())?;
+
+    enrichment::enrich_certificate_transparency(&vault, workspace, &domain).await
+        .map_err(|e| format!("Failed to search certificate transparency logs: {}", e))
+}
+
+#[tauri::command]
+pub async fn list_enrichment_connectors(
+    enrichment: State<'_, Arc<EnrichmentService>>,
+) -> Result<Vec<ConnectorInfo>, String> {
+    enrichment.connectors().await
+        .map_err(|e| format!("Failed to list connectors: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_connector_settings(
+    connector: String,
+    enrichment: State<'_, Arc<EnrichmentService>>,
+) -> Result<ConnectorSettings, String> {
+    enrichment.connector_settings(&connector).await
+        .map_err(|e| format!("Failed to get connector settings: {}", e))
+}
+
+#[tauri::command]
+pub async fn set_connector_settings(
+    connector: String,
+    settings: ConnectorSettings,
+    enrichment: State<'_, Arc<EnrichmentService>>,
+) -> Result<(), String> {
+    enrichment.set_connector_settings(&connector, &settings).await
+        .map_err(|e| format!("Failed to save connector settings: {}", e))
+}
+
+// Connector API keys are write-only, like the AI provider keys
+#[tauri::command]
+pub async fn store_connector_key(
+    connector: String,
+    api_key: String,
+    security: State<'_, Arc<SecurityService>>,
+) -> Result<(), String> {
+    security.store_connector_key(&connector, &api_key).await
+        .map_err(|e| format!("Failed to store API key: {}", e))
+}
+
+#[tauri::command]
+pub async fn delete_connector_key(
+    connector: String,
+    security: State<'_, Arc<SecurityService>>,
+) -> Result<(), String> {
+    security.delete_connector_key(&connector).await
+        .map_err(|e| format!("Failed to delete API key: {}", e))
+}
+
+/// Event carrying `JobProgress` for running enrichment jobs
+const ENRICHMENT_PROGRESS_EVENT: &str = "enrichment-progress";
+
+// Run entities through a connector. Progress arrives as
+// `enrichment-progress` events tagged with `job_id`.
+#[tauri::command]
+pub async fn run_enrichment_job(
+    job_id: String,
+    job: EnrichmentJob,
+    workspace: Option<String>,
+    app: tauri::AppHandle,
+    enrichment: State<'_, Arc<EnrichmentService>>,
+) -> Result<JobReport, String> {
+    let workspace = workspace.as_deref().unwrap_or(DEFAULT_WORKSPACE);
+    crate::ai::validate_workspace(workspace).map_err(|e| e.to_string())?;
+
+    enrichment.run_job(&job_id, &job, workspace, |progress| {
+        if let Err(e) = app.emit(ENRICHMENT_PROGRESS_EVENT, &progress) {
+            tracing::warn!("Failed to emit enrichment progress: {}", e);
+        }
+    })
+    .await
+    .map_err(|e| format!("Failed to run enrichment: {}", e))
+}
+
+/// Drop cached connector responses, of one connector or all of them
+#[tauri::command]
+pub async fn clear_enrichment_cache(
+    connector: Option<String>,
+    vault: State<'_, Arc<VaultService>>,
+) -> Result<usize, String> {
+    vault.clear_enrichment_cache(connector.as_deref()).await
+        .map_err(|e| format!("Failed to clear enrichment cache: {}", e))
+}
+
+// ========================================================================
+// IMAGE FORENSICS COMMANDS
+// ========================================================================
+
+/// EXIF, GPS, XMP, IPTC, thumbnails and ICC profile of an image's bytes
+#[tauri::command]
+pub async fn extract_image_metadata(data: Vec<u8>) -> Result<ImageMetadata, String> {
+    tokio::task::spawn_blocking(move || imaging::extract_metadata(&data))
+        .await
+        .map_err(|e| format!("Metadata task failed: {}", e))?
+        .map_err(|e| format!("Failed to extract image metadata: {}", e))
+}
+
+#[derive(Debug, Serialize)]
+pub struct SanitizedImage {
+    pub path: String,
+    #[serde(flatten)]
+    pub report: ScrubReport,
+}
+
+/// Write a copy of an image without its metadata to Documents/Parallax/Sanitized
+#[tauri::command]
+pub async fn scrub_image_metadata(
+    data: Vec<u8>,
+    file_name: String,
+    options: Option<ScrubOptions>,
+) -> Result<SanitizedImage, String> {
+    let options = options.unwrap_or_default();
+    let (sanitized, report) = tokio::task::spawn_blocking(move || imaging::scrub_metadata(&data, &options))
+        .await
+        .map_err(|e| format!("Sanitize task failed: {}", e))?
+        .map_err(|e| format!("Failed to sanitize image: {}", e))?;
+
+    let docs_dir = dirs::document_dir()
+        .ok_or_else(|| "Could not determine documents directory".to_string())?;
+
+    let output_dir = docs_dir.join("Parallax").join("Sanitized");
+    std::fs::create_dir_all(&output_dir)
+        .map_err(|e| format!("Failed to create output directory: {}", e))?;
+
+    let base_name = sanitized_file_name(&file_name);
+    let file_path = write_new_file(&output_dir, &base_name, report.format.extension(), &sanitized)
+        .map_err(|e| format!("Failed to write sanitized image: {}", e))?;
+
+    Ok(SanitizedImage {
+        path: file_path.to_string_lossy().to_string(),
+        report,
+    })
+}
+
+/// Write `<base>.<extension>` in `dir`, never over an existing file; a name
+/// already taken gets a numeric suffix
+fn write_new_file(
+    dir: &std::path::Path,
+    base: &str,
+    extension: &str,
+    data: &[u8],
+) -> std::io::Result<std::path::PathBuf> {
+    use std::io::Write;
+
+    for attempt in 1..=100 {
+        let name = match attempt {
+            1 => format!("{}.{}", base, extension),
+            n => format!("{}-{}.{}", base, n, extension),
+        };
+        let path = dir.join(name);
+        match std::fs::OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(mut file) => {
+                file.write_all(data)?;
+                return Ok(path);
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Err(std::io::Error::new(std::io::ErrorKind::AlreadyExists, format!("No free file name for {}", base)))
+}
+
+/// `<stem>-sanitized-<timestamp>`, with characters unsafe in file names replaced
+fn sanitized_file_name(file_name: &str) -> String {
+    let stem: String = std::path::Path::new(file_name)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy())
+        .unwrap_or_default()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '-' | '_') { c } else { '_' })
+        .take(64)
+        .collect();
+    let stem = if stem.is_empty() { "image".to_string() } else { stem };
+    format!("{}-sanitized-{}", stem, chrono::Utc::now().format("%Y%m%d-%H%M%S"))
+}
+
+// ========================================================================
+// BACKUP & RESTORE COMMANDS
+// ========================================================================
+
+/// Write a full vault backup to Documents/Parallax/Backups and return its path
+#[tauri::command]
+pub async fn create_backup(
+    passphrase: Option<String>,
+    vault: State<'_, Arc<VaultService>>,
+) -> Result<String, String> {
+    let archive = vault.create_backup(passphrase.as_deref()).await
+        .map_err(|e| format!("Failed to create backup: {}", e))?;
+
+    let docs_dir = dirs::document_dir()
+        .ok_or_else(|| "Could not determine documents directory".to_string())?;
+
+    let backup_dir = docs_dir.join("Parallax").join("Backups");
+    std::fs::create_dir_all(&backup_dir)
+        .map_err(|e| format!("Failed to create backup directory: {}", e))?;
+
+    let base_name = format!("parallax-backup-{}", chrono::Utc::now().format("%Y%m%d-%H%M%S"));
+
+    let json = archive.to_json()
+        .map_err(|e| format!("Failed to serialize backup: {}", e))?;
+    let file_path = write_new_file(&backup_dir, &base_name, "pxbak", json.as_bytes())
+        .map_err(|e| format!("Failed to write backup file: {}", e))?;
+
+    Ok(file_path.to_string_lossy().to_string())
+}
+
+/// Read a backup's manifest without decrypting or restoring it
+#[tauri::command]
+pub async fn inspect_backup(
+    path: String,
+) -> Result<serde_json::Value, String> {
+    let archive = read_backup_file(&path)?;
+
+    Ok(serde_json::json!({
+        "version": archive.version,
+        "app_version": archive.app_version,
+        "created_at": archive.created_at,
+        "encrypted": archive.is_encrypted(),
+        "manifest": archive.manifest,
+    }))
+}
+
+#[tauri::command]
+pub async fn restore_backup(
+    path: String,
+    passphrase: Option<String>,
+    mode: RestoreMode,
+    vault: State<'_, Arc<VaultService>>,
+) -> Result<RestoreReport, String> {
+    let archive = read_backup_file(&path)?;
+
+    vault.restore_backup(&archive, passphrase.as_deref(), mode).await
+        .map_err(|e| format!("Failed to restore backup: {}", e))
+}
+
+fn read_backup_file(path: &str) -> Result<BackupArchive, String> {
+    let json = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read backup file: {}", e))?;
+
+    BackupArchive::from_json(&json)
+        .map_err(|e| format!("Invalid backup file: {}", e))
+}
+
 // ========================================================================
 // ENHANCED LICENSE COMMANDS
 // ========================================================================
@@ -0,0 +1,492 @@
+//! Dork query text helpers shared by import, deduplication and linting.
+//!
+//! Queries are parsed into units (operators, phrases, words, `OR` chains and
+//! parenthesized groups) so that two queries differing only in operator
+//! order, operator case, quoting style or whitespace normalize to the same
+//! canonical string. Google matching is case-insensitive, so the canonical
+//! form is folded to lower case.
+
+mod templates;
+
+pub use templates::{template_library, DorkTemplate, PLACEHOLDER_DOMAIN};
+
+use crate::vault::DorkQuery;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+
+/// Operators Google treats as synonyms, mapped to one canonical name
+const OPERATOR_ALIASES: &[(&str, &str)] = &[("ext", "filetype")];
+
+/// Operator names understood by at least one supported search engine
+const KNOWN_OPERATORS: &[&str] = &[
+    "after", "allinanchor", "allintext", "allintitle", "allinurl", "before", "cache", "contains",
+    "define", "domain", "ext", "feed", "filetype", "hasfeed", "inanchor", "inbody", "info",
+    "instreamset", "intext", "intitle", "inurl", "ip", "language", "link", "loc", "location",
+    "related", "site", "source", "url",
+];
+
+/// Google ignores terms past this many words
+const MAX_QUERY_WORDS: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LintLevel {
+    /// The query is malformed and won't search as intended
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LintIssue {
+    pub level: LintLevel,
+    pub message: String,
+}
+
+impl LintIssue {
+    fn error(message: impl Into<String>) -> Self {
+        Self { level: LintLevel::Error, message: message.into() }
+    }
+
+    fn warning(message: impl Into<String>) -> Self {
+        Self { level: LintLevel::Warning, message: message.into() }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateCluster {
+    pub canonical_query: String,
+    /// True when every member normalizes to the same canonical query
+    pub exact: bool,
+    /// Lowest pairwise similarity linking the cluster together
+    pub min_similarity: f64,
+    pub dorks: Vec<DorkQuery>,
+}
+
+/// Normalize a dork query to its canonical form for comparison
+pub fn normalize_query(query: &str) -> String {
+    canonical_units(query).join(" ")
+}
+
+/// Dice similarity over canonical query units, from 0.0 to 1.0
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let a: BTreeSet<String> = canonical_units(a).into_iter().collect();
+    let b: BTreeSet<String> = canonical_units(b).into_iter().collect();
+    unit_similarity(&a, &b)
+}
+
+/// Group dorks whose queries are identical after normalization or at least
+/// `threshold` similar. Singletons are omitted.
+pub fn find_duplicate_clusters(dorks: &[DorkQuery], threshold: f64) -> Vec<DuplicateCluster> {
+    let units: Vec<BTreeSet<String>> = dorks
+        .iter()
+        .map(|d| canonical_units(&d.query).into_iter().collect())
+        .collect();
+
+    // Only compare dorks that share at least one unit
+    let mut index: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (i, set) in units.iter().enumerate() {
+        for unit in set {
+            index.entry(unit.as_str()).or_default().push(i);
+        }
+    }
+
+    let mut parent: Vec<usize> = (0..dorks.len()).collect();
+    let mut min_link: HashMap<usize, f64> = HashMap::new();
+    // The last dork each candidate was compared against, so a pair sharing
+    // several units is scored once without remembering every pair
+    let mut compared_with = vec![usize::MAX; dorks.len()];
+
+    for i in 0..dorks.len() {
+        for unit in &units[i] {
+            for &j in &index[unit.as_str()] {
+                if j <= i || compared_with[j] == i {
+                    continue;
+                }
+                compared_with[j] = i;
+
+                let (ri, rj) = (find(&mut parent, i), find(&mut parent, j));
+                if ri == rj {
+                    continue;
+                }
+
+                let score = unit_similarity(&units[i], &units[j]);
+                if score >= threshold {
+                    let link = min_link
+                        .remove(&ri)
+                        .into_iter()
+                        .chain(min_link.remove(&rj))
+                        .fold(score, f64::min);
+                    parent[rj] = ri;
+                    min_link.insert(ri, link);
+                }
+            }
+        }
+    }
+
+    let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+    for i in 0..dorks.len() {
+        let root = find(&mut parent, i);
+        groups.entry(root).or_default().push(i);
+    }
+
+    let mut clusters: Vec<DuplicateCluster> = groups
+        .into_iter()
+        .filter(|(_, members)| members.len() > 1)
+        .map(|(root, members)| {
+            let canonical_query = normalize_query(&dorks[members[0]].query);
+            let exact = members
+                .iter()
+                .all(|&m| normalize_query(&dorks[m].query) == canonical_query);
+
+            DuplicateCluster {
+                canonical_query,
+                exact,
+                min_similarity: min_link.get(&root).copied().unwrap_or(1.0),
+                dorks: members.iter().map(|&m| dorks[m].clone()).collect(),
+            }
+        })
+        .collect();
+
+    clusters.sort_by(|a, b| {
+        b.dorks.len()
+            .cmp(&a.dorks.len())
+            .then_with(|| a.canonical_query.cmp(&b.canonical_query))
+    });
+
+    clusters
+}
+
+/// Check a query for syntax problems: unbalanced quotes or parentheses,
+/// empty or unknown operators, dangling `OR`s and stray formatting
+pub fn lint_query(query: &str) -> Vec<LintIssue> {
+    let mut issues = Vec::new();
+    let query = query.trim();
+
+    if query.is_empty() {
+        issues.push(LintIssue::error("Query is empty"));
+        return issues;
+    }
+    if query.contains('\n') {
+        issues.push(LintIssue::error("Query spans multiple lines"));
+    }
+    if query.contains('`') {
+        issues.push(LintIssue::error("Query contains markdown formatting"));
+    }
+
+    let unified: String = query.chars().map(unify_quotes).collect();
+    if unified.matches('"').count() % 2 != 0 {
+        issues.push(LintIssue::error("Unbalanced double quotes"));
+    }
+
+    let chars: Vec<char> = unified.chars().collect();
+    let mut pos = 0;
+    let mut depth = 0i32;
+    let mut words = 0;
+    let mut previous_was_boolean = true;
+    let mut dangling_boolean = false;
+
+    while let Some(token) = next_token(&chars, &mut pos) {
+        match token.as_str() {
+            "(" => {
+                depth += 1;
+                previous_was_boolean = true;
+                continue;
+            }
+            ")" => {
+                depth -= 1;
+                if depth < 0 {
+                    issues.push(LintIssue::error("Closing parenthesis without a matching opening one"));
+                    depth = 0;
+                }
+                continue;
+            }
+            "OR" | "or" | "|" | "AND" | "and" | "&" => {
+                dangling_boolean |= previous_was_boolean;
+                previous_was_boolean = true;
+                continue;
+            }
+            _ => {}
+        }
+        previous_was_boolean = false;
+        words += token.split_whitespace().count();
+
+        if let Some((name, value)) = token.split_once(':') {
+            let bare_name = name.strip_prefix('-').unwrap_or(name);
+            let is_operator = !bare_name.is_empty()
+                && bare_name.chars().all(|c| c.is_ascii_alphabetic())
+                && !value.starts_with("//");
+            if is_operator {
+                let lower = bare_name.to_ascii_lowercase();
+                if value.is_empty() || value == "\"\"" {
+                    issues.push(LintIssue::error(format!("Operator {}: has no value", lower)));
+                }
+                if !KNOWN_OPERATORS.contains(&lower.as_str()) {
+                    issues.push(LintIssue::warning(format!("Unknown operator {}:", lower)));
+                }
+            }
+        }
+    }
+
+    if depth > 0 {
+        issues.push(LintIssue::error("Unclosed parenthesis"));
+    }
+    if dangling_boolean || previous_was_boolean {
+        issues.push(LintIssue::warning("OR/AND without a term on both sides"));
+    }
+    if words > MAX_QUERY_WORDS {
+        issues.push(LintIssue::warning(format!(
+            "Query has {} words; Google ignores terms past {}",
+            words, MAX_QUERY_WORDS
+        )));
+    }
+
+    issues
+}
+
+fn find(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+fn unit_similarity(a: &BTreeSet<String>, b: &BTreeSet<String>) -> f64 {
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    let shared = a.intersection(b).count();
+    (2 * shared) as f64 / (a.len() + b.len()) as f64
+}
+
+/// Parse a query into canonical, sorted units
+fn canonical_units(query: &str) -> Vec<String> {
+    let unified: String = query
+        .chars()
+        .map(|c| {
+            let c = unify_quotes(c);
+            c.to_lowercase().next().unwrap_or(c)
+        })
+        .collect();
+
+    let chars: Vec<char> = unified.chars().collect();
+    let mut pos = 0;
+    parse_units(&chars, &mut pos)
+}
+
+/// Map typographic quotes to their ASCII forms
+fn unify_quotes(c: char) -> char {
+    match c {
+        '\u{201C}' | '\u{201D}' | '\u{201E}' | '\u{2033}' => '"',
+        '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{2032}' => '\'',
+        other => other,
+    }
+}
+
+/// Parse terms until end of input or a closing parenthesis, merging `OR`
+/// chains into single units and sorting the result
+fn parse_units(chars: &[char], pos: &mut usize) -> Vec<String> {
+    let mut chains: Vec<Vec<String>> = Vec::new();
+    let mut pending_or = false;
+
+    while let Some(token) = next_token(chars, pos) {
+        let term = match token.as_str() {
+            ")" => break,
+            "or" | "|" => {
+                pending_or = !chains.is_empty();
+                continue;
+            }
+            "and" | "&" => continue,
+            "(" => {
+                let inner = parse_units(chars, pos);
+                if inner.is_empty() {
+                    continue;
+                }
+                format!("({})", inner.join(" "))
+            }
+            _ => canonical_term(&token),
+        };
+
+        match chains.last_mut() {
+            Some(chain) if pending_or => chain.push(term),
+            _ => chains.push(vec![term]),
+        }
+        pending_or = false;
+    }
+
+    let mut units: Vec<String> = chains
+        .into_iter()
+        .map(|mut chain| {
+            chain.sort();
+            chain.dedup();
+            chain.join(" or ")
+        })
+        .collect();
+
+    units.sort_by(|a, b| {
+        let a_op = is_operator(a);
+        let b_op = is_operator(b);
+        b_op.cmp(&a_op).then_with(|| a.cmp(b))
+    });
+    units.dedup();
+    units
+}
+
+/// Read the next token: a parenthesis, or a run of non-space characters in
+/// which double-quoted sections may contain spaces
+fn next_token(chars: &[char], pos: &mut usize) -> Option<String> {
+    while *pos < chars.len() && chars[*pos].is_whitespace() {
+        *pos += 1;
+    }
+    if *pos >= chars.len() {
+        return None;
+    }
+
+    if chars[*pos] == '(' || chars[*pos] == ')' {
+        *pos += 1;
+        return Some(chars[*pos - 1].to_string());
+    }
+
+    let mut token = String::new();
+    let mut in_quotes = false;
+    while *pos < chars.len() {
+        let c = chars[*pos];
+        if !in_quotes && (c.is_whitespace() || c == '(' || c == ')') {
+            break;
+        }
+        if c == '"' {
+            in_quotes = !in_quotes;
+        }
+        token.push(c);
+        *pos += 1;
+    }
+
+    Some(token)
+}
+
+fn canonical_term(token: &str) -> String {
+    if let Some((name, value)) = token.split_once(':') {
+        let bare_name = name.strip_prefix('-').unwrap_or(name);
+        if !bare_name.is_empty() && bare_name.chars().all(|c| c.is_ascii_alphabetic()) {
+            let canonical_name = OPERATOR_ALIASES
+                .iter()
+                .find(|(alias, _)| *alias == bare_name)
+                .map(|(_, canonical)| *canonical)
+                .unwrap_or(bare_name);
+            let negation = if name.starts_with('-') { "-" } else { "" };
+            return format!("{}{}:{}", negation, canonical_name, canonical_value(value));
+        }
+    }
+
+    canonical_value(token)
+}
+
+/// Drop quotes that don't change meaning (single-word values)
+fn canonical_value(value: &str) -> String {
+    let (negation, rest) = match value.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", value),
+    };
+
+    match rest.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+        Some(inner) => {
+            let inner = inner.split_whitespace().collect::<Vec<_>>().join(" ");
+            if inner.contains(' ') || inner.is_empty() {
+                format!("{}\"{}\"", negation, inner)
+            } else {
+                format!("{}{}", negation, inner)
+            }
+        }
+        None => value.to_string(),
+    }
+}
+
+fn is_operator(unit: &str) -> bool {
+    match unit.split_once(':') {
+        Some((name, _)) => {
+            let name = name.strip_prefix('-').unwrap_or(name);
+            !name.is_empty() && name.chars().all(|c| c.is_ascii_alphabetic())
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dork(id: &str, query: &str) -> DorkQuery {
+        DorkQuery {
+            id: id.to_string(),
+            name: id.to_string(),
+            query: query.to_string(),
+            category: "test".to_string(),
+            tags: vec![],
+            created_at: String::new(),
+            updated_at: None,
+        }
+    }
+
+    #[test]
+    fn test_normalize_query() {
+        assert_eq!(
+            normalize_query("  INTITLE:\u{201C}Index of\u{201D}   Site:\"example.com\" "),
+            "intitle:\"index of\" site:example.com"
+        );
+        assert_eq!(
+            normalize_query("filetype:env site:a.com"),
+            normalize_query("Site:A.com   EXT:env")
+        );
+        assert_eq!(
+            normalize_query("(site:b.com | site:a.com) password"),
+            normalize_query("password (site:a.com OR site:b.com)")
+        );
+        assert_ne!(normalize_query("site:a.com -inurl:admin"), normalize_query("site:a.com inurl:admin"));
+    }
+
+    #[test]
+    fn test_duplicate_clusters() {
+        let dorks = vec![
+            dork("a", "site:example.com filetype:env"),
+            dork("b", "filetype:env  SITE:example.com"),
+            dork("c", "site:example.com filetype:env intext:DB_PASSWORD"),
+            dork("d", "inurl:admin intitle:login"),
+        ];
+
+        assert!((similarity(&dorks[0].query, &dorks[2].query) - 0.8).abs() < 1e-9);
+
+        let exact = find_duplicate_clusters(&dorks, 1.0);
+        assert_eq!(exact.len(), 1);
+        assert!(exact[0].exact);
+        assert_eq!(exact[0].dorks.len(), 2);
+
+        let near = find_duplicate_clusters(&dorks, 0.75);
+        assert_eq!(near.len(), 1);
+        assert_eq!(near[0].dorks.len(), 3);
+        assert!(!near[0].exact);
+        assert!((near[0].min_similarity - 0.8).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_lint_query() {
+        assert!(lint_query("site:example.com (ext:env | ext:ini) intext:\"DB_PASSWORD\"").is_empty());
+        assert!(lint_query("inurl:https://example.com/login").is_empty());
+
+        let errors = |query: &str| -> Vec<String> {
+            lint_query(query)
+                .into_iter()
+                .filter(|issue| issue.level == LintLevel::Error)
+                .map(|issue| issue.message)
+                .collect()
+        };
+        assert_eq!(errors("   "), vec!["Query is empty"]);
+        assert_eq!(errors("intitle:\"index of"), vec!["Unbalanced double quotes"]);
+        assert_eq!(errors("(site:a.com OR site:b.com"), vec!["Unclosed parenthesis"]);
+        assert_eq!(errors("site: password"), vec!["Operator site: has no value"]);
+        assert_eq!(errors("`inurl:admin`"), vec!["Query contains markdown formatting"]);
+
+        let warnings = lint_query("intitle:login OR filename:passwd");
+        assert_eq!(warnings, vec![LintIssue::warning("Unknown operator filename:")]);
+        assert_eq!(lint_query("inurl:admin OR").len(), 1);
+    }
+}
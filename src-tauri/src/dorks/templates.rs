@@ -0,0 +1,121 @@
+//! The dork template library, read from the frontend's
+//! `src/data/dorkTemplates.ts` at compile time so both sides share one list.
+//!
+//! The file is a plain array of object literals with single-quoted strings,
+//! which is all the parser here understands.
+
+use crate::ai::Severity;
+use std::sync::OnceLock;
+
+const TEMPLATE_SOURCE: &str = include_str!("../../../src/data/dorkTemplates.ts");
+
+/// Placeholder domain used by templates that need a target
+pub const PLACEHOLDER_DOMAIN: &str = "example.com";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DorkTemplate {
+    pub id: String,
+    pub name: String,
+    pub query: String,
+    pub category: String,
+    pub description: String,
+    pub severity: Severity,
+    pub tags: Vec<String>,
+    pub legal_warning: bool,
+}
+
+impl DorkTemplate {
+    /// True when the query only makes sense with a target domain filled in
+    pub fn needs_target(&self) -> bool {
+        self.query.contains(PLACEHOLDER_DOMAIN)
+    }
+}
+
+/// Every template in the library, parsed once
+pub fn template_library() -> &'static [DorkTemplate] {
+    static LIBRARY: OnceLock<Vec<DorkTemplate>> = OnceLock::new();
+    LIBRARY.get_or_init(|| parse_templates(TEMPLATE_SOURCE))
+}
+
+fn parse_templates(source: &str) -> Vec<DorkTemplate> {
+    let mut templates = Vec::new();
+    let mut fields: Option<Vec<(&str, &str)>> = None;
+
+    for line in source.lines().map(str::trim) {
+        if line == "{" {
+            fields = Some(Vec::new());
+        } else if line == "}," || line == "}" {
+            if let Some(template) = fields.take().and_then(|fields| template_from_fields(&fields)) {
+                templates.push(template);
+            }
+        } else if let Some(fields) = fields.as_mut() {
+            if let Some((key, value)) = line.split_once(':') {
+                fields.push((key.trim(), value.trim().trim_end_matches(',')));
+            }
+        }
+    }
+
+    templates
+}
+
+fn template_from_fields(fields: &[(&str, &str)]) -> Option<DorkTemplate> {
+    let field = |name: &str| fields.iter().find(|(key, _)| *key == name).map(|(_, value)| *value);
+    let string = |name: &str| field(name).and_then(string_literal);
+
+    Some(DorkTemplate {
+        id: string("id")?,
+        name: string("name")?,
+        query: string("query")?,
+        category: string("category")?,
+        description: string("description").unwrap_or_default(),
+        severity: Severity::parse(&string("severity")?)?,
+        tags: field("tags").map(string_array).unwrap_or_default(),
+        legal_warning: field("legalWarning") == Some("true"),
+    })
+}
+
+/// Unescape a `'single quoted'` TypeScript string
+fn string_literal(literal: &str) -> Option<String> {
+    let inner = literal.strip_prefix('\'')?.strip_suffix('\'')?;
+    let mut value = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            value.push(chars.next()?);
+        } else {
+            value.push(c);
+        }
+    }
+    Some(value)
+}
+
+fn string_array(literal: &str) -> Vec<String> {
+    literal
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .split(',')
+        .filter_map(|item| string_literal(item.trim()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_library_is_parsed_from_frontend_source() {
+        let library = template_library();
+        assert_eq!(library.len(), TEMPLATE_SOURCE.matches("\n    id: '").count());
+        assert!(library.len() >= 100);
+
+        let s3 = library.iter().find(|t| t.id == "cloud-001").unwrap();
+        assert_eq!(s3.query, "site:s3.amazonaws.com intitle:\"index of\"");
+        assert_eq!(s3.severity, Severity::Critical);
+        assert_eq!(s3.tags[0], "AWS");
+        assert!(s3.legal_warning);
+
+        let cached = library.iter().find(|t| t.id == "adv-004").unwrap();
+        assert_eq!(cached.description, "View Google's cached version of a website");
+        assert!(cached.needs_target());
+    }
+}
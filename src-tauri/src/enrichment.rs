@@ -0,0 +1,17 @@
+//! Enrichment of graph entities from outside sources.
+//!
+//! Each source resolves or looks up what it knows about a set of entities
+//! and records the results in the workspace graph, with the source as
+//! provenance.
+
+mod connectors;
+mod crtsh;
+mod dns;
+
+pub use connectors::{
+    ConnectorInfo, ConnectorSettings, EnrichmentJob, EnrichmentService, JobReport,
+};
+pub use crtsh::{
+    ct_config, enrich_certificate_transparency, set_ct_config, CertTransparencyConfig, CtReport,
+};
+pub use dns::{dns_config, enrich_dns, set_dns_config, DnsConfig, DnsReport};
@@ -0,0 +1,549 @@
+//! Connectors to third-party OSINT APIs (Shodan, VirusTotal, ...).
+//!
+//! A [`Connector`] declares which entity types it accepts and turns one
+//! entity into properties, new entities and relationships. Connectors make
+//! their requests through a [`ConnectorContext`], which adds the API key
+//! from the secret store, waits out the connector's rate limit and serves
+//! repeated requests from the vault cache. [`EnrichmentService`] runs jobs
+//! of entities through a connector and records the results in the graph.
+
+mod shodan;
+mod virustotal;
+
+use crate::security::SecurityService;
+use crate::vault::{
+    EntityInput, EntityKey, EntityType, GraphBatch, GraphSource, GraphUpsertReport, RelationshipInput, SourceKind,
+    VaultService,
+};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Entities accepted by one job
+const MAX_JOB_ENTITIES: usize = 200;
+
+/// What a connector is and what it accepts
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectorDescriptor {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    pub input_types: &'static [EntityType],
+    pub requires_key: bool,
+    pub default_base_url: &'static str,
+    /// The provider's limit for its free tier
+    pub requests_per_minute: u32,
+}
+
+/// Where a connector's API key goes on each request
+#[derive(Debug, Clone, Copy)]
+pub enum Auth {
+    Header(&'static str),
+    Query(&'static str),
+}
+
+/// What a connector learned about one entity
+#[derive(Debug, Clone, Default)]
+pub struct ConnectorOutput {
+    /// Merged into the input entity
+    pub properties: Map<String, Value>,
+    pub entities: Vec<EntityInput>,
+    pub relationships: Vec<RelationshipInput>,
+}
+
+#[async_trait]
+pub trait Connector: Send + Sync {
+    fn descriptor(&self) -> &ConnectorDescriptor;
+
+    async fn enrich(&self, ctx: &ConnectorContext<'_>, entity: &EntityKey) -> Result<ConnectorOutput>;
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConnectorSettings {
+    pub enabled: bool,
+    /// Overrides the connector's API root, for proxies and self-hosted mirrors
+    pub base_url: Option<String>,
+    /// 0 turns caching off
+    pub cache_ttl_hours: u32,
+    /// Overrides the connector's default limit
+    pub requests_per_minute: Option<u32>,
+}
+
+impl Default for ConnectorSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            base_url: None,
+            cache_ttl_hours: 24,
+            requests_per_minute: None,
+        }
+    }
+}
+
+impl ConnectorSettings {
+    fn validate(&self) -> Result<()> {
+        if let Some(base_url) = &self.base_url {
+            let url = reqwest::Url::parse(base_url).with_context(|| format!("Invalid connector URL: {}", base_url))?;
+            if !matches!(url.scheme(), "http" | "https") {
+                anyhow::bail!("Connector URL must be http or https");
+            }
+        }
+        if self.requests_per_minute == Some(0) {
+            anyhow::bail!("Rate limit must allow at least one request per minute");
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectorInfo {
+    #[serde(flatten)]
+    pub descriptor: ConnectorDescriptor,
+    pub has_key: bool,
+    pub settings: ConnectorSettings,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnrichmentJob {
+    pub connector: String,
+    pub entities: Vec<EntityKey>,
+    /// Ask the API again even for cached requests
+    #[serde(default)]
+    pub bypass_cache: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JobProgress {
+    pub job_id: String,
+    pub completed: usize,
+    pub total: usize,
+    pub entity: EntityKey,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct JobReport {
+    pub connector: String,
+    pub enriched: usize,
+    /// Entities of a type the connector doesn't accept
+    pub skipped: usize,
+    pub errors: Vec<String>,
+    pub requests: usize,
+    pub cache_hits: usize,
+    pub graph: GraphUpsertReport,
+}
+
+/// Spaces a connector's requests evenly to stay under its limit
+struct Limiter {
+    requests_per_minute: u32,
+    next: tokio::sync::Mutex<Instant>,
+}
+
+impl Limiter {
+    fn new(requests_per_minute: u32) -> Self {
+        Self {
+            requests_per_minute,
+            next: tokio::sync::Mutex::new(Instant::now()),
+        }
+    }
+
+    async fn wait(&self) {
+        let interval = Duration::from_secs(60) / self.requests_per_minute.max(1);
+        let mut next = self.next.lock().await;
+        tokio::time::sleep_until(*next).await;
+        *next = Instant::now().max(*next) + interval;
+    }
+}
+
+/// Requests made on a connector's behalf during one job
+pub struct ConnectorContext<'a> {
+    http_client: &'a reqwest::Client,
+    vault: &'a VaultService,
+    connector: &'static str,
+    base_url: String,
+    api_key: Option<String>,
+    cache_ttl_hours: u32,
+    bypass_cache: bool,
+    limiter: Arc<Limiter>,
+    requests: AtomicUsize,
+    cache_hits: AtomicUsize,
+}
+
+impl ConnectorContext<'_> {
+    /// GET `path` (with its query string) under the connector's API root.
+    /// A 404 comes back as `Value::Null`, since the entity is simply unknown.
+    pub async fn get_json(&self, path: &str, auth: Auth) -> Result<Value> {
+        let url = format!("{}{}", self.base_url, path);
+        // The key is never part of the cache key
+        let cache_key = hex::encode(Sha256::digest(format!("{}\n{}", self.connector, url)));
+
+        if !self.bypass_cache && self.cache_ttl_hours > 0 {
+            if let Some(body) = self.vault.enrichment_cache_get(&cache_key).await? {
+                self.cache_hits.fetch_add(1, Ordering::Relaxed);
+                return serde_json::from_str(&body).context("Failed to parse cached response");
+            }
+        }
+
+        self.limiter.wait().await;
+        self.requests.fetch_add(1, Ordering::Relaxed);
+
+        let mut request = self.http_client.get(&url);
+        match (auth, &self.api_key) {
+            (Auth::Header(name), Some(key)) => request = request.header(name, key),
+            (Auth::Query(name), Some(key)) => request = request.query(&[(name, key)]),
+            (_, None) => {}
+        }
+        let response = request
+            .send()
+            .await
+            .with_context(|| format!("Failed to reach {}", self.base_url))?;
+
+        let status = response.status();
+        let body = response.text().await.context("Failed to read response")?;
+        let value = match status.as_u16() {
+            404 => Value::Null,
+            429 => anyhow::bail!("{} rate limit exceeded", self.connector),
+            401 | 403 => anyhow::bail!("{} rejected the API key ({})", self.connector, status),
+            _ if !status.is_success() => {
+                let snippet: String = body.chars().take(200).collect();
+                anyhow::bail!("{} error ({}): {}", self.connector, status, snippet.trim());
+            }
+            _ => serde_json::from_str(&body).with_context(|| format!("{} didn't return JSON", self.connector))?,
+        };
+
+        if self.cache_ttl_hours > 0 {
+            let body = value.to_string();
+            self.vault
+                .enrichment_cache_put(&cache_key, self.connector, &body, self.cache_ttl_hours)
+                .await?;
+        }
+        Ok(value)
+    }
+}
+
+pub struct ConnectorRegistry {
+    connectors: Vec<Arc<dyn Connector>>,
+}
+
+impl ConnectorRegistry {
+    /// The connectors that ship with Parallax
+    pub fn builtin() -> Self {
+        Self {
+            connectors: vec![Arc::new(shodan::ShodanConnector), Arc::new(virustotal::VirusTotalConnector)],
+        }
+    }
+
+    pub fn get(&self, id: &str) -> Option<Arc<dyn Connector>> {
+        self.connectors.iter().find(|c| c.descriptor().id == id).cloned()
+    }
+
+    pub fn descriptors(&self) -> impl Iterator<Item = &ConnectorDescriptor> {
+        self.connectors.iter().map(|c| c.descriptor())
+    }
+}
+
+pub struct EnrichmentService {
+    security: Arc<SecurityService>,
+    vault: Arc<VaultService>,
+    registry: ConnectorRegistry,
+    http_client: reqwest::Client,
+    limiters: std::sync::Mutex<HashMap<&'static str, Arc<Limiter>>>,
+}
+
+fn settings_key(connector: &str) -> String {
+    format!("enrichment.connector.{}", connector)
+}
+
+impl EnrichmentService {
+    pub fn new(security: Arc<SecurityService>, vault: Arc<VaultService>) -> Result<Self> {
+        let http_client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(30))
+            .user_agent(concat!("Parallax/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .context("Failed to create HTTP client")?;
+
+        Ok(Self {
+            security,
+            vault,
+            registry: ConnectorRegistry::builtin(),
+            http_client,
+            limiters: std::sync::Mutex::new(HashMap::new()),
+        })
+    }
+
+    fn connector(&self, id: &str) -> Result<Arc<dyn Connector>> {
+        self.registry.get(id).with_context(|| format!("Unknown connector: {}", id))
+    }
+
+    pub async fn connectors(&self) -> Result<Vec<ConnectorInfo>> {
+        let mut infos = Vec::new();
+        for descriptor in self.registry.descriptors() {
+            infos.push(ConnectorInfo {
+                descriptor: descriptor.clone(),
+                has_key: self.security.get_connector_key(descriptor.id).await?.is_some(),
+                settings: self.connector_settings(descriptor.id).await?,
+            });
+        }
+        Ok(infos)
+    }
+
+    pub async fn connector_settings(&self, id: &str) -> Result<ConnectorSettings> {
+        self.connector(id)?;
+        match self.vault.get_setting(&settings_key(id)).await? {
+            Some(value) => serde_json::from_value(value).context("Failed to parse connector settings"),
+            None => Ok(ConnectorSettings::default()),
+        }
+    }
+
+    pub async fn set_connector_settings(&self, id: &str, settings: &ConnectorSettings) -> Result<()> {
+        self.connector(id)?;
+        settings.validate()?;
+        let value = serde_json::to_value(settings).context("Failed to serialize connector settings")?;
+        self.vault.set_setting(&settings_key(id), &value).await
+    }
+
+    /// Run `job` and record what its connector found in the workspace graph
+    pub async fn run_job(
+        &self,
+        job_id: &str,
+        job: &EnrichmentJob,
+        workspace: &str,
+        emit: impl Fn(JobProgress) + Send + Sync,
+    ) -> Result<JobReport> {
+        let connector = self.connector(&job.connector)?;
+        let api_key = self.security.get_connector_key(connector.descriptor().id).await?;
+        self.run_job_with(connector.as_ref(), api_key, job_id, job, workspace, emit).await
+    }
+
+    pub(crate) async fn run_job_with(
+        &self,
+        connector: &dyn Connector,
+        api_key: Option<String>,
+        job_id: &str,
+        job: &EnrichmentJob,
+        workspace: &str,
+        emit: impl Fn(JobProgress) + Send + Sync,
+    ) -> Result<JobReport> {
+        let descriptor = connector.descriptor();
+        let settings = self.connector_settings(descriptor.id).await?;
+        if !settings.enabled {
+            anyhow::bail!("{} is disabled", descriptor.name);
+        }
+        if descriptor.requires_key && api_key.is_none() {
+            anyhow::bail!("{} needs an API key. Add one in settings.", descriptor.name);
+        }
+        if job.entities.len() > MAX_JOB_ENTITIES {
+            anyhow::bail!("At most {} entities can be enriched at once", MAX_JOB_ENTITIES);
+        }
+
+        let base_url = settings.base_url.as_deref().unwrap_or(descriptor.default_base_url);
+        let requests_per_minute = settings.requests_per_minute.unwrap_or(descriptor.requests_per_minute);
+        let ctx = ConnectorContext {
+            http_client: &self.http_client,
+            vault: &self.vault,
+            connector: descriptor.id,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+            cache_ttl_hours: settings.cache_ttl_hours,
+            bypass_cache: job.bypass_cache,
+            limiter: self.limiter(descriptor.id, requests_per_minute),
+            requests: AtomicUsize::new(0),
+            cache_hits: AtomicUsize::new(0),
+        };
+
+        let mut report = JobReport {
+            connector: descriptor.id.to_string(),
+            ..JobReport::default()
+        };
+
+        for (index, entity) in job.entities.iter().enumerate() {
+            let error = if !descriptor.input_types.contains(&entity.entity_type) {
+                report.skipped += 1;
+                None
+            } else {
+                // Each entity's results are stored on their own, so one bad
+                // item only costs that entity
+                let stored = match connector.enrich(&ctx, entity).await {
+                    Ok(output) => self.store_output(descriptor, workspace, entity, output).await,
+                    Err(e) => Err(e),
+                };
+                match stored {
+                    Ok(graph) => {
+                        report.enriched += 1;
+                        report.graph += graph;
+                        None
+                    }
+                    Err(e) => {
+                        let message = format!("{}: {}", entity.value, e);
+                        report.errors.push(message.clone());
+                        Some(message)
+                    }
+                }
+            };
+
+            emit(JobProgress {
+                job_id: job_id.to_string(),
+                completed: index + 1,
+                total: job.entities.len(),
+                entity: entity.clone(),
+                error,
+            });
+        }
+
+        report.requests = ctx.requests.load(Ordering::Relaxed);
+        report.cache_hits = ctx.cache_hits.load(Ordering::Relaxed);
+        tracing::info!(
+            "{} enriched {} entities ({} requests, {} cached, {} errors)",
+            descriptor.name,
+            report.enriched,
+            report.requests,
+            report.cache_hits,
+            report.errors.len()
+        );
+        Ok(report)
+    }
+
+    async fn store_output(
+        &self,
+        descriptor: &ConnectorDescriptor,
+        workspace: &str,
+        entity: &EntityKey,
+        output: ConnectorOutput,
+    ) -> Result<GraphUpsertReport> {
+        let mut entities = vec![EntityInput {
+            entity_type: entity.entity_type,
+            value: entity.value.clone(),
+            properties: output.properties,
+            seen: None,
+        }];
+        entities.extend(output.entities);
+        let batch = GraphBatch {
+            source: GraphSource {
+                kind: SourceKind::Tool,
+                reference: descriptor.id.to_string(),
+            },
+            observed_at: None,
+            entities,
+            relationships: output.relationships,
+        };
+        self.vault.upsert_graph(workspace, &batch).await
+    }
+
+    /// The connector's limiter, replaced when its rate changes
+    fn limiter(&self, connector: &'static str, requests_per_minute: u32) -> Arc<Limiter> {
+        let mut limiters = self.limiters.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let limiter = limiters
+            .entry(connector)
+            .or_insert_with(|| Arc::new(Limiter::new(requests_per_minute)));
+        if limiter.requests_per_minute != requests_per_minute {
+            *limiter = Arc::new(Limiter::new(requests_per_minute));
+        }
+        limiter.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{StubResponse, StubServer};
+
+    pub(super) async fn service_for(server: &StubServer, connector: &str) -> EnrichmentService {
+        let vault = Arc::new(VaultService::open_in_memory().unwrap());
+        let service = EnrichmentService::new(Arc::new(SecurityService::new()), vault).unwrap();
+        let settings = ConnectorSettings {
+            base_url: Some(server.url()),
+            requests_per_minute: Some(6000),
+            ..ConnectorSettings::default()
+        };
+        service.set_connector_settings(connector, &settings).await.unwrap();
+        service
+    }
+
+    #[tokio::test]
+    async fn test_job_runner_caches_and_skips() {
+        let server = StubServer::start(vec![(
+            "/shodan/host/203.0.113.5",
+            StubResponse::json(r#"{"ip_str": "203.0.113.5", "ports": [22], "hostnames": []}"#),
+        )])
+        .await;
+        let service = service_for(&server, "shodan").await;
+        let job = EnrichmentJob {
+            connector: "shodan".to_string(),
+            entities: vec![
+                EntityKey::new(EntityType::Ip, "203.0.113.5"),
+                EntityKey::new(EntityType::Domain, "example.com"),
+                EntityKey::new(EntityType::Ip, "203.0.113.9"),
+            ],
+            bypass_cache: false,
+        };
+        let shodan = shodan::ShodanConnector;
+
+        let missing_key = service.run_job_with(&shodan, None, "j0", &job, "default", |_| {}).await;
+        assert!(missing_key.unwrap_err().to_string().contains("API key"));
+
+        let progress = std::sync::Mutex::new(Vec::new());
+        let key = Some("secret-key".to_string());
+        let report = service
+            .run_job_with(&shodan, key.clone(), "j1", &job, "default", |p| progress.lock().unwrap().push(p))
+            .await
+            .unwrap();
+        assert_eq!((report.enriched, report.skipped, report.requests, report.cache_hits), (2, 1, 2, 0));
+        assert_eq!(progress.into_inner().unwrap().last().unwrap().completed, 3);
+        assert_eq!(server.requests()[0].query, "key=secret-key");
+
+        // Both answers, including the 404, are served from the cache
+        let again = service.run_job_with(&shodan, key.clone(), "j2", &job, "default", |_| {}).await.unwrap();
+        assert_eq!((again.requests, again.cache_hits), (0, 2));
+        let bypass = EnrichmentJob { bypass_cache: true, ..job.clone() };
+        let fresh = service.run_job_with(&shodan, key, "j3", &bypass, "default", |_| {}).await.unwrap();
+        assert_eq!(fresh.requests, 2);
+
+        let ip = service
+            .vault
+            .find_entity("default", &EntityKey::new(EntityType::Ip, "203.0.113.5"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(ip.properties["open_ports"][0], 22);
+        assert_eq!(ip.sources[0].reference, "shodan");
+    }
+
+    #[tokio::test]
+    async fn test_unstorable_result_only_fails_its_entity() {
+        let server = StubServer::start(vec![
+            ("/shodan/host/203.0.113.5", StubResponse::json(r#"{"ports": [22], "org": "Example Hosting"}"#)),
+            // An organization without a name can't be stored
+            ("/shodan/host/203.0.113.9", StubResponse::json(r#"{"ports": [80], "org": " "}"#)),
+        ])
+        .await;
+        let service = service_for(&server, "shodan").await;
+        let job = EnrichmentJob {
+            connector: "shodan".to_string(),
+            entities: vec![
+                EntityKey::new(EntityType::Ip, "203.0.113.5"),
+                EntityKey::new(EntityType::Ip, "203.0.113.9"),
+            ],
+            bypass_cache: false,
+        };
+
+        let report = service
+            .run_job_with(&shodan::ShodanConnector, Some("k".to_string()), "j", &job, "default", |_| {})
+            .await
+            .unwrap();
+        assert_eq!(report.enriched, 1);
+        assert_eq!(report.errors.len(), 1);
+        assert!(report.errors[0].starts_with("203.0.113.9"));
+        assert_eq!(report.graph.entities_created, 2);
+
+        let graph = service.vault.load_graph("default").await.unwrap();
+        assert_eq!(graph.entities.len(), 2);
+    }
+}
@@ -0,0 +1,181 @@
+//! Shodan host lookups (`/shodan/host/{ip}`): open ports, the services
+//! behind them, host names and the owning organization.
+
+use super::{Auth, Connector, ConnectorContext, ConnectorDescriptor, ConnectorOutput};
+use crate::indicators;
+use crate::vault::{EntityInput, EntityKey, EntityType, RelationType, RelationshipInput};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::net::IpAddr;
+
+const DESCRIPTOR: ConnectorDescriptor = ConnectorDescriptor {
+    id: "shodan",
+    name: "Shodan",
+    description: "Open ports, services and host names of an IP address",
+    input_types: &[EntityType::Ip],
+    requires_key: true,
+    default_base_url: "https://api.shodan.io",
+    requests_per_minute: 60,
+};
+
+#[derive(Deserialize)]
+struct Host {
+    #[serde(default)]
+    ports: Vec<u16>,
+    #[serde(default)]
+    hostnames: Vec<String>,
+    org: Option<String>,
+    isp: Option<String>,
+    asn: Option<String>,
+    country_code: Option<String>,
+    os: Option<String>,
+    #[serde(default)]
+    data: Vec<Banner>,
+}
+
+#[derive(Deserialize)]
+struct Banner {
+    port: u16,
+    transport: Option<String>,
+    product: Option<String>,
+    version: Option<String>,
+}
+
+pub struct ShodanConnector;
+
+#[async_trait]
+impl Connector for ShodanConnector {
+    fn descriptor(&self) -> &ConnectorDescriptor {
+        &DESCRIPTOR
+    }
+
+    async fn enrich(&self, ctx: &ConnectorContext<'_>, entity: &EntityKey) -> Result<ConnectorOutput> {
+        // The value goes into the request path, so only a parsed address is sent
+        let ip: IpAddr = entity.value.trim().parse().with_context(|| format!("Not an IP address: {}", entity.value))?;
+        let response = ctx.get_json(&format!("/shodan/host/{}", ip), Auth::Query("key")).await?;
+        if response.is_null() {
+            return Ok(ConnectorOutput::default());
+        }
+        let host: Host = serde_json::from_value(response).context("Unexpected Shodan response")?;
+        Ok(host_output(entity, host))
+    }
+}
+
+fn relationship(source: EntityKey, target: EntityKey, relation_type: RelationType) -> RelationshipInput {
+    RelationshipInput {
+        source,
+        target,
+        relation_type,
+        properties: Map::new(),
+        seen: None,
+    }
+}
+
+fn host_output(ip: &EntityKey, host: Host) -> ConnectorOutput {
+    let mut output = ConnectorOutput::default();
+    let properties = &mut output.properties;
+    properties.insert("open_ports".to_string(), host.ports.into());
+    for (name, value) in [("isp", host.isp), ("asn", host.asn), ("country", host.country_code), ("os", host.os)] {
+        if let Some(value) = value {
+            properties.insert(name.to_string(), value.into());
+        }
+    }
+
+    for hostname in &host.hostnames {
+        if let Some(domain) = indicators::domain_indicator(hostname) {
+            let key = EntityKey::new(domain.entity_type, domain.value);
+            output.relationships.push(relationship(key, ip.clone(), RelationType::ResolvesTo));
+        }
+    }
+
+    // The network's owner, not a host of it: there's no ownership relation
+    if let Some(org) = host.org {
+        let key = EntityKey::new(EntityType::Organization, org);
+        output.relationships.push(relationship(key, ip.clone(), RelationType::RelatedTo));
+    }
+
+    for banner in host.data {
+        let transport = banner.transport.unwrap_or_else(|| "tcp".to_string());
+        let mut properties = Map::new();
+        properties.insert("port".to_string(), banner.port.into());
+        properties.insert("transport".to_string(), transport.clone().into());
+        if let Some(product) = &banner.product {
+            properties.insert("product".to_string(), product.clone().into());
+        }
+        if let Some(version) = &banner.version {
+            properties.insert("version".to_string(), version.clone().into());
+        }
+
+        let service = EntityKey::new(EntityType::Service, format!("{}:{}/{}", ip.value, banner.port, transport));
+        output.entities.push(EntityInput {
+            entity_type: service.entity_type,
+            value: service.value.clone(),
+            properties,
+            seen: None,
+        });
+        output.relationships.push(relationship(ip.clone(), service.clone(), RelationType::Hosts));
+
+        if let Some(product) = banner.product {
+            let technology_key = EntityKey::new(EntityType::Technology, product);
+            let mut technology = relationship(service, technology_key, RelationType::Uses);
+            if let Some(version) = banner.version {
+                technology.properties.insert("version".to_string(), Value::String(version));
+            }
+            output.relationships.push(technology);
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::service_for;
+    use super::super::EnrichmentJob;
+    use super::*;
+    use crate::test_support::{StubResponse, StubServer};
+
+    #[tokio::test]
+    async fn test_host_lookup_against_stub() {
+        let server = StubServer::start(vec![(
+            "/shodan/host/198.51.100.20",
+            StubResponse::json(
+                r#"{"ip_str": "198.51.100.20", "ports": [443, 22], "hostnames": ["www.example.com"],
+                    "org": "Example Hosting", "isp": "Example ISP", "asn": "AS64500", "country_code": "NL",
+                    "data": [{"port": 443, "transport": "tcp", "product": "nginx", "version": "1.25.3"},
+                             {"port": 22, "transport": "tcp", "product": "OpenSSH"}]}"#,
+            ),
+        )])
+        .await;
+        let service = service_for(&server, "shodan").await;
+        let job = EnrichmentJob {
+            connector: "shodan".to_string(),
+            entities: vec![
+                EntityKey::new(EntityType::Ip, "198.51.100.20"),
+                EntityKey::new(EntityType::Ip, "198.51.100.20/../../account/profile"),
+            ],
+            bypass_cache: false,
+        };
+
+        let report = service
+            .run_job_with(&ShodanConnector, Some("k".to_string()), "j", &job, "default", |_| {})
+            .await
+            .unwrap();
+        assert_eq!(report.errors.len(), 1);
+        assert!(report.errors[0].contains("Not an IP address"));
+        assert_eq!(server.requests().len(), 1);
+
+        let graph = service.vault.load_graph("default").await.unwrap();
+        let has = |entity_type: EntityType, value: &str| {
+            graph.entities.iter().any(|e| e.entity_type == entity_type && e.value == value)
+        };
+        assert!(has(EntityType::Service, "198.51.100.20:443/tcp"));
+        assert!(has(EntityType::Technology, "nginx"));
+        assert!(has(EntityType::Subdomain, "www.example.com"));
+        assert!(has(EntityType::Organization, "Example Hosting"));
+        let ip = graph.entities.iter().find(|e| e.entity_type == EntityType::Ip).unwrap();
+        assert_eq!(ip.properties["asn"], "AS64500");
+        assert_eq!(graph.relationships.len(), 6);
+    }
+}
@@ -0,0 +1,173 @@
+//! VirusTotal domain reports (`/api/v3/domains/{domain}`): detection
+//! counts, reputation, categories and the DNS records VirusTotal saw.
+
+use super::{Auth, Connector, ConnectorContext, ConnectorDescriptor, ConnectorOutput};
+use crate::indicators;
+use crate::vault::{EntityKey, EntityType, RelationType, RelationshipInput};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::{BTreeSet, HashMap};
+
+const DESCRIPTOR: ConnectorDescriptor = ConnectorDescriptor {
+    id: "virustotal",
+    name: "VirusTotal",
+    description: "Detections, reputation, categories and DNS history of a domain",
+    input_types: &[EntityType::Domain, EntityType::Subdomain],
+    requires_key: true,
+    default_base_url: "https://www.virustotal.com",
+    requests_per_minute: 4,
+};
+
+#[derive(Deserialize)]
+struct Report {
+    data: ReportData,
+}
+
+#[derive(Deserialize)]
+struct ReportData {
+    attributes: Attributes,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct Attributes {
+    last_analysis_stats: HashMap<String, u64>,
+    reputation: Option<i64>,
+    categories: HashMap<String, String>,
+    registrar: Option<String>,
+    /// Unix seconds
+    creation_date: Option<i64>,
+    last_dns_records: Vec<DnsRecord>,
+}
+
+#[derive(Deserialize)]
+struct DnsRecord {
+    #[serde(rename = "type")]
+    record_type: String,
+    value: String,
+}
+
+pub struct VirusTotalConnector;
+
+#[async_trait]
+impl Connector for VirusTotalConnector {
+    fn descriptor(&self) -> &ConnectorDescriptor {
+        &DESCRIPTOR
+    }
+
+    async fn enrich(&self, ctx: &ConnectorContext<'_>, entity: &EntityKey) -> Result<ConnectorOutput> {
+        // The value goes into the request path, so only a normalized domain is sent
+        let domain = indicators::normalize_domain(&entity.value)
+            .with_context(|| format!("Not a valid domain: {}", entity.value))?;
+        let response = ctx.get_json(&format!("/api/v3/domains/{}", domain), Auth::Header("x-apikey")).await?;
+        if response.is_null() {
+            return Ok(ConnectorOutput::default());
+        }
+        let report: Report = serde_json::from_value(response).context("Unexpected VirusTotal response")?;
+        Ok(domain_output(entity, report.data.attributes))
+    }
+}
+
+fn domain_output(domain: &EntityKey, attributes: Attributes) -> ConnectorOutput {
+    let mut output = ConnectorOutput::default();
+    let properties = &mut output.properties;
+    for verdict in ["malicious", "suspicious", "harmless", "undetected"] {
+        if let Some(count) = attributes.last_analysis_stats.get(verdict) {
+            properties.insert(format!("vt_{}", verdict), (*count).into());
+        }
+    }
+    if let Some(reputation) = attributes.reputation {
+        properties.insert("vt_reputation".to_string(), reputation.into());
+    }
+    let categories: BTreeSet<String> = attributes.categories.into_values().map(|c| c.to_lowercase()).collect();
+    if !categories.is_empty() {
+        properties.insert("vt_categories".to_string(), categories.into_iter().collect::<Vec<_>>().into());
+    }
+    if let Some(registrar) = attributes.registrar {
+        properties.insert("registrar".to_string(), registrar.into());
+    }
+    let created = attributes.creation_date.and_then(|at| chrono::DateTime::from_timestamp(at, 0));
+    if let Some(created) = created {
+        properties.insert("created".to_string(), created.to_rfc3339().into());
+    }
+
+    for record in attributes.last_dns_records {
+        let target = match record.record_type.as_str() {
+            "A" | "AAAA" => Some((EntityKey::new(EntityType::Ip, record.value), RelationType::ResolvesTo)),
+            "CNAME" => host(&record.value).map(|key| (key, RelationType::AliasOf)),
+            "MX" => host(&record.value).map(|key| (key, RelationType::MailServer)),
+            "NS" => host(&record.value).map(|key| (key, RelationType::NameServer)),
+            _ => None,
+        };
+        if let Some((target, relation_type)) = target {
+            output.relationships.push(RelationshipInput {
+                source: domain.clone(),
+                target,
+                relation_type,
+                properties: Map::from_iter([("via".to_string(), Value::from("virustotal"))]),
+                seen: None,
+            });
+        }
+    }
+    output
+}
+
+fn host(name: &str) -> Option<EntityKey> {
+    indicators::domain_indicator(name).map(|host| EntityKey::new(host.entity_type, host.value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::service_for;
+    use super::super::EnrichmentJob;
+    use super::*;
+    use crate::test_support::{StubResponse, StubServer};
+
+    #[tokio::test]
+    async fn test_domain_report_against_stub() {
+        let server = StubServer::start(vec![(
+            "/api/v3/domains/example.com",
+            StubResponse::json(
+                r#"{"data": {"id": "example.com", "type": "domain", "attributes": {
+                    "last_analysis_stats": {"malicious": 2, "suspicious": 0, "harmless": 60, "undetected": 10},
+                    "reputation": -5, "categories": {"Forcepoint": "Phishing", "Sophos": "phishing"},
+                    "registrar": "Example Registrar", "creation_date": 1000000000,
+                    "last_dns_records": [{"type": "A", "value": "192.0.2.10", "ttl": 300},
+                                         {"type": "MX", "value": "mx.example.com", "ttl": 300},
+                                         {"type": "TXT", "value": "v=spf1 -all", "ttl": 300}]}}}"#,
+            ),
+        )])
+        .await;
+        let service = service_for(&server, "virustotal").await;
+        let job = EnrichmentJob {
+            connector: "virustotal".to_string(),
+            entities: vec![
+                EntityKey::new(EntityType::Domain, "example.com"),
+                EntityKey::new(EntityType::Domain, "example.com/../../users/me?x="),
+            ],
+            bypass_cache: false,
+        };
+
+        let report = service
+            .run_job_with(&VirusTotalConnector, Some("vt-key".to_string()), "j", &job, "default", |_| {})
+            .await
+            .unwrap();
+        assert_eq!(report.errors.len(), 1);
+        assert!(report.errors[0].contains("Not a valid domain"));
+        assert_eq!(server.requests().len(), 1);
+        assert_eq!(server.requests()[0].headers["x-apikey"], "vt-key");
+
+        let domain = service
+            .vault
+            .find_entity("default", &EntityKey::new(EntityType::Domain, "example.com"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(domain.properties["vt_malicious"], 2);
+        assert_eq!(domain.properties["vt_categories"], serde_json::json!(["phishing"]));
+        assert_eq!(domain.properties["created"], "2001-09-09T01:46:40+00:00");
+        assert_eq!(report.graph.relationships_created, 2);
+    }
+}
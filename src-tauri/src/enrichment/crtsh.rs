@@ -0,0 +1,364 @@
+//! Subdomain discovery from certificate transparency logs, through a
+//! crt.sh-style JSON search (`?q=%.example.com&output=json`).
+//!
+//! Every certificate lists the names it covers; names under the searched
+//! domain are collected, `*.` wildcards are folded into the name they
+//! cover, and each name keeps the span of certificates issued for it.
+
+use crate::indicators::{self, Indicator};
+use crate::vault::{GraphSource, GraphUpsertReport, SourceKind, VaultService};
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::time::Duration;
+
+/// Settings key holding the [`CertTransparencyConfig`]
+const CT_SETTING: &str = "enrichment.crtsh";
+pub const DEFAULT_BASE_URL: &str = "https://crt.sh";
+/// Issuer names kept per subdomain
+const MAX_ISSUERS: usize = 5;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CertTransparencyConfig {
+    pub base_url: String,
+    /// crt.sh is slow for large domains
+    pub timeout_secs: u64,
+    /// Count names only seen on certificates that have since expired
+    pub include_expired: bool,
+}
+
+impl Default for CertTransparencyConfig {
+    fn default() -> Self {
+        Self {
+            base_url: DEFAULT_BASE_URL.to_string(),
+            timeout_secs: 90,
+            include_expired: true,
+        }
+    }
+}
+
+impl CertTransparencyConfig {
+    fn validate(&self) -> Result<()> {
+        let url = reqwest::Url::parse(&self.base_url)
+            .with_context(|| format!("Invalid certificate transparency URL: {}", self.base_url))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            anyhow::bail!("Certificate transparency URL must be http or https");
+        }
+        if !(5..=600).contains(&self.timeout_secs) {
+            anyhow::bail!("Certificate transparency timeout must be between 5 and 600 seconds");
+        }
+        Ok(())
+    }
+}
+
+/// One certificate from the search results
+#[derive(Debug, Deserialize)]
+struct CertificateEntry {
+    id: Option<u64>,
+    issuer_name: Option<String>,
+    common_name: Option<String>,
+    /// Newline-separated subject alternative names
+    name_value: Option<String>,
+    not_before: Option<String>,
+    not_after: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CtSubdomain {
+    pub name: String,
+    /// Covered by a `*.` certificate name
+    pub wildcard: bool,
+    pub certificates: usize,
+    /// RFC 3339 `not_before` of the oldest certificate
+    pub first_issued: Option<String>,
+    /// RFC 3339 `not_before` of the newest certificate
+    pub last_issued: Option<String>,
+    /// RFC 3339, the latest `not_after`
+    pub expires: Option<String>,
+    pub issuers: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CtReport {
+    pub domain: String,
+    pub certificates: usize,
+    pub subdomains: Vec<CtSubdomain>,
+    pub graph: GraphUpsertReport,
+}
+
+#[derive(Default)]
+struct Seen {
+    wildcard: bool,
+    certificates: BTreeSet<u64>,
+    first_issued: Option<DateTime<Utc>>,
+    last_issued: Option<DateTime<Utc>>,
+    expires: Option<DateTime<Utc>>,
+    issuers: BTreeSet<String>,
+}
+
+pub async fn ct_config(vault: &VaultService) -> Result<CertTransparencyConfig> {
+    match vault.get_setting(CT_SETTING).await? {
+        Some(value) => serde_json::from_value(value).context("Failed to parse certificate transparency settings"),
+        None => Ok(CertTransparencyConfig::default()),
+    }
+}
+
+pub async fn set_ct_config(vault: &VaultService, config: &CertTransparencyConfig) -> Result<()> {
+    config.validate()?;
+    let value = serde_json::to_value(config).context("Failed to serialize certificate transparency settings")?;
+    vault.set_setting(CT_SETTING, &value).await
+}
+
+/// Search certificate transparency logs for names under `domain` and
+/// record them in the workspace graph
+pub async fn enrich_certificate_transparency(vault: &VaultService, workspace: &str, domain: &str) -> Result<CtReport> {
+    let config = ct_config(vault).await?;
+    config.validate()?;
+
+    let domain = indicators::domain_indicator(domain).with_context(|| format!("Not a domain name: {}", domain))?;
+    let entries = fetch_entries(&config, &domain.value).await?;
+    let (certificates, subdomains) = collect_subdomains(&domain.value, &entries, config.include_expired, Utc::now());
+
+    let batch = graph_batch(&domain, &subdomains);
+    let graph = vault.upsert_graph(workspace, &batch).await?;
+    tracing::info!(
+        "Certificate transparency: {} names under {} from {} certificates",
+        subdomains.len(),
+        domain.value,
+        certificates
+    );
+
+    Ok(CtReport {
+        domain: domain.value,
+        certificates,
+        subdomains,
+        graph,
+    })
+}
+
+async fn fetch_entries(config: &CertTransparencyConfig, domain: &str) -> Result<Vec<CertificateEntry>> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(config.timeout_secs))
+        .build()
+        .context("Failed to create HTTP client")?;
+    let base_url = config.base_url.trim_end_matches('/');
+
+    let response = client
+        .get(format!("{}/", base_url))
+        .query(&[("q", format!("%.{}", domain).as_str()), ("output", "json")])
+        .send()
+        .await
+        .with_context(|| format!("Failed to reach {}", base_url))?;
+
+    let status = response.status();
+    let body = response.text().await.context("Failed to read certificate transparency response")?;
+    if !status.is_success() {
+        anyhow::bail!("Certificate transparency search failed ({}): {}", status, body.trim());
+    }
+    // No matches come back as an empty body or `[]`
+    if body.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let entries: Vec<Value> =
+        serde_json::from_str(&body).context("Certificate transparency search didn't return JSON")?;
+    Ok(entries
+        .into_iter()
+        .filter_map(|entry| serde_json::from_value(entry).ok())
+        .collect())
+}
+
+/// crt.sh timestamps have no zone and are UTC; RFC 3339 is accepted too
+fn parse_time(value: Option<&str>) -> Option<DateTime<Utc>> {
+    let value = value?.trim();
+    DateTime::parse_from_rfc3339(value)
+        .map(|at| at.with_timezone(&Utc))
+        .ok()
+        .or_else(|| NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f").ok().map(|at| at.and_utc()))
+}
+
+fn format_time(at: Option<DateTime<Utc>>) -> Option<String> {
+    at.map(|at| at.to_rfc3339_opts(SecondsFormat::Secs, true))
+}
+
+/// Certificates counted and the names under `domain` they cover, sorted
+fn collect_subdomains(
+    domain: &str,
+    entries: &[CertificateEntry],
+    include_expired: bool,
+    now: DateTime<Utc>,
+) -> (usize, Vec<CtSubdomain>) {
+    let suffix = format!(".{}", domain);
+    let mut names: BTreeMap<String, Seen> = BTreeMap::new();
+    let mut certificates = BTreeSet::new();
+
+    for (index, entry) in entries.iter().enumerate() {
+        let not_before = parse_time(entry.not_before.as_deref());
+        let not_after = parse_time(entry.not_after.as_deref());
+        if !include_expired && not_after.is_some_and(|at| at < now) {
+            continue;
+        }
+        // Without an id, each entry counts as its own certificate
+        let id = entry.id.unwrap_or(u64::MAX - index as u64);
+        certificates.insert(id);
+
+        let listed = entry.name_value.iter().chain(&entry.common_name).flat_map(|names| names.lines());
+        for name in listed {
+            let name = name.trim().trim_end_matches('.').to_lowercase();
+            let (name, wildcard) = match name.strip_prefix("*.") {
+                Some(covered) => (covered.to_string(), true),
+                None => (name, false),
+            };
+            // Email SANs and names outside the domain are skipped
+            let Some(host) = indicators::domain_indicator(&name) else {
+                continue;
+            };
+            if host.value != domain && !host.value.ends_with(&suffix) {
+                continue;
+            }
+
+            let seen = names.entry(host.value).or_default();
+            seen.wildcard |= wildcard;
+            seen.certificates.insert(id);
+            seen.first_issued = match (seen.first_issued, not_before) {
+                (Some(first), Some(issued)) => Some(first.min(issued)),
+                (first, issued) => first.or(issued),
+            };
+            seen.last_issued = seen.last_issued.max(not_before);
+            seen.expires = seen.expires.max(not_after);
+            if let Some(issuer) = entry.issuer_name.as_deref().filter(|issuer| !issuer.is_empty()) {
+                if seen.issuers.len() < MAX_ISSUERS {
+                    seen.issuers.insert(issuer.to_string());
+                }
+            }
+        }
+    }
+
+    let subdomains = names
+        .into_iter()
+        .map(|(name, seen)| CtSubdomain {
+            name,
+            wildcard: seen.wildcard,
+            certificates: seen.certificates.len(),
+            first_issued: format_time(seen.first_issued),
+            last_issued: format_time(seen.last_issued),
+            expires: format_time(seen.expires),
+            issuers: seen.issuers.into_iter().collect(),
+        })
+        .collect();
+    (certificates.len(), subdomains)
+}
+
+fn graph_batch(domain: &Indicator, subdomains: &[CtSubdomain]) -> crate::vault::GraphBatch {
+    let hosts: Vec<Indicator> = std::iter::once(domain.clone())
+        .chain(subdomains.iter().filter_map(|subdomain| indicators::domain_indicator(&subdomain.name)))
+        .collect();
+    let source = GraphSource {
+        kind: SourceKind::Tool,
+        reference: "crt.sh".to_string(),
+    };
+    let mut batch = indicators::indicator_batch(&hosts, source);
+    let by_name: HashMap<&str, &CtSubdomain> = subdomains.iter().map(|s| (s.name.as_str(), s)).collect();
+
+    for entity in &mut batch.entities {
+        let Some(subdomain) = by_name.get(entity.value.as_str()) else {
+            continue;
+        };
+        let properties = &mut entity.properties;
+        properties.insert("ct_certificates".to_string(), subdomain.certificates.into());
+        properties.insert("ct_wildcard".to_string(), subdomain.wildcard.into());
+        if let Some(first_issued) = &subdomain.first_issued {
+            properties.insert("ct_first_issued".to_string(), first_issued.clone().into());
+        }
+        if let Some(last_issued) = &subdomain.last_issued {
+            properties.insert("ct_last_issued".to_string(), last_issued.clone().into());
+        }
+        if let Some(expires) = &subdomain.expires {
+            properties.insert("ct_expires".to_string(), expires.clone().into());
+        }
+    }
+    batch
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{StubResponse, StubServer};
+    use crate::vault::{EntityKey, EntityType};
+
+    const FIXTURE: &str = r#"[
+        {"issuer_ca_id": 1, "issuer_name": "C=US, O=Let's Encrypt, CN=R3", "common_name": "example.com",
+         "name_value": "example.com\nwww.example.com", "id": 101,
+         "not_before": "2023-01-10T00:00:00", "not_after": "2023-04-10T00:00:00"},
+        {"issuer_ca_id": 1, "issuer_name": "C=US, O=Let's Encrypt, CN=R3", "common_name": "*.api.example.com",
+         "name_value": "*.api.example.com\nAPI.example.com\nadmin@example.com\nexample.org", "id": 102,
+         "not_before": "2024-02-01T00:00:00", "not_after": "2099-05-01T00:00:00"},
+        {"issuer_ca_id": 2, "issuer_name": "C=US, O=DigiCert Inc, CN=DigiCert TLS", "common_name": "www.example.com",
+         "name_value": "www.example.com", "id": 103,
+         "not_before": "2024-06-01T12:00:00", "not_after": "2099-06-01T12:00:00"},
+        {"issuer_ca_id": 2, "issuer_name": "C=US, O=DigiCert Inc, CN=DigiCert TLS", "common_name": "www.example.com",
+         "name_value": "www.example.com", "id": 103,
+         "not_before": "2024-06-01T12:00:00", "not_after": "2099-06-01T12:00:00"}
+    ]"#;
+
+    #[test]
+    fn test_names_are_deduplicated_and_dated() {
+        let entries: Vec<CertificateEntry> = serde_json::from_str(FIXTURE).unwrap();
+        let (certificates, subdomains) = collect_subdomains("example.com", &entries, true, Utc::now());
+        assert_eq!(certificates, 3);
+
+        let names: Vec<&str> = subdomains.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["api.example.com", "example.com", "www.example.com"]);
+
+        let www = &subdomains[2];
+        assert_eq!(www.certificates, 2);
+        assert_eq!(www.first_issued.as_deref(), Some("2023-01-10T00:00:00Z"));
+        assert_eq!(www.last_issued.as_deref(), Some("2024-06-01T12:00:00Z"));
+        assert_eq!(www.issuers.len(), 2);
+        assert!(subdomains[0].wildcard);
+        assert!(!www.wildcard);
+
+        let (_, current) = collect_subdomains("example.com", &entries, false, Utc::now());
+        assert_eq!(current.iter().find(|s| s.name == "www.example.com").unwrap().certificates, 1);
+    }
+
+    #[tokio::test]
+    async fn test_enrichment_against_fixture_server() {
+        let server = StubServer::start(vec![("/", StubResponse::json(FIXTURE))]).await;
+        let vault = VaultService::open_in_memory().unwrap();
+        set_ct_config(
+            &vault,
+            &CertTransparencyConfig {
+                base_url: server.url(),
+                ..CertTransparencyConfig::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        let report = enrich_certificate_transparency(&vault, "default", "Example.com").await.unwrap();
+        assert_eq!(report.subdomains.len(), 3);
+        assert_eq!(server.requests()[0].query, "q=%25.example.com&output=json");
+
+        let www = vault
+            .find_entity("default", &EntityKey::new(EntityType::Subdomain, "www.example.com"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(www.properties["ct_certificates"], 2);
+        assert_eq!(www.sources[0].reference, "crt.sh");
+        let neighbors = vault.entity_neighbors("default", &www.id, &[]).await.unwrap();
+        assert_eq!(neighbors.entities.iter().find(|e| e.id != www.id).unwrap().value, "example.com");
+
+        // An empty body means no certificates were found
+        let empty = StubServer::start(vec![("/", StubResponse::json(""))]).await;
+        let config = CertTransparencyConfig {
+            base_url: empty.url(),
+            ..CertTransparencyConfig::default()
+        };
+        assert!(fetch_entries(&config, "example.com").await.unwrap().is_empty());
+    }
+}
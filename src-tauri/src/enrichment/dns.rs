@@ -0,0 +1,577 @@
+//! DNS enrichment: A/AAAA/CNAME/MX/NS/TXT lookups for domains, recorded
+//! in the entity graph.
+//!
+//! Lookups go through a [`DnsResolver`]; [`UdpResolver`] queries the
+//! configured recursive resolvers directly, retrying over TCP when an
+//! answer is truncated. Before a subdomain's addresses are trusted, its
+//! parent zone is probed with a random label: if that resolves too, the
+//! zone has a wildcard record and matching answers are flagged instead of
+//! being drawn as real hosts.
+
+#[cfg(test)]
+mod stub;
+mod wire;
+
+use crate::indicators::{self, Indicator};
+use crate::vault::{
+    EntityKey, EntityType, GraphSource, GraphUpsertReport, RelationType, RelationshipInput, SourceKind,
+    VaultService,
+};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpStream, UdpSocket};
+use wire::{Message, RData};
+
+/// Settings key holding the [`DnsConfig`]
+const DNS_SETTING: &str = "enrichment.dns";
+/// Names accepted by one enrichment run
+const MAX_NAMES: usize = 500;
+/// Graph source reference for DNS results
+const SOURCE_REFERENCE: &str = "dns";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DnsRecordType {
+    A,
+    Aaaa,
+    Cname,
+    Mx,
+    Ns,
+    Txt,
+}
+
+impl DnsRecordType {
+    pub const ALL: [DnsRecordType; 6] = [
+        DnsRecordType::A,
+        DnsRecordType::Aaaa,
+        DnsRecordType::Cname,
+        DnsRecordType::Mx,
+        DnsRecordType::Ns,
+        DnsRecordType::Txt,
+    ];
+
+    fn code(self) -> u16 {
+        match self {
+            DnsRecordType::A => wire::TYPE_A,
+            DnsRecordType::Aaaa => wire::TYPE_AAAA,
+            DnsRecordType::Cname => wire::TYPE_CNAME,
+            DnsRecordType::Mx => wire::TYPE_MX,
+            DnsRecordType::Ns => wire::TYPE_NS,
+            DnsRecordType::Txt => wire::TYPE_TXT,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DnsConfig {
+    /// `ip` or `ip:port`; tried in order
+    pub resolvers: Vec<String>,
+    pub timeout_ms: u64,
+    /// Passes over the resolver list before a lookup fails
+    pub attempts: u32,
+    /// Lookups in flight at once
+    pub concurrency: usize,
+    pub record_types: Vec<DnsRecordType>,
+    pub detect_wildcards: bool,
+}
+
+impl Default for DnsConfig {
+    fn default() -> Self {
+        Self {
+            resolvers: vec!["1.1.1.1".to_string(), "8.8.8.8".to_string()],
+            timeout_ms: 2000,
+            attempts: 2,
+            concurrency: 16,
+            record_types: DnsRecordType::ALL.to_vec(),
+            detect_wildcards: true,
+        }
+    }
+}
+
+impl DnsConfig {
+    fn validate(&self) -> Result<()> {
+        if self.resolvers.is_empty() {
+            anyhow::bail!("Configure at least one DNS resolver");
+        }
+        self.resolver_addrs()?;
+        if !(100..=30_000).contains(&self.timeout_ms) {
+            anyhow::bail!("DNS timeout must be between 100 ms and 30 s");
+        }
+        if !(1..=5).contains(&self.attempts) {
+            anyhow::bail!("DNS attempts must be between 1 and 5");
+        }
+        if !(1..=64).contains(&self.concurrency) {
+            anyhow::bail!("DNS concurrency must be between 1 and 64");
+        }
+        Ok(())
+    }
+
+    fn resolver_addrs(&self) -> Result<Vec<SocketAddr>> {
+        self.resolvers
+            .iter()
+            .map(|resolver| {
+                let resolver = resolver.trim();
+                resolver
+                    .parse::<SocketAddr>()
+                    .or_else(|_| resolver.parse::<IpAddr>().map(|ip| SocketAddr::new(ip, 53)))
+                    .with_context(|| format!("Invalid DNS resolver: {}", resolver))
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DnsRecord {
+    /// The name that was looked up
+    pub name: String,
+    #[serde(rename = "type")]
+    pub record_type: DnsRecordType,
+    pub value: String,
+    pub ttl: u32,
+    /// MX preference
+    pub priority: Option<u16>,
+    /// Answer matches the parent zone's wildcard rather than a real host
+    pub wildcard: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DnsReport {
+    pub records: Vec<DnsRecord>,
+    /// Zones that answer for any name
+    pub wildcard_zones: Vec<String>,
+    pub errors: Vec<String>,
+    pub graph: GraphUpsertReport,
+}
+
+#[async_trait]
+pub trait DnsResolver: Send + Sync {
+    /// Records of `record_type` for `name`; empty if the name doesn't exist
+    async fn lookup(&self, name: &str, record_type: DnsRecordType) -> Result<Vec<DnsRecord>>;
+}
+
+pub struct UdpResolver {
+    servers: Vec<SocketAddr>,
+    timeout: Duration,
+    attempts: u32,
+}
+
+impl UdpResolver {
+    pub fn new(config: &DnsConfig) -> Result<Self> {
+        config.validate()?;
+        Ok(Self {
+            servers: config.resolver_addrs()?,
+            timeout: Duration::from_millis(config.timeout_ms),
+            attempts: config.attempts,
+        })
+    }
+
+    async fn exchange(&self, server: SocketAddr, query: &Message) -> Result<Message> {
+        let bytes = query.encode()?;
+        let bind: SocketAddr = if server.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" }.parse()?;
+        let socket = UdpSocket::bind(bind).await.context("Failed to open DNS socket")?;
+        socket.connect(server).await.context("Failed to reach DNS resolver")?;
+        socket.send(&bytes).await.context("Failed to send DNS query")?;
+
+        let mut buffer = vec![0u8; 4096];
+        let response = loop {
+            let len = tokio::time::timeout(self.timeout, socket.recv(&mut buffer))
+                .await
+                .with_context(|| format!("DNS resolver {} timed out", server))?
+                .context("Failed to read DNS response")?;
+            // Ignore stray datagrams that don't answer this query
+            match Message::decode(&buffer[..len]) {
+                Ok(response) if response.id == query.id && response.questions == query.questions => break response,
+                _ => continue,
+            }
+        };
+
+        if response.truncated {
+            return self.exchange_tcp(server, &bytes).await;
+        }
+        Ok(response)
+    }
+
+    async fn exchange_tcp(&self, server: SocketAddr, bytes: &[u8]) -> Result<Message> {
+        let exchange = async {
+            let mut stream = TcpStream::connect(server).await.context("Failed to reach DNS resolver")?;
+            let len = u16::try_from(bytes.len()).context("DNS query too long")?;
+            stream.write_all(&len.to_be_bytes()).await?;
+            stream.write_all(bytes).await?;
+
+            let len = stream.read_u16().await.context("Failed to read DNS response")?;
+            let mut response = vec![0u8; usize::from(len)];
+            stream.read_exact(&mut response).await.context("Failed to read DNS response")?;
+            Message::decode(&response)
+        };
+        tokio::time::timeout(self.timeout, exchange)
+            .await
+            .with_context(|| format!("DNS resolver {} timed out", server))?
+    }
+}
+
+#[async_trait]
+impl DnsResolver for UdpResolver {
+    async fn lookup(&self, name: &str, record_type: DnsRecordType) -> Result<Vec<DnsRecord>> {
+        let query = Message::query(rand::random(), name, record_type.code());
+        let mut last_error = None;
+
+        for _ in 0..self.attempts {
+            for server in &self.servers {
+                let response = match self.exchange(*server, &query).await {
+                    Ok(response) => response,
+                    Err(e) => {
+                        last_error = Some(e);
+                        continue;
+                    }
+                };
+                match response.rcode {
+                    wire::RCODE_NOERROR => return Ok(answer_records(name, record_type, &response)),
+                    wire::RCODE_NXDOMAIN => return Ok(Vec::new()),
+                    rcode => {
+                        last_error = Some(anyhow::anyhow!("DNS resolver {} answered with rcode {}", server, rcode));
+                    }
+                }
+            }
+        }
+        Err(last_error.unwrap_or_else(|| anyhow::anyhow!("No DNS resolvers configured")))
+    }
+}
+
+/// Answers of the requested type, attributed to the name that was asked
+/// about even when they sit at the end of a CNAME chain
+fn answer_records(name: &str, record_type: DnsRecordType, response: &Message) -> Vec<DnsRecord> {
+    response
+        .answers
+        .iter()
+        .filter(|answer| answer.record_type == record_type.code())
+        .filter_map(|answer| {
+            let (value, priority) = match &answer.data {
+                RData::A(ip) => (ip.to_string(), None),
+                RData::Aaaa(ip) => (ip.to_string(), None),
+                RData::Cname(host) | RData::Ns(host) => (host.clone(), None),
+                RData::Mx { preference, exchange } => (exchange.clone(), Some(*preference)),
+                RData::Txt(text) => (text.clone(), None),
+                RData::Other => return None,
+            };
+            Some(DnsRecord {
+                name: name.to_string(),
+                record_type,
+                value,
+                ttl: answer.ttl,
+                priority,
+                wildcard: false,
+            })
+        })
+        .collect()
+}
+
+/// The saved DNS settings, defaults if none were saved
+pub async fn dns_config(vault: &VaultService) -> Result<DnsConfig> {
+    match vault.get_setting(DNS_SETTING).await? {
+        Some(value) => serde_json::from_value(value).context("Failed to parse DNS settings"),
+        None => Ok(DnsConfig::default()),
+    }
+}
+
+pub async fn set_dns_config(vault: &VaultService, config: &DnsConfig) -> Result<()> {
+    config.validate()?;
+    let value = serde_json::to_value(config).context("Failed to serialize DNS settings")?;
+    vault.set_setting(DNS_SETTING, &value).await
+}
+
+/// Resolve `names` with the saved settings and record the results in the
+/// workspace graph
+pub async fn enrich_dns(vault: &VaultService, workspace: &str, names: &[String]) -> Result<DnsReport> {
+    let config = dns_config(vault).await?;
+    let resolver = UdpResolver::new(&config)?;
+    enrich_dns_with(&resolver, &config, vault, workspace, names).await
+}
+
+pub(crate) async fn enrich_dns_with(
+    resolver: &dyn DnsResolver,
+    config: &DnsConfig,
+    vault: &VaultService,
+    workspace: &str,
+    names: &[String],
+) -> Result<DnsReport> {
+    if names.len() > MAX_NAMES {
+        anyhow::bail!("At most {} names can be resolved at once", MAX_NAMES);
+    }
+
+    let mut report = DnsReport::default();
+    let mut domains: Vec<Indicator> = Vec::new();
+    for name in names {
+        match indicators::domain_indicator(name) {
+            Some(domain) if !domains.contains(&domain) => domains.push(domain),
+            Some(_) => {}
+            None => report.errors.push(format!("Not a domain name: {}", name)),
+        }
+    }
+
+    let wildcards = if config.detect_wildcards {
+        detect_wildcards(resolver, config, &domains).await
+    } else {
+        HashMap::new()
+    };
+    report.wildcard_zones = wildcards.keys().cloned().collect();
+    report.wildcard_zones.sort();
+
+    let lookups: Vec<(&str, DnsRecordType)> = domains
+        .iter()
+        .flat_map(|domain| config.record_types.iter().map(move |t| (domain.value.as_str(), *t)))
+        .collect();
+    let results: Vec<_> = stream::iter(lookups)
+        .map(|(name, record_type)| async move { (name, record_type, resolver.lookup(name, record_type).await) })
+        .buffered(config.concurrency.max(1))
+        .collect()
+        .await;
+
+    for (name, record_type, result) in results {
+        match result {
+            Ok(records) => report.records.extend(records),
+            Err(e) => report.errors.push(format!("{} {:?} lookup failed: {}", name, record_type, e)),
+        }
+    }
+    flag_wildcard_matches(&mut report.records, &wildcards);
+
+    let batch = graph_batch(&domains, &report.records, &report.wildcard_zones);
+    report.graph = vault.upsert_graph(workspace, &batch).await?;
+    tracing::info!(
+        "Resolved {} names: {} records, {} errors",
+        domains.len(),
+        report.records.len(),
+        report.errors.len()
+    );
+    Ok(report)
+}
+
+/// Parent zone → addresses a random name under it resolves to, for the
+/// parents of `domains` that have a wildcard record
+async fn detect_wildcards(
+    resolver: &dyn DnsResolver,
+    config: &DnsConfig,
+    domains: &[Indicator],
+) -> HashMap<String, HashSet<String>> {
+    let zones: HashSet<&str> = domains
+        .iter()
+        .filter(|domain| domain.entity_type == EntityType::Subdomain)
+        .filter_map(|domain| domain.value.split_once('.').map(|(_, parent)| parent))
+        .collect();
+
+    let probes = zones.into_iter().flat_map(|zone| {
+        [DnsRecordType::A, DnsRecordType::Aaaa].map(move |record_type| (zone, record_type))
+    });
+    let answers: Vec<_> = stream::iter(probes)
+        .map(|(zone, record_type)| async move {
+            let probe = format!("parallax-{}.{}", &uuid::Uuid::new_v4().simple().to_string()[..12], zone);
+            (zone, resolver.lookup(&probe, record_type).await.unwrap_or_default())
+        })
+        .buffer_unordered(config.concurrency.max(1))
+        .collect()
+        .await;
+
+    let mut wildcards: HashMap<String, HashSet<String>> = HashMap::new();
+    for (zone, records) in answers.into_iter().filter(|(_, records)| !records.is_empty()) {
+        wildcards
+            .entry(zone.to_string())
+            .or_default()
+            .extend(records.into_iter().map(|record| record.value));
+    }
+    wildcards
+}
+
+/// Flag the addresses of names whose every address is their parent's
+/// wildcard answer
+fn flag_wildcard_matches(records: &mut [DnsRecord], wildcards: &HashMap<String, HashSet<String>>) {
+    let mut addresses: HashMap<&str, Vec<&str>> = HashMap::new();
+    for record in records.iter().filter(|r| matches!(r.record_type, DnsRecordType::A | DnsRecordType::Aaaa)) {
+        addresses.entry(record.name.as_str()).or_default().push(record.value.as_str());
+    }
+
+    let matching: HashSet<String> = addresses
+        .into_iter()
+        .filter(|(name, values)| {
+            let wildcard = name.split_once('.').and_then(|(_, parent)| wildcards.get(parent));
+            wildcard.is_some_and(|wildcard| values.iter().all(|value| wildcard.contains(*value)))
+        })
+        .map(|(name, _)| name.to_string())
+        .collect();
+
+    for record in records.iter_mut() {
+        let is_address = matches!(record.record_type, DnsRecordType::A | DnsRecordType::Aaaa);
+        record.wildcard = is_address && matching.contains(&record.name);
+    }
+}
+
+/// Entity key for a host name found in a record
+fn host_key(name: &str) -> EntityKey {
+    match indicators::domain_indicator(name) {
+        Some(host) => EntityKey::new(host.entity_type, host.value),
+        None => EntityKey::new(EntityType::Domain, name),
+    }
+}
+
+fn graph_batch(domains: &[Indicator], records: &[DnsRecord], wildcard_zones: &[String]) -> crate::vault::GraphBatch {
+    let source = GraphSource {
+        kind: SourceKind::Tool,
+        reference: SOURCE_REFERENCE.to_string(),
+    };
+    // Names that didn't resolve to anything aren't worth a node
+    let resolved: Vec<Indicator> = domains
+        .iter()
+        .filter(|domain| records.iter().any(|record| record.name == domain.value))
+        .cloned()
+        .collect();
+    let mut batch = indicators::indicator_batch(&resolved, source);
+
+    let mut txt: BTreeMap<&str, Vec<Value>> = BTreeMap::new();
+    for record in records {
+        let (target, relation_type) = match record.record_type {
+            DnsRecordType::A | DnsRecordType::Aaaa if record.wildcard => continue,
+            DnsRecordType::A | DnsRecordType::Aaaa => {
+                (EntityKey::new(EntityType::Ip, &record.value), RelationType::ResolvesTo)
+            }
+            DnsRecordType::Cname => (host_key(&record.value), RelationType::AliasOf),
+            DnsRecordType::Mx => (host_key(&record.value), RelationType::MailServer),
+            DnsRecordType::Ns => (host_key(&record.value), RelationType::NameServer),
+            DnsRecordType::Txt => {
+                txt.entry(record.name.as_str()).or_default().push(record.value.clone().into());
+                continue;
+            }
+        };
+
+        let mut properties = Map::new();
+        properties.insert("ttl".to_string(), record.ttl.into());
+        if let Some(priority) = record.priority {
+            properties.insert("priority".to_string(), priority.into());
+        }
+        batch.relationships.push(RelationshipInput {
+            source: host_key(&record.name),
+            target,
+            relation_type,
+            properties,
+            seen: None,
+        });
+    }
+
+    let wildcard_matches: HashSet<&str> =
+        records.iter().filter(|record| record.wildcard).map(|record| record.name.as_str()).collect();
+    for entity in &mut batch.entities {
+        if let Some(values) = txt.remove(entity.value.as_str()) {
+            entity.properties.insert("txt_records".to_string(), Value::Array(values));
+        }
+        if wildcard_zones.contains(&entity.value) {
+            entity.properties.insert("wildcard_dns".to_string(), true.into());
+        }
+        if wildcard_matches.contains(entity.value.as_str()) {
+            entity.properties.insert("wildcard_match".to_string(), true.into());
+        }
+    }
+    batch
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stub::StubDnsServer;
+
+    fn config(server: &StubDnsServer) -> DnsConfig {
+        DnsConfig {
+            resolvers: vec![server.addr().to_string()],
+            timeout_ms: 500,
+            attempts: 1,
+            ..DnsConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_lookups_against_local_server() {
+        let server = StubDnsServer::start(vec![
+            ("example.com", RData::A("93.184.216.34".parse().unwrap())),
+            ("example.com", RData::Aaaa("2606:2800:220:1::1".parse().unwrap())),
+            ("example.com", RData::Mx { preference: 10, exchange: "mail.example.com".to_string() }),
+            ("example.com", RData::Ns("ns1.example.net".to_string())),
+            ("example.com", RData::Txt("v=spf1 -all".to_string())),
+            ("www.example.com", RData::Cname("example.com".to_string())),
+            ("www.example.com", RData::A("93.184.216.34".parse().unwrap())),
+        ])
+        .await;
+        let resolver = UdpResolver::new(&config(&server)).unwrap();
+
+        let mx = resolver.lookup("example.com", DnsRecordType::Mx).await.unwrap();
+        assert_eq!(mx[0].value, "mail.example.com");
+        assert_eq!(mx[0].priority, Some(10));
+        let a = resolver.lookup("www.example.com", DnsRecordType::A).await.unwrap();
+        assert_eq!(a.len(), 1);
+        assert_eq!(a[0].value, "93.184.216.34");
+        assert!(resolver.lookup("missing.example.com", DnsRecordType::A).await.unwrap().is_empty());
+
+        // Long TXT answers are truncated over UDP and retried over TCP
+        let long = "k=".repeat(1000);
+        let server = StubDnsServer::start(vec![("big.example.org", RData::Txt(long.clone()))]).await;
+        let resolver = UdpResolver::new(&config(&server)).unwrap();
+        let txt = resolver.lookup("big.example.org", DnsRecordType::Txt).await.unwrap();
+        assert_eq!(txt[0].value, long);
+    }
+
+    #[tokio::test]
+    async fn test_enrichment_records_graph_and_flags_wildcards() {
+        let server = StubDnsServer::start(vec![
+            ("example.com", RData::A("203.0.113.1".parse().unwrap())),
+            ("example.com", RData::Mx { preference: 5, exchange: "mx.example.com".to_string() }),
+            ("example.com", RData::Txt("v=spf1 -all".to_string())),
+            ("api.example.com", RData::A("203.0.113.7".parse().unwrap())),
+            ("*.example.com", RData::A("203.0.113.99".parse().unwrap())),
+        ])
+        .await;
+        let vault = VaultService::open_in_memory().unwrap();
+        let names = ["Example.com", "api.example.com", "typo.example.com", "nope.example.net"].map(String::from);
+
+        let report = enrich_dns_with(
+            &UdpResolver::new(&config(&server)).unwrap(),
+            &config(&server),
+            &vault,
+            "default",
+            &names,
+        )
+        .await
+        .unwrap();
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        assert_eq!(report.wildcard_zones, vec!["example.com".to_string()]);
+        let typo = report.records.iter().find(|r| r.name == "typo.example.com").unwrap();
+        assert!(typo.wildcard);
+
+        let graph = vault.load_graph("default").await.unwrap();
+        let value = |id: &str| graph.entities.iter().find(|e| e.id == id).unwrap().value.clone();
+        let mut edges: Vec<(String, RelationType, String)> = graph
+            .relationships
+            .iter()
+            .filter(|r| r.relation_type != RelationType::SubdomainOf)
+            .map(|r| (value(&r.source_id), r.relation_type, value(&r.target_id)))
+            .collect();
+        edges.sort_by(|a, b| a.0.cmp(&b.0).then(a.2.cmp(&b.2)));
+        assert_eq!(
+            edges,
+            vec![
+                ("api.example.com".to_string(), RelationType::ResolvesTo, "203.0.113.7".to_string()),
+                ("example.com".to_string(), RelationType::ResolvesTo, "203.0.113.1".to_string()),
+                ("example.com".to_string(), RelationType::MailServer, "mx.example.com".to_string()),
+            ]
+        );
+
+        let apex = graph.entities.iter().find(|e| e.value == "example.com").unwrap();
+        assert_eq!(apex.properties["wildcard_dns"], true);
+        assert_eq!(apex.properties["txt_records"][0], "v=spf1 -all");
+        let typo = graph.entities.iter().find(|e| e.value == "typo.example.com").unwrap();
+        assert_eq!(typo.properties["wildcard_match"], true);
+        assert!(!graph.entities.iter().any(|e| e.value == "nope.example.net"));
+    }
+}
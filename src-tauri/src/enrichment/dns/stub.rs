@@ -0,0 +1,143 @@
+//! Local DNS stand-in for resolver tests.
+//!
+//! [`StubDnsServer`] answers UDP and TCP queries on one ephemeral localhost
+//! port from a fixed record list. `*.zone` entries answer for names under
+//! the zone that have no records of their own, names with no records get
+//! NXDOMAIN, and UDP answers over 512 bytes come back truncated so clients
+//! have to retry over TCP.
+
+use super::wire::{self, Message, RData, Record};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, UdpSocket};
+
+/// Largest UDP answer sent without the truncation flag
+const UDP_LIMIT: usize = 512;
+
+pub struct StubDnsServer {
+    addr: SocketAddr,
+    tasks: Vec<tokio::task::JoinHandle<()>>,
+}
+
+impl StubDnsServer {
+    pub async fn start(records: Vec<(&str, RData)>) -> Self {
+        let records: Arc<Vec<(String, RData)>> =
+            Arc::new(records.into_iter().map(|(name, data)| (name.to_string(), data)).collect());
+
+        // Both transports share the port, as they do on a real resolver
+        let (udp, tcp) = loop {
+            let udp = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+            if let Ok(tcp) = TcpListener::bind(udp.local_addr().unwrap()).await {
+                break (udp, tcp);
+            }
+        };
+        let addr = udp.local_addr().unwrap();
+
+        let udp_records = records.clone();
+        let udp_task = tokio::spawn(async move {
+            let mut buffer = [0u8; 4096];
+            while let Ok((len, peer)) = udp.recv_from(&mut buffer).await {
+                let Some(mut reply) = answer(&udp_records, &buffer[..len]) else {
+                    continue;
+                };
+                if reply.encode().unwrap().len() > UDP_LIMIT {
+                    reply.truncated = true;
+                    reply.answers.clear();
+                }
+                let _ = udp.send_to(&reply.encode().unwrap(), peer).await;
+            }
+        });
+
+        let tcp_task = tokio::spawn(async move {
+            while let Ok((mut stream, _)) = tcp.accept().await {
+                let records = records.clone();
+                tokio::spawn(async move {
+                    let Ok(len) = stream.read_u16().await else {
+                        return;
+                    };
+                    let mut query = vec![0u8; usize::from(len)];
+                    if stream.read_exact(&mut query).await.is_err() {
+                        return;
+                    }
+                    if let Some(reply) = answer(&records, &query) {
+                        let bytes = reply.encode().unwrap();
+                        let _ = stream.write_all(&(bytes.len() as u16).to_be_bytes()).await;
+                        let _ = stream.write_all(&bytes).await;
+                    }
+                });
+            }
+        });
+
+        Self {
+            addr,
+            tasks: vec![udp_task, tcp_task],
+        }
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+impl Drop for StubDnsServer {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+fn type_code(data: &RData) -> u16 {
+    match data {
+        RData::A(_) => wire::TYPE_A,
+        RData::Aaaa(_) => wire::TYPE_AAAA,
+        RData::Cname(_) => wire::TYPE_CNAME,
+        RData::Mx { .. } => wire::TYPE_MX,
+        RData::Ns(_) => wire::TYPE_NS,
+        RData::Txt(_) => wire::TYPE_TXT,
+        RData::Other => 0,
+    }
+}
+
+fn answer(records: &[(String, RData)], query: &[u8]) -> Option<Message> {
+    let query = Message::decode(query).ok()?;
+    let question = query.questions.first()?.clone();
+
+    let own: Vec<&RData> = records.iter().filter(|(name, _)| *name == question.name).map(|(_, data)| data).collect();
+    let matching = if own.is_empty() {
+        let wildcard = question.name.split_once('.').map(|(_, parent)| format!("*.{}", parent));
+        records
+            .iter()
+            .filter(|(name, _)| Some(name) == wildcard.as_ref())
+            .map(|(_, data)| data)
+            .collect()
+    } else {
+        own
+    };
+
+    let answers = matching
+        .into_iter()
+        .filter(|data| type_code(data) == question.record_type || type_code(data) == wire::TYPE_CNAME)
+        .map(|data| Record {
+            name: question.name.clone(),
+            record_type: type_code(data),
+            ttl: 300,
+            data: data.clone(),
+        })
+        .collect::<Vec<_>>();
+    let exists = records.iter().any(|(name, _)| *name == question.name)
+        || question
+            .name
+            .split_once('.')
+            .is_some_and(|(_, parent)| records.iter().any(|(name, _)| *name == format!("*.{}", parent)));
+
+    Some(Message {
+        id: query.id,
+        response: true,
+        truncated: false,
+        rcode: if exists { wire::RCODE_NOERROR } else { wire::RCODE_NXDOMAIN },
+        questions: query.questions,
+        answers,
+    })
+}
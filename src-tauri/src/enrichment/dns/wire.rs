@@ -0,0 +1,303 @@
+//! DNS message encoding and decoding (RFC 1035), limited to what lookups
+//! need: one question, the answer section, and the record types in
+//! [`RData`]. Authority and additional sections are skipped.
+
+use anyhow::{Context, Result};
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+pub const TYPE_A: u16 = 1;
+pub const TYPE_NS: u16 = 2;
+pub const TYPE_CNAME: u16 = 5;
+pub const TYPE_MX: u16 = 15;
+pub const TYPE_TXT: u16 = 16;
+pub const TYPE_AAAA: u16 = 28;
+const TYPE_OPT: u16 = 41;
+const CLASS_IN: u16 = 1;
+
+pub const RCODE_NOERROR: u8 = 0;
+pub const RCODE_NXDOMAIN: u8 = 3;
+
+/// UDP payload size advertised through EDNS(0)
+const EDNS_PAYLOAD: u16 = 1232;
+/// Compression pointers followed before a name is rejected as a loop
+const MAX_POINTERS: usize = 32;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Question {
+    pub name: String,
+    pub record_type: u16,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RData {
+    A(Ipv4Addr),
+    Aaaa(Ipv6Addr),
+    Cname(String),
+    Mx { preference: u16, exchange: String },
+    Ns(String),
+    /// Character-strings joined together
+    Txt(String),
+    Other,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    pub name: String,
+    pub record_type: u16,
+    pub ttl: u32,
+    pub data: RData,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Message {
+    pub id: u16,
+    pub response: bool,
+    pub truncated: bool,
+    pub rcode: u8,
+    pub questions: Vec<Question>,
+    pub answers: Vec<Record>,
+}
+
+impl Message {
+    /// A recursive query for one name
+    pub fn query(id: u16, name: &str, record_type: u16) -> Self {
+        Self {
+            id,
+            questions: vec![Question {
+                name: name.to_string(),
+                record_type,
+            }],
+            ..Self::default()
+        }
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        let mut flags: u16 = 0x0100; // RD
+        if self.response {
+            flags |= 0x8000 | 0x0080; // QR, RA
+        }
+        if self.truncated {
+            flags |= 0x0200;
+        }
+        flags |= u16::from(self.rcode & 0x0f);
+
+        let mut out = Vec::with_capacity(512);
+        out.extend_from_slice(&self.id.to_be_bytes());
+        out.extend_from_slice(&flags.to_be_bytes());
+        for count in [self.questions.len(), self.answers.len(), 0, usize::from(!self.response)] {
+            out.extend_from_slice(&u16::try_from(count).context("Too many DNS records")?.to_be_bytes());
+        }
+
+        for question in &self.questions {
+            encode_name(&mut out, &question.name)?;
+            out.extend_from_slice(&question.record_type.to_be_bytes());
+            out.extend_from_slice(&CLASS_IN.to_be_bytes());
+        }
+        for record in &self.answers {
+            encode_record(&mut out, record)?;
+        }
+        if !self.response {
+            // EDNS(0) OPT pseudo-record: root name, payload size, no options
+            out.push(0);
+            out.extend_from_slice(&TYPE_OPT.to_be_bytes());
+            out.extend_from_slice(&EDNS_PAYLOAD.to_be_bytes());
+            out.extend_from_slice(&[0, 0, 0, 0, 0, 0]);
+        }
+        Ok(out)
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader { bytes, pos: 0 };
+        let id = reader.u16()?;
+        let flags = reader.u16()?;
+        let question_count = reader.u16()?;
+        let answer_count = reader.u16()?;
+        reader.skip(4)?; // authority and additional counts
+
+        let mut questions = Vec::with_capacity(usize::from(question_count));
+        for _ in 0..question_count {
+            let name = reader.name()?;
+            let record_type = reader.u16()?;
+            reader.skip(2)?;
+            questions.push(Question { name, record_type });
+        }
+
+        let mut answers = Vec::with_capacity(usize::from(answer_count));
+        for _ in 0..answer_count {
+            answers.push(reader.record()?);
+        }
+
+        Ok(Self {
+            id,
+            response: flags & 0x8000 != 0,
+            truncated: flags & 0x0200 != 0,
+            rcode: (flags & 0x000f) as u8,
+            questions,
+            answers,
+        })
+    }
+}
+
+fn encode_name(out: &mut Vec<u8>, name: &str) -> Result<()> {
+    for label in name.trim_end_matches('.').split('.').filter(|label| !label.is_empty()) {
+        let len = u8::try_from(label.len())
+            .ok()
+            .filter(|len| *len <= 63)
+            .with_context(|| format!("DNS label too long in {}", name))?;
+        out.push(len);
+        out.extend_from_slice(label.as_bytes());
+    }
+    out.push(0);
+    Ok(())
+}
+
+fn encode_record(out: &mut Vec<u8>, record: &Record) -> Result<()> {
+    let mut data = Vec::new();
+    match &record.data {
+        RData::A(ip) => data.extend_from_slice(&ip.octets()),
+        RData::Aaaa(ip) => data.extend_from_slice(&ip.octets()),
+        RData::Cname(name) | RData::Ns(name) => encode_name(&mut data, name)?,
+        RData::Mx { preference, exchange } => {
+            data.extend_from_slice(&preference.to_be_bytes());
+            encode_name(&mut data, exchange)?;
+        }
+        RData::Txt(text) => {
+            for chunk in text.as_bytes().chunks(255) {
+                data.push(chunk.len() as u8);
+                data.extend_from_slice(chunk);
+            }
+        }
+        RData::Other => {}
+    }
+
+    encode_name(out, &record.name)?;
+    out.extend_from_slice(&record.record_type.to_be_bytes());
+    out.extend_from_slice(&CLASS_IN.to_be_bytes());
+    out.extend_from_slice(&record.ttl.to_be_bytes());
+    out.extend_from_slice(&u16::try_from(data.len()).context("DNS record too long")?.to_be_bytes());
+    out.extend_from_slice(&data);
+    Ok(())
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, len: usize) -> Result<&[u8]> {
+        let end = self.pos.checked_add(len).filter(|end| *end <= self.bytes.len());
+        let end = end.context("DNS message is truncated")?;
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn skip(&mut self, len: usize) -> Result<()> {
+        self.take(len).map(|_| ())
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// A possibly compressed name, lower-cased and without the root dot
+    fn name(&mut self) -> Result<String> {
+        let mut labels: Vec<String> = Vec::new();
+        let mut pos = self.pos;
+        let mut resume = None;
+        let mut pointers = 0;
+
+        loop {
+            let len = *self.bytes.get(pos).context("DNS name is truncated")?;
+            match len {
+                0 => {
+                    pos += 1;
+                    break;
+                }
+                len if len & 0xc0 == 0xc0 => {
+                    let low = *self.bytes.get(pos + 1).context("DNS name is truncated")?;
+                    pointers += 1;
+                    if pointers > MAX_POINTERS {
+                        anyhow::bail!("DNS name compression loop");
+                    }
+                    resume.get_or_insert(pos + 2);
+                    pos = usize::from(u16::from_be_bytes([len & 0x3f, low]));
+                }
+                len if len & 0xc0 == 0 => {
+                    let start = pos + 1;
+                    let label = self
+                        .bytes
+                        .get(start..start + usize::from(len))
+                        .context("DNS name is truncated")?;
+                    labels.push(String::from_utf8_lossy(label).to_lowercase());
+                    pos = start + usize::from(len);
+                }
+                _ => anyhow::bail!("Unsupported DNS label type"),
+            }
+        }
+
+        self.pos = resume.unwrap_or(pos);
+        Ok(labels.join("."))
+    }
+
+    fn record(&mut self) -> Result<Record> {
+        let name = self.name()?;
+        let record_type = self.u16()?;
+        self.skip(2)?;
+        let ttl = self.u32()?;
+        let len = usize::from(self.u16()?);
+        let end = self.pos + len;
+        if end > self.bytes.len() {
+            anyhow::bail!("DNS message is truncated");
+        }
+
+        let data = match record_type {
+            TYPE_A => {
+                let b = self.take(4)?;
+                RData::A(Ipv4Addr::new(b[0], b[1], b[2], b[3]))
+            }
+            TYPE_AAAA => {
+                let octets: [u8; 16] = self.take(16)?.try_into().context("Invalid AAAA record")?;
+                RData::Aaaa(Ipv6Addr::from(octets))
+            }
+            TYPE_CNAME => RData::Cname(self.name()?),
+            TYPE_NS => RData::Ns(self.name()?),
+            TYPE_MX => RData::Mx {
+                preference: self.u16()?,
+                exchange: self.name()?,
+            },
+            TYPE_TXT => {
+                let mut text = Vec::new();
+                while self.pos < end {
+                    let len = usize::from(self.u8()?);
+                    text.extend_from_slice(self.take(len)?);
+                }
+                RData::Txt(String::from_utf8_lossy(&text).to_string())
+            }
+            _ => RData::Other,
+        };
+
+        // Names inside the data may be compressed; the length is authoritative
+        if self.pos > end {
+            anyhow::bail!("DNS record overruns its length");
+        }
+        self.pos = end;
+        Ok(Record {
+            name,
+            record_type,
+            ttl,
+            data,
+        })
+    }
+}
@@ -0,0 +1,372 @@
+//! Queries over the entity graph stored in the vault.
+//!
+//! Everything here works on a loaded [`Subgraph`]: a [`GraphFilter`] first
+//! narrows it to the entities and relationships that may be walked, then
+//! relationships are followed in either direction. Results are subgraphs
+//! again so the frontend can draw them as they are.
+
+mod interchange;
+mod layout;
+
+pub use interchange::{export_graph, import_graph, GraphDocument, GraphFormat, GraphImportReport};
+pub use layout::{compute_layout, LayoutOptions, NodePosition};
+
+use crate::vault::{EntityType, RelationType, Subgraph};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// What a traversal may walk through. Empty lists allow every type; the
+/// time window keeps anything seen at some point inside it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GraphFilter {
+    pub entity_types: Vec<EntityType>,
+    pub relation_types: Vec<RelationType>,
+    /// RFC 3339
+    pub since: Option<String>,
+    /// RFC 3339
+    pub until: Option<String>,
+}
+
+struct Window {
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+}
+
+impl Window {
+    fn new(filter: &GraphFilter) -> Result<Self> {
+        let parse = |at: &Option<String>| {
+            at.as_deref()
+                .map(|at| {
+                    DateTime::parse_from_rfc3339(at)
+                        .map(|at| at.with_timezone(&Utc))
+                        .with_context(|| format!("Invalid time: {}", at))
+                })
+                .transpose()
+        };
+        Ok(Self {
+            since: parse(&filter.since)?,
+            until: parse(&filter.until)?,
+        })
+    }
+
+    fn overlaps(&self, first_seen: &str, last_seen: &str) -> bool {
+        let parse = |at: &str| DateTime::parse_from_rfc3339(at).ok().map(|at| at.with_timezone(&Utc));
+        let after_since = match (self.since, parse(last_seen)) {
+            (Some(since), Some(last_seen)) => last_seen >= since,
+            _ => true,
+        };
+        let before_until = match (self.until, parse(first_seen)) {
+            (Some(until), Some(first_seen)) => first_seen <= until,
+            _ => true,
+        };
+        after_since && before_until
+    }
+}
+
+/// Adjacency over the part of a graph a filter allows
+struct Walkable<'a> {
+    graph: &'a Subgraph,
+    index: HashMap<&'a str, usize>,
+    /// Per entity: (neighbor entity, relationship) in relationship order
+    adjacency: Vec<Vec<(usize, usize)>>,
+    allowed: Vec<bool>,
+}
+
+impl<'a> Walkable<'a> {
+    /// `always` entities stay walkable whatever their type, so a query's
+    /// own endpoints are never filtered out
+    fn new(graph: &'a Subgraph, filter: &GraphFilter, always: &[&str]) -> Result<Self> {
+        let window = Window::new(filter)?;
+        let index: HashMap<&str, usize> =
+            graph.entities.iter().enumerate().map(|(i, e)| (e.id.as_str(), i)).collect();
+
+        let allowed: Vec<bool> = graph
+            .entities
+            .iter()
+            .map(|e| {
+                always.contains(&e.id.as_str())
+                    || ((filter.entity_types.is_empty() || filter.entity_types.contains(&e.entity_type))
+                        && window.overlaps(&e.first_seen, &e.last_seen))
+            })
+            .collect();
+
+        let mut adjacency = vec![Vec::new(); graph.entities.len()];
+        for (r, relationship) in graph.relationships.iter().enumerate() {
+            let (Some(&source), Some(&target)) =
+                (index.get(relationship.source_id.as_str()), index.get(relationship.target_id.as_str()))
+            else {
+                continue;
+            };
+            let walkable = allowed[source]
+                && allowed[target]
+                && (filter.relation_types.is_empty() || filter.relation_types.contains(&relationship.relation_type))
+                && window.overlaps(&relationship.first_seen, &relationship.last_seen);
+            if walkable {
+                adjacency[source].push((target, r));
+                adjacency[target].push((source, r));
+            }
+        }
+
+        Ok(Self { graph, index, adjacency, allowed })
+    }
+
+    fn position(&self, entity_id: &str) -> Result<usize> {
+        self.index
+            .get(entity_id)
+            .copied()
+            .with_context(|| format!("Entity not found: {}", entity_id))
+    }
+
+    /// Hop counts from `start`, up to `max_hops`
+    fn distances(&self, start: usize, max_hops: Option<usize>) -> HashMap<usize, usize> {
+        let mut distances = HashMap::from([(start, 0)]);
+        let mut queue = VecDeque::from([start]);
+        while let Some(current) = queue.pop_front() {
+            let distance = distances[&current];
+            if max_hops.is_some_and(|max| distance >= max) {
+                continue;
+            }
+            for &(neighbor, _) in &self.adjacency[current] {
+                if let Entry::Vacant(entry) = distances.entry(neighbor) {
+                    entry.insert(distance + 1);
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        distances
+    }
+
+    /// The given entities, in graph order, with every walkable relationship among them
+    fn induced(&self, members: &HashSet<usize>) -> Subgraph {
+        let mut relationships: Vec<usize> = members
+            .iter()
+            .flat_map(|&m| self.adjacency[m].iter().filter(|(n, _)| members.contains(n)).map(|&(_, r)| r))
+            .collect();
+        relationships.sort_unstable();
+        relationships.dedup();
+
+        Subgraph {
+            entities: (0..self.graph.entities.len())
+                .filter(|i| members.contains(i))
+                .map(|i| self.graph.entities[i].clone())
+                .collect(),
+            relationships: relationships.into_iter().map(|r| self.graph.relationships[r].clone()).collect(),
+        }
+    }
+}
+
+/// Only the entities and relationships `filter` allows
+pub fn filter_graph(graph: &Subgraph, filter: &GraphFilter) -> Result<Subgraph> {
+    let walkable = Walkable::new(graph, filter, &[])?;
+    let members = (0..graph.entities.len()).filter(|&i| walkable.allowed[i]).collect();
+    Ok(walkable.induced(&members))
+}
+
+/// Entities within `hops` of `start`, e.g. every IP within two hops of a
+/// domain. `result_types` limits what is returned (the start is always
+/// included) without limiting what is walked through to get there.
+pub fn neighborhood(
+    graph: &Subgraph,
+    start: &str,
+    hops: usize,
+    filter: &GraphFilter,
+    result_types: &[EntityType],
+) -> Result<Subgraph> {
+    let walkable = Walkable::new(graph, filter, &[start])?;
+    let origin = walkable.position(start)?;
+
+    let members = walkable
+        .distances(origin, Some(hops))
+        .into_keys()
+        .filter(|&i| {
+            i == origin || result_types.is_empty() || result_types.contains(&graph.entities[i].entity_type)
+        })
+        .collect();
+    Ok(walkable.induced(&members))
+}
+
+/// The fewest-hop path between two entities, with entities and
+/// relationships in path order, or `None` if they aren't connected
+pub fn shortest_path(graph: &Subgraph, from: &str, to: &str, filter: &GraphFilter) -> Result<Option<Subgraph>> {
+    let walkable = Walkable::new(graph, filter, &[from, to])?;
+    let (start, goal) = (walkable.position(from)?, walkable.position(to)?);
+
+    // Breadth-first from the start, remembering how each entity was reached
+    let mut previous: HashMap<usize, (usize, usize)> = HashMap::new();
+    let mut seen = HashSet::from([start]);
+    let mut queue = VecDeque::from([start]);
+    while let Some(current) = queue.pop_front() {
+        if current == goal {
+            break;
+        }
+        for &(neighbor, relationship) in &walkable.adjacency[current] {
+            if seen.insert(neighbor) {
+                previous.insert(neighbor, (current, relationship));
+                queue.push_back(neighbor);
+            }
+        }
+    }
+    if !seen.contains(&goal) {
+        return Ok(None);
+    }
+
+    let mut path = Subgraph {
+        entities: vec![graph.entities[goal].clone()],
+        relationships: Vec::new(),
+    };
+    let mut current = goal;
+    while let Some(&(before, relationship)) = previous.get(&current) {
+        path.entities.push(graph.entities[before].clone());
+        path.relationships.push(graph.relationships[relationship].clone());
+        current = before;
+    }
+    path.entities.reverse();
+    path.relationships.reverse();
+    Ok(Some(path))
+}
+
+/// Groups of entities connected to each other, largest first
+pub fn connected_components(graph: &Subgraph, filter: &GraphFilter) -> Result<Vec<Subgraph>> {
+    let walkable = Walkable::new(graph, filter, &[])?;
+    let mut assigned = vec![false; graph.entities.len()];
+    let mut components = Vec::new();
+
+    for start in 0..graph.entities.len() {
+        if assigned[start] || !walkable.allowed[start] {
+            continue;
+        }
+        let members: HashSet<usize> = walkable.distances(start, None).into_keys().collect();
+        for &member in &members {
+            assigned[member] = true;
+        }
+        components.push(walkable.induced(&members));
+    }
+
+    // Stable, so equal-sized components keep graph order
+    components.sort_by_key(|component| std::cmp::Reverse(component.entities.len()));
+    Ok(components)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vault::{Entity, Relationship};
+    use serde_json::Map;
+
+    fn entity(id: &str, entity_type: EntityType, seen: &str) -> Entity {
+        Entity {
+            id: id.to_string(),
+            entity_type,
+            value: id.to_string(),
+            properties: Map::new(),
+            sources: Vec::new(),
+            first_seen: seen.to_string(),
+            last_seen: seen.to_string(),
+        }
+    }
+
+    fn relationship(source: &str, target: &str, relation_type: RelationType) -> Relationship {
+        Relationship {
+            id: format!("{}->{}", source, target),
+            source_id: source.to_string(),
+            target_id: target.to_string(),
+            relation_type,
+            properties: Map::new(),
+            sources: Vec::new(),
+            first_seen: "2026-01-01T00:00:00Z".to_string(),
+            last_seen: "2026-01-01T00:00:00Z".to_string(),
+        }
+    }
+
+    /// example.com with two subdomains on two IPs, an unrelated island, and
+    /// an older mail server
+    fn sample() -> Subgraph {
+        use EntityType::*;
+        use RelationType::*;
+        let seen = "2026-01-01T00:00:00Z";
+        Subgraph {
+            entities: vec![
+                entity("example.com", Domain, seen),
+                entity("www", Subdomain, seen),
+                entity("api", Subdomain, seen),
+                entity("10.0.0.1", Ip, seen),
+                entity("10.0.0.2", Ip, seen),
+                entity("nginx", Technology, seen),
+                entity("mx", Subdomain, "2024-01-01T00:00:00Z"),
+                entity("other.org", Domain, seen),
+                entity("10.9.9.9", Ip, seen),
+            ],
+            relationships: vec![
+                relationship("www", "example.com", SubdomainOf),
+                relationship("api", "example.com", SubdomainOf),
+                relationship("www", "10.0.0.1", ResolvesTo),
+                relationship("api", "10.0.0.2", ResolvesTo),
+                relationship("10.0.0.2", "nginx", Uses),
+                relationship("example.com", "mx", MailServer),
+                relationship("other.org", "10.9.9.9", ResolvesTo),
+            ],
+        }
+    }
+
+    fn ids(graph: &Subgraph) -> Vec<&str> {
+        graph.entities.iter().map(|e| e.id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_neighborhood_and_filters() {
+        let graph = sample();
+        let none = GraphFilter::default();
+
+        let ips = neighborhood(&graph, "example.com", 2, &none, &[EntityType::Ip]).unwrap();
+        assert_eq!(ids(&ips), vec!["example.com", "10.0.0.1", "10.0.0.2"]);
+        assert!(ips.relationships.is_empty());
+
+        let one_hop = neighborhood(&graph, "example.com", 1, &none, &[]).unwrap();
+        assert_eq!(ids(&one_hop), vec!["example.com", "www", "api", "mx"]);
+        assert_eq!(one_hop.relationships.len(), 3);
+
+        // The old mail server falls outside the window
+        let recent = GraphFilter { since: Some("2025-06-01T00:00:00Z".to_string()), ..Default::default() };
+        assert!(!ids(&neighborhood(&graph, "example.com", 1, &recent, &[]).unwrap()).contains(&"mx"));
+
+        // Only subdomain_of edges: the IPs can't be reached
+        let subdomains = GraphFilter { relation_types: vec![RelationType::SubdomainOf], ..Default::default() };
+        assert_eq!(neighborhood(&graph, "example.com", 3, &subdomains, &[]).unwrap().entities.len(), 3);
+
+        let domains = GraphFilter { entity_types: vec![EntityType::Domain], ..Default::default() };
+        assert_eq!(ids(&filter_graph(&graph, &domains).unwrap()), vec!["example.com", "other.org"]);
+        assert!(neighborhood(&graph, "missing", 1, &none, &[]).is_err());
+    }
+
+    #[test]
+    fn test_shortest_path_and_components() {
+        let graph = sample();
+        let none = GraphFilter::default();
+
+        let path = shortest_path(&graph, "10.0.0.1", "nginx", &none).unwrap().unwrap();
+        assert_eq!(ids(&path), vec!["10.0.0.1", "www", "example.com", "api", "10.0.0.2", "nginx"]);
+        assert_eq!(path.relationships.len(), 5);
+        assert_eq!(path.relationships[0].id, "www->10.0.0.1");
+
+        assert!(shortest_path(&graph, "www", "other.org", &none).unwrap().is_none());
+        let trivial = shortest_path(&graph, "www", "www", &none).unwrap().unwrap();
+        assert_eq!((trivial.entities.len(), trivial.relationships.len()), (1, 0));
+
+        // Without IPs to walk through, nginx is cut off
+        let no_ips = GraphFilter {
+            entity_types: vec![EntityType::Domain, EntityType::Subdomain],
+            ..Default::default()
+        };
+        assert!(shortest_path(&graph, "10.0.0.1", "nginx", &no_ips).unwrap().is_none());
+
+        let components = connected_components(&graph, &none).unwrap();
+        let sizes: Vec<usize> = components.iter().map(|c| c.entities.len()).collect();
+        assert_eq!(sizes, vec![7, 2]);
+        assert_eq!(components[1].relationships.len(), 1);
+        assert_eq!(connected_components(&graph, &no_ips).unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,991 @@
+//! Entity graph exchange with Gephi (GraphML, GEXF) and Maltego (entity and
+//! link CSV tables).
+//!
+//! Exports carry the entity or relationship type, when it was first and last
+//! seen, and every property. Property columns are typed (boolean, long,
+//! double or string); arrays and objects are written as JSON into attributes
+//! whose id ends in `_json`, so they come back unchanged. Maltego columns
+//! carry the type as a header suffix such as ` (long)`, and columns without
+//! one are read as strings. Properties whose names clash with a fixed
+//! attribute or column are written with a `property.` prefix. Files from
+//! other tools are read leniently: entities without a known type get one
+//! guessed from their value, and anything that can't be placed is skipped
+//! with a warning.
+
+use crate::vault::{
+    normalize_value, EntityInput, EntityKey, EntityType, GraphBatch, GraphSource, GraphUpsertReport, RelationType,
+    RelationshipInput, Seen, Subgraph,
+};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::net::IpAddr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GraphFormat {
+    Graphml,
+    Gexf,
+    MaltegoCsv,
+}
+
+/// A graph file, or the pair of tables Maltego uses
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "format", rename_all = "snake_case")]
+pub enum GraphDocument {
+    Graphml { content: String },
+    Gexf { content: String },
+    MaltegoCsv { entities: String, links: String },
+}
+
+/// A parsed document, ready to merge into the vault
+#[derive(Debug, Clone)]
+pub struct GraphImport {
+    pub batch: GraphBatch,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphImportReport {
+    #[serde(flatten)]
+    pub merged: GraphUpsertReport,
+    /// Nodes and edges that were skipped
+    pub warnings: Vec<String>,
+}
+
+// Attribute names with a fixed meaning on nodes and edges
+const TYPE: &str = "type";
+const LABEL: &str = "label";
+const FIRST_SEEN: &str = "first_seen";
+const LAST_SEEN: &str = "last_seen";
+const RESERVED: &[&str] = &[TYPE, LABEL, "value", FIRST_SEEN, LAST_SEEN];
+/// Marks a property whose name would be read as a fixed attribute or column
+const PROPERTY_PREFIX: &str = "property.";
+
+const MALTEGO_TYPES: &[(EntityType, &str)] = &[
+    (EntityType::Domain, "maltego.Domain"),
+    (EntityType::Subdomain, "maltego.DNSName"),
+    (EntityType::Ip, "maltego.IPv4Address"),
+    (EntityType::Cidr, "maltego.Netblock"),
+    (EntityType::Url, "maltego.URL"),
+    (EntityType::Email, "maltego.EmailAddress"),
+    (EntityType::Hash, "maltego.Hash"),
+    (EntityType::Technology, "maltego.Phrase"),
+    (EntityType::Service, "maltego.Service"),
+    (EntityType::Organization, "maltego.Company"),
+    (EntityType::Person, "maltego.Person"),
+];
+
+/// Further Maltego types read back as one of ours
+const MALTEGO_ALIASES: &[(&str, EntityType)] = &[
+    ("maltego.IPv6Address", EntityType::Ip),
+    ("maltego.Website", EntityType::Subdomain),
+    ("maltego.MXRecord", EntityType::Subdomain),
+    ("maltego.NSRecord", EntityType::Subdomain),
+    ("maltego.Organization", EntityType::Organization),
+];
+
+const ENTITY_COLUMNS: &[&str] = &["Entity ID", "Entity Type", "Value", "Parallax Type", "First Seen", "Last Seen"];
+const LINK_COLUMNS: &[&str] = &["Source ID", "Target ID", "Label", "Parallax Type", "First Seen", "Last Seen"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AttributeKind {
+    Boolean,
+    Long,
+    Double,
+    String,
+    Json,
+}
+
+/// A property column shared by every node or every edge
+struct Attribute {
+    id: String,
+    /// Property name
+    name: String,
+    /// Name in the file, see [`exported_name`]
+    title: String,
+    kind: AttributeKind,
+}
+
+impl AttributeKind {
+    /// The narrowest kind that holds every value
+    fn of<'a>(values: impl Iterator<Item = &'a Value>) -> Self {
+        let mut kind = None;
+        for value in values {
+            let this = match value {
+                Value::Bool(_) => AttributeKind::Boolean,
+                Value::Number(n) if n.is_i64() => AttributeKind::Long,
+                Value::Number(_) => AttributeKind::Double,
+                Value::String(_) => AttributeKind::String,
+                _ => AttributeKind::Json,
+            };
+            kind = Some(match (kind, this) {
+                (None, this) => this,
+                (Some(kind), this) if kind == this => kind,
+                (Some(AttributeKind::Long | AttributeKind::Double), AttributeKind::Long | AttributeKind::Double) => {
+                    AttributeKind::Double
+                }
+                _ => AttributeKind::Json,
+            });
+        }
+        kind.unwrap_or(AttributeKind::String)
+    }
+
+    /// Maltego header suffix; plain columns are strings
+    fn suffix(self) -> Option<&'static str> {
+        match self {
+            AttributeKind::Boolean => Some("boolean"),
+            AttributeKind::Long => Some("long"),
+            AttributeKind::Double => Some("double"),
+            AttributeKind::Json => Some("json"),
+            AttributeKind::String => None,
+        }
+    }
+
+    fn type_name(self) -> &'static str {
+        match self {
+            AttributeKind::Boolean => "boolean",
+            AttributeKind::Long => "long",
+            AttributeKind::Double => "double",
+            AttributeKind::String | AttributeKind::Json => "string",
+        }
+    }
+
+    /// Kind for a declared attribute; `_json` ids mark encoded values
+    fn declared(id: &str, type_name: &str) -> Self {
+        if id.ends_with("_json") {
+            return AttributeKind::Json;
+        }
+        match type_name {
+            "boolean" => AttributeKind::Boolean,
+            "int" | "integer" | "long" => AttributeKind::Long,
+            "float" | "double" => AttributeKind::Double,
+            _ => AttributeKind::String,
+        }
+    }
+
+    fn encode(self, value: &Value) -> String {
+        match (self, value) {
+            (AttributeKind::Json, value) => value.to_string(),
+            (_, Value::String(s)) => s.clone(),
+            (_, value) => value.to_string(),
+        }
+    }
+
+    fn decode(self, text: &str) -> Value {
+        let parsed = match self {
+            AttributeKind::Boolean => text.trim().parse::<bool>().ok().map(Value::Bool),
+            AttributeKind::Long | AttributeKind::Double => {
+                let text = text.trim();
+                text.parse::<i64>()
+                    .ok()
+                    .map(Value::from)
+                    .or_else(|| text.parse::<f64>().ok().and_then(serde_json::Number::from_f64).map(Value::Number))
+            }
+            AttributeKind::Json => serde_json::from_str(text).ok(),
+            AttributeKind::String => None,
+        };
+        parsed.unwrap_or_else(|| Value::String(text.to_string()))
+    }
+}
+
+/// Name of a property in a file. Names taken by fixed attributes or columns,
+/// and names that already start with the prefix, get [`PROPERTY_PREFIX`].
+fn exported_name(name: &str) -> String {
+    let taken = RESERVED.iter().chain(ENTITY_COLUMNS).chain(LINK_COLUMNS).any(|fixed| fixed.eq_ignore_ascii_case(name));
+    if taken || name.starts_with(PROPERTY_PREFIX) {
+        format!("{}{}", PROPERTY_PREFIX, name)
+    } else {
+        name.to_string()
+    }
+}
+
+/// Property name for a name read from a file, undoing [`exported_name`]
+fn property_name(name: &str) -> String {
+    name.strip_prefix(PROPERTY_PREFIX).unwrap_or(name).to_string()
+}
+
+/// One column per property name, in name order
+fn attributes<'a>(prefix: &str, properties: impl Iterator<Item = &'a Map<String, Value>>) -> Vec<Attribute> {
+    let mut values: BTreeMap<&str, Vec<&Value>> = BTreeMap::new();
+    for map in properties {
+        for (name, value) in map {
+            values.entry(name).or_default().push(value);
+        }
+    }
+
+    values
+        .into_iter()
+        .enumerate()
+        .map(|(index, (name, values))| {
+            let kind = AttributeKind::of(values.into_iter());
+            let suffix = if kind == AttributeKind::Json { "_json" } else { "" };
+            Attribute {
+                id: format!("{}{}{}", prefix, index, suffix),
+                name: name.to_string(),
+                title: exported_name(name),
+                kind,
+            }
+        })
+        .collect()
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Write `graph` in `format`
+pub fn export_graph(graph: &Subgraph, format: GraphFormat) -> Result<GraphDocument> {
+    let node_attributes = attributes("n", graph.entities.iter().map(|e| &e.properties));
+    let edge_attributes = attributes("e", graph.relationships.iter().map(|r| &r.properties));
+
+    Ok(match format {
+        GraphFormat::Graphml => GraphDocument::Graphml {
+            content: write_graphml(graph, &node_attributes, &edge_attributes)?,
+        },
+        GraphFormat::Gexf => GraphDocument::Gexf {
+            content: write_gexf(graph, &node_attributes, &edge_attributes)?,
+        },
+        GraphFormat::MaltegoCsv => {
+            let (entities, links) = write_maltego(graph, &node_attributes, &edge_attributes)?;
+            GraphDocument::MaltegoCsv { entities, links }
+        }
+    })
+}
+
+fn write_graphml(graph: &Subgraph, nodes: &[Attribute], edges: &[Attribute]) -> Result<String> {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n");
+    for (id, scope, name) in [
+        ("node_type", "node", TYPE),
+        (LABEL, "node", LABEL),
+        ("edge_type", "edge", TYPE),
+        (FIRST_SEEN, "all", FIRST_SEEN),
+        (LAST_SEEN, "all", LAST_SEEN),
+    ] {
+        writeln!(xml, "  <key id=\"{}\" for=\"{}\" attr.name=\"{}\" attr.type=\"string\"/>", id, scope, name)?;
+    }
+    for (scope, attributes) in [("node", nodes), ("edge", edges)] {
+        for attribute in attributes {
+            writeln!(
+                xml,
+                "  <key id=\"{}\" for=\"{}\" attr.name=\"{}\" attr.type=\"{}\"/>",
+                attribute.id,
+                scope,
+                escape(&attribute.title),
+                attribute.kind.type_name()
+            )?;
+        }
+    }
+
+    xml.push_str("  <graph id=\"parallax\" edgedefault=\"directed\">\n");
+    let data = |xml: &mut String, key: &str, value: &str| {
+        writeln!(xml, "      <data key=\"{}\">{}</data>", key, escape(value))
+    };
+    for entity in &graph.entities {
+        writeln!(xml, "    <node id=\"{}\">", escape(&entity.id))?;
+        data(&mut xml, "node_type", entity.entity_type.as_str())?;
+        data(&mut xml, LABEL, &entity.value)?;
+        data(&mut xml, FIRST_SEEN, &entity.first_seen)?;
+        data(&mut xml, LAST_SEEN, &entity.last_seen)?;
+        for attribute in nodes {
+            if let Some(value) = entity.properties.get(&attribute.name) {
+                data(&mut xml, &attribute.id, &attribute.kind.encode(value))?;
+            }
+        }
+        xml.push_str("    </node>\n");
+    }
+    for relationship in &graph.relationships {
+        writeln!(
+            xml,
+            "    <edge id=\"{}\" source=\"{}\" target=\"{}\">",
+            escape(&relationship.id),
+            escape(&relationship.source_id),
+            escape(&relationship.target_id)
+        )?;
+        data(&mut xml, "edge_type", relationship.relation_type.as_str())?;
+        data(&mut xml, FIRST_SEEN, &relationship.first_seen)?;
+        data(&mut xml, LAST_SEEN, &relationship.last_seen)?;
+        for attribute in edges {
+            if let Some(value) = relationship.properties.get(&attribute.name) {
+                data(&mut xml, &attribute.id, &attribute.kind.encode(value))?;
+            }
+        }
+        xml.push_str("    </edge>\n");
+    }
+    xml.push_str("  </graph>\n</graphml>\n");
+    Ok(xml)
+}
+
+fn write_gexf(graph: &Subgraph, nodes: &[Attribute], edges: &[Attribute]) -> Result<String> {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<gexf xmlns=\"http://gexf.net/1.3\" version=\"1.3\">\n");
+    xml.push_str("  <meta>\n    <creator>Parallax</creator>\n  </meta>\n");
+    xml.push_str("  <graph defaultedgetype=\"directed\" mode=\"static\">\n");
+
+    for (class, attributes) in [("node", nodes), ("edge", edges)] {
+        writeln!(xml, "    <attributes class=\"{}\">", class)?;
+        for name in [TYPE, FIRST_SEEN, LAST_SEEN] {
+            writeln!(xml, "      <attribute id=\"{}\" title=\"{}\" type=\"string\"/>", name, name)?;
+        }
+        for attribute in attributes {
+            writeln!(
+                xml,
+                "      <attribute id=\"{}\" title=\"{}\" type=\"{}\"/>",
+                attribute.id,
+                escape(&attribute.title),
+                attribute.kind.type_name()
+            )?;
+        }
+        xml.push_str("    </attributes>\n");
+    }
+
+    xml.push_str("    <nodes>\n");
+    for entity in &graph.entities {
+        writeln!(xml, "      <node id=\"{}\" label=\"{}\">", escape(&entity.id), escape(&entity.value))?;
+        let fixed = [
+            (TYPE, entity.entity_type.as_str()),
+            (FIRST_SEEN, entity.first_seen.as_str()),
+            (LAST_SEEN, entity.last_seen.as_str()),
+        ];
+        gexf_values(&mut xml, fixed, &entity.properties, nodes)?;
+        xml.push_str("      </node>\n");
+    }
+    xml.push_str("    </nodes>\n    <edges>\n");
+    for relationship in &graph.relationships {
+        writeln!(
+            xml,
+            "      <edge id=\"{}\" source=\"{}\" target=\"{}\" label=\"{}\">",
+            escape(&relationship.id),
+            escape(&relationship.source_id),
+            escape(&relationship.target_id),
+            relationship.relation_type.as_str()
+        )?;
+        let fixed = [
+            (TYPE, relationship.relation_type.as_str()),
+            (FIRST_SEEN, relationship.first_seen.as_str()),
+            (LAST_SEEN, relationship.last_seen.as_str()),
+        ];
+        gexf_values(&mut xml, fixed, &relationship.properties, edges)?;
+        xml.push_str("      </edge>\n");
+    }
+    xml.push_str("    </edges>\n  </graph>\n</gexf>\n");
+    Ok(xml)
+}
+
+fn gexf_values(
+    xml: &mut String,
+    fixed: [(&str, &str); 3],
+    properties: &Map<String, Value>,
+    attributes: &[Attribute],
+) -> std::fmt::Result {
+    xml.push_str("        <attvalues>\n");
+    for (id, value) in fixed {
+        writeln!(xml, "          <attvalue for=\"{}\" value=\"{}\"/>", id, escape(value))?;
+    }
+    for attribute in attributes {
+        if let Some(value) = properties.get(&attribute.name) {
+            let value = escape(&attribute.kind.encode(value));
+            writeln!(xml, "          <attvalue for=\"{}\" value=\"{}\"/>", attribute.id, value)?;
+        }
+    }
+    xml.push_str("        </attvalues>\n");
+    Ok(())
+}
+
+fn write_maltego(graph: &Subgraph, nodes: &[Attribute], edges: &[Attribute]) -> Result<(String, String)> {
+    let cell = |attribute: &Attribute, properties: &Map<String, Value>| {
+        properties.get(&attribute.name).map(|v| attribute.kind.encode(v)).unwrap_or_default()
+    };
+    let header = |attribute: &Attribute| match attribute.kind.suffix() {
+        Some(suffix) => format!("{} ({})", attribute.title, suffix),
+        None => attribute.title.clone(),
+    };
+
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    let columns = ENTITY_COLUMNS.iter().map(|c| c.to_string()).chain(nodes.iter().map(header));
+    writer.write_record(columns.collect::<Vec<_>>())?;
+    for entity in &graph.entities {
+        let maltego_type = match entity.entity_type {
+            EntityType::Ip if entity.value.contains(':') => "maltego.IPv6Address",
+            entity_type => MALTEGO_TYPES.iter().find(|(t, _)| *t == entity_type).map_or("maltego.Phrase", |(_, m)| m),
+        };
+        let mut record = vec![
+            entity.id.clone(),
+            maltego_type.to_string(),
+            entity.value.clone(),
+            entity.entity_type.as_str().to_string(),
+            entity.first_seen.clone(),
+            entity.last_seen.clone(),
+        ];
+        record.extend(nodes.iter().map(|a| cell(a, &entity.properties)));
+        writer.write_record(&record)?;
+    }
+    let entities = String::from_utf8(writer.into_inner().context("Failed to write entity table")?)?;
+
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    let columns = LINK_COLUMNS.iter().map(|c| c.to_string()).chain(edges.iter().map(header));
+    writer.write_record(columns.collect::<Vec<_>>())?;
+    for relationship in &graph.relationships {
+        let mut record = vec![
+            relationship.source_id.clone(),
+            relationship.target_id.clone(),
+            relationship.relation_type.as_str().replace('_', " "),
+            relationship.relation_type.as_str().to_string(),
+            relationship.first_seen.clone(),
+            relationship.last_seen.clone(),
+        ];
+        record.extend(edges.iter().map(|a| cell(a, &relationship.properties)));
+        writer.write_record(&record)?;
+    }
+    let links = String::from_utf8(writer.into_inner().context("Failed to write link table")?)?;
+
+    Ok((entities, links))
+}
+
+/// A node as read from a file, keyed by its id there
+struct ParsedNode {
+    id: String,
+    entity_type: Option<String>,
+    value: Option<String>,
+    first_seen: Option<String>,
+    last_seen: Option<String>,
+    properties: Map<String, Value>,
+}
+
+struct ParsedEdge {
+    source: String,
+    target: String,
+    relation_type: Option<String>,
+    first_seen: Option<String>,
+    last_seen: Option<String>,
+    properties: Map<String, Value>,
+}
+
+/// The first and last times a file gives for an item. Times that aren't
+/// RFC 3339 are left out with a warning.
+fn parsed_seen(
+    first: Option<String>,
+    last: Option<String>,
+    item: &str,
+    warnings: &mut Vec<String>,
+) -> Option<Seen> {
+    let mut valid = |time: Option<String>| {
+        let time = time.filter(|t| !t.is_empty())?;
+        if chrono::DateTime::parse_from_rfc3339(&time).is_ok() {
+            Some(time)
+        } else {
+            warnings.push(format!("Ignored invalid time {} on {}", time, item));
+            None
+        }
+    };
+    match (valid(first), valid(last)) {
+        (Some(first), Some(last)) => Some(Seen { first, last }),
+        (Some(only), None) | (None, Some(only)) => Some(Seen { first: only.clone(), last: only }),
+        (None, None) => None,
+    }
+}
+
+/// Read a document into a batch of observations from `source`
+pub fn import_graph(document: &GraphDocument, source: GraphSource) -> Result<GraphImport> {
+    let (nodes, edges) = match document {
+        GraphDocument::Graphml { content } => read_graphml(content)?,
+        GraphDocument::Gexf { content } => read_gexf(content)?,
+        GraphDocument::MaltegoCsv { entities, links } => read_maltego(entities, links)?,
+    };
+
+    let mut warnings = Vec::new();
+    let mut keys: HashMap<String, EntityKey> = HashMap::new();
+    let mut batch = GraphBatch {
+        source,
+        observed_at: None,
+        entities: Vec::new(),
+        relationships: Vec::new(),
+    };
+
+    for node in nodes {
+        let value = node.value.unwrap_or_else(|| node.id.clone()).trim().to_string();
+        let entity_type = node
+            .entity_type
+            .as_deref()
+            .and_then(|t| EntityType::parse(t).or_else(|| maltego_type(t)))
+            .or_else(|| guess_entity_type(&value));
+        let Some(entity_type) = entity_type.filter(|_| !value.is_empty()) else {
+            warnings.push(format!("Skipped node {}: unknown entity type", node.id));
+            continue;
+        };
+        // Normalized as the vault stores it, so two spellings of one entity
+        // are recognized as the same endpoint
+        let value = normalize_value(entity_type, &value);
+        if value.is_empty() {
+            warnings.push(format!("Skipped node {}: empty value", node.id));
+            continue;
+        }
+
+        let seen = parsed_seen(node.first_seen, node.last_seen, &format!("node {}", node.id), &mut warnings);
+        keys.insert(node.id, EntityKey::new(entity_type, value.clone()));
+        batch.entities.push(EntityInput {
+            entity_type,
+            value,
+            properties: node.properties,
+            seen,
+        });
+    }
+
+    for edge in edges {
+        let (Some(source), Some(target)) = (keys.get(&edge.source), keys.get(&edge.target)) else {
+            warnings.push(format!("Skipped edge {} -> {}: unknown endpoint", edge.source, edge.target));
+            continue;
+        };
+        if source == target {
+            warnings.push(format!("Skipped edge {} -> {}: self-loop", edge.source, edge.target));
+            continue;
+        }
+
+        let relation_type = edge
+            .relation_type
+            .as_deref()
+            .and_then(|t| RelationType::parse(&t.trim().to_lowercase().replace(' ', "_")))
+            .unwrap_or(RelationType::RelatedTo);
+        let item = format!("edge {} -> {}", edge.source, edge.target);
+        batch.relationships.push(RelationshipInput {
+            source: source.clone(),
+            target: target.clone(),
+            relation_type,
+            properties: edge.properties,
+            seen: parsed_seen(edge.first_seen, edge.last_seen, &item, &mut warnings),
+        });
+    }
+
+    Ok(GraphImport { batch, warnings })
+}
+
+fn maltego_type(name: &str) -> Option<EntityType> {
+    MALTEGO_TYPES
+        .iter()
+        .filter(|(_, m)| *m != "maltego.Phrase")
+        .find(|(_, m)| m.eq_ignore_ascii_case(name))
+        .map(|(t, _)| *t)
+        .or_else(|| MALTEGO_ALIASES.iter().find(|(m, _)| m.eq_ignore_ascii_case(name)).map(|(_, t)| *t))
+}
+
+/// Best guess at what an untyped value is
+fn guess_entity_type(value: &str) -> Option<EntityType> {
+    if value.parse::<IpAddr>().is_ok() {
+        Some(EntityType::Ip)
+    } else if value
+        .split_once('/')
+        .is_some_and(|(ip, prefix)| ip.parse::<IpAddr>().is_ok() && prefix.parse::<u8>().is_ok())
+    {
+        Some(EntityType::Cidr)
+    } else if value.contains("://") {
+        Some(EntityType::Url)
+    } else if value.contains('@') && !value.contains(' ') {
+        Some(EntityType::Email)
+    } else if value.contains('.') && !value.contains(' ') {
+        Some(if value.matches('.').count() > 1 { EntityType::Subdomain } else { EntityType::Domain })
+    } else {
+        None
+    }
+}
+
+fn read_graphml(content: &str) -> Result<(Vec<ParsedNode>, Vec<ParsedEdge>)> {
+    let doc = roxmltree::Document::parse(content).context("Failed to parse GraphML")?;
+
+    // Key id -> (attribute name, kind)
+    let keys: HashMap<&str, (&str, AttributeKind)> = doc
+        .descendants()
+        .filter(|n| n.has_tag_name("key"))
+        .filter_map(|key| {
+            let id = key.attribute("id")?;
+            let name = key.attribute("attr.name").unwrap_or(id);
+            Some((id, (name, AttributeKind::declared(id, key.attribute("attr.type").unwrap_or("string")))))
+        })
+        .collect();
+
+    let data = |element: roxmltree::Node| {
+        let mut fixed: HashMap<&str, String> = HashMap::new();
+        let mut properties = Map::new();
+        for data in element.children().filter(|c| c.has_tag_name("data")) {
+            let Some(&(name, kind)) = data.attribute("key").and_then(|key| keys.get(key)) else {
+                continue;
+            };
+            let text = data.text().unwrap_or_default();
+            if RESERVED.contains(&name) {
+                fixed.insert(name, text.trim().to_string());
+            } else {
+                properties.insert(property_name(name), kind.decode(text));
+            }
+        }
+        (fixed, properties)
+    };
+
+    let mut nodes = Vec::new();
+    for node in doc.descendants().filter(|n| n.has_tag_name("node")) {
+        let id = node.attribute("id").context("GraphML node without an id")?;
+        let (mut fixed, properties) = data(node);
+        nodes.push(ParsedNode {
+            id: id.to_string(),
+            entity_type: fixed.remove(TYPE),
+            value: fixed.remove(LABEL).or_else(|| fixed.remove("value")),
+            first_seen: fixed.remove(FIRST_SEEN),
+            last_seen: fixed.remove(LAST_SEEN),
+            properties,
+        });
+    }
+
+    let mut edges = Vec::new();
+    for edge in doc.descendants().filter(|n| n.has_tag_name("edge")) {
+        let (mut fixed, properties) = data(edge);
+        edges.push(ParsedEdge {
+            source: edge.attribute("source").context("GraphML edge without a source")?.to_string(),
+            target: edge.attribute("target").context("GraphML edge without a target")?.to_string(),
+            relation_type: fixed.remove(TYPE).or_else(|| fixed.remove(LABEL)),
+            first_seen: fixed.remove(FIRST_SEEN),
+            last_seen: fixed.remove(LAST_SEEN),
+            properties,
+        });
+    }
+
+    Ok((nodes, edges))
+}
+
+fn read_gexf(content: &str) -> Result<(Vec<ParsedNode>, Vec<ParsedEdge>)> {
+    let doc = roxmltree::Document::parse(content).context("Failed to parse GEXF")?;
+
+    // (class, attribute id) -> (title, kind)
+    let mut attributes: HashMap<(&str, &str), (&str, AttributeKind)> = HashMap::new();
+    for group in doc.descendants().filter(|n| n.has_tag_name("attributes")) {
+        let class = group.attribute("class").unwrap_or("node");
+        for attribute in group.children().filter(|c| c.has_tag_name("attribute")) {
+            let Some(id) = attribute.attribute("id") else {
+                continue;
+            };
+            let title = attribute.attribute("title").unwrap_or(id);
+            let kind = AttributeKind::declared(id, attribute.attribute("type").unwrap_or("string"));
+            attributes.insert((class, id), (title, kind));
+        }
+    }
+
+    let values = |element: roxmltree::Node, class: &str| {
+        let mut fixed: HashMap<&str, String> = HashMap::new();
+        let mut properties = Map::new();
+        for value in element.descendants().filter(|c| c.has_tag_name("attvalue")) {
+            let (Some(id), Some(text)) = (value.attribute("for").or(value.attribute("id")), value.attribute("value"))
+            else {
+                continue;
+            };
+            let Some(&(title, kind)) = attributes.get(&(class, id)) else {
+                continue;
+            };
+            if RESERVED.contains(&title) {
+                fixed.insert(title, text.trim().to_string());
+            } else {
+                properties.insert(property_name(title), kind.decode(text));
+            }
+        }
+        (fixed, properties)
+    };
+
+    let mut nodes = Vec::new();
+    for node in doc.descendants().filter(|n| n.has_tag_name("node")) {
+        let id = node.attribute("id").context("GEXF node without an id")?;
+        let (mut fixed, properties) = values(node, "node");
+        nodes.push(ParsedNode {
+            id: id.to_string(),
+            entity_type: fixed.remove(TYPE),
+            value: node.attribute("label").map(str::to_string).or_else(|| fixed.remove(LABEL)),
+            first_seen: fixed.remove(FIRST_SEEN),
+            last_seen: fixed.remove(LAST_SEEN),
+            properties,
+        });
+    }
+
+    let mut edges = Vec::new();
+    for edge in doc.descendants().filter(|n| n.has_tag_name("edge")) {
+        let (mut fixed, properties) = values(edge, "edge");
+        edges.push(ParsedEdge {
+            source: edge.attribute("source").context("GEXF edge without a source")?.to_string(),
+            target: edge.attribute("target").context("GEXF edge without a target")?.to_string(),
+            relation_type: fixed.remove(TYPE).or_else(|| edge.attribute("label").map(str::to_string)),
+            first_seen: fixed.remove(FIRST_SEEN),
+            last_seen: fixed.remove(LAST_SEEN),
+            properties,
+        });
+    }
+
+    Ok((nodes, edges))
+}
+
+/// Property name and kind of a Maltego column. A suffix such as ` (long)`
+/// gives the type; columns without one are strings.
+fn csv_column(header: &str) -> (String, AttributeKind) {
+    let typed = header.strip_suffix(')').and_then(|h| h.rsplit_once(" (")).and_then(|(name, suffix)| {
+        let kinds = [AttributeKind::Boolean, AttributeKind::Long, AttributeKind::Double, AttributeKind::Json];
+        kinds.into_iter().find(|k| k.suffix() == Some(suffix)).map(|kind| (name, kind))
+    });
+    let (name, kind) = typed.unwrap_or((header, AttributeKind::String));
+    (property_name(name), kind)
+}
+
+/// Fixed columns (lower-cased header to cell) and properties of one row
+type CsvRow = (HashMap<String, String>, Map<String, Value>);
+
+fn read_csv_table(content: &str, fixed_columns: &[&str]) -> Result<Vec<CsvRow>> {
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(content.as_bytes());
+    let headers: Vec<String> = reader
+        .headers()
+        .context("Failed to read CSV header row")?
+        .iter()
+        .map(|h| h.trim().trim_start_matches('\u{feff}').to_string())
+        .collect();
+
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record.context("Failed to read CSV row")?;
+        let mut fixed = HashMap::new();
+        let mut properties = Map::new();
+        for (header, cell) in headers.iter().zip(record.iter()) {
+            if fixed_columns.iter().any(|c| c.eq_ignore_ascii_case(header)) {
+                fixed.insert(header.to_lowercase(), cell.trim().to_string());
+            } else if !cell.is_empty() {
+                let (name, kind) = csv_column(header);
+                properties.insert(name, kind.decode(cell));
+            }
+        }
+        rows.push((fixed, properties));
+    }
+    Ok(rows)
+}
+
+fn read_maltego(entities: &str, links: &str) -> Result<(Vec<ParsedNode>, Vec<ParsedEdge>)> {
+    let non_empty = |fixed: &mut HashMap<String, String>, column: &str| {
+        fixed.remove(&column.to_lowercase()).filter(|v| !v.is_empty())
+    };
+
+    let mut nodes = Vec::new();
+    for (index, (mut fixed, properties)) in read_csv_table(entities, ENTITY_COLUMNS)?.into_iter().enumerate() {
+        let value = non_empty(&mut fixed, "Value");
+        nodes.push(ParsedNode {
+            id: non_empty(&mut fixed, "Entity ID")
+                .or_else(|| value.clone())
+                .unwrap_or_else(|| format!("row {}", index + 2)),
+            entity_type: non_empty(&mut fixed, "Parallax Type").or_else(|| non_empty(&mut fixed, "Entity Type")),
+            value,
+            first_seen: non_empty(&mut fixed, "First Seen"),
+            last_seen: non_empty(&mut fixed, "Last Seen"),
+            properties,
+        });
+    }
+
+    let mut edges = Vec::new();
+    for (mut fixed, properties) in read_csv_table(links, LINK_COLUMNS)? {
+        edges.push(ParsedEdge {
+            source: non_empty(&mut fixed, "Source ID").unwrap_or_default(),
+            target: non_empty(&mut fixed, "Target ID").unwrap_or_default(),
+            relation_type: non_empty(&mut fixed, "Parallax Type").or_else(|| non_empty(&mut fixed, "Label")),
+            first_seen: non_empty(&mut fixed, "First Seen"),
+            last_seen: non_empty(&mut fixed, "Last Seen"),
+            properties,
+        });
+    }
+
+    Ok((nodes, edges))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vault::{SourceKind, VaultService};
+    use serde_json::json;
+
+    fn source() -> GraphSource {
+        GraphSource { kind: SourceKind::Import, reference: "test".to_string() }
+    }
+
+    fn properties(value: Value) -> Map<String, Value> {
+        value.as_object().unwrap().clone()
+    }
+
+    /// (type, value, times, properties) of every entity and relationship, comparable across workspaces
+    fn contents(graph: &Subgraph) -> (Vec<String>, Vec<String>) {
+        let names: HashMap<&str, String> = graph
+            .entities
+            .iter()
+            .map(|e| (e.id.as_str(), format!("{}:{}", e.entity_type.as_str(), e.value)))
+            .collect();
+        let mut entities: Vec<String> = graph
+            .entities
+            .iter()
+            .map(|e| {
+                let properties = Value::Object(e.properties.clone());
+                format!("{} {}..{} {}", names[e.id.as_str()], e.first_seen, e.last_seen, properties)
+            })
+            .collect();
+        let mut relationships: Vec<String> = graph
+            .relationships
+            .iter()
+            .map(|r| {
+                format!(
+                    "{} -{}-> {} {}..{} {}",
+                    names[r.source_id.as_str()],
+                    r.relation_type.as_str(),
+                    names[r.target_id.as_str()],
+                    r.first_seen,
+                    r.last_seen,
+                    Value::Object(r.properties.clone())
+                )
+            })
+            .collect();
+        entities.sort();
+        relationships.sort();
+        (entities, relationships)
+    }
+
+    #[tokio::test]
+    async fn test_formats_round_trip() {
+        let vault = VaultService::open_in_memory().unwrap();
+        let mut batch = GraphBatch {
+            source: source(),
+            observed_at: Some("2024-03-01T12:00:00Z".to_string()),
+            entities: vec![
+                EntityInput {
+                    entity_type: EntityType::Domain,
+                    value: "example.com".to_string(),
+                    properties: properties(json!({
+                        "registrar": "R&D <Names>",
+                        "age": 12,
+                        "dnssec": true,
+                        "zip": "0123",
+                        "flag": "true",
+                        "type": "apex",
+                        "Value": 3,
+                        "property.note": "kept",
+                    })),
+                    seen: None,
+                },
+                EntityInput {
+                    entity_type: EntityType::Technology,
+                    value: "nginx".to_string(),
+                    properties: properties(json!({"versions": ["1.24", "1.25"], "age": 0.5})),
+                    seen: Some(Seen {
+                        first: "2023-06-01T00:00:00Z".to_string(),
+                        last: "2023-01-01T00:00:00Z".to_string(),
+                    }),
+                },
+                EntityInput {
+                    entity_type: EntityType::Ip,
+                    value: "2001:db8::1".to_string(),
+                    properties: Map::new(),
+                    seen: None,
+                },
+            ],
+            relationships: vec![],
+        };
+        batch.relationships.push(RelationshipInput {
+            source: EntityKey::new(EntityType::Domain, "example.com"),
+            target: EntityKey::new(EntityType::Ip, "2001:db8::1"),
+            relation_type: RelationType::ResolvesTo,
+            properties: properties(json!({"ttl": 300, "record": {"class": "IN"}})),
+            seen: None,
+        });
+        batch.relationships.push(RelationshipInput {
+            source: EntityKey::new(EntityType::Ip, "2001:db8::1"),
+            target: EntityKey::new(EntityType::Technology, "nginx"),
+            relation_type: RelationType::Uses,
+            properties: Map::new(),
+            seen: None,
+        });
+        vault.upsert_graph("original", &batch).await.unwrap();
+        let original = vault.load_graph("original").await.unwrap();
+
+        for format in [GraphFormat::Graphml, GraphFormat::Gexf, GraphFormat::MaltegoCsv] {
+            let document = export_graph(&original, format).unwrap();
+            let imported = import_graph(&document, source()).unwrap();
+            assert!(imported.warnings.is_empty(), "{:?}: {:?}", format, imported.warnings);
+
+            // Imported later, but keeps the times recorded in the file
+            let workspace = format!("{:?}", format).to_lowercase();
+            let mut batch = imported.batch;
+            batch.observed_at = Some("2025-01-01T00:00:00Z".to_string());
+            vault.upsert_graph(&workspace, &batch).await.unwrap();
+            assert_eq!(contents(&vault.load_graph(&workspace).await.unwrap()), contents(&original), "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn test_foreign_files_are_read_leniently() {
+        // A Gephi-style export: labels only, an edge to a missing node
+        let graphml = r#"<?xml version="1.0"?>
+            <graphml xmlns="http://graphml.graphdrawing.org/xmlns">
+              <key id="d0" for="node" attr.name="label" attr.type="string"/>
+              <key id="d1" for="node" attr.name="weight" attr.type="double"/>
+              <graph edgedefault="undirected">
+                <node id="a"><data key="d0">mail.example.com</data><data key="d1">2</data></node>
+                <node id="b"><data key="d0">192.0.2.10</data></node>
+                <node id="c"><data key="d0">Some Phrase</data></node>
+                <edge source="a" target="b"/>
+                <edge source="a" target="zzz"/>
+              </graph>
+            </graphml>"#;
+        let imported = import_graph(&GraphDocument::Graphml { content: graphml.to_string() }, source()).unwrap();
+        let types: Vec<EntityType> = imported.batch.entities.iter().map(|e| e.entity_type).collect();
+        assert_eq!(types, vec![EntityType::Subdomain, EntityType::Ip]);
+        assert_eq!(imported.batch.entities[0].properties["weight"], 2);
+        assert_eq!(imported.batch.relationships.len(), 1);
+        assert_eq!(imported.batch.relationships[0].relation_type, RelationType::RelatedTo);
+        assert_eq!(imported.warnings.len(), 2);
+
+        // Maltego types without a Parallax column
+        let entities = "Entity ID,Entity Type,Value,Notes (json),Zip\n\
+                        1,maltego.Website,www.example.org,\"{\"\"a\"\":1}\",01234\n\
+                        2,maltego.Company,Acme,,\n";
+        let links = "Source ID,Target ID,Label\n1,2,hosts\n";
+        let document = GraphDocument::MaltegoCsv { entities: entities.to_string(), links: links.to_string() };
+        let imported = import_graph(&document, source()).unwrap();
+        assert_eq!(imported.batch.entities[0].entity_type, EntityType::Subdomain);
+        assert_eq!(imported.batch.entities[0].properties["Notes"], json!({"a": 1}));
+        assert_eq!(imported.batch.entities[0].properties["Zip"], "01234");
+        assert_eq!(imported.batch.entities[1].entity_type, EntityType::Organization);
+        assert_eq!(imported.batch.relationships[0].relation_type, RelationType::Hosts);
+    }
+
+    #[tokio::test]
+    async fn test_edges_between_spellings_of_one_entity_are_skipped() {
+        let graphml = r#"<?xml version="1.0"?>
+            <graphml xmlns="http://graphml.graphdrawing.org/xmlns">
+              <key id="d0" for="node" attr.name="label" attr.type="string"/>
+              <graph edgedefault="directed">
+                <node id="a"><data key="d0">WWW.Example.com</data></node>
+                <node id="b"><data key="d0">www.example.com.</data></node>
+                <node id="c"><data key="d0">.</data></node>
+                <node id="d"><data key="d0">192.0.2.10</data></node>
+                <edge source="a" target="b"/>
+                <edge source="c" target="d"/>
+                <edge source="b" target="d"/>
+              </graph>
+            </graphml>"#;
+        let imported = import_graph(&GraphDocument::Graphml { content: graphml.to_string() }, source()).unwrap();
+        assert_eq!(imported.batch.relationships.len(), 1);
+        assert!(imported.warnings.iter().any(|w| w.contains("self-loop")));
+        assert!(imported.warnings.iter().any(|w| w.contains("empty value")));
+
+        let vault = VaultService::open_in_memory().unwrap();
+        let report = vault.upsert_graph("default", &imported.batch).await.unwrap();
+        assert_eq!(report.entities_created, 2);
+        assert_eq!(report.relationships_created, 1);
+    }
+}
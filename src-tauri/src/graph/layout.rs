@@ -0,0 +1,437 @@
+//! Node positions for drawing a graph.
+//!
+//! Force-directed layout is Fruchterman-Reingold with repulsion limited to
+//! nearby nodes through a spatial grid, so it stays usable past a few
+//! thousand nodes. Hierarchical and radial layouts place a breadth-first
+//! tree from a root entity. Every layout is deterministic: the same graph,
+//! options and seed always give the same coordinates.
+
+use super::{GraphFilter, Walkable};
+use crate::vault::{EntityType, Subgraph};
+use anyhow::{Context, Result};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::f64::consts::TAU;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LayoutAlgorithm {
+    ForceDirected,
+    /// Tree from the root downwards
+    Hierarchical,
+    /// Rings around the root by hop count
+    Radial,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LayoutOptions {
+    pub algorithm: LayoutAlgorithm,
+    /// Entity at the top or centre of tree layouts; defaults to the
+    /// best-connected domain
+    pub root: Option<String>,
+    /// Starting positions for force-directed layout
+    pub seed: u64,
+    /// Force-directed simulation steps
+    pub iterations: u32,
+    /// Ideal distance between connected nodes, in canvas units
+    pub spacing: f64,
+}
+
+impl Default for LayoutOptions {
+    fn default() -> Self {
+        Self {
+            algorithm: LayoutAlgorithm::ForceDirected,
+            root: None,
+            seed: 0,
+            iterations: 300,
+            spacing: 150.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodePosition {
+    pub id: String,
+    pub x: f64,
+    pub y: f64,
+}
+
+/// Closer than this counts as the same point
+const MIN_DISTANCE: f64 = 0.01;
+
+/// Positions for every entity in `graph`, in entity order, with the
+/// top-left of the drawing at (0, 0)
+pub fn compute_layout(graph: &Subgraph, options: &LayoutOptions) -> Result<Vec<NodePosition>> {
+    if !(options.spacing > 0.0 && options.spacing.is_finite()) {
+        anyhow::bail!("Layout spacing must be a positive number");
+    }
+
+    let walkable = Walkable::new(graph, &GraphFilter::default(), &[])?;
+    if graph.entities.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let points = match options.algorithm {
+        LayoutAlgorithm::ForceDirected => force_directed(&walkable, options),
+        LayoutAlgorithm::Hierarchical => {
+            hierarchical(&Forest::new(&walkable, root(&walkable, options)?), options.spacing)
+        }
+        LayoutAlgorithm::Radial => radial(&Forest::new(&walkable, root(&walkable, options)?), options.spacing),
+    };
+
+    let min_x = points.iter().map(|p| p.0).fold(f64::INFINITY, f64::min);
+    let min_y = points.iter().map(|p| p.1).fold(f64::INFINITY, f64::min);
+    Ok(graph
+        .entities
+        .iter()
+        .zip(points)
+        .map(|(entity, (x, y))| NodePosition {
+            id: entity.id.clone(),
+            x: round(x - min_x),
+            y: round(y - min_y),
+        })
+        .collect())
+}
+
+fn round(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+fn root(walkable: &Walkable, options: &LayoutOptions) -> Result<usize> {
+    if let Some(root) = options.root.as_deref() {
+        return walkable.position(root).context("Layout root not found");
+    }
+
+    // The best-connected domain, or the best-connected entity if there are no domains
+    let degree = |i: &usize| walkable.adjacency[*i].len();
+    let entities = &walkable.graph.entities;
+    // Reversed so ties go to the earliest entity
+    let best = |candidates: Vec<usize>| candidates.into_iter().rev().max_by_key(degree);
+    best((0..entities.len()).filter(|&i| entities[i].entity_type == EntityType::Domain).collect())
+        .or_else(|| best((0..entities.len()).collect()))
+        .context("The graph is empty")
+}
+
+/// Breadth-first trees: one from the root, then one per component it doesn't reach
+struct Forest {
+    /// Trees one after another, each in breadth-first order
+    order: Vec<usize>,
+    /// Where each tree starts in `order`
+    tree_starts: Vec<usize>,
+    depth: Vec<usize>,
+    children: Vec<Vec<usize>>,
+}
+
+impl Forest {
+    fn new(walkable: &Walkable, root: usize) -> Self {
+        let n = walkable.adjacency.len();
+        let mut forest = Forest {
+            order: Vec::with_capacity(n),
+            tree_starts: Vec::new(),
+            depth: vec![0; n],
+            children: vec![Vec::new(); n],
+        };
+        let mut visited = vec![false; n];
+
+        for start in std::iter::once(root).chain(0..n) {
+            if visited[start] {
+                continue;
+            }
+            visited[start] = true;
+            forest.tree_starts.push(forest.order.len());
+            forest.order.push(start);
+
+            let mut next = forest.order.len() - 1;
+            while next < forest.order.len() {
+                let current = forest.order[next];
+                next += 1;
+                for &(neighbor, _) in &walkable.adjacency[current] {
+                    if !visited[neighbor] {
+                        visited[neighbor] = true;
+                        forest.depth[neighbor] = forest.depth[current] + 1;
+                        forest.children[current].push(neighbor);
+                        forest.order.push(neighbor);
+                    }
+                }
+            }
+        }
+        forest
+    }
+
+    /// Entities of tree `index` in breadth-first order
+    fn tree(&self, index: usize) -> &[usize] {
+        let end = self.tree_starts.get(index + 1).copied().unwrap_or(self.order.len());
+        &self.order[self.tree_starts[index]..end]
+    }
+
+    /// Leaves under each entity (an entity without children counts itself)
+    fn leaf_counts(&self) -> Vec<usize> {
+        let mut leaves = vec![1; self.depth.len()];
+        // Reverse breadth-first order visits children before their parents
+        for &node in self.order.iter().rev() {
+            if !self.children[node].is_empty() {
+                leaves[node] = self.children[node].iter().map(|&c| leaves[c]).sum();
+            }
+        }
+        leaves
+    }
+}
+
+fn force_directed(walkable: &Walkable, options: &LayoutOptions) -> Vec<(f64, f64)> {
+    let n = walkable.adjacency.len();
+    let k = options.spacing;
+    let side = k * (n as f64).sqrt();
+    let mut rng = StdRng::seed_from_u64(options.seed);
+    let mut points: Vec<(f64, f64)> = (0..n).map(|_| (rng.gen::<f64>() * side, rng.gen::<f64>() * side)).collect();
+
+    let edges: Vec<(usize, usize)> = walkable
+        .graph
+        .relationships
+        .iter()
+        .filter_map(|r| Some((walkable.position(&r.source_id).ok()?, walkable.position(&r.target_id).ok()?)))
+        .filter(|(a, b)| a != b)
+        .collect();
+
+    // Nodes further apart than one cell don't push each other
+    let cell = 2.0 * k;
+    let cell_of = |(x, y): (f64, f64)| ((x / cell).floor() as i64, (y / cell).floor() as i64);
+    let initial_temperature = side / 10.0;
+
+    for iteration in 0..options.iterations {
+        let temperature = initial_temperature * (1.0 - iteration as f64 / options.iterations as f64);
+        let mut displacement = vec![(0.0, 0.0); n];
+
+        let mut grid: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+        for (i, &point) in points.iter().enumerate() {
+            grid.entry(cell_of(point)).or_default().push(i);
+        }
+
+        for i in 0..n {
+            let (cx, cy) = cell_of(points[i]);
+            for dx in -1..=1 {
+                for dy in -1..=1 {
+                    for &j in grid.get(&(cx + dx, cy + dy)).map_or(&[][..], Vec::as_slice) {
+                        if i == j {
+                            continue;
+                        }
+                        let (mut x, mut y) = (points[i].0 - points[j].0, points[i].1 - points[j].1);
+                        let mut distance = x.hypot(y);
+                        if distance < MIN_DISTANCE {
+                            // Separate coincident nodes along a fixed direction
+                            (x, y, distance) = (if i < j { -MIN_DISTANCE } else { MIN_DISTANCE }, 0.0, MIN_DISTANCE);
+                        }
+                        if distance < cell {
+                            let force = k * k / distance;
+                            displacement[i].0 += x / distance * force;
+                            displacement[i].1 += y / distance * force;
+                        }
+                    }
+                }
+            }
+        }
+
+        for &(a, b) in &edges {
+            let (x, y) = (points[a].0 - points[b].0, points[a].1 - points[b].1);
+            let distance = x.hypot(y).max(MIN_DISTANCE);
+            let force = distance * distance / k;
+            displacement[a].0 -= x / distance * force;
+            displacement[a].1 -= y / distance * force;
+            displacement[b].0 += x / distance * force;
+            displacement[b].1 += y / distance * force;
+        }
+
+        for (point, (x, y)) in points.iter_mut().zip(displacement) {
+            let length = x.hypot(y);
+            if length > 0.0 {
+                let step = length.min(temperature);
+                point.0 += x / length * step;
+                point.1 += y / length * step;
+            }
+        }
+    }
+
+    points
+}
+
+/// Trees side by side, each level one `spacing` lower, parents centred over
+/// their children
+fn hierarchical(forest: &Forest, spacing: f64) -> Vec<(f64, f64)> {
+    let n = forest.depth.len();
+    let mut x = vec![0.0; n];
+
+    // Leaves take consecutive slots in depth-first order
+    let mut slot = 0.0;
+    for tree in 0..forest.tree_starts.len() {
+        let mut stack = vec![forest.tree(tree)[0]];
+        while let Some(node) = stack.pop() {
+            if forest.children[node].is_empty() {
+                x[node] = slot * spacing;
+                slot += 1.0;
+            }
+            stack.extend(forest.children[node].iter().rev());
+        }
+    }
+
+    for &node in forest.order.iter().rev() {
+        if let (Some(&first), Some(&last)) = (forest.children[node].first(), forest.children[node].last()) {
+            x[node] = (x[first] + x[last]) / 2.0;
+        }
+    }
+
+    (0..n).map(|i| (x[i], forest.depth[i] as f64 * spacing)).collect()
+}
+
+/// The root's tree in rings by depth, each subtree getting a wedge sized by
+/// its leaves; anything the root doesn't reach goes on an outer ring
+fn radial(forest: &Forest, spacing: f64) -> Vec<(f64, f64)> {
+    let n = forest.depth.len();
+    let main = forest.tree(0);
+    let leaves = forest.leaf_counts();
+
+    // Grow rings that would put nodes closer than `spacing` apart
+    let max_depth = main.iter().map(|&i| forest.depth[i]).max().unwrap_or(0);
+    let mut per_depth = vec![0usize; max_depth + 1];
+    for &node in main {
+        per_depth[forest.depth[node]] += 1;
+    }
+    let mut radii = vec![0.0; max_depth + 2];
+    for depth in 1..radii.len() {
+        let crowd = per_depth.get(depth).copied().unwrap_or(0) as f64 * spacing / TAU;
+        radii[depth] = (radii[depth - 1] + spacing).max(crowd);
+    }
+
+    let mut points = vec![(0.0, 0.0); n];
+    let mut wedge = vec![(0.0, TAU); n];
+    for &node in main {
+        let (start, end) = wedge[node];
+        if node != main[0] {
+            let angle = (start + end) / 2.0;
+            let radius = radii[forest.depth[node]];
+            points[node] = (radius * angle.cos(), radius * angle.sin());
+        }
+
+        let total = leaves[node] as f64;
+        let mut from = start;
+        for &child in &forest.children[node] {
+            let to = from + (end - start) * leaves[child] as f64 / total;
+            wedge[child] = (from, to);
+            from = to;
+        }
+    }
+
+    let outer: Vec<usize> = forest.order[main.len()..].to_vec();
+    let radius = (radii[max_depth + 1]).max(outer.len() as f64 * spacing / TAU);
+    for (i, &node) in outer.iter().enumerate() {
+        let angle = TAU * i as f64 / outer.len() as f64;
+        points[node] = (radius * angle.cos(), radius * angle.sin());
+    }
+
+    points
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vault::{Entity, RelationType, Relationship};
+    use serde_json::Map;
+
+    fn subgraph(entities: &[(&str, EntityType)], edges: &[(&str, &str)]) -> Subgraph {
+        let seen = "2026-01-01T00:00:00Z".to_string();
+        Subgraph {
+            entities: entities
+                .iter()
+                .map(|(id, entity_type)| Entity {
+                    id: id.to_string(),
+                    entity_type: *entity_type,
+                    value: id.to_string(),
+                    properties: Map::new(),
+                    sources: Vec::new(),
+                    first_seen: seen.clone(),
+                    last_seen: seen.clone(),
+                })
+                .collect(),
+            relationships: edges
+                .iter()
+                .map(|(source, target)| Relationship {
+                    id: format!("{}->{}", source, target),
+                    source_id: source.to_string(),
+                    target_id: target.to_string(),
+                    relation_type: RelationType::RelatedTo,
+                    properties: Map::new(),
+                    sources: Vec::new(),
+                    first_seen: seen.clone(),
+                    last_seen: seen.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    fn sample() -> Subgraph {
+        use EntityType::*;
+        subgraph(
+            &[("www", Subdomain), ("example.com", Domain), ("api", Subdomain), ("10.0.0.1", Ip), ("lone", Ip)],
+            &[("www", "example.com"), ("api", "example.com"), ("api", "10.0.0.1")],
+        )
+    }
+
+    fn at<'a>(positions: &'a [NodePosition], id: &str) -> &'a NodePosition {
+        positions.iter().find(|p| p.id == id).unwrap()
+    }
+
+    fn distance(a: &NodePosition, b: &NodePosition) -> f64 {
+        (a.x - b.x).hypot(a.y - b.y)
+    }
+
+    #[test]
+    fn test_force_directed_is_seeded_and_scales() {
+        let graph = sample();
+        let options = LayoutOptions { seed: 7, ..Default::default() };
+        let first = compute_layout(&graph, &options).unwrap();
+        assert_eq!(first, compute_layout(&graph, &options).unwrap());
+        assert_ne!(first, compute_layout(&graph, &LayoutOptions { seed: 8, ..options.clone() }).unwrap());
+        assert!(first.iter().all(|p| p.x >= 0.0 && p.y >= 0.0));
+
+        // Connected nodes settle near the ideal distance
+        let edge = distance(at(&first, "www"), at(&first, "example.com"));
+        assert!(edge > 30.0 && edge < 450.0, "edge length {}", edge);
+
+        // A 2,000 node ring lays out without blowing up
+        let ids: Vec<String> = (0..2000).map(|i| format!("n{}", i)).collect();
+        let entities: Vec<(&str, EntityType)> = ids.iter().map(|id| (id.as_str(), EntityType::Ip)).collect();
+        let edges: Vec<(&str, &str)> = (0..2000).map(|i| (ids[i].as_str(), ids[(i + 1) % 2000].as_str())).collect();
+        let large = compute_layout(&subgraph(&entities, &edges), &LayoutOptions { iterations: 30, ..Default::default() })
+            .unwrap();
+        assert_eq!(large.len(), 2000);
+        assert!(large.iter().all(|p| p.x.is_finite() && p.y.is_finite()));
+    }
+
+    #[test]
+    fn test_tree_layouts_start_from_root_domain() {
+        let graph = sample();
+        let spacing = 100.0;
+
+        let tree = compute_layout(&graph, &LayoutOptions { algorithm: LayoutAlgorithm::Hierarchical, spacing, ..Default::default() }).unwrap();
+        let (root, www, api, ip) = (at(&tree, "example.com"), at(&tree, "www"), at(&tree, "api"), at(&tree, "10.0.0.1"));
+        assert_eq!((root.y, www.y, api.y, ip.y), (0.0, 100.0, 100.0, 200.0));
+        assert_eq!(root.x, (www.x + api.x) / 2.0);
+        assert_eq!(api.x, ip.x);
+        // The unconnected IP is its own tree to the right
+        assert_eq!(at(&tree, "lone").y, 0.0);
+        assert!(at(&tree, "lone").x > ip.x);
+
+        let rings = compute_layout(&graph, &LayoutOptions { algorithm: LayoutAlgorithm::Radial, spacing, ..Default::default() }).unwrap();
+        let centre = at(&rings, "example.com");
+        assert!((distance(centre, at(&rings, "www")) - 100.0).abs() < 0.1);
+        assert!((distance(centre, at(&rings, "api")) - 100.0).abs() < 0.1);
+        assert!((distance(centre, at(&rings, "10.0.0.1")) - 200.0).abs() < 0.1);
+        assert!((distance(centre, at(&rings, "lone")) - 300.0).abs() < 0.1);
+
+        let rooted = LayoutOptions { algorithm: LayoutAlgorithm::Hierarchical, root: Some("10.0.0.1".to_string()), ..Default::default() };
+        assert_eq!(at(&compute_layout(&graph, &rooted).unwrap(), "10.0.0.1").y, 0.0);
+        let missing = LayoutOptions { root: Some("nope".to_string()), ..rooted };
+        assert!(compute_layout(&graph, &missing).is_err());
+    }
+}
@@ -0,0 +1,14 @@
+//! Forensic inspection and sanitizing of image files.
+//!
+//! [`extract_metadata`] reports what an image carries besides its pixels:
+//! EXIF, GPS, XMP, IPTC, embedded thumbnails and the ICC profile. JPEG, PNG,
+//! WebP, TIFF and HEIF files are supported; the file itself is never
+//! modified. [`scrub_metadata`] makes a copy of a JPEG, PNG or WebP image
+//! with that metadata cut out, for sharing in reports.
+
+mod container;
+mod metadata;
+mod scrub;
+
+pub use metadata::{extract_metadata, ImageMetadata};
+pub use scrub::{scrub_metadata, ScrubOptions, ScrubReport};
@@ -0,0 +1,734 @@
+//! Locates the metadata blocks inside JPEG, PNG, WebP, TIFF and HEIF files.
+//!
+//! Only the container structure is read here; what the blocks say is
+//! decoded in `metadata`. Each block keeps the byte range it occupies in the
+//! file, so reports can point at it.
+
+use anyhow::{bail, Result};
+use flate2::read::ZlibDecoder;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::Read;
+use std::ops::Range;
+
+const EXIF_HEADER: &[u8] = b"Exif\0\0";
+const XMP_HEADER: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+const XMP_EXTENSION_HEADER: &[u8] = b"http://ns.adobe.com/xmp/extension/\0";
+const ICC_HEADER: &[u8] = b"ICC_PROFILE\0";
+const PHOTOSHOP_HEADER: &[u8] = b"Photoshop 3.0\0";
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+const PNG_XMP_KEYWORD: &str = "XML:com.adobe.xmp";
+/// PNG chunks that describe how to render the pixels; anything else is metadata
+const PNG_IMAGE_CHUNKS: &[&[u8; 4]] = &[
+    b"IHDR", b"PLTE", b"IDAT", b"IEND", b"tRNS", b"gAMA", b"cHRM", b"sRGB", b"sBIT", b"pHYs", b"bKGD", b"hIST",
+    b"cICP", b"mDCv", b"cLLi", b"acTL", b"fcTL", b"fdAT",
+];
+/// WebP chunks that make up the image
+const WEBP_IMAGE_CHUNKS: &[&[u8; 4]] = &[b"VP8X", b"VP8 ", b"VP8L", b"ALPH", b"ANIM", b"ANMF"];
+/// Upper bound for inflated PNG text and profile chunks
+const MAX_INFLATED: u64 = 16 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFormat {
+    Jpeg,
+    Png,
+    Webp,
+    Tiff,
+    Heif,
+}
+
+impl ImageFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Jpeg => "jpg",
+            ImageFormat::Png => "png",
+            ImageFormat::Webp => "webp",
+            ImageFormat::Tiff => "tiff",
+            ImageFormat::Heif => "heic",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockKind {
+    /// TIFF-structured EXIF data
+    Exif,
+    Xmp,
+    /// Photoshop image resources, which carry IPTC-IIM and thumbnails in JPEG
+    Photoshop,
+    /// One ICC profile, or one chunk of a profile split across JPEG segments
+    Icc,
+    /// JPEG comment segment
+    Comment,
+    /// PNG `tEXt`, `zTXt` or `iTXt` chunk
+    Text,
+    /// JFIF APP0 segment carrying a thumbnail
+    Thumbnail,
+    /// Segment or chunk that is neither image data nor a known metadata
+    /// block: other application segments, private chunks, timestamps
+    Other,
+    /// Bytes after the end of the image: appended files, secondary images
+    Trailer,
+}
+
+#[derive(Debug, Clone)]
+pub struct Block {
+    pub kind: BlockKind,
+    /// Segment, chunk or item holding the block, headers included
+    pub span: Range<usize>,
+    /// The payload, inflated where the container compresses it
+    pub data: Vec<u8>,
+    /// PNG text keyword or ICC profile name
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Container {
+    pub format: ImageFormat,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub blocks: Vec<Block>,
+    pub warnings: Vec<String>,
+}
+
+impl Container {
+    fn new(format: ImageFormat) -> Self {
+        Self {
+            format,
+            width: None,
+            height: None,
+            blocks: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, kind: BlockKind, span: Range<usize>, data: Vec<u8>, label: Option<String>) {
+        self.blocks.push(Block {
+            kind,
+            span,
+            data,
+            label,
+        });
+    }
+
+    fn set_dimensions(&mut self, width: u32, height: u32) {
+        if self.width.is_none() {
+            self.width = Some(width);
+            self.height = Some(height);
+        }
+    }
+}
+
+pub fn detect_format(data: &[u8]) -> Option<ImageFormat> {
+    if data.starts_with(b"\xFF\xD8\xFF") {
+        Some(ImageFormat::Jpeg)
+    } else if data.starts_with(PNG_SIGNATURE) {
+        Some(ImageFormat::Png)
+    } else if data.len() >= 12 && data.starts_with(b"RIFF") && &data[8..12] == b"WEBP" {
+        Some(ImageFormat::Webp)
+    } else if data.starts_with(b"II*\0") || data.starts_with(b"MM\0*") {
+        Some(ImageFormat::Tiff)
+    } else if data.len() >= 12 && &data[4..8] == b"ftyp" && is_heif_brand(&data[8..12]) {
+        Some(ImageFormat::Heif)
+    } else {
+        None
+    }
+}
+
+fn is_heif_brand(brand: &[u8]) -> bool {
+    matches!(brand, b"heic" | b"heix" | b"heim" | b"heis" | b"hevc" | b"hevx" | b"mif1" | b"msf1" | b"avif")
+}
+
+pub fn parse(data: &[u8]) -> Result<Container> {
+    let Some(format) = detect_format(data) else {
+        bail!("Unrecognized image format");
+    };
+
+    let mut container = Container::new(format);
+    let complete = match format {
+        ImageFormat::Jpeg => parse_jpeg(data, &mut container),
+        ImageFormat::Png => parse_png(data, &mut container),
+        ImageFormat::Webp => parse_webp(data, &mut container),
+        ImageFormat::Tiff => {
+            // The whole file is one TIFF structure; what it embeds is read with the EXIF
+            container.push(BlockKind::Exif, 0..data.len(), data.to_vec(), None);
+            Some(())
+        }
+        ImageFormat::Heif => parse_heif(data, &mut container),
+    };
+    if complete.is_none() {
+        container
+            .warnings
+            .push("File structure is truncated or malformed; blocks after the damage were not read".to_string());
+    }
+    Ok(container)
+}
+
+pub(super) fn be_u16(data: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(at..at + 2)?.try_into().ok()?))
+}
+
+pub(super) fn be_u32(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn le_u32(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn le_u24(data: &[u8], at: usize) -> Option<u32> {
+    let bytes = data.get(at..at + 3)?;
+    Some(u32::from(bytes[0]) | u32::from(bytes[1]) << 8 | u32::from(bytes[2]) << 16)
+}
+
+fn inflate(data: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    ZlibDecoder::new(data).take(MAX_INFLATED).read_to_end(&mut out).ok()?;
+    Some(out)
+}
+
+/// Splits off a NUL-terminated string
+fn split_cstr(data: &[u8]) -> Option<(&[u8], &[u8])> {
+    let end = data.iter().position(|&b| b == 0)?;
+    Some((&data[..end], &data[end + 1..]))
+}
+
+fn latin1(bytes: &[u8]) -> String {
+    bytes.iter().map(|&b| b as char).collect()
+}
+
+/// Marker segment of a JPEG file
+pub(super) struct Segment {
+    pub marker: u8,
+    /// Marker, length and payload
+    pub span: Range<usize>,
+}
+
+/// Walks the marker segments through every scan up to the end of image.
+/// Returns the segments and the offset just past the EOI marker, or `None`
+/// when the file ends early or is malformed.
+pub(super) fn jpeg_segments(data: &[u8]) -> (Vec<Segment>, Option<usize>) {
+    let mut segments = Vec::new();
+    let end = walk_jpeg(data, &mut segments);
+    (segments, end)
+}
+
+fn walk_jpeg(data: &[u8], segments: &mut Vec<Segment>) -> Option<usize> {
+    let mut pos = 2;
+    loop {
+        if *data.get(pos)? != 0xFF {
+            return None;
+        }
+        let marker = *data.get(pos + 1)?;
+        match marker {
+            // Fill byte
+            0xFF => {
+                pos += 1;
+                continue;
+            }
+            0x01 | 0xD0..=0xD8 => {
+                pos += 2;
+                continue;
+            }
+            0xD9 => return Some(pos + 2),
+            _ => {}
+        }
+
+        let length = usize::from(be_u16(data, pos + 2)?);
+        let end = pos + 2 + length;
+        if length < 2 || end > data.len() {
+            return None;
+        }
+        segments.push(Segment { marker, span: pos..end });
+        pos = if marker == 0xDA { skip_entropy_coded(data, end)? } else { end };
+    }
+}
+
+/// Offset of the first marker after entropy-coded scan data
+fn skip_entropy_coded(data: &[u8], mut pos: usize) -> Option<usize> {
+    loop {
+        let at = pos + data.get(pos..)?.iter().position(|&b| b == 0xFF)?;
+        match *data.get(at + 1)? {
+            // Stuffed zero, restart marker, fill byte
+            0x00 | 0xD0..=0xD7 => pos = at + 2,
+            0xFF => pos = at + 1,
+            _ => return Some(at),
+        }
+    }
+}
+
+fn parse_jpeg(data: &[u8], container: &mut Container) -> Option<()> {
+    let (segments, end) = jpeg_segments(data);
+    for Segment { marker, span } in segments {
+        let payload = &data[span.start + 4..span.end];
+        match marker {
+            0xC0..=0xCF if !matches!(marker, 0xC4 | 0xC8 | 0xCC) => {
+                if let (Some(height), Some(width)) = (be_u16(payload, 1), be_u16(payload, 3)) {
+                    container.set_dimensions(width.into(), height.into());
+                }
+            }
+            // Thumbnail width and height follow version, units and density
+            0xE0 if payload.starts_with(b"JFIF\0") && payload.get(12..14).is_some_and(|size| size != [0, 0]) => {
+                container.push(BlockKind::Thumbnail, span, payload.to_vec(), Some("JFIF".to_string()));
+            }
+            0xE0 if payload.starts_with(b"JFXX\0") => {
+                container.push(BlockKind::Thumbnail, span, payload.to_vec(), Some("JFXX".to_string()));
+            }
+            0xE1 if payload.starts_with(EXIF_HEADER) => {
+                container.push(BlockKind::Exif, span, payload[EXIF_HEADER.len()..].to_vec(), None);
+            }
+            0xE1 if payload.starts_with(XMP_HEADER) => {
+                container.push(BlockKind::Xmp, span, payload[XMP_HEADER.len()..].to_vec(), None);
+            }
+            0xE1 if payload.starts_with(XMP_EXTENSION_HEADER) => {
+                // GUID, full length and offset precede each extension chunk
+                let chunk = payload.get(XMP_EXTENSION_HEADER.len() + 40..).unwrap_or_default();
+                container.push(BlockKind::Xmp, span, chunk.to_vec(), Some("extended".to_string()));
+            }
+            0xE2 if payload.starts_with(ICC_HEADER) => {
+                // Sequence number and chunk count precede each profile chunk
+                let chunk = payload.get(ICC_HEADER.len() + 2..).unwrap_or_default();
+                container.push(BlockKind::Icc, span, chunk.to_vec(), None);
+            }
+            0xED if payload.starts_with(PHOTOSHOP_HEADER) => {
+                container.push(BlockKind::Photoshop, span, payload[PHOTOSHOP_HEADER.len()..].to_vec(), None);
+            }
+            // JFIF density and the APP14 "Adobe" color transform belong to the image
+            0xE0 if payload.starts_with(b"JFIF\0") => {}
+            0xEE if payload.starts_with(b"Adobe") => {}
+            0xE0..=0xEF => {
+                let label = format!("APP{}", marker - 0xE0);
+                container.push(BlockKind::Other, span, payload.to_vec(), Some(label));
+            }
+            0xFE => container.push(BlockKind::Comment, span, payload.to_vec(), None),
+            _ => {}
+        }
+    }
+    let end = end?;
+    push_trailer(data, end, container);
+    Some(())
+}
+
+fn push_trailer(data: &[u8], end: usize, container: &mut Container) {
+    if end < data.len() {
+        container.push(BlockKind::Trailer, end..data.len(), data[end..].to_vec(), None);
+    }
+}
+
+fn parse_png(data: &[u8], container: &mut Container) -> Option<()> {
+    let mut pos = PNG_SIGNATURE.len();
+    while pos < data.len() {
+        let length = be_u32(data, pos)? as usize;
+        let chunk_type = data.get(pos + 4..pos + 8)?;
+        let body = data.get(pos + 8..(pos + 8).checked_add(length)?)?;
+        let span = pos..pos + 12 + length;
+        if span.end > data.len() {
+            return None;
+        }
+
+        match chunk_type {
+            b"IHDR" => container.set_dimensions(be_u32(body, 0)?, be_u32(body, 4)?),
+            b"eXIf" => container.push(BlockKind::Exif, span.clone(), body.to_vec(), None),
+            b"iCCP" => {
+                let (name, rest) = split_cstr(body)?;
+                // One byte of compression method, always zlib
+                let profile = inflate(rest.get(1..)?).unwrap_or_default();
+                container.push(BlockKind::Icc, span.clone(), profile, Some(latin1(name)));
+            }
+            b"tEXt" => {
+                let (keyword, text) = split_cstr(body)?;
+                container.push(BlockKind::Text, span.clone(), latin1(text).into_bytes(), Some(latin1(keyword)));
+            }
+            b"zTXt" => {
+                let (keyword, rest) = split_cstr(body)?;
+                let text = inflate(rest.get(1..)?).unwrap_or_default();
+                container.push(BlockKind::Text, span.clone(), latin1(&text).into_bytes(), Some(latin1(keyword)));
+            }
+            b"iTXt" => {
+                let (keyword, rest) = split_cstr(body)?;
+                let compressed = *rest.first()? == 1;
+                let (_language, rest) = split_cstr(rest.get(2..)?)?;
+                let (_translated, text) = split_cstr(rest)?;
+                let text = if compressed { inflate(text).unwrap_or_default() } else { text.to_vec() };
+                let keyword = latin1(keyword);
+                if keyword == PNG_XMP_KEYWORD {
+                    container.push(BlockKind::Xmp, span.clone(), text, None);
+                } else {
+                    container.push(BlockKind::Text, span.clone(), text, Some(keyword));
+                }
+            }
+            b"IEND" => {
+                push_trailer(data, span.end, container);
+                return Some(());
+            }
+            _ if !PNG_IMAGE_CHUNKS.iter().any(|image| image.as_slice() == chunk_type) => {
+                container.push(BlockKind::Other, span.clone(), body.to_vec(), Some(latin1(chunk_type)));
+            }
+            _ => {}
+        }
+        pos = span.end;
+    }
+    None
+}
+
+fn parse_webp(data: &[u8], container: &mut Container) -> Option<()> {
+    let riff_end = le_u32(data, 4)? as usize + 8;
+    if riff_end > data.len() {
+        return None;
+    }
+    let mut pos = 12;
+    while pos + 8 <= riff_end {
+        let fourcc = &data[pos..pos + 4];
+        let length = le_u32(data, pos + 4)? as usize;
+        let body = data.get(pos + 8..(pos + 8).checked_add(length)?)?;
+        let span = pos..(pos + 8 + length + (length & 1)).min(riff_end);
+
+        match fourcc {
+            b"VP8X" => container.set_dimensions(le_u24(body, 4)? + 1, le_u24(body, 7)? + 1),
+            b"VP8 " if body.get(3..6) == Some(b"\x9d\x01\x2a") => {
+                let width = u32::from(u16::from_le_bytes([*body.get(6)?, *body.get(7)?]) & 0x3FFF);
+                let height = u32::from(u16::from_le_bytes([*body.get(8)?, *body.get(9)?]) & 0x3FFF);
+                container.set_dimensions(width, height);
+            }
+            b"VP8L" if body.first() == Some(&0x2F) => {
+                let bits = le_u32(body, 1)?;
+                container.set_dimensions((bits & 0x3FFF) + 1, (bits >> 14 & 0x3FFF) + 1);
+            }
+            b"EXIF" => {
+                // Some writers keep the JPEG segment header
+                let exif = body.strip_prefix(EXIF_HEADER).unwrap_or(body);
+                container.push(BlockKind::Exif, span.clone(), exif.to_vec(), None);
+            }
+            b"XMP " => container.push(BlockKind::Xmp, span.clone(), body.to_vec(), None),
+            b"ICCP" => container.push(BlockKind::Icc, span.clone(), body.to_vec(), None),
+            _ if !WEBP_IMAGE_CHUNKS.iter().any(|image| image.as_slice() == fourcc) => {
+                container.push(BlockKind::Other, span.clone(), body.to_vec(), Some(latin1(fourcc)));
+            }
+            _ => {}
+        }
+        pos = span.end;
+    }
+    push_trailer(data, riff_end, container);
+    Some(())
+}
+
+/// ISO base media file format box
+struct IsoBox<'a> {
+    box_type: &'a [u8],
+    span: Range<usize>,
+    /// Content after the header, relative to the whole file
+    body: Range<usize>,
+}
+
+/// Boxes laid out back to back in `data[range]`
+fn iso_boxes(data: &[u8], range: Range<usize>) -> Option<Vec<IsoBox<'_>>> {
+    let mut boxes = Vec::new();
+    let mut pos = range.start;
+    while pos + 8 <= range.end {
+        let size = be_u32(data, pos)? as usize;
+        let box_type = &data[pos + 4..pos + 8];
+        let (header, size) = match size {
+            0 => (8, range.end - pos),
+            1 => {
+                let large = u64::from(be_u32(data, pos + 8)?) << 32 | u64::from(be_u32(data, pos + 12)?);
+                (16, usize::try_from(large).ok()?)
+            }
+            size => (8, size),
+        };
+        let end = pos.checked_add(size)?;
+        if size < header || end > range.end {
+            return None;
+        }
+        boxes.push(IsoBox {
+            box_type,
+            span: pos..end,
+            body: pos + header..end,
+        });
+        pos = end;
+    }
+    Some(boxes)
+}
+
+struct Cursor<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn uint(&mut self, size: usize) -> Option<u64> {
+        let bytes = self.data.get(self.pos..self.pos + size)?;
+        self.pos += size;
+        Some(bytes.iter().fold(0, |value, &b| value << 8 | u64::from(b)))
+    }
+
+    fn cstr(&mut self) -> Option<&'a [u8]> {
+        let (text, _) = split_cstr(self.data.get(self.pos..)?)?;
+        self.pos += text.len() + 1;
+        Some(text)
+    }
+}
+
+struct HeifItem {
+    item_type: Vec<u8>,
+    content_type: Vec<u8>,
+}
+
+/// Finds the Exif and XMP items through `meta`'s item info and locations,
+/// and ICC profiles among the item properties
+fn parse_heif(data: &[u8], container: &mut Container) -> Option<()> {
+    let top = iso_boxes(data, 0..data.len())?;
+    let Some(meta) = top.iter().find(|b| b.box_type == b"meta") else {
+        return Some(());
+    };
+    // Full box: version and flags first
+    let children = iso_boxes(data, meta.body.start + 4..meta.body.end)?;
+
+    let mut items = HashMap::new();
+    let mut locations = HashMap::new();
+    for child in &children {
+        let body = &data[child.body.clone()];
+        match child.box_type {
+            b"iinf" => {
+                let offset = if *body.first()? == 0 { 6 } else { 8 };
+                for infe in iso_boxes(data, child.body.start + offset..child.body.end)? {
+                    let mut cursor = Cursor {
+                        data: &data[infe.body.clone()],
+                        pos: 0,
+                    };
+                    let version = cursor.uint(4)? >> 24;
+                    if version < 2 {
+                        continue;
+                    }
+                    let item_id = cursor.uint(if version == 2 { 2 } else { 4 })?;
+                    cursor.uint(2)?;
+                    let item_type = cursor.data.get(cursor.pos..cursor.pos + 4)?.to_vec();
+                    cursor.pos += 4;
+                    cursor.cstr()?;
+                    let content_type = if item_type == b"mime" { cursor.cstr()?.to_vec() } else { Vec::new() };
+                    items.insert(item_id, HeifItem {
+                        item_type,
+                        content_type,
+                    });
+                }
+            }
+            b"iloc" => locations = heif_locations(body, data.len())?,
+            b"iprp" => {
+                for properties in iso_boxes(data, child.body.clone())? {
+                    if properties.box_type == b"ipco" {
+                        heif_properties(data, properties.body, container)?;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut ids: Vec<_> = items.keys().copied().collect();
+    ids.sort_unstable();
+    for id in ids {
+        let item = &items[&id];
+        let Some(extents) = locations.get(&id).filter(|e: &&Vec<Range<usize>>| !e.is_empty()) else {
+            continue;
+        };
+        let mut payload = Vec::new();
+        for extent in extents {
+            payload.extend_from_slice(data.get(extent.clone())?);
+            if payload.len() > data.len() {
+                return None;
+            }
+        }
+        let span = extents[0].start..extents[extents.len() - 1].end;
+        if item.item_type == b"Exif" {
+            // Offset from the end of this field to the TIFF header
+            let tiff_offset = be_u32(&payload, 0)? as usize;
+            let exif = payload.get(4 + tiff_offset..).unwrap_or_default().to_vec();
+            container.push(BlockKind::Exif, span, exif, None);
+        } else if item.item_type == b"mime" && item.content_type == b"application/rdf+xml" {
+            container.push(BlockKind::Xmp, span, payload, None);
+        }
+    }
+    Some(())
+}
+
+/// Item extents stored in the file itself (construction method 0). Fields can
+/// be zero bytes wide, so extent counts and lengths are capped at the file size
+/// rather than trusting the bytes they take up.
+fn heif_locations(body: &[u8], file_len: usize) -> Option<HashMap<u64, Vec<Range<usize>>>> {
+    let mut cursor = Cursor { data: body, pos: 0 };
+    let version = cursor.uint(4)? >> 24;
+    let sizes = cursor.uint(2)?;
+    let offset_size = (sizes >> 12 & 0xF) as usize;
+    let length_size = (sizes >> 8 & 0xF) as usize;
+    let base_offset_size = (sizes >> 4 & 0xF) as usize;
+    let index_size = if version >= 1 { (sizes & 0xF) as usize } else { 0 };
+    let count = cursor.uint(if version < 2 { 2 } else { 4 })?;
+
+    let mut locations = HashMap::new();
+    let (mut extent_total, mut length_total) = (0usize, 0usize);
+    for _ in 0..count {
+        let item_id = cursor.uint(if version < 2 { 2 } else { 4 })?;
+        let construction_method = if version >= 1 { cursor.uint(2)? & 0xF } else { 0 };
+        cursor.uint(2)?;
+        let base_offset = cursor.uint(base_offset_size)?;
+        let extent_count = cursor.uint(2)? as usize;
+        extent_total += extent_count;
+        if extent_total > file_len {
+            return None;
+        }
+        let mut extents = Vec::new();
+        for _ in 0..extent_count {
+            cursor.uint(index_size)?;
+            let offset = usize::try_from(base_offset.checked_add(cursor.uint(offset_size)?)?).ok()?;
+            let length = usize::try_from(cursor.uint(length_size)?).ok()?;
+            length_total = length_total.checked_add(length)?;
+            if length_total > file_len {
+                return None;
+            }
+            extents.push(offset..offset.checked_add(length)?);
+        }
+        if construction_method == 0 {
+            locations.insert(item_id, extents);
+        }
+    }
+    Some(locations)
+}
+
+fn heif_properties(data: &[u8], range: Range<usize>, container: &mut Container) -> Option<()> {
+    let mut largest = 0u64;
+    for property in iso_boxes(data, range)? {
+        let body = &data[property.body.clone()];
+        match property.box_type {
+            b"ispe" => {
+                let (width, height) = (be_u32(body, 4)?, be_u32(body, 8)?);
+                // Grids and thumbnails each get their own extent; the largest is the image
+                if u64::from(width) * u64::from(height) > largest {
+                    largest = u64::from(width) * u64::from(height);
+                    container.width = Some(width);
+                    container.height = Some(height);
+                }
+            }
+            b"colr" if matches!(body.get(..4), Some(b"prof" | b"rICC")) => {
+                container.push(BlockKind::Icc, property.span, body[4..].to_vec(), None);
+            }
+            _ => {}
+        }
+    }
+    Some(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blocks_located_in_each_format() {
+        let fixtures: [(&[u8], ImageFormat, &[BlockKind]); 5] = [
+            (
+                include_bytes!("../../tests/fixtures/images/photo.jpg"),
+                ImageFormat::Jpeg,
+                &[BlockKind::Exif, BlockKind::Xmp, BlockKind::Icc, BlockKind::Photoshop, BlockKind::Comment],
+            ),
+            (
+                include_bytes!("../../tests/fixtures/images/photo.png"),
+                ImageFormat::Png,
+                &[BlockKind::Icc, BlockKind::Exif, BlockKind::Xmp, BlockKind::Text],
+            ),
+            (
+                include_bytes!("../../tests/fixtures/images/photo.webp"),
+                ImageFormat::Webp,
+                &[BlockKind::Icc, BlockKind::Exif, BlockKind::Xmp],
+            ),
+            (include_bytes!("../../tests/fixtures/images/photo.tiff"), ImageFormat::Tiff, &[BlockKind::Exif]),
+            (
+                include_bytes!("../../tests/fixtures/images/photo.heic"),
+                ImageFormat::Heif,
+                &[BlockKind::Icc, BlockKind::Exif, BlockKind::Xmp],
+            ),
+        ];
+
+        for (data, format, kinds) in fixtures {
+            let container = parse(data).unwrap();
+            assert_eq!(container.format, format);
+            assert!(container.warnings.is_empty(), "{:?}: {:?}", format, container.warnings);
+            assert_eq!(container.blocks.iter().map(|b| b.kind).collect::<Vec<_>>(), kinds, "{:?}", format);
+            if format != ImageFormat::Tiff {
+                assert_eq!((container.width, container.height), (Some(16), Some(12)), "{:?}", format);
+            }
+            for block in &container.blocks {
+                assert!(block.span.end <= data.len());
+            }
+        }
+    }
+
+    #[test]
+    fn test_truncated_file_keeps_blocks_read_so_far() {
+        let data = include_bytes!("../../tests/fixtures/images/photo.jpg");
+        let cut = parse(data).unwrap().blocks[1].span.end + 10;
+        let container = parse(&data[..cut]).unwrap();
+        assert_eq!(container.blocks.len(), 2);
+        assert_eq!(container.warnings.len(), 1);
+        assert!(parse(b"GIF89a").is_err());
+    }
+
+    #[test]
+    fn test_malformed_files_do_not_panic() {
+        let fixtures: [&[u8]; 5] = [
+            include_bytes!("../../tests/fixtures/images/photo.jpg"),
+            include_bytes!("../../tests/fixtures/images/photo.png"),
+            include_bytes!("../../tests/fixtures/images/photo.webp"),
+            include_bytes!("../../tests/fixtures/images/photo.tiff"),
+            include_bytes!("../../tests/fixtures/images/photo.heic"),
+        ];
+
+        for data in fixtures {
+            for len in 0..data.len() {
+                let _ = parse(&data[..len]);
+            }
+            for pos in 0..data.len() {
+                for byte in [0x00, 0xFF, data[pos] ^ 0x80] {
+                    let mut mutated = data.to_vec();
+                    mutated[pos] = byte;
+                    let _ = parse(&mutated);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_heif_extents_are_capped_at_file_size() {
+        fn iso_box(box_type: &[u8], body: &[u8]) -> Vec<u8> {
+            let mut out = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+            out.extend_from_slice(box_type);
+            out.extend_from_slice(body);
+            out
+        }
+        fn heic(iloc: &[u8]) -> Vec<u8> {
+            let mut meta = vec![0; 4];
+            meta.extend(iso_box(b"iloc", iloc));
+            // infe version 2: item 1 is an Exif item
+            let infe = iso_box(b"infe", &[2, 0, 0, 0, 0, 1, 0, 0, b'E', b'x', b'i', b'f', 0]);
+            meta.extend(iso_box(b"iinf", &[[0, 0, 0, 0, 0, 1].as_slice(), &infe].concat()));
+            [iso_box(b"ftyp", b"heic\0\0\0\0"), iso_box(b"meta", &meta)].concat()
+        }
+
+        // Offset, length and index fields zero bytes wide: every extent is free to encode
+        let mut free = vec![1, 0, 0, 0, 0x00, 0x00, 0xFF, 0xFF];
+        for item_id in 0..=u16::MAX {
+            free.extend_from_slice(&item_id.to_be_bytes());
+            free.extend_from_slice(&[0, 0, 0, 0, 0xFF, 0xFF]);
+        }
+        // One item whose extents all repeat the same bytes, adding up to more than the file
+        let mut repeated = vec![0, 0, 0, 0, 0x44, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x01, 0x00];
+        for _ in 0..256 {
+            repeated.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0x10]);
+        }
+
+        for data in [heic(&free), heic(&repeated)] {
+            let container = parse(&data).unwrap();
+            assert!(container.blocks.is_empty());
+            assert_eq!(container.warnings.len(), 1);
+        }
+    }
+}
@@ -0,0 +1,744 @@
+//! Decodes the metadata blocks of an image into a report: EXIF fields and
+//! the capture summary, GPS position, XMP properties, IPTC-IIM datasets,
+//! embedded thumbnails and the ICC profile header.
+
+use super::container::{self, be_u16, be_u32, BlockKind, ImageFormat};
+use anyhow::Result;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use exif::{Context as ExifContext, Exif, Field, In, Reader, Tag, Value};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+/// TIFF tags that embed XMP, IPTC-IIM and ICC data
+const TAG_XMP: Tag = Tag(ExifContext::Tiff, 700);
+const TAG_IPTC: Tag = Tag(ExifContext::Tiff, 33723);
+const TAG_ICC: Tag = Tag(ExifContext::Tiff, 34675);
+/// Photoshop image resource IDs
+const RESOURCE_IPTC: u16 = 0x0404;
+const RESOURCE_THUMBNAIL: u16 = 0x040C;
+/// Header before the JPEG data of a Photoshop thumbnail resource
+const PHOTOSHOP_THUMBNAIL_HEADER: usize = 28;
+/// Binary EXIF values larger than this are reported by size only
+const MAX_BINARY_VALUE: usize = 64;
+const RDF_NS: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+const XML_NS: &str = "http://www.w3.org/XML/1998/namespace";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImageMetadata {
+    pub format: ImageFormat,
+    pub file_size: usize,
+    pub sha256: String,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub exif: Option<ExifData>,
+    pub gps: Option<GpsPosition>,
+    pub xmp: Option<XmpPacket>,
+    /// IPTC-IIM datasets by name; repeatable ones such as keywords in order
+    pub iptc: BTreeMap<String, Vec<String>>,
+    pub thumbnails: Vec<Thumbnail>,
+    pub icc: Option<IccProfile>,
+    /// JPEG comments and PNG text chunks
+    pub text: Vec<TextEntry>,
+    pub blocks: Vec<BlockInfo>,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ExifData {
+    pub make: Option<String>,
+    pub model: Option<String>,
+    pub serial_number: Option<String>,
+    pub lens_make: Option<String>,
+    pub lens_model: Option<String>,
+    pub lens_serial_number: Option<String>,
+    pub software: Option<String>,
+    pub artist: Option<String>,
+    pub copyright: Option<String>,
+    pub description: Option<String>,
+    /// ISO 8601, with the UTC offset when the camera recorded one
+    pub date_time_original: Option<String>,
+    pub date_time_digitized: Option<String>,
+    /// When the file was last changed, usually by editing software
+    pub date_time_modified: Option<String>,
+    pub exposure_time: Option<String>,
+    pub f_number: Option<String>,
+    pub iso: Option<String>,
+    pub focal_length: Option<String>,
+    pub orientation: Option<u32>,
+    pub fields: Vec<ExifField>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExifField {
+    /// 0 for the main image, 1 for the thumbnail
+    pub ifd: u16,
+    /// tiff, exif, gps or interop
+    pub group: String,
+    pub tag: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GpsPosition {
+    /// Decimal degrees, negative south of the equator
+    pub latitude: f64,
+    /// Decimal degrees, negative west of Greenwich
+    pub longitude: f64,
+    /// Meters, negative below sea level
+    pub altitude: Option<f64>,
+    /// RFC 3339, UTC
+    pub timestamp: Option<String>,
+    /// Block the position was read from: exif or xmp
+    pub source: BlockKind,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct XmpPacket {
+    /// Simple properties and arrays by prefixed name, array items joined with "; "
+    pub properties: BTreeMap<String, String>,
+    pub packet: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Thumbnail {
+    /// Block the thumbnail is embedded in: exif or photoshop
+    pub source: BlockKind,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub size: usize,
+    pub data_url: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IccProfile {
+    pub size: usize,
+    pub version: String,
+    pub cmm: Option<String>,
+    pub device_class: Option<String>,
+    pub color_space: Option<String>,
+    pub connection_space: Option<String>,
+    pub created: Option<String>,
+    pub platform: Option<String>,
+    pub manufacturer: Option<String>,
+    pub creator: Option<String>,
+    pub description: Option<String>,
+    pub copyright: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TextEntry {
+    /// PNG keyword; none for JPEG comments
+    pub keyword: Option<String>,
+    pub text: String,
+}
+
+/// Where a metadata block sits in the file
+#[derive(Debug, Clone, Serialize)]
+pub struct BlockInfo {
+    pub kind: BlockKind,
+    pub offset: usize,
+    pub length: usize,
+    pub label: Option<String>,
+}
+
+/// Read every metadata block the image carries. Damaged blocks are skipped
+/// with a warning so the rest can still be reported.
+pub fn extract_metadata(data: &[u8]) -> Result<ImageMetadata> {
+    let parsed = container::parse(data)?;
+    let mut report = ImageMetadata {
+        format: parsed.format,
+        file_size: data.len(),
+        sha256: hex::encode(Sha256::digest(data)),
+        width: parsed.width,
+        height: parsed.height,
+        exif: None,
+        gps: None,
+        xmp: None,
+        iptc: BTreeMap::new(),
+        thumbnails: Vec::new(),
+        icc: None,
+        text: Vec::new(),
+        blocks: Vec::new(),
+        warnings: parsed.warnings,
+    };
+
+    let mut xmp = None;
+    let mut icc = Vec::new();
+    let mut exif = None;
+    for block in &parsed.blocks {
+        match block.kind {
+            BlockKind::Exif if exif.is_none() => exif = read_exif(&block.data, &mut report.warnings),
+            // Extended XMP continues the main packet and is not a packet of its own
+            BlockKind::Xmp if xmp.is_none() && block.label.is_none() => xmp = Some(block.data.clone()),
+            BlockKind::Icc => icc.extend_from_slice(&block.data),
+            BlockKind::Photoshop => {
+                for (id, resource) in photoshop_resources(&block.data) {
+                    match id {
+                        RESOURCE_IPTC => iptc_datasets(resource, &mut report.iptc),
+                        RESOURCE_THUMBNAIL => {
+                            let jpeg = resource.get(PHOTOSHOP_THUMBNAIL_HEADER..).unwrap_or_default();
+                            report.thumbnails.extend(thumbnail(BlockKind::Photoshop, jpeg));
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            BlockKind::Comment | BlockKind::Text => report.text.push(TextEntry {
+                keyword: block.label.clone(),
+                text: String::from_utf8_lossy(&block.data).trim_end_matches('\0').to_string(),
+            }),
+            _ => {}
+        }
+        report.blocks.push(BlockInfo {
+            kind: block.kind,
+            offset: block.span.start,
+            length: block.span.len(),
+            label: block.label.clone(),
+        });
+    }
+
+    if let Some(exif) = &exif {
+        // TIFF files keep XMP, IPTC and ICC data in tags of the main image
+        if xmp.is_none() {
+            xmp = field_bytes(exif, TAG_XMP);
+        }
+        if report.iptc.is_empty() {
+            if let Some(iptc) = field_bytes(exif, TAG_IPTC) {
+                iptc_datasets(&iptc, &mut report.iptc);
+            }
+        }
+        if icc.is_empty() {
+            icc = field_bytes(exif, TAG_ICC).unwrap_or_default();
+        }
+        if report.width.is_none() {
+            report.width = uint(exif, Tag::ImageWidth);
+            report.height = uint(exif, Tag::ImageLength);
+        }
+        if let Some(jpeg) = exif_thumbnail(exif) {
+            report.thumbnails.extend(thumbnail(BlockKind::Exif, jpeg));
+        }
+        report.gps = gps_position(exif);
+        report.exif = Some(exif_data(exif));
+    }
+
+    if let Some(xmp) = xmp {
+        let packet = xmp_packet(&xmp, &mut report.warnings);
+        if report.gps.is_none() {
+            report.gps = xmp_position(&packet.properties);
+        }
+        report.xmp = Some(packet);
+    }
+    if !icc.is_empty() {
+        report.icc = icc_profile(&icc);
+        if report.icc.is_none() {
+            report.warnings.push("ICC profile header is not valid".to_string());
+        }
+    }
+    Ok(report)
+}
+
+fn read_exif(data: &[u8], warnings: &mut Vec<String>) -> Option<Exif> {
+    let mut reader = Reader::new();
+    reader.continue_on_error(true);
+    let result = reader
+        .read_raw(data.to_vec())
+        .or_else(|e| e.distill_partial_result(|errors| warnings.extend(errors.iter().map(|e| format!("EXIF: {}", e)))));
+    match result {
+        Ok(exif) => Some(exif),
+        Err(e) => {
+            warnings.push(format!("EXIF could not be read: {}", e));
+            None
+        }
+    }
+}
+
+fn text(exif: &Exif, tag: Tag) -> Option<String> {
+    match &exif.get_field(tag, In::PRIMARY)?.value {
+        Value::Ascii(parts) => {
+            let text = String::from_utf8_lossy(parts.first()?).trim().to_string();
+            (!text.is_empty()).then_some(text)
+        }
+        _ => None,
+    }
+}
+
+fn display(exif: &Exif, tag: Tag) -> Option<String> {
+    Some(exif.get_field(tag, In::PRIMARY)?.display_value().with_unit(exif).to_string())
+}
+
+fn uint(exif: &Exif, tag: Tag) -> Option<u32> {
+    exif.get_field(tag, In::PRIMARY)?.value.get_uint(0)
+}
+
+fn field_bytes(exif: &Exif, tag: Tag) -> Option<Vec<u8>> {
+    match &exif.get_field(tag, In::PRIMARY)?.value {
+        Value::Byte(bytes) | Value::Undefined(bytes, _) => Some(bytes.clone()),
+        // IPTC is often declared as LONG; the bytes are the same either way
+        Value::Long(values) if exif.little_endian() => Some(values.iter().flat_map(|v| v.to_le_bytes()).collect()),
+        Value::Long(values) => Some(values.iter().flat_map(|v| v.to_be_bytes()).collect()),
+        _ => None,
+    }
+}
+
+/// EXIF "YYYY:MM:DD HH:MM:SS" as ISO 8601, with the offset when one is recorded
+fn timestamp(value: &str, offset: Option<String>) -> String {
+    let Ok(parsed) = NaiveDateTime::parse_from_str(value, "%Y:%m:%d %H:%M:%S") else {
+        return value.to_string();
+    };
+    let local = parsed.format("%Y-%m-%dT%H:%M:%S").to_string();
+    match offset {
+        Some(offset) if offset.len() == 6 && offset.starts_with(['+', '-']) => local + &offset,
+        _ => local,
+    }
+}
+
+fn exif_data(exif: &Exif) -> ExifData {
+    let time = |tag, offset_tag| text(exif, tag).map(|value| timestamp(&value, text(exif, offset_tag)));
+    ExifData {
+        make: text(exif, Tag::Make),
+        model: text(exif, Tag::Model),
+        serial_number: text(exif, Tag::BodySerialNumber),
+        lens_make: text(exif, Tag::LensMake),
+        lens_model: text(exif, Tag::LensModel),
+        lens_serial_number: text(exif, Tag::LensSerialNumber),
+        software: text(exif, Tag::Software),
+        artist: text(exif, Tag::Artist),
+        copyright: text(exif, Tag::Copyright),
+        description: text(exif, Tag::ImageDescription),
+        date_time_original: time(Tag::DateTimeOriginal, Tag::OffsetTimeOriginal),
+        date_time_digitized: time(Tag::DateTimeDigitized, Tag::OffsetTimeDigitized),
+        date_time_modified: time(Tag::DateTime, Tag::OffsetTime),
+        exposure_time: display(exif, Tag::ExposureTime),
+        f_number: display(exif, Tag::FNumber),
+        iso: display(exif, Tag::PhotographicSensitivity),
+        focal_length: display(exif, Tag::FocalLength),
+        orientation: uint(exif, Tag::Orientation),
+        fields: exif.fields().map(|field| exif_field(exif, field)).collect(),
+    }
+}
+
+fn exif_field(exif: &Exif, field: &Field) -> ExifField {
+    let value = match &field.value {
+        Value::Ascii(parts) => {
+            parts.iter().map(|p| String::from_utf8_lossy(p).trim().to_string()).collect::<Vec<_>>().join(", ")
+        }
+        Value::Byte(bytes) | Value::Undefined(bytes, _) if bytes.len() > MAX_BINARY_VALUE => {
+            format!("{} bytes", bytes.len())
+        }
+        Value::Long(values) if values.len() * 4 > MAX_BINARY_VALUE => format!("{} bytes", values.len() * 4),
+        _ => field.display_value().with_unit(exif).to_string(),
+    };
+    let group = match field.tag.context() {
+        ExifContext::Tiff => "tiff",
+        ExifContext::Exif => "exif",
+        ExifContext::Gps => "gps",
+        ExifContext::Interop => "interop",
+        _ => "other",
+    };
+    ExifField {
+        ifd: field.ifd_num.index(),
+        group: group.to_string(),
+        tag: field.tag.to_string(),
+        value,
+    }
+}
+
+fn gps_position(exif: &Exif) -> Option<GpsPosition> {
+    let latitude = gps_coordinate(exif, Tag::GPSLatitude, Tag::GPSLatitudeRef, b'S')?;
+    let longitude = gps_coordinate(exif, Tag::GPSLongitude, Tag::GPSLongitudeRef, b'W')?;
+    let altitude = match exif.get_field(Tag::GPSAltitude, In::PRIMARY).map(|f| &f.value) {
+        Some(Value::Rational(values)) if !values.is_empty() => {
+            let meters = values[0].to_f64();
+            let below_sea_level = uint(exif, Tag::GPSAltitudeRef) == Some(1);
+            meters.is_finite().then_some(if below_sea_level { -meters } else { meters })
+        }
+        _ => None,
+    };
+    Some(GpsPosition {
+        latitude,
+        longitude,
+        altitude,
+        timestamp: gps_timestamp(exif),
+        source: BlockKind::Exif,
+    })
+}
+
+/// Degrees, minutes and seconds as signed decimal degrees
+fn gps_coordinate(exif: &Exif, tag: Tag, reference_tag: Tag, negative: u8) -> Option<f64> {
+    let Value::Rational(parts) = &exif.get_field(tag, In::PRIMARY)?.value else {
+        return None;
+    };
+    let degrees: f64 = parts.iter().zip([1.0, 60.0, 3600.0]).map(|(part, unit)| part.to_f64() / unit).sum();
+    if !degrees.is_finite() {
+        return None;
+    }
+    let reference = match &exif.get_field(reference_tag, In::PRIMARY)?.value {
+        Value::Ascii(parts) => parts.first().and_then(|p| p.first()).copied(),
+        _ => None,
+    };
+    Some(if reference == Some(negative) { -degrees } else { degrees })
+}
+
+fn gps_timestamp(exif: &Exif) -> Option<String> {
+    let date = NaiveDate::parse_from_str(&text(exif, Tag::GPSDateStamp)?, "%Y:%m:%d").ok()?;
+    let Value::Rational(parts) = &exif.get_field(Tag::GPSTimeStamp, In::PRIMARY)?.value else {
+        return None;
+    };
+    let seconds: f64 = parts.iter().zip([3600.0, 60.0, 1.0]).map(|(part, unit)| part.to_f64() * unit).sum();
+    if !seconds.is_finite() || seconds < 0.0 {
+        return None;
+    }
+    let time = NaiveTime::from_num_seconds_from_midnight_opt(seconds as u32, 0)?;
+    Some(date.and_time(time).format("%Y-%m-%dT%H:%M:%SZ").to_string())
+}
+
+fn exif_thumbnail(exif: &Exif) -> Option<&[u8]> {
+    let offset = exif.get_field(Tag::JPEGInterchangeFormat, In::THUMBNAIL)?.value.get_uint(0)? as usize;
+    let length = exif.get_field(Tag::JPEGInterchangeFormatLength, In::THUMBNAIL)?.value.get_uint(0)? as usize;
+    exif.buf().get(offset..offset.checked_add(length)?)
+}
+
+fn thumbnail(source: BlockKind, jpeg: &[u8]) -> Option<Thumbnail> {
+    if !jpeg.starts_with(b"\xFF\xD8") {
+        return None;
+    }
+    let dimensions = container::parse(jpeg).ok();
+    Some(Thumbnail {
+        source,
+        width: dimensions.as_ref().and_then(|d| d.width),
+        height: dimensions.as_ref().and_then(|d| d.height),
+        size: jpeg.len(),
+        data_url: format!("data:image/jpeg;base64,{}", BASE64.encode(jpeg)),
+    })
+}
+
+fn xmp_packet(data: &[u8], warnings: &mut Vec<String>) -> XmpPacket {
+    let packet = String::from_utf8_lossy(data).trim_end_matches(['\0', ' ', '\n']).to_string();
+    let properties = match roxmltree::Document::parse(&packet) {
+        Ok(document) => xmp_properties(&document),
+        Err(e) => {
+            warnings.push(format!("XMP packet is not well-formed: {}", e));
+            BTreeMap::new()
+        }
+    };
+    XmpPacket { properties, packet }
+}
+
+fn xmp_properties(document: &roxmltree::Document) -> BTreeMap<String, String> {
+    let mut properties = BTreeMap::new();
+    for description in document.descendants().filter(|n| n.has_tag_name((RDF_NS, "Description"))) {
+        for attribute in description.attributes() {
+            match attribute.namespace() {
+                Some(namespace) if namespace != RDF_NS && namespace != XML_NS => {
+                    let name = qualified_name(description, namespace, attribute.name());
+                    properties.insert(name, attribute.value().to_string());
+                }
+                _ => {}
+            }
+        }
+        for property in description.children().filter(|n| n.is_element()) {
+            let Some(namespace) = property.tag_name().namespace() else {
+                continue;
+            };
+            if let Some(value) = xmp_value(property) {
+                properties.insert(qualified_name(property, namespace, property.tag_name().name()), value);
+            }
+        }
+    }
+    properties
+}
+
+fn qualified_name(node: roxmltree::Node, namespace: &str, name: &str) -> String {
+    match node.lookup_prefix(namespace) {
+        Some(prefix) if !prefix.is_empty() => format!("{}:{}", prefix, name),
+        _ => name.to_string(),
+    }
+}
+
+/// Simple values and arrays; structures are left to the raw packet
+fn xmp_value(property: roxmltree::Node) -> Option<String> {
+    if let Some(resource) = property.attribute((RDF_NS, "resource")) {
+        return Some(resource.to_string());
+    }
+    match property.children().find(|n| n.is_element()) {
+        None => property.text().map(str::trim).filter(|t| !t.is_empty()).map(str::to_string),
+        Some(array) if ["Alt", "Seq", "Bag"].iter().any(|name| array.has_tag_name((RDF_NS, *name))) => {
+            let items: Vec<_> = array
+                .children()
+                .filter(|n| n.has_tag_name((RDF_NS, "li")))
+                .filter_map(|li| li.text())
+                .map(str::trim)
+                .filter(|t| !t.is_empty())
+                .collect();
+            (!items.is_empty()).then(|| items.join("; "))
+        }
+        _ => None,
+    }
+}
+
+/// XMP stores EXIF GPS as "DDD,MM,SSk" or "DDD,MM.mmk", k being N, S, E or W
+fn xmp_coordinate(value: &str) -> Option<f64> {
+    let value = value.trim();
+    let direction = value.chars().last()?;
+    let sign = match direction {
+        'N' | 'E' => 1.0,
+        'S' | 'W' => -1.0,
+        _ => return None,
+    };
+    let mut degrees = 0.0;
+    for (part, unit) in value[..value.len() - 1].split(',').zip([1.0, 60.0, 3600.0]) {
+        degrees += part.trim().parse::<f64>().ok()? / unit;
+    }
+    Some(sign * degrees)
+}
+
+fn xmp_position(properties: &BTreeMap<String, String>) -> Option<GpsPosition> {
+    Some(GpsPosition {
+        latitude: xmp_coordinate(properties.get("exif:GPSLatitude")?)?,
+        longitude: xmp_coordinate(properties.get("exif:GPSLongitude")?)?,
+        altitude: None,
+        timestamp: properties.get("exif:GPSTimeStamp").cloned(),
+        source: BlockKind::Xmp,
+    })
+}
+
+/// Photoshop image resources: "8BIM", an ID, a padded Pascal name and padded data
+fn photoshop_resources(data: &[u8]) -> Vec<(u16, &[u8])> {
+    let mut resources = Vec::new();
+    let mut pos = 0;
+    while data.get(pos..pos + 4) == Some(b"8BIM") {
+        let (Some(id), Some(&name_length)) = (be_u16(data, pos + 4), data.get(pos + 6)) else {
+            break;
+        };
+        let size_at = pos + 6 + ((usize::from(name_length) + 2) & !1);
+        let Some(size) = be_u32(data, size_at).map(|size| size as usize) else {
+            break;
+        };
+        let Some(resource) = data.get(size_at + 4..size_at + 4 + size) else {
+            break;
+        };
+        resources.push((id, resource));
+        pos = size_at + 4 + size + (size & 1);
+    }
+    resources
+}
+
+/// Application record (2:xx) datasets of an IPTC-IIM stream
+fn iptc_datasets(data: &[u8], iptc: &mut BTreeMap<String, Vec<String>>) {
+    let mut pos = 0;
+    while let Some(&[0x1C, record, dataset, high, low]) = data.get(pos..pos + 5) {
+        let length = usize::from(u16::from_be_bytes([high, low]));
+        // Extended datasets give the number of length bytes that follow instead
+        let (header, length) = if length & 0x8000 == 0 {
+            (5, length)
+        } else {
+            let count = length & 0x7FFF;
+            let Some(bytes) = data.get(pos + 5..pos + 5 + count).filter(|_| count <= 4) else {
+                return;
+            };
+            (5 + count, bytes.iter().fold(0, |size, &b| size << 8 | usize::from(b)))
+        };
+        let Some(value) = data.get(pos + header..pos + header + length) else {
+            return;
+        };
+        if record == 2 && dataset != 0 {
+            let name = iptc_name(dataset).map_or_else(|| format!("2:{}", dataset), str::to_string);
+            iptc.entry(name).or_default().push(String::from_utf8_lossy(value).trim().to_string());
+        }
+        pos += header + length;
+    }
+}
+
+fn iptc_name(dataset: u8) -> Option<&'static str> {
+    Some(match dataset {
+        5 => "Object Name",
+        7 => "Edit Status",
+        10 => "Urgency",
+        15 => "Category",
+        20 => "Supplemental Category",
+        25 => "Keywords",
+        40 => "Special Instructions",
+        55 => "Date Created",
+        60 => "Time Created",
+        62 => "Digital Creation Date",
+        63 => "Digital Creation Time",
+        65 => "Originating Program",
+        70 => "Program Version",
+        80 => "By-line",
+        85 => "By-line Title",
+        90 => "City",
+        92 => "Sub-location",
+        95 => "Province/State",
+        100 => "Country Code",
+        101 => "Country",
+        103 => "Original Transmission Reference",
+        105 => "Headline",
+        110 => "Credit",
+        115 => "Source",
+        116 => "Copyright Notice",
+        118 => "Contact",
+        120 => "Caption/Abstract",
+        122 => "Writer/Editor",
+        _ => return None,
+    })
+}
+
+fn icc_profile(data: &[u8]) -> Option<IccProfile> {
+    if data.get(36..40) != Some(b"acsp") {
+        return None;
+    }
+    let date: Vec<u16> = (0..6).map(|i| be_u16(data, 24 + i * 2)).collect::<Option<_>>()?;
+    let created = (date[0] != 0).then(|| {
+        format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", date[0], date[1], date[2], date[3], date[4], date[5])
+    });
+    Some(IccProfile {
+        size: data.len(),
+        version: format!("{}.{}.{}", data[8], data[9] >> 4, data[9] & 0xF),
+        cmm: signature(data, 4),
+        device_class: signature(data, 12),
+        color_space: signature(data, 16),
+        connection_space: signature(data, 20),
+        created,
+        platform: signature(data, 40),
+        manufacturer: signature(data, 48),
+        creator: signature(data, 80),
+        description: icc_text(data, b"desc"),
+        copyright: icc_text(data, b"cprt"),
+    })
+}
+
+fn signature(data: &[u8], at: usize) -> Option<String> {
+    let bytes = data.get(at..at + 4)?;
+    let text: String = bytes.iter().map(|&b| b as char).collect();
+    let text = text.trim_matches(['\0', ' ']);
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// Text of a `desc`, `text` or `mluc` tag; for `mluc` the first translation
+fn icc_text(data: &[u8], signature: &[u8; 4]) -> Option<String> {
+    let count = be_u32(data, 128)? as usize;
+    let entry = (0..count.min(256)).map(|i| 132 + i * 12).find(|&at| data.get(at..at + 4) == Some(signature))?;
+    let offset = be_u32(data, entry + 4)? as usize;
+    let size = be_u32(data, entry + 8)? as usize;
+    let tag = data.get(offset..offset.checked_add(size)?)?;
+    let text = match tag.get(..4)? {
+        b"desc" => {
+            let length = be_u32(tag, 8)? as usize;
+            String::from_utf8_lossy(tag.get(12..12 + length)?).to_string()
+        }
+        b"text" => String::from_utf8_lossy(tag.get(8..)?).to_string(),
+        b"mluc" if be_u32(tag, 8)? > 0 => {
+            let length = be_u32(tag, 20)? as usize;
+            let start = be_u32(tag, 24)? as usize;
+            let units: Vec<u16> =
+                tag.get(start..start + length)?.chunks_exact(2).map(|c| u16::from_be_bytes([c[0], c[1]])).collect();
+            String::from_utf16_lossy(&units)
+        }
+        _ => return None,
+    };
+    let text = text.trim_matches(['\0', ' ']);
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-6, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn test_jpeg_fixture_report() {
+        let report = extract_metadata(include_bytes!("../../tests/fixtures/images/photo.jpg")).unwrap();
+        assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+        assert_eq!((report.width, report.height), (Some(16), Some(12)));
+
+        let exif = report.exif.unwrap();
+        assert_eq!(exif.make.as_deref(), Some("Canon"));
+        assert_eq!(exif.model.as_deref(), Some("Canon EOS R5"));
+        assert_eq!(exif.lens_model.as_deref(), Some("RF50mm F1.8 STM"));
+        assert_eq!(exif.serial_number.as_deref(), Some("012345678901"));
+        assert_eq!(exif.software.as_deref(), Some("Adobe Photoshop 25.0"));
+        assert_eq!(exif.date_time_original.as_deref(), Some("2024-04-30T18:42:07+02:00"));
+        assert_eq!(exif.date_time_modified.as_deref(), Some("2024-05-01T10:00:00"));
+        assert_eq!(exif.exposure_time.as_deref(), Some("1/250 s"));
+        assert!(exif.fields.iter().any(|f| f.group == "gps" && f.tag == "GPSLatitudeRef" && f.value == "N"));
+
+        let gps = report.gps.unwrap();
+        assert_near(gps.latitude, 52.0 + 22.0 / 60.0 + 23.4 / 3600.0);
+        assert_near(gps.longitude, 4.0 + 53.0 / 60.0 + 42.0 / 3600.0);
+        assert_eq!(gps.altitude, Some(12.0));
+        assert_eq!(gps.timestamp.as_deref(), Some("2024-04-30T16:42:07Z"));
+
+        let xmp = report.xmp.unwrap();
+        assert_eq!(xmp.properties["xmp:CreatorTool"], "Adobe Photoshop 25.0");
+        assert_eq!(xmp.properties["dc:creator"], "Jane Doe");
+        assert_eq!(xmp.properties["dc:title"], "Harbour at dusk");
+
+        assert_eq!(report.iptc["City"], ["Amsterdam"]);
+        assert_eq!(report.iptc["Keywords"], ["harbour", "evidence"]);
+        assert_eq!(report.iptc["By-line"], ["Jane Doe"]);
+
+        assert_eq!(report.thumbnails.len(), 1);
+        assert_eq!((report.thumbnails[0].width, report.thumbnails[0].height), (Some(4), Some(3)));
+        assert!(report.thumbnails[0].data_url.starts_with("data:image/jpeg;base64,/9j/"));
+
+        let icc = report.icc.unwrap();
+        assert_eq!(icc.description.as_deref(), Some("Display P3"));
+        assert_eq!(icc.color_space.as_deref(), Some("RGB"));
+        assert_eq!(icc.version, "2.1.0");
+        assert_eq!(icc.created.as_deref(), Some("2023-03-09T10:30:00Z"));
+
+        assert_eq!(report.text[0].text, "Taken from the ferry");
+        assert_eq!(report.blocks.len(), 5);
+    }
+
+    #[test]
+    fn test_other_container_fixtures() {
+        let png = extract_metadata(include_bytes!("../../tests/fixtures/images/photo.png")).unwrap();
+        let gps = png.gps.unwrap();
+        assert_near(gps.latitude, -(33.0 + 51.0 / 60.0 + 24.48 / 3600.0));
+        assert_near(gps.longitude, 151.0 + 12.0 / 60.0 + 55.08 / 3600.0);
+        assert!(png.thumbnails.is_empty());
+        assert_eq!(png.icc.unwrap().description.as_deref(), Some("Display P3"));
+        assert_eq!(png.text[0].keyword.as_deref(), Some("Comment"));
+        assert_eq!(png.xmp.unwrap().properties["dc:creator"], "Jane Doe");
+
+        let webp = extract_metadata(include_bytes!("../../tests/fixtures/images/photo.webp")).unwrap();
+        assert_eq!(webp.format, ImageFormat::Webp);
+        assert_eq!(webp.exif.unwrap().model.as_deref(), Some("Canon EOS R5"));
+        assert_eq!(webp.thumbnails.len(), 1);
+        assert!(webp.xmp.is_some() && webp.icc.is_some());
+
+        let tiff = extract_metadata(include_bytes!("../../tests/fixtures/images/photo.tiff")).unwrap();
+        assert_eq!((tiff.width, tiff.height), (Some(4), Some(4)));
+        assert_eq!(tiff.iptc["City"], ["Amsterdam"]);
+        assert_eq!(tiff.xmp.unwrap().properties["xmp:CreatorTool"], "Adobe Photoshop 25.0");
+        assert_eq!(tiff.icc.unwrap().copyright.as_deref(), Some("Copyright Apple Inc., 2017"));
+        assert!(tiff.gps.unwrap().latitude > 52.0);
+
+        let heic = extract_metadata(include_bytes!("../../tests/fixtures/images/photo.heic")).unwrap();
+        assert!(heic.warnings.is_empty(), "{:?}", heic.warnings);
+        assert_eq!(heic.format, ImageFormat::Heif);
+        assert_eq!(heic.exif.unwrap().date_time_original.as_deref(), Some("2024-04-30T18:42:07+02:00"));
+        assert!(heic.gps.is_some() && heic.xmp.is_some() && heic.icc.is_some());
+    }
+
+    #[test]
+    fn test_xmp_gps_fallback() {
+        assert_near(xmp_coordinate("52,22.39N").unwrap(), 52.0 + 22.39 / 60.0);
+        assert_near(xmp_coordinate("33,51,24.48S").unwrap(), -(33.0 + 51.0 / 60.0 + 24.48 / 3600.0));
+        assert_eq!(xmp_coordinate("52.1"), None);
+
+        let properties = BTreeMap::from([
+            ("exif:GPSLatitude".to_string(), "40,42.768N".to_string()),
+            ("exif:GPSLongitude".to_string(), "74,0.36W".to_string()),
+        ]);
+        let position = xmp_position(&properties).unwrap();
+        assert!(position.longitude < 0.0);
+        assert_eq!(position.source, BlockKind::Xmp);
+    }
+}
@@ -0,0 +1,177 @@
+//! Sanitized copies of images for sharing.
+//!
+//! Metadata blocks are cut out of the file as they are, so the pixel data
+//! is never decoded or re-encoded. The copy is read back afterwards and
+//! refused if any metadata block is still found in it.
+
+use super::container::{self, BlockKind, ImageFormat};
+use super::metadata::{extract_metadata, BlockInfo, ImageMetadata};
+use anyhow::{bail, ensure, Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::ops::Range;
+
+/// VP8X feature flags announcing ICC, EXIF and XMP chunks
+const WEBP_FLAG_ICC: u8 = 0x20;
+const WEBP_FLAG_EXIF: u8 = 0x08;
+const WEBP_FLAG_XMP: u8 = 0x04;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScrubOptions {
+    /// Keep the ICC profile so colors render as before. It describes the
+    /// color space, but may name the device or software that made it.
+    pub keep_color_profile: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScrubReport {
+    pub format: ImageFormat,
+    pub original_size: usize,
+    pub sanitized_size: usize,
+    pub original_sha256: String,
+    pub sanitized_sha256: String,
+    /// Blocks cut from the original, at their offsets there
+    pub removed: Vec<BlockInfo>,
+    /// Blocks left in on request, at their offsets in the copy
+    pub kept: Vec<BlockInfo>,
+    pub removed_gps: bool,
+    pub removed_thumbnails: usize,
+}
+
+/// Copy of `data` without EXIF, GPS, XMP, IPTC, thumbnails, comments, text
+/// chunks, unknown segments or trailing data
+pub fn scrub_metadata(data: &[u8], options: &ScrubOptions) -> Result<(Vec<u8>, ScrubReport)> {
+    let original = extract_metadata(data)?;
+    if matches!(original.format, ImageFormat::Tiff | ImageFormat::Heif) {
+        bail!("TIFF and HEIF images keep metadata inside the image structure; convert to JPEG or PNG first");
+    }
+    if !original.warnings.is_empty() {
+        bail!("Image is damaged, so not all metadata can be found: {}", original.warnings.join("; "));
+    }
+
+    let keep = |block: &BlockInfo| options.keep_color_profile && block.kind == BlockKind::Icc;
+    let removed: Vec<BlockInfo> = original.blocks.iter().filter(|b| !keep(b)).cloned().collect();
+    let mut spans: Vec<Range<usize>> = removed.iter().map(|b| b.offset..b.offset + b.length).collect();
+    spans.sort_by_key(|span| span.start);
+
+    let mut sanitized = Vec::with_capacity(data.len());
+    let mut pos = 0;
+    for span in spans {
+        sanitized.extend_from_slice(&data[pos..span.start]);
+        pos = span.end;
+    }
+    sanitized.extend_from_slice(&data[pos..]);
+    if original.format == ImageFormat::Webp {
+        fix_webp_header(&mut sanitized, options.keep_color_profile)?;
+    }
+
+    let check = verify(&original, &sanitized, options)?;
+    let report = ScrubReport {
+        format: original.format,
+        original_size: data.len(),
+        sanitized_size: sanitized.len(),
+        original_sha256: original.sha256,
+        sanitized_sha256: hex::encode(Sha256::digest(&sanitized)),
+        removed,
+        kept: check.blocks,
+        removed_gps: original.gps.is_some(),
+        removed_thumbnails: original.thumbnails.len(),
+    };
+    Ok((sanitized, report))
+}
+
+/// Corrects the RIFF size and clears the VP8X flags of removed chunks
+fn fix_webp_header(data: &mut [u8], kept_icc: bool) -> Result<()> {
+    let riff_size = u32::try_from(data.len() - 8).context("WebP file is too large")?;
+    data[4..8].copy_from_slice(&riff_size.to_le_bytes());
+    if data.get(12..16) == Some(b"VP8X") {
+        let mut cleared = WEBP_FLAG_EXIF | WEBP_FLAG_XMP;
+        if !kept_icc {
+            cleared |= WEBP_FLAG_ICC;
+        }
+        data[20] &= !cleared;
+    }
+    Ok(())
+}
+
+/// Reads the copy back: it must parse cleanly, keep the original's
+/// dimensions and hold no metadata block other than those kept on request
+fn verify(original: &ImageMetadata, sanitized: &[u8], options: &ScrubOptions) -> Result<ImageMetadata> {
+    let check = extract_metadata(sanitized).context("Sanitized image could not be read back")?;
+    ensure!(check.warnings.is_empty(), "Sanitized image is malformed: {}", check.warnings.join("; "));
+    ensure!(
+        (check.width, check.height) == (original.width, original.height),
+        "Sanitized image dimensions differ from the original"
+    );
+
+    let residual: Vec<String> = check
+        .blocks
+        .iter()
+        .filter(|b| !(options.keep_color_profile && b.kind == BlockKind::Icc))
+        .map(|b| format!("{:?} at offset {}", b.kind, b.offset))
+        .collect();
+    ensure!(residual.is_empty(), "Metadata remains in the sanitized image: {}", residual.join(", "));
+
+    // The removed spans must not have taken image segments with them
+    if check.format == ImageFormat::Jpeg {
+        let (segments, end) = container::jpeg_segments(sanitized);
+        ensure!(end == Some(sanitized.len()), "Sanitized JPEG does not end where its image data ends");
+        ensure!(segments.iter().any(|s| s.marker == 0xDA), "Sanitized JPEG has no image data");
+    }
+    Ok(check)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JPEG: &[u8] = include_bytes!("../../tests/fixtures/images/photo.jpg");
+    const PNG: &[u8] = include_bytes!("../../tests/fixtures/images/photo.png");
+    const WEBP: &[u8] = include_bytes!("../../tests/fixtures/images/photo.webp");
+
+    #[test]
+    fn test_scrubbed_copies_hold_no_metadata() {
+        for (data, removed) in [(JPEG, 5), (PNG, 4), (WEBP, 3)] {
+            let (sanitized, report) = scrub_metadata(data, &ScrubOptions::default()).unwrap();
+            assert_eq!(report.removed.len(), removed, "{:?}", report.format);
+            assert!(report.kept.is_empty());
+            assert!(report.removed_gps);
+            assert_eq!(report.sanitized_size, sanitized.len());
+            assert_ne!(report.original_sha256, report.sanitized_sha256);
+
+            let check = extract_metadata(&sanitized).unwrap();
+            assert!(check.exif.is_none() && check.gps.is_none() && check.xmp.is_none() && check.icc.is_none());
+            assert!(check.iptc.is_empty() && check.thumbnails.is_empty() && check.text.is_empty());
+            assert!(!sanitized.windows(5).any(|w| w == b"Canon"), "{:?}", report.format);
+        }
+
+        let (sanitized, _) = scrub_metadata(WEBP, &ScrubOptions::default()).unwrap();
+        assert_eq!(sanitized[20] & (WEBP_FLAG_ICC | WEBP_FLAG_EXIF | WEBP_FLAG_XMP), 0);
+        assert_eq!(u32::from_le_bytes(sanitized[4..8].try_into().unwrap()) as usize, sanitized.len() - 8);
+    }
+
+    #[test]
+    fn test_color_profile_kept_on_request() {
+        let options = ScrubOptions {
+            keep_color_profile: true,
+        };
+        let (sanitized, report) = scrub_metadata(JPEG, &options).unwrap();
+        assert_eq!(report.kept.len(), 1);
+        assert_eq!(report.kept[0].kind, BlockKind::Icc);
+        assert_eq!(extract_metadata(&sanitized).unwrap().icc.unwrap().description.as_deref(), Some("Display P3"));
+        assert_eq!(report.removed_thumbnails, 1);
+    }
+
+    #[test]
+    fn test_trailers_removed_and_unsupported_formats_refused() {
+        let mut appended = JPEG.to_vec();
+        appended.extend_from_slice(b"PK\x03\x04hidden archive");
+        let (sanitized, report) = scrub_metadata(&appended, &ScrubOptions::default()).unwrap();
+        assert_eq!(report.removed.last().unwrap().kind, BlockKind::Trailer);
+        assert!(sanitized.ends_with(b"\xFF\xD9"));
+
+        let tiff = include_bytes!("../../tests/fixtures/images/photo.tiff");
+        assert!(scrub_metadata(tiff, &ScrubOptions::default()).is_err());
+    }
+}
@@ -0,0 +1,329 @@
+//! Indicator extraction and normalization.
+//!
+//! Free text (AI replies, pasted reports) is refanged, split into tokens and
+//! each token classified as a URL, email, CIDR, IP, hash or domain. Values
+//! come out in the same normalized form the entity graph stores, so
+//! extracted indicators merge with ones recorded by tools and imports.
+
+mod public_suffix;
+
+pub use public_suffix::registrable_domain;
+
+use crate::vault::{EntityInput, EntityType, GraphBatch, GraphSource, RelationType, RelationshipInput};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::net::IpAddr;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Indicator {
+    #[serde(rename = "type")]
+    pub entity_type: EntityType,
+    pub value: String,
+    /// Registrable domain of a domain, URL host or email address
+    pub registrable_domain: Option<String>,
+    /// Unicode form of an internationalized domain
+    pub unicode: Option<String>,
+}
+
+/// Defanged spellings and what they stand for, matched case-insensitively
+const DEFANGED: &[(&str, &str)] = &[
+    ("hxxps://", "https://"),
+    ("hxxp://", "http://"),
+    ("fxp://", "ftp://"),
+    ("[://]", "://"),
+    ("[:]", ":"),
+    ("[.]", "."),
+    ("(.)", "."),
+    ("{.}", "."),
+    ("[dot]", "."),
+    ("(dot)", "."),
+    ("{dot}", "."),
+    ("[@]", "@"),
+    ("[at]", "@"),
+    ("(at)", "@"),
+    ("{at}", "@"),
+];
+
+/// Characters that never appear inside an indicator
+const DELIMITERS: &[char] = &['"', '\'', '`', '<', '>', '(', ')', '[', ']', '{', '}', '|', ',', ';', '\\'];
+
+/// Extensions that are also top-level domains but, after a single label,
+/// far more often name a file (`setup.py`, `README.md`)
+const FILE_EXTENSIONS: &[&str] = &["md", "py", "rs", "sh", "zip", "mov", "pl", "ps"];
+
+/// Undo common defanging: `hxxp://evil[.]com` becomes `http://evil.com`
+pub fn refang(text: &str) -> String {
+    let mut refanged = String::with_capacity(text.len());
+    let mut rest = text;
+    'outer: while let Some(c) = rest.chars().next() {
+        for (defanged, fanged) in DEFANGED {
+            let matches = rest
+                .get(..defanged.len())
+                .is_some_and(|head| head.eq_ignore_ascii_case(defanged));
+            if matches {
+                refanged.push_str(fanged);
+                rest = &rest[defanged.len()..];
+                continue 'outer;
+            }
+        }
+        refanged.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    refanged
+}
+
+/// Defang a value so it can be shared without becoming a live link
+pub fn defang(value: &str) -> String {
+    let value = match value.get(..4) {
+        Some(scheme) if scheme.eq_ignore_ascii_case("http") => format!("hxxp{}", &value[4..]),
+        _ => value.to_string(),
+    };
+    value.replace('.', "[.]").replace('@', "[@]")
+}
+
+/// Normalize a domain to lower-case ASCII, converting internationalized
+/// names to punycode. Returns `None` for anything that isn't a valid
+/// hostname under a known top-level domain.
+pub fn normalize_domain(domain: &str) -> Option<String> {
+    let domain = domain.trim().trim_end_matches('.');
+    let ascii = idna::domain_to_ascii(domain).ok()?;
+    if ascii.len() > 253 || !ascii.contains('.') {
+        return None;
+    }
+
+    let valid_labels = ascii.split('.').all(|label| {
+        !label.is_empty()
+            && label.len() <= 63
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+    });
+    let tld = ascii.rsplit('.').next()?;
+    if !valid_labels || !public_suffix::is_known_tld(tld) {
+        return None;
+    }
+    Some(ascii)
+}
+
+/// A domain or subdomain indicator for a host name
+pub fn domain_indicator(domain: &str) -> Option<Indicator> {
+    let value = normalize_domain(domain)?;
+    let registrable = registrable_domain(&value)?.to_string();
+    let unicode = match idna::domain_to_unicode(&value) {
+        (unicode, Ok(())) if unicode != value => Some(unicode),
+        _ => None,
+    };
+    Some(Indicator {
+        entity_type: if registrable == value { EntityType::Domain } else { EntityType::Subdomain },
+        value,
+        registrable_domain: Some(registrable),
+        unicode,
+    })
+}
+
+fn plain(entity_type: EntityType, value: String) -> Indicator {
+    Indicator {
+        entity_type,
+        value,
+        registrable_domain: None,
+        unicode: None,
+    }
+}
+
+fn url_indicator(token: &str) -> Option<Indicator> {
+    let url = Url::parse(token).ok()?;
+    let registrable_domain = match url.host_str()? {
+        host if host.starts_with('[') || host.parse::<IpAddr>().is_ok() => None,
+        host => Some(domain_indicator(host)?.registrable_domain?),
+    };
+    Some(Indicator {
+        entity_type: EntityType::Url,
+        value: url.to_string(),
+        registrable_domain,
+        unicode: None,
+    })
+}
+
+fn email_indicator(token: &str) -> Option<Indicator> {
+    let token = token.strip_prefix("mailto:").unwrap_or(token);
+    let (local, domain) = token.rsplit_once('@')?;
+    let valid_local = !local.is_empty()
+        && local.len() <= 64
+        && local.chars().all(|c| c.is_alphanumeric() || "._%+-".contains(c));
+    if !valid_local {
+        return None;
+    }
+    let domain = domain_indicator(domain)?;
+    Some(Indicator {
+        entity_type: EntityType::Email,
+        value: format!("{}@{}", local.to_lowercase(), domain.value),
+        registrable_domain: domain.registrable_domain,
+        unicode: None,
+    })
+}
+
+fn cidr_indicator(token: &str) -> Option<Indicator> {
+    let (ip, prefix) = token.split_once('/')?;
+    let ip: IpAddr = ip.parse().ok()?;
+    let prefix: u8 = prefix.parse().ok()?;
+    let max = if ip.is_ipv4() { 32 } else { 128 };
+    (prefix <= max).then(|| plain(EntityType::Cidr, format!("{}/{}", ip, prefix)))
+}
+
+fn hash_indicator(token: &str) -> Option<Indicator> {
+    let is_hash = matches!(token.len(), 32 | 40 | 64 | 128) && token.bytes().all(|b| b.is_ascii_hexdigit());
+    is_hash.then(|| plain(EntityType::Hash, token.to_lowercase()))
+}
+
+/// Classify one token, or `None` if it isn't an indicator
+fn classify(token: &str) -> Option<Indicator> {
+    if let Ok(ip) = token.parse::<IpAddr>() {
+        return Some(plain(EntityType::Ip, ip.to_string()));
+    }
+    if token.contains("://") {
+        return url_indicator(token);
+    }
+    if token.contains('@') {
+        return email_indicator(token);
+    }
+    if let Some(cidr) = cidr_indicator(token) {
+        return Some(cidr);
+    }
+    if let Some(hash) = hash_indicator(token) {
+        return Some(hash);
+    }
+
+    // Search operators (`site:example.com`), ports and paths
+    let host = token.rsplit_once(':').map_or(token, |(head, tail)| {
+        if tail.bytes().all(|b| b.is_ascii_digit()) {
+            head
+        } else {
+            tail
+        }
+    });
+    let host = host.split('/').next().unwrap_or(host);
+    if let Ok(ip) = host.parse::<IpAddr>() {
+        return Some(plain(EntityType::Ip, ip.to_string()));
+    }
+    let indicator = domain_indicator(host)?;
+    let file_like = indicator.entity_type == EntityType::Domain
+        && !token.contains('/')
+        && FILE_EXTENSIONS.iter().any(|ext| indicator.value.ends_with(&format!(".{}", ext)));
+    (!file_like).then_some(indicator)
+}
+
+/// Extract the indicators in `text`, deduplicated in order of appearance
+pub fn extract_indicators(text: &str) -> Vec<Indicator> {
+    let text = refang(text);
+    let mut seen = HashSet::new();
+    text.split(|c: char| c.is_whitespace() || DELIMITERS.contains(&c))
+        .map(|token| token.trim_matches(|c: char| ".,:;!?*".contains(c)))
+        .filter(|token| token.len() > 2)
+        .filter_map(classify)
+        .filter(|indicator| seen.insert((indicator.entity_type, indicator.value.clone())))
+        .collect()
+}
+
+/// A graph batch recording `indicators` with their registrable domains and
+/// the relationships between them
+pub fn indicator_batch(indicators: &[Indicator], source: GraphSource) -> GraphBatch {
+    let mut batch = GraphBatch {
+        source,
+        observed_at: None,
+        entities: Vec::new(),
+        relationships: Vec::new(),
+    };
+    let key = |entity_type, value: &str| crate::vault::EntityKey::new(entity_type, value);
+
+    for indicator in indicators {
+        let mut properties = serde_json::Map::new();
+        if let Some(unicode) = &indicator.unicode {
+            properties.insert("unicode".to_string(), unicode.clone().into());
+        }
+        batch.entities.push(EntityInput {
+            entity_type: indicator.entity_type,
+            value: indicator.value.clone(),
+            properties,
+            seen: None,
+        });
+
+        let Some(registrable) = indicator.registrable_domain.as_deref() else {
+            continue;
+        };
+        let relation_type = match indicator.entity_type {
+            EntityType::Subdomain => RelationType::SubdomainOf,
+            EntityType::Url | EntityType::Email => RelationType::RelatedTo,
+            _ => continue,
+        };
+        batch.relationships.push(RelationshipInput {
+            source: key(indicator.entity_type, &indicator.value),
+            target: key(EntityType::Domain, registrable),
+            relation_type,
+            properties: serde_json::Map::new(),
+            seen: None,
+        });
+    }
+    batch
+}
+
+#[cfg(test)]
+mod tests {
+    use super::public_suffix::public_suffix;
+    use super::*;
+
+    #[test]
+    fn test_public_suffix_rules() {
+        assert_eq!(registrable_domain("www.example.co.uk"), Some("example.co.uk"));
+        assert_eq!(registrable_domain("co.uk"), None);
+        assert_eq!(registrable_domain("a.b.example.com"), Some("example.com"));
+        // `*.ck` with the `!www.ck` exception
+        assert_eq!(public_suffix("foo.bar.ck"), "bar.ck");
+        assert_eq!(registrable_domain("www.ck"), Some("www.ck"));
+        // Unlisted top-level labels fall back to the default rule
+        assert_eq!(registrable_domain("host.example.internal"), Some("example.internal"));
+    }
+
+    #[test]
+    fn test_refang_and_normalize() {
+        assert_eq!(refang("hXXps://evil[.]example(dot)com/x"), "https://evil.example.com/x");
+        assert_eq!(refang("admin[at]example[.]org"), "admin@example.org");
+        assert_eq!(defang("https://evil.example.com"), "hxxps://evil[.]example[.]com");
+
+        assert_eq!(normalize_domain("Bücher.Example.DE."), Some("xn--bcher-kva.example.de".to_string()));
+        assert_eq!(normalize_domain("report.notatld"), None);
+        assert_eq!(normalize_domain("-bad.example.com"), None);
+    }
+
+    #[test]
+    fn test_extract_indicators() {
+        let text = "Try site:Example.com and hxxps://login.example[.]co.uk:443/Path, mail ops[@]bücher.de. \
+                    Hosts 10.0.0.1, 2001:DB8::1 and 192.168.0.0/16 serve d41d8cd98f00b204e9800998ecf8427e; \
+                    see README.md. Again: example.com";
+        let found = extract_indicators(text);
+        let found: Vec<(EntityType, &str)> = found.iter().map(|i| (i.entity_type, i.value.as_str())).collect();
+        assert_eq!(
+            found,
+            vec![
+                (EntityType::Domain, "example.com"),
+                (EntityType::Url, "https://login.example.co.uk/Path"),
+                (EntityType::Email, "ops@xn--bcher-kva.de"),
+                (EntityType::Ip, "10.0.0.1"),
+                (EntityType::Ip, "2001:db8::1"),
+                (EntityType::Cidr, "192.168.0.0/16"),
+                (EntityType::Hash, "d41d8cd98f00b204e9800998ecf8427e"),
+            ]
+        );
+
+        let indicators = extract_indicators("https://a.b.example.com/x");
+        let batch = indicator_batch(
+            &indicators,
+            GraphSource {
+                kind: crate::vault::SourceKind::Ai,
+                reference: "c1".to_string(),
+            },
+        );
+        assert_eq!(indicators[0].registrable_domain.as_deref(), Some("example.com"));
+        assert_eq!(batch.relationships[0].target.value, "example.com");
+    }
+}
@@ -0,0 +1,94 @@
+//! Public suffix lookups against the Mozilla Public Suffix List, bundled
+//! from `data/public_suffix_list.dat` (ICANN and private sections).
+//!
+//! Rules are matched as the list's own algorithm describes: the longest
+//! matching rule wins, `*.` rules match any one label, `!` exceptions
+//! override them, and a name no rule matches has its last label as suffix.
+
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+const LIST_SOURCE: &str = include_str!("../../data/public_suffix_list.dat");
+
+#[derive(Default)]
+struct Rules {
+    exact: HashSet<String>,
+    /// Parents of `*.` rules
+    wildcard: HashSet<String>,
+    exceptions: HashSet<String>,
+    /// Last labels of every rule
+    top_level: HashSet<String>,
+}
+
+fn rules() -> &'static Rules {
+    static RULES: OnceLock<Rules> = OnceLock::new();
+    RULES.get_or_init(|| parse_rules(LIST_SOURCE))
+}
+
+fn parse_rules(source: &str) -> Rules {
+    let mut rules = Rules::default();
+    for line in source.lines() {
+        let Some(rule) = line.split_whitespace().next().filter(|rule| !rule.starts_with("//")) else {
+            continue;
+        };
+        // Internationalized rules are stored in the form lookups use
+        let (prefix, name) = match rule.strip_prefix('!') {
+            Some(name) => ("!", name),
+            None => match rule.strip_prefix("*.") {
+                Some(name) => ("*.", name),
+                None => ("", rule),
+            },
+        };
+        let Ok(name) = idna::domain_to_ascii(name) else {
+            continue;
+        };
+
+        if let Some(tld) = name.rsplit('.').next() {
+            rules.top_level.insert(tld.to_string());
+        }
+        match prefix {
+            "!" => rules.exceptions.insert(name),
+            "*." => rules.wildcard.insert(name),
+            _ => rules.exact.insert(name),
+        };
+    }
+    rules
+}
+
+/// True when `label` is a top-level domain on the list
+pub fn is_known_tld(label: &str) -> bool {
+    rules().top_level.contains(label)
+}
+
+/// The public suffix of a normalized (lower-case ASCII) domain, e.g.
+/// `co.uk` for `www.example.co.uk`
+pub fn public_suffix(domain: &str) -> &str {
+    let rules = rules();
+    let mut start = 0;
+    loop {
+        let candidate = &domain[start..];
+        let parent = candidate.split_once('.').map(|(_, parent)| parent);
+
+        if rules.exceptions.contains(candidate) {
+            return parent.unwrap_or(candidate);
+        }
+        if rules.exact.contains(candidate) || parent.is_some_and(|p| rules.wildcard.contains(p)) {
+            return candidate;
+        }
+        match candidate.find('.') {
+            Some(dot) => start += dot + 1,
+            // No rule matched: the last label is the suffix
+            None => return candidate,
+        }
+    }
+}
+
+/// The registrable domain (public suffix plus one label), e.g.
+/// `example.co.uk` for `www.example.co.uk`, or `None` if `domain` is
+/// itself a public suffix
+pub fn registrable_domain(domain: &str) -> Option<&str> {
+    let suffix = public_suffix(domain);
+    let rest = domain.strip_suffix(suffix)?.strip_suffix('.')?;
+    let label_start = rest.rfind('.').map_or(0, |dot| dot + 1);
+    Some(&domain[label_start..])
+}
@@ -1,9 +1,18 @@
 // Module declarations
+mod ai;
 mod commands;
+mod dorks;
+mod enrichment;
+mod graph;
+mod imaging;
+mod indicators;
 mod security;
 mod licensing;
 mod vault;
 
+#[cfg(test)]
+mod test_support;
+
 use std::sync::Arc;
 use tauri::Manager;
 use tracing_subscriber::{fmt, prelude::*, EnvFilter};
@@ -71,16 +80,20 @@ fn init_services() -> Result<
         Arc<security::SecurityService>,
         Arc<licensing::LicenseService>,
         Arc<vault::VaultService>,
+        Arc<ai::AiService>,
+        Arc<enrichment::EnrichmentService>,
     ),
     anyhow::Error,
 > {
     let security = Arc::new(security::SecurityService::new());
     let license = Arc::new(licensing::LicenseService::new()?);
     let vault = Arc::new(vault::VaultService::new()?);
+    let ai = Arc::new(ai::AiService::new(security.clone(), vault.clone())?);
+    let enrichment = Arc::new(enrichment::EnrichmentService::new(security.clone(), vault.clone())?);
 
     tracing::info!("All services initialized successfully");
 
-    Ok((security, license, vault))
+    Ok((security, license, vault, ai, enrichment))
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -92,7 +105,7 @@ pub fn run() {
     let _sentry_guard = init_sentry();
 
     // Initialize services
-    let (security, license, vault) = match init_services() {
+    let (security, license, vault, ai, enrichment) = match init_services() {
         Ok(services) => services,
         Err(e) => {
             tracing::error!("Failed to initialize services: {}", e);
@@ -107,6 +120,8 @@ pub fn run() {
         .manage(security)
         .manage(license)
         .manage(vault)
+        .manage(ai)
+        .manage(enrichment)
         // Register plugins
         .plugin(tauri_plugin_clipboard_manager::init())
         .plugin(tauri_plugin_dialog::init())
@@ -120,7 +135,6 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             commands::get_app_config,
             commands::store_gemini_api_key,
-            commands::get_gemini_api_key,
             commands::delete_gemini_api_key,
             commands::activate_license,
             commands::deactivate_license,
@@ -128,21 +142,89 @@ pub fn run() {
             commands::save_dork,
             commands::get_all_dorks,
             commands::delete_dork,
+            commands::save_dork_from_message,
+            commands::list_dork_provenance,
+            commands::import_dorks,
+            commands::find_duplicate_dorks,
+            commands::merge_dorks,
+            commands::get_dork_merge_history,
             commands::export_data,
             commands::get_system_info,
             commands::check_for_updates,
             commands::open_external_url,
             // Usage tracking commands
             commands::get_usage_stats,
-            commands::increment_ai_usage,
             commands::can_generate_ai,
             commands::can_save_dork,
             commands::get_remaining_ai_generations,
+            commands::get_ai_quota_status,
+            commands::get_ai_quota_config,
+            commands::set_ai_quota_config,
+            commands::get_ai_cache_stats,
+            commands::get_ai_cache_config,
+            commands::set_ai_cache_config,
+            commands::clear_ai_cache,
+            commands::get_usage_report,
+            commands::get_model_costs,
+            commands::set_model_costs,
+            // AI generation commands
+            commands::generate_dork,
+            commands::generate_dork_offline,
+            commands::stream_generate_dork,
+            commands::get_conversation_context,
+            commands::set_message_pinned,
+            commands::cancel_generation,
+            commands::get_ai_provider_config,
+            commands::set_ai_provider_config,
+            commands::store_ai_provider_key,
+            commands::delete_ai_provider_key,
+            commands::list_prompt_templates,
+            commands::get_prompt_template,
+            commands::set_prompt_override,
+            commands::reset_prompt_override,
             // Conversation persistence commands
             commands::save_conversation,
             commands::get_conversation,
             commands::list_conversations,
+            commands::list_conversation_summaries,
+            commands::append_message,
             commands::delete_conversation,
+            // Entity graph commands
+            commands::upsert_graph,
+            commands::load_graph,
+            commands::get_graph_neighborhood,
+            commands::find_graph_path,
+            commands::get_graph_components,
+            commands::compute_graph_layout,
+            commands::export_graph,
+            commands::import_graph,
+            commands::get_entity_neighbors,
+            commands::find_graph_entity,
+            commands::clear_graph,
+            // Indicator extraction commands
+            commands::extract_indicators,
+            commands::record_indicators,
+            // Enrichment commands
+            commands::get_dns_config,
+            commands::set_dns_config,
+            commands::enrich_dns,
+            commands::get_ct_config,
+            commands::set_ct_config,
+            commands::enrich_certificate_transparency,
+            commands::list_enrichment_connectors,
+            commands::get_connector_settings,
+            commands::set_connector_settings,
+            commands::store_connector_key,
+            commands::delete_connector_key,
+            commands::run_enrichment_job,
+            commands::clear_enrichment_cache,
+            // Image forensics commands
+            commands::extract_image_metadata,
+            commands::scrub_image_metadata,
+            // Backup & restore commands
+            commands::create_backup,
+            commands::inspect_backup,
+            commands::restore_backup,
             // Enhanced license commands
             commands::get_license_tier,
             commands::has_feature,
@@ -34,12 +34,7 @@ impl SecurityService {
 
     // Gemini API Key Management
     pub async fn store_gemini_api_key(&self, api_key: &str) -> Result<()> {
-        if self.keyring_available {
-            self.store_in_keyring(GEMINI_KEY_NAME, api_key)?;
-        } else {
-            self.store_encrypted_fallback(GEMINI_KEY_NAME, api_key).await?;
-        }
-
+        self.store_secret(GEMINI_KEY_NAME, api_key).await?;
         tracing::info!("Gemini API key stored securely");
         Ok(())
     }
@@ -60,14 +55,98 @@ impl SecurityService {
     }
 
     pub async fn delete_gemini_api_key(&self) -> Result<()> {
+        self.delete_secret(GEMINI_KEY_NAME).await?;
+        tracing::info!("Gemini API key deleted");
+        Ok(())
+    }
+
+    // AI provider API keys, one per workspace
+    pub async fn store_ai_provider_key(&self, workspace: &str, api_key: &str) -> Result<()> {
+        self.store_secret(&Self::ai_provider_key_name(workspace)?, api_key).await?;
+        tracing::info!("AI provider API key stored for workspace {}", workspace);
+        Ok(())
+    }
+
+    /// The workspace's key, or `None` if none is stored
+    pub async fn get_ai_provider_key(&self, workspace: &str) -> Result<Option<String>> {
+        self.find_secret(&Self::ai_provider_key_name(workspace)?).await
+    }
+
+    pub async fn delete_ai_provider_key(&self, workspace: &str) -> Result<()> {
+        self.delete_secret(&Self::ai_provider_key_name(workspace)?).await?;
+        tracing::info!("AI provider API key deleted for workspace {}", workspace);
+        Ok(())
+    }
+
+    // Enrichment connector API keys, one per connector
+    pub async fn store_connector_key(&self, connector: &str, api_key: &str) -> Result<()> {
+        self.store_secret(&Self::connector_key_name(connector)?, api_key).await?;
+        tracing::info!("API key stored for connector {}", connector);
+        Ok(())
+    }
+
+    /// The connector's key, or `None` if none is stored
+    pub async fn get_connector_key(&self, connector: &str) -> Result<Option<String>> {
+        self.find_secret(&Self::connector_key_name(connector)?).await
+    }
+
+    pub async fn delete_connector_key(&self, connector: &str) -> Result<()> {
+        self.delete_secret(&Self::connector_key_name(connector)?).await?;
+        tracing::info!("API key deleted for connector {}", connector);
+        Ok(())
+    }
+
+    fn connector_key_name(connector: &str) -> Result<String> {
+        // Also used as the encrypted fallback's file name
+        let valid = connector.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'_');
+        if connector.is_empty() || !valid {
+            anyhow::bail!("Invalid connector id: {}", connector);
+        }
+        Ok(format!("connector_key_{}", connector))
+    }
+
+    fn ai_provider_key_name(workspace: &str) -> Result<String> {
+        // Also used as the encrypted fallback's file name
+        crate::ai::validate_workspace(workspace)?;
+        Ok(format!("ai_provider_key_{}", workspace))
+    }
+
+    /// Store a secret in the OS keyring, or the encrypted fallback without one
+    async fn store_secret(&self, key_name: &str, value: &str) -> Result<()> {
         if self.keyring_available {
-            self.delete_from_keyring(GEMINI_KEY_NAME)?;
+            self.store_in_keyring(key_name, value)
         } else {
-            self.delete_encrypted_fallback(GEMINI_KEY_NAME).await?;
+            self.store_encrypted_fallback(key_name, value).await
         }
+    }
 
-        tracing::info!("Gemini API key deleted");
-        Ok(())
+    async fn delete_secret(&self, key_name: &str) -> Result<()> {
+        if self.keyring_available {
+            self.delete_from_keyring(key_name)
+        } else {
+            self.delete_encrypted_fallback(key_name).await
+        }
+    }
+
+    /// A stored secret, or `None` if there is none. Keyring and decryption
+    /// failures are errors, not a missing secret.
+    async fn find_secret(&self, key_name: &str) -> Result<Option<String>> {
+        if self.keyring_available {
+            let entry = Entry::new(SERVICE_NAME, key_name)
+                .context("Failed to create keyring entry")?;
+
+            match entry.get_password() {
+                Ok(value) => Ok(Some(value)),
+                Err(keyring::Error::NoEntry) => Ok(None),
+                Err(e) => Err(e).context("Failed to retrieve from OS keyring"),
+            }
+        } else {
+            let file_path = self.get_secure_data_dir()?.join(format!("{}.enc", key_name));
+            if !file_path.exists() {
+                return Ok(None);
+            }
+            self.get_encrypted_fallback(key_name).await.map(Some)
+        }
     }
 
     // OS Keyring operations
@@ -226,4 +305,11 @@ mod tests {
 
         service.delete_gemini_api_key().await.unwrap();
     }
+
+    #[test]
+    fn test_key_names_reject_unsafe_ids() {
+        assert_eq!(SecurityService::ai_provider_key_name("team-1").unwrap(), "ai_provider_key_team-1");
+        assert!(SecurityService::ai_provider_key_name("../../keys").is_err());
+        assert!(SecurityService::connector_key_name("../shodan").is_err());
+    }
 }
@@ -0,0 +1,170 @@
+//! Local HTTP stand-in for tests that exercise real HTTP clients.
+//!
+//! [`StubServer`] listens on an ephemeral localhost port, answers each
+//! request with the canned response registered for its path and records
+//! what it received so tests can assert on headers and bodies.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+#[derive(Debug, Clone)]
+pub struct StubResponse {
+    pub status: u16,
+    pub content_type: String,
+    pub body: String,
+}
+
+impl StubResponse {
+    pub fn json(body: impl Into<String>) -> Self {
+        Self {
+            status: 200,
+            content_type: "application/json".to_string(),
+            body: body.into(),
+        }
+    }
+
+    pub fn with_status(mut self, status: u16) -> Self {
+        self.status = status;
+        self
+    }
+
+    pub fn with_content_type(mut self, content_type: &str) -> Self {
+        self.content_type = content_type.to_string();
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub path: String,
+    /// Raw query string, empty if there was none
+    pub query: String,
+    /// Header names are lower-cased
+    pub headers: HashMap<String, String>,
+    pub body: String,
+}
+
+impl RecordedRequest {
+    pub fn json(&self) -> serde_json::Value {
+        serde_json::from_str(&self.body).expect("request body is not JSON")
+    }
+}
+
+pub struct StubServer {
+    addr: SocketAddr,
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl StubServer {
+    /// Serve `routes` (path without query string → response); unknown
+    /// paths get a 404
+    pub async fn start(routes: Vec<(&str, StubResponse)>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let routes: Arc<HashMap<String, StubResponse>> = Arc::new(
+            routes.into_iter().map(|(path, response)| (path.to_string(), response)).collect(),
+        );
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let recorded = requests.clone();
+        let task = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let routes = routes.clone();
+                let recorded = recorded.clone();
+                tokio::spawn(async move {
+                    let _ = handle_connection(stream, &routes, &recorded).await;
+                });
+            }
+        });
+
+        Self { addr, requests, task }
+    }
+
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+impl Drop for StubServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn handle_connection(
+    mut stream: TcpStream,
+    routes: &HashMap<String, StubResponse>,
+    recorded: &Mutex<Vec<RecordedRequest>>,
+) -> std::io::Result<()> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 4096];
+
+    let header_end = loop {
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            return Ok(());
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+        if let Some(pos) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+    };
+
+    let head = String::from_utf8_lossy(&buffer[..header_end]).to_string();
+    let mut lines = head.lines();
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let method = request_line.next().unwrap_or_default().to_string();
+    let target = request_line.next().unwrap_or_default().to_string();
+
+    let headers: HashMap<String, String> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim().to_string()))
+        .collect();
+
+    let content_length: usize = headers
+        .get("content-length")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0);
+    while buffer.len() < header_end + content_length {
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            break;
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    }
+
+    let (path, query) = target.split_once('?').unwrap_or((&target, ""));
+    let (path, query) = (path.to_string(), query.to_string());
+    let body_end = buffer.len().min(header_end + content_length);
+    recorded.lock().unwrap().push(RecordedRequest {
+        method,
+        path: path.clone(),
+        query,
+        headers,
+        body: String::from_utf8_lossy(&buffer[header_end..body_end]).to_string(),
+    });
+
+    let response = routes
+        .get(&path)
+        .cloned()
+        .unwrap_or_else(|| StubResponse::json(r#"{"error": "not found"}"#).with_status(404));
+
+    let reply = format!(
+        "HTTP/1.1 {} STUB\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        response.content_type,
+        response.body.len(),
+        response.body
+    );
+    stream.write_all(reply.as_bytes()).await?;
+    stream.shutdown().await
+}
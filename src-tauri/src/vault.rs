@@ -1,11 +1,41 @@
 use anyhow::{Context, Result};
-use rusqlite::{Connection, params};
+use rusqlite::{Connection, OptionalExtension, params};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use chrono::Utc;
 
+mod backup;
+mod cache;
+mod dedupe;
+mod enrichment_cache;
+mod graph;
+mod import;
+mod prompts;
+mod provenance;
+mod quota;
+mod usage;
+
+pub use backup::{BackupArchive, RestoreMode, RestoreReport};
+pub use cache::{CacheConfig, CacheStats};
+pub use dedupe::DorkMergeRecord;
+pub use graph::{
+    normalize_value, Entity, EntityInput, EntityKey, EntityType, GraphBatch, GraphSource, GraphUpsertReport,
+    RelationType, Relationship, RelationshipInput, Seen, SourceKind, Subgraph,
+};
+pub use import::{ImportFormat, ImportOptions, ImportReport};
+pub use prompts::PromptOverride;
+pub use provenance::DorkProvenance;
+pub use quota::{QuotaConfig, QuotaGrant, QuotaStatus};
+pub use usage::{ModelCost, UsageOutcome, UsagePeriod, UsageRecord, UsageReport};
+
+/// Characters of the last message shown in conversation summaries
+const PREVIEW_LENGTH: i64 = 120;
+
+/// AI generations allowed per day without a paid license
+pub const FREE_TIER_DAILY_AI_LIMIT: i32 = 10;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DorkQuery {
     pub id: String,
@@ -23,6 +53,14 @@ pub struct UsageStats {
     pub last_reset_date: String,
     pub total_dorks: i32,
     pub total_conversations: i32,
+    #[serde(default)]
+    pub prompt_tokens_today: i64,
+    #[serde(default)]
+    pub completion_tokens_today: i64,
+    #[serde(default)]
+    pub total_prompt_tokens: i64,
+    #[serde(default)]
+    pub total_completion_tokens: i64,
     pub created_at: String,
     pub updated_at: String,
 }
@@ -36,6 +74,18 @@ pub struct Conversation {
     pub updated_at: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversationSummary {
+    pub id: String,
+    pub title: String,
+    pub message_count: i64,
+    pub last_message_role: Option<String>,
+    pub last_message_preview: Option<String>,
+    pub last_message_at: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Message {
     pub id: String,
@@ -43,6 +93,194 @@ pub struct Message {
     pub content: String,
     pub timestamp: String,
     pub dork: Option<String>,
+    /// Saved dork created from this message, if any
+    #[serde(default)]
+    pub dork_id: Option<String>,
+    /// Prompt template version (`<name>@<version>`) behind an AI message
+    #[serde(default)]
+    pub prompt_version: Option<String>,
+    /// Always included when the conversation is replayed to a model
+    #[serde(default)]
+    pub pinned: bool,
+    /// On a [`SUMMARY_ROLE`] message, the id of the last message it covers
+    #[serde(default)]
+    pub summarizes_through: Option<String>,
+}
+
+/// Role of messages holding a summary of earlier turns
+pub const SUMMARY_ROLE: &str = "summary";
+
+/// Map a `SELECT id, name, query, category, tags, created_at, updated_at` row
+fn dork_from_row(row: &rusqlite::Row) -> rusqlite::Result<DorkQuery> {
+    let tags_json: String = row.get(4)?;
+    let tags: Vec<String> = serde_json::from_str(&tags_json)
+        .unwrap_or_default();
+
+    Ok(DorkQuery {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        query: row.get(2)?,
+        category: row.get(3)?,
+        tags,
+        created_at: row.get(5)?,
+        updated_at: row.get(6)?,
+    })
+}
+
+/// Add a column to an existing table if an older schema lacks it
+fn ensure_column(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<()> {
+    let exists = conn
+        .prepare(&format!("SELECT 1 FROM pragma_table_info('{}') WHERE name = ?1", table))?
+        .exists([column])
+        .with_context(|| format!("Failed to inspect table {}", table))?;
+
+    if !exists {
+        conn.execute(
+            &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+            [],
+        ).with_context(|| format!("Failed to add column {}.{}", table, column))?;
+    }
+
+    Ok(())
+}
+
+fn insert_dork(conn: &Connection, dork: &DorkQuery) -> Result<()> {
+    let tags_json = serde_json::to_string(&dork.tags)
+        .context("Failed to serialize tags")?;
+
+    conn.execute(
+        "INSERT OR REPLACE INTO dorks (id, name, query, category, tags, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            dork.id,
+            dork.name,
+            dork.query,
+            dork.category,
+            tags_json,
+            dork.created_at,
+            chrono::Utc::now().to_rfc3339(),
+        ],
+    ).context("Failed to save dork")?;
+
+    Ok(())
+}
+
+fn insert_message(conn: &Connection, conversation_id: &str, position: i64, message: &Message) -> Result<()> {
+    conn.execute(
+        "INSERT INTO conversation_messages
+         (id, conversation_id, position, role, content, timestamp, dork, dork_id, prompt_version,
+          pinned, summarizes_through)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        params![
+            message.id,
+            conversation_id,
+            position,
+            message.role,
+            message.content,
+            message.timestamp,
+            message.dork,
+            message.dork_id,
+            message.prompt_version,
+            message.pinned,
+            message.summarizes_through,
+        ],
+    ).context("Failed to save message")?;
+
+    Ok(())
+}
+
+/// Part of a legacy `conversations.messages` blob that isn't a valid message
+struct UnreadableMessage {
+    /// Index in the blob, or `None` when the blob itself isn't readable
+    index: Option<usize>,
+    data: String,
+    error: String,
+}
+
+impl UnreadableMessage {
+    /// Stable `conversations_legacy` id, so migrating twice doesn't duplicate
+    fn id(&self, conversation_id: &str) -> String {
+        match self.index {
+            Some(index) => format!("{}:{}", conversation_id, index),
+            None => format!("{}:blob", conversation_id),
+        }
+    }
+}
+
+/// Split a legacy `conversations.messages` JSON blob into messages, keeping
+/// whatever can't be read for quarantine
+fn split_legacy_blob(blob: &str) -> Vec<Result<Message, UnreadableMessage>> {
+    let values: Vec<serde_json::Value> = match serde_json::from_str(blob) {
+        Ok(values) => values,
+        Err(e) => {
+            return vec![Err(UnreadableMessage {
+                index: None,
+                data: blob.to_string(),
+                error: e.to_string(),
+            })];
+        }
+    };
+
+    values
+        .into_iter()
+        .enumerate()
+        .map(|(index, value)| {
+            serde_json::from_value(value.clone()).map_err(|e| UnreadableMessage {
+                index: Some(index),
+                data: value.to_string(),
+                error: e.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Columns read by [`message_from_row`], in order
+const MESSAGE_COLUMNS: &str = "id, role, content, timestamp, dork, dork_id, prompt_version, pinned, summarizes_through";
+
+fn message_from_row(row: &rusqlite::Row) -> rusqlite::Result<Message> {
+    Ok(Message {
+        id: row.get(0)?,
+        role: row.get(1)?,
+        content: row.get(2)?,
+        timestamp: row.get(3)?,
+        dork: row.get(4)?,
+        dork_id: row.get(5)?,
+        prompt_version: row.get(6)?,
+        pinned: row.get(7)?,
+        summarizes_through: row.get(8)?,
+    })
+}
+
+fn load_messages(conn: &Connection, conversation_id: &str) -> Result<Vec<Message>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM conversation_messages WHERE conversation_id = ?1 ORDER BY position ASC",
+        MESSAGE_COLUMNS
+    )).context("Failed to prepare messages query")?;
+
+    let messages = stmt.query_map([conversation_id], message_from_row)
+    .context("Failed to query messages")?
+    .collect::<Result<Vec<_>, _>>()
+    .context("Failed to collect messages")?;
+
+    Ok(messages)
+}
+
+/// Read a JSON setting on an already locked connection, falling back to the
+/// default when it isn't set
+fn load_setting<T: serde::de::DeserializeOwned + Default>(conn: &Connection, key: &str) -> Result<T> {
+    let value: Option<String> = conn
+        .query_row("SELECT value FROM settings WHERE key = ?1", [key], |row| row.get(0))
+        .optional()
+        .with_context(|| format!("Failed to load setting {}", key))?;
+
+    match value {
+        Some(value) => serde_json::from_str(&value).with_context(|| format!("Failed to parse setting {}", key)),
+        None => Ok(T::default()),
+    }
+}
+
+fn is_unlimited_tier(license_tier: &str) -> bool {
+    matches!(license_tier, "professional" | "team" | "enterprise")
 }
 
 pub struct VaultService {
@@ -52,11 +290,40 @@ pub struct VaultService {
 impl VaultService {
     pub fn new() -> Result<Self> {
         let vault_path = Self::get_vault_path()?;
+        Self::open(&vault_path)
+    }
 
-        let conn = Connection::open(&vault_path)
+    /// Open (or create) a vault database at an explicit path
+    pub fn open(vault_path: &Path) -> Result<Self> {
+        let conn = Connection::open(vault_path)
             .context("Failed to open vault database")?;
+        // Another connection may hold the write lock briefly
+        conn.busy_timeout(std::time::Duration::from_secs(5))
+            .context("Failed to set vault busy timeout")?;
 
-        // Initialize schema
+        Self::init_schema(&conn)?;
+
+        tracing::info!("Vault database initialized at {:?}", vault_path);
+
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    /// Open a throwaway in-memory vault (used by tests)
+    #[cfg(test)]
+    pub fn open_in_memory() -> Result<Self> {
+        let conn = Connection::open_in_memory()
+            .context("Failed to open in-memory vault")?;
+
+        Self::init_schema(&conn)?;
+
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    fn init_schema(conn: &Connection) -> Result<()> {
         conn.execute(
             "CREATE TABLE IF NOT EXISTS dorks (
                 id TEXT PRIMARY KEY,
@@ -88,12 +355,27 @@ impl VaultService {
                 last_reset_date TEXT NOT NULL,
                 total_dorks INTEGER DEFAULT 0,
                 total_conversations INTEGER DEFAULT 0,
+                prompt_tokens_today INTEGER DEFAULT 0,
+                completion_tokens_today INTEGER DEFAULT 0,
+                total_prompt_tokens INTEGER DEFAULT 0,
+                total_completion_tokens INTEGER DEFAULT 0,
                 created_at TEXT NOT NULL,
                 updated_at TEXT NOT NULL
             )",
             [],
         ).context("Failed to create usage_stats table")?;
 
+        for column in [
+            "prompt_tokens_today",
+            "completion_tokens_today",
+            "total_prompt_tokens",
+            "total_completion_tokens",
+            "ai_cache_hits",
+            "ai_cache_misses",
+        ] {
+            ensure_column(conn, "usage_stats", column, "INTEGER DEFAULT 0")?;
+        }
+
         // Insert default row if doesn't exist
         conn.execute(
             "INSERT OR IGNORE INTO usage_stats (id, last_reset_date, created_at, updated_at)
@@ -106,7 +388,6 @@ impl VaultService {
             "CREATE TABLE IF NOT EXISTS conversations (
                 id TEXT PRIMARY KEY,
                 title TEXT NOT NULL,
-                messages TEXT NOT NULL,
                 created_at TEXT NOT NULL,
                 updated_at TEXT NOT NULL
             )",
@@ -118,11 +399,332 @@ impl VaultService {
             [],
         ).context("Failed to create conversations index")?;
 
-        tracing::info!("Vault database initialized at {:?}", vault_path);
+        // Messages are stored one row each, ordered by position
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS conversation_messages (
+                id TEXT PRIMARY KEY,
+                conversation_id TEXT NOT NULL,
+                position INTEGER NOT NULL,
+                role TEXT NOT NULL,
+                content TEXT NOT NULL,
+                timestamp TEXT NOT NULL,
+                dork TEXT,
+                dork_id TEXT,
+                prompt_version TEXT,
+                UNIQUE (conversation_id, position)
+            )",
+            [],
+        ).context("Failed to create conversation_messages table")?;
 
-        Ok(Self {
-            conn: Arc::new(Mutex::new(conn)),
-        })
+        ensure_column(conn, "conversation_messages", "dork_id", "TEXT")?;
+        ensure_column(conn, "conversation_messages", "prompt_version", "TEXT")?;
+        ensure_column(conn, "conversation_messages", "pinned", "INTEGER NOT NULL DEFAULT 0")?;
+        ensure_column(conn, "conversation_messages", "summarizes_through", "TEXT")?;
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_messages_dork ON conversation_messages(dork_id)",
+            [],
+        ).context("Failed to create message dork index")?;
+
+        // Where a saved dork came from. Conversation and message ids are
+        // cleared when the conversation is deleted; the title snapshot stays.
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS dork_provenance (
+                dork_id TEXT PRIMARY KEY,
+                conversation_id TEXT,
+                message_id TEXT,
+                conversation_title TEXT NOT NULL,
+                message_excerpt TEXT NOT NULL,
+                recorded_at TEXT NOT NULL
+            )",
+            [],
+        ).context("Failed to create dork_provenance table")?;
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_provenance_conversation ON dork_provenance(conversation_id)",
+            [],
+        ).context("Failed to create provenance index")?;
+
+        // Legacy messages that couldn't be read, kept as they were
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS conversations_legacy (
+                id TEXT PRIMARY KEY,
+                conversation_id TEXT NOT NULL,
+                data TEXT NOT NULL,
+                error TEXT NOT NULL,
+                quarantined_at TEXT NOT NULL
+            )",
+            [],
+        ).context("Failed to create conversations_legacy table")?;
+
+        Self::migrate_conversation_blobs(conn)?;
+
+        // Dorks folded into another by a duplicate merge
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS dork_merges (
+                merged_id TEXT PRIMARY KEY,
+                into_id TEXT NOT NULL,
+                name TEXT NOT NULL,
+                query TEXT NOT NULL,
+                category TEXT NOT NULL,
+                tags TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                merged_at TEXT NOT NULL
+            )",
+            [],
+        ).context("Failed to create dork_merges table")?;
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_dork_merges_into ON dork_merges(into_id)",
+            [],
+        ).context("Failed to create dork_merges index")?;
+
+        // Create settings table (key/value, values stored as JSON)
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS settings (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            )",
+            [],
+        ).context("Failed to create settings table")?;
+
+        // Revisions of user-edited prompt templates
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS prompt_overrides (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                revision INTEGER NOT NULL,
+                template TEXT NOT NULL,
+                temperature REAL,
+                max_tokens INTEGER,
+                active INTEGER NOT NULL DEFAULT 1,
+                created_at TEXT NOT NULL,
+                UNIQUE (name, revision)
+            )",
+            [],
+        ).context("Failed to create prompt_overrides table")?;
+
+        // One row per AI provider request
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS ai_usage_ledger (
+                id TEXT PRIMARY KEY,
+                timestamp TEXT NOT NULL,
+                provider TEXT NOT NULL,
+                model TEXT NOT NULL,
+                feature TEXT NOT NULL,
+                prompt_tokens INTEGER NOT NULL,
+                completion_tokens INTEGER NOT NULL,
+                estimated INTEGER NOT NULL,
+                latency_ms INTEGER NOT NULL,
+                outcome TEXT NOT NULL
+            )",
+            [],
+        ).context("Failed to create ai_usage_ledger table")?;
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_usage_ledger_timestamp ON ai_usage_ledger(timestamp)",
+            [],
+        ).context("Failed to create usage ledger index")?;
+
+        // Generations counted against the AI quota window
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS ai_quota_events (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                consumed_at TEXT NOT NULL
+            )",
+            [],
+        ).context("Failed to create ai_quota_events table")?;
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_quota_events_consumed ON ai_quota_events(consumed_at)",
+            [],
+        ).context("Failed to create quota events index")?;
+
+        // Validated AI results by content hash of what produced them
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS ai_response_cache (
+                key TEXT PRIMARY KEY,
+                provider TEXT NOT NULL,
+                model TEXT NOT NULL,
+                prompt_version TEXT NOT NULL,
+                value TEXT NOT NULL,
+                size_bytes INTEGER NOT NULL,
+                hits INTEGER NOT NULL DEFAULT 0,
+                created_at TEXT NOT NULL,
+                last_used_at TEXT NOT NULL,
+                expires_at TEXT NOT NULL
+            )",
+            [],
+        ).context("Failed to create ai_response_cache table")?;
+
+        // Entity graph, unique per workspace by type and normalized value
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS graph_entities (
+                id TEXT PRIMARY KEY,
+                workspace TEXT NOT NULL,
+                entity_type TEXT NOT NULL,
+                value TEXT NOT NULL,
+                properties TEXT NOT NULL DEFAULT '{}',
+                first_seen TEXT NOT NULL,
+                last_seen TEXT NOT NULL,
+                UNIQUE(workspace, entity_type, value)
+            )",
+            [],
+        ).context("Failed to create graph_entities table")?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS graph_relationships (
+                id TEXT PRIMARY KEY,
+                workspace TEXT NOT NULL,
+                source_id TEXT NOT NULL,
+                target_id TEXT NOT NULL,
+                relation_type TEXT NOT NULL,
+                properties TEXT NOT NULL DEFAULT '{}',
+                first_seen TEXT NOT NULL,
+                last_seen TEXT NOT NULL,
+                UNIQUE(workspace, source_id, target_id, relation_type)
+            )",
+            [],
+        ).context("Failed to create graph_relationships table")?;
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_graph_relationships_target ON graph_relationships(target_id)",
+            [],
+        ).context("Failed to create graph relationships index")?;
+
+        // Which dork, tool or import observed an entity or relationship
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS graph_provenance (
+                id TEXT PRIMARY KEY,
+                workspace TEXT NOT NULL,
+                subject_id TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                reference TEXT NOT NULL,
+                first_seen TEXT NOT NULL,
+                last_seen TEXT NOT NULL,
+                UNIQUE(subject_id, kind, reference)
+            )",
+            [],
+        ).context("Failed to create graph_provenance table")?;
+
+        // Enrichment connector responses by connector and request
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS enrichment_cache (
+                key TEXT PRIMARY KEY,
+                connector TEXT NOT NULL,
+                body TEXT NOT NULL,
+                fetched_at TEXT NOT NULL,
+                expires_at TEXT NOT NULL
+            )",
+            [],
+        ).context("Failed to create enrichment_cache table")?;
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_enrichment_cache_connector ON enrichment_cache(connector)",
+            [],
+        ).context("Failed to create enrichment cache index")?;
+
+        Ok(())
+    }
+
+    /// Move messages out of the legacy `conversations.messages` JSON column
+    /// into `conversation_messages`, then drop the column. Messages that fail
+    /// to parse go to `conversations_legacy` rather than failing the whole
+    /// conversation.
+    fn migrate_conversation_blobs(conn: &Connection) -> Result<()> {
+        let has_blob_column = conn
+            .prepare("SELECT 1 FROM pragma_table_info('conversations') WHERE name = 'messages'")?
+            .exists([])
+            .context("Failed to inspect conversations table")?;
+
+        if !has_blob_column {
+            return Ok(());
+        }
+
+        let blobs = conn
+            .prepare("SELECT id, messages FROM conversations")?
+            .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+            .collect::<Result<Vec<_>, _>>()
+            .context("Failed to read legacy conversation messages")?;
+
+        let tx = conn.unchecked_transaction()
+            .context("Failed to start conversation migration")?;
+
+        let mut migrated = 0;
+        let mut quarantined = 0;
+        let mut remapped = 0;
+        for (conversation_id, blob) in blobs {
+            let mut position = 0;
+            for message in split_legacy_blob(&blob) {
+                let message = match message {
+                    Ok(message) => message,
+                    Err(unreadable) => {
+                        tracing::warn!(
+                            "Quarantining unreadable message in conversation {}: {}",
+                            conversation_id,
+                            unreadable.error
+                        );
+                        tx.execute(
+                            "INSERT OR IGNORE INTO conversations_legacy
+                             (id, conversation_id, data, error, quarantined_at)
+                             VALUES (?1, ?2, ?3, ?4, ?5)",
+                            params![
+                                unreadable.id(&conversation_id),
+                                conversation_id,
+                                unreadable.data,
+                                unreadable.error,
+                                Utc::now().to_rfc3339(),
+                            ],
+                        ).context("Failed to quarantine legacy message")?;
+                        quarantined += 1;
+                        continue;
+                    }
+                };
+
+                // Blob ids were only unique within their conversation, if at all
+                let taken = tx
+                    .prepare_cached("SELECT 1 FROM conversation_messages WHERE id = ?1")?
+                    .exists([&message.id])
+                    .context("Failed to check legacy message id")?;
+                let id = if taken {
+                    remapped += 1;
+                    uuid::Uuid::new_v4().to_string()
+                } else {
+                    message.id
+                };
+
+                tx.execute(
+                    "INSERT INTO conversation_messages
+                     (id, conversation_id, position, role, content, timestamp, dork)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                    params![
+                        id,
+                        conversation_id,
+                        position,
+                        message.role,
+                        message.content,
+                        message.timestamp,
+                        message.dork,
+                    ],
+                ).context("Failed to migrate conversation message")?;
+
+                position += 1;
+                migrated += 1;
+            }
+        }
+
+        tx.execute("ALTER TABLE conversations DROP COLUMN messages", [])
+            .context("Failed to drop legacy messages column")?;
+        tx.commit().context("Failed to commit conversation migration")?;
+
+        tracing::info!(
+            "Migrated {} conversation messages to row storage ({} quarantined, {} given new ids)",
+            migrated,
+            quarantined,
+            remapped
+        );
+        Ok(())
     }
 
     fn get_vault_path() -> Result<PathBuf> {
@@ -139,23 +741,7 @@ impl VaultService {
 
     pub async fn save_dork(&self, dork: DorkQuery) -> Result<()> {
         let conn = self.conn.lock().await;
-
-        let tags_json = serde_json::to_string(&dork.tags)
-            .context("Failed to serialize tags")?;
-
-        conn.execute(
-            "INSERT OR REPLACE INTO dorks (id, name, query, category, tags, created_at, updated_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-            params![
-                dork.id,
-                dork.name,
-                dork.query,
-                dork.category,
-                tags_json,
-                dork.created_at,
-                chrono::Utc::now().to_rfc3339(),
-            ],
-        ).context("Failed to save dork")?;
+        insert_dork(&conn, &dork)?;
 
         tracing::debug!("Dork saved: {} ({})", dork.name, dork.id);
         Ok(())
@@ -170,21 +756,7 @@ impl VaultService {
              ORDER BY created_at DESC"
         ).context("Failed to prepare query")?;
 
-        let dorks = stmt.query_map([], |row| {
-            let tags_json: String = row.get(4)?;
-            let tags: Vec<String> = serde_json::from_str(&tags_json)
-                .unwrap_or_default();
-
-            Ok(DorkQuery {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                query: row.get(2)?,
-                category: row.get(3)?,
-                tags,
-                created_at: row.get(5)?,
-                updated_at: row.get(6)?,
-            })
-        })
+        let dorks = stmt.query_map([], dork_from_row)
         .context("Failed to query dorks")?
         .collect::<Result<Vec<_>, _>>()
         .context("Failed to collect dorks")?;
@@ -201,21 +773,7 @@ impl VaultService {
              WHERE id = ?1"
         ).context("Failed to prepare query")?;
 
-        let result = stmt.query_row(params![id], |row| {
-            let tags_json: String = row.get(4)?;
-            let tags: Vec<String> = serde_json::from_str(&tags_json)
-                .unwrap_or_default();
-
-            Ok(DorkQuery {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                query: row.get(2)?,
-                category: row.get(3)?,
-                tags,
-                created_at: row.get(5)?,
-                updated_at: row.get(6)?,
-            })
-        });
+        let result = stmt.query_row(params![id], dork_from_row);
 
         match result {
             Ok(dork) => Ok(Some(dork)),
@@ -236,6 +794,21 @@ impl VaultService {
             anyhow::bail!("Dork not found: {}", id);
         }
 
+        conn.execute(
+            "DELETE FROM dork_merges WHERE into_id = ?1",
+            params![id],
+        ).context("Failed to delete dork merge history")?;
+
+        conn.execute(
+            "DELETE FROM dork_provenance WHERE dork_id = ?1",
+            params![id],
+        ).context("Failed to delete dork provenance")?;
+
+        conn.execute(
+            "UPDATE conversation_messages SET dork_id = NULL WHERE dork_id = ?1",
+            params![id],
+        ).context("Failed to unlink dork from messages")?;
+
         tracing::debug!("Dork deleted: {}", id);
         Ok(())
     }
@@ -252,21 +825,7 @@ impl VaultService {
              ORDER BY created_at DESC"
         ).context("Failed to prepare search query")?;
 
-        let dorks = stmt.query_map(params![search_pattern], |row| {
-            let tags_json: String = row.get(4)?;
-            let tags: Vec<String> = serde_json::from_str(&tags_json)
-                .unwrap_or_default();
-
-            Ok(DorkQuery {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                query: row.get(2)?,
-                category: row.get(3)?,
-                tags,
-                created_at: row.get(5)?,
-                updated_at: row.get(6)?,
-            })
-        })
+        let dorks = stmt.query_map(params![search_pattern], dork_from_row)
         .context("Failed to search dorks")?
         .collect::<Result<Vec<_>, _>>()
         .context("Failed to collect search results")?;
@@ -316,6 +875,15 @@ impl VaultService {
         conn.execute("DELETE FROM dorks", [])
             .context("Failed to clear vault")?;
 
+        conn.execute("DELETE FROM dork_merges", [])
+            .context("Failed to clear dork merge history")?;
+
+        conn.execute("DELETE FROM dork_provenance", [])
+            .context("Failed to clear dork provenance")?;
+
+        conn.execute("UPDATE conversation_messages SET dork_id = NULL", [])
+            .context("Failed to unlink dorks from messages")?;
+
         tracing::warn!("Vault cleared - all dorks deleted");
         Ok(())
     }
@@ -352,7 +920,8 @@ impl VaultService {
 
         let stats = conn.query_row(
             "SELECT ai_generations_today, last_reset_date, total_dorks,
-                    total_conversations, created_at, updated_at
+                    total_conversations, prompt_tokens_today, completion_tokens_today,
+                    total_prompt_tokens, total_completion_tokens, created_at, updated_at
              FROM usage_stats WHERE id = 1",
             [],
             |row| Ok(UsageStats {
@@ -360,8 +929,12 @@ impl VaultService {
                 last_reset_date: row.get(1)?,
                 total_dorks: row.get(2)?,
                 total_conversations: row.get(3)?,
-                created_at: row.get(4)?,
-                updated_at: row.get(5)?,
+                prompt_tokens_today: row.get(4)?,
+                completion_tokens_today: row.get(5)?,
+                total_prompt_tokens: row.get(6)?,
+                total_completion_tokens: row.get(7)?,
+                created_at: row.get(8)?,
+                updated_at: row.get(9)?,
             }),
         ).context("Failed to get usage stats")?;
 
@@ -370,52 +943,7 @@ impl VaultService {
 
     pub async fn check_and_reset_daily_usage(&self) -> Result<()> {
         let conn = self.conn.lock().await;
-
-        let today = Utc::now().format("%Y-%m-%d").to_string();
-
-        let last_reset: String = conn.query_row(
-            "SELECT last_reset_date FROM usage_stats WHERE id = 1",
-            [],
-            |row| row.get(0),
-        ).context("Failed to get last reset date")?;
-
-        if last_reset != today {
-            conn.execute(
-                "UPDATE usage_stats
-                 SET ai_generations_today = 0,
-                     last_reset_date = ?1,
-                     updated_at = datetime('now')
-                 WHERE id = 1",
-                [&today],
-            ).context("Failed to reset daily usage")?;
-
-            tracing::info!("Daily usage counter reset for new day: {}", today);
-        }
-
-        Ok(())
-    }
-
-    pub async fn increment_ai_usage(&self) -> Result<i32> {
-        self.check_and_reset_daily_usage().await?;
-
-        let conn = self.conn.lock().await;
-
-        conn.execute(
-            "UPDATE usage_stats
-             SET ai_generations_today = ai_generations_today + 1,
-                 updated_at = datetime('now')
-             WHERE id = 1",
-            [],
-        ).context("Failed to increment AI usage")?;
-
-        let count: i32 = conn.query_row(
-            "SELECT ai_generations_today FROM usage_stats WHERE id = 1",
-            [],
-            |row| row.get(0),
-        ).context("Failed to get AI generation count")?;
-
-        tracing::debug!("AI generation count: {}", count);
-        Ok(count)
+        quota::reset_daily_usage_if_stale(&conn)
     }
 
     pub async fn get_total_dorks_count(&self) -> Result<i32> {
@@ -438,17 +966,8 @@ impl VaultService {
         Ok(count)
     }
 
-    pub async fn can_generate_ai(&self, license_tier: &str) -> Result<bool> {
-        if matches!(license_tier, "professional" | "team" | "enterprise") {
-            return Ok(true);
-        }
-
-        let stats = self.get_usage_stats().await?;
-        Ok(stats.ai_generations_today < 10)
-    }
-
     pub async fn can_save_dork(&self, license_tier: &str) -> Result<bool> {
-        if matches!(license_tier, "professional" | "team" | "enterprise") {
+        if is_unlimited_tier(license_tier) {
             return Ok(true);
         }
 
@@ -460,27 +979,76 @@ impl VaultService {
     // CONVERSATION PERSISTENCE METHODS
     // ========================================================================
 
+    /// Upsert a conversation and replace its full message list
     pub async fn save_conversation(&self, conversation: &Conversation) -> Result<()> {
-        let conn = self.conn.lock().await;
-
-        let messages_json = serde_json::to_string(&conversation.messages)
-            .context("Failed to serialize messages")?;
-
-        conn.execute(
-            "INSERT OR REPLACE INTO conversations
-             (id, title, messages, created_at, updated_at)
-             VALUES (?1, ?2, ?3, ?4, ?5)",
+        let mut conn = self.conn.lock().await;
+        let tx = conn.transaction().context("Failed to start transaction")?;
+
+        tx.execute(
+            "INSERT INTO conversations (id, title, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(id) DO UPDATE SET
+                title = excluded.title,
+                updated_at = excluded.updated_at",
             params![
                 &conversation.id,
                 &conversation.title,
-                &messages_json,
                 &conversation.created_at,
                 &conversation.updated_at,
             ],
         ).context("Failed to save conversation")?;
 
+        // Callers that don't know about dork links or summaries shouldn't
+        // erase them. Pins are whatever the caller sends, so they can be undone.
+        let existing_links: std::collections::HashMap<String, (Option<String>, Option<String>)> = tx
+            .prepare(
+                "SELECT id, dork_id, summarizes_through FROM conversation_messages
+                 WHERE conversation_id = ?1
+                   AND (dork_id IS NOT NULL OR summarizes_through IS NOT NULL)"
+            )?
+            .query_map([&conversation.id], |row| Ok((row.get(0)?, (row.get(1)?, row.get(2)?))))?
+            .collect::<Result<_, _>>()
+            .context("Failed to read message dork links")?;
+
+        // Summaries are written by the backend, so clients usually don't
+        // send them back. Keep each one after the turn it covers, unless
+        // that turn is gone.
+        let mut summaries: Vec<Message> = load_messages(&tx, &conversation.id)?
+            .into_iter()
+            .filter(|m| m.role == SUMMARY_ROLE && !conversation.messages.iter().any(|sent| sent.id == m.id))
+            .collect();
+
+        tx.execute(
+            "DELETE FROM conversation_messages WHERE conversation_id = ?1",
+            [&conversation.id],
+        ).context("Failed to clear conversation messages")?;
+
+        let mut position = 0;
+        for message in &conversation.messages {
+            let mut message = message.clone();
+            if let Some((dork_id, summarizes_through)) = existing_links.get(&message.id) {
+                if message.dork_id.is_none() {
+                    message.dork_id = dork_id.clone();
+                }
+                if message.summarizes_through.is_none() {
+                    message.summarizes_through = summarizes_through.clone();
+                }
+            }
+            insert_message(&tx, &conversation.id, position, &message)?;
+            position += 1;
+
+            let (covering, rest): (Vec<Message>, Vec<Message>) = summaries
+                .into_iter()
+                .partition(|summary| summary.summarizes_through.as_deref() == Some(message.id.as_str()));
+            summaries = rest;
+            for summary in covering {
+                insert_message(&tx, &conversation.id, position, &summary)?;
+                position += 1;
+            }
+        }
+
         // Update total_conversations count
-        conn.execute(
+        tx.execute(
             "UPDATE usage_stats
              SET total_conversations = (SELECT COUNT(*) FROM conversations),
                  updated_at = datetime('now')
@@ -488,75 +1056,189 @@ impl VaultService {
             [],
         ).context("Failed to update conversation count")?;
 
+        tx.commit().context("Failed to commit conversation")?;
+
         tracing::debug!("Conversation saved: {} ({})", conversation.title, conversation.id);
         Ok(())
     }
 
+    /// Append one message to the end of an existing conversation
+    pub async fn append_message(&self, conversation_id: &str, message: &Message) -> Result<()> {
+        let mut conn = self.conn.lock().await;
+        let tx = conn.transaction().context("Failed to start transaction")?;
+
+        let rows_affected = tx.execute(
+            "UPDATE conversations SET updated_at = ?1 WHERE id = ?2",
+            params![Utc::now().to_rfc3339(), conversation_id],
+        ).context("Failed to touch conversation")?;
+
+        if rows_affected == 0 {
+            anyhow::bail!("Conversation not found: {}", conversation_id);
+        }
+
+        let position: i64 = tx.query_row(
+            "SELECT COALESCE(MAX(position) + 1, 0)
+             FROM conversation_messages WHERE conversation_id = ?1",
+            [conversation_id],
+            |row| row.get(0),
+        ).context("Failed to get next message position")?;
+
+        insert_message(&tx, conversation_id, position, message)?;
+
+        tx.commit().context("Failed to commit message")?;
+
+        tracing::debug!("Message appended to conversation {}", conversation_id);
+        Ok(())
+    }
+
+    /// Overwrite one message's content, e.g. while an AI reply streams in
+    pub async fn update_message_content(
+        &self,
+        conversation_id: &str,
+        message_id: &str,
+        content: &str,
+        dork: Option<&str>,
+    ) -> Result<()> {
+        let conn = self.conn.lock().await;
+
+        let rows_affected = conn.execute(
+            "UPDATE conversation_messages
+             SET content = ?1, dork = COALESCE(?2, dork)
+             WHERE conversation_id = ?3 AND id = ?4",
+            params![content, dork, conversation_id, message_id],
+        ).context("Failed to update message")?;
+
+        if rows_affected == 0 {
+            anyhow::bail!("Message {} not found in conversation {}", message_id, conversation_id);
+        }
+
+        Ok(())
+    }
+
+    /// Pin or unpin a message for context assembly
+    pub async fn set_message_pinned(&self, conversation_id: &str, message_id: &str, pinned: bool) -> Result<()> {
+        let conn = self.conn.lock().await;
+
+        let rows_affected = conn.execute(
+            "UPDATE conversation_messages SET pinned = ?1 WHERE conversation_id = ?2 AND id = ?3",
+            params![pinned, conversation_id, message_id],
+        ).context("Failed to pin message")?;
+
+        if rows_affected == 0 {
+            anyhow::bail!("Message {} not found in conversation {}", message_id, conversation_id);
+        }
+
+        Ok(())
+    }
+
     pub async fn get_conversation(&self, id: &str) -> Result<Conversation> {
         let conn = self.conn.lock().await;
 
-        let conversation = conn.query_row(
-            "SELECT id, title, messages, created_at, updated_at
-             FROM conversations WHERE id = ?1",
+        let (title, created_at, updated_at) = conn.query_row(
+            "SELECT title, created_at, updated_at FROM conversations WHERE id = ?1",
             [id],
-            |row| {
-                let messages_json: String = row.get(2)?;
-                let messages: Vec<Message> = serde_json::from_str(&messages_json)
-                    .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
-
-                Ok(Conversation {
-                    id: row.get(0)?,
-                    title: row.get(1)?,
-                    messages,
-                    created_at: row.get(3)?,
-                    updated_at: row.get(4)?,
-                })
-            },
+            |row| Ok((row.get::<_, String>(0)?, row.get(1)?, row.get(2)?)),
         ).context("Failed to get conversation")?;
 
-        Ok(conversation)
+        let messages = load_messages(&conn, id)?;
+
+        Ok(Conversation {
+            id: id.to_string(),
+            title,
+            messages,
+            created_at,
+            updated_at,
+        })
     }
 
     pub async fn list_conversations(&self, limit: Option<i32>) -> Result<Vec<Conversation>> {
         let conn = self.conn.lock().await;
 
-        let mut stmt = if let Some(lim) = limit {
-            conn.prepare(
-                &format!("SELECT id, title, messages, created_at, updated_at
-                         FROM conversations
-                         ORDER BY updated_at DESC
-                         LIMIT {}", lim)
-            )?
-        } else {
-            conn.prepare(
-                "SELECT id, title, messages, created_at, updated_at
-                 FROM conversations
-                 ORDER BY updated_at DESC"
-            )?
-        };
-
-        let rows = stmt.query_map([], |row| {
-            let messages_json: String = row.get(2)?;
-            let messages: Vec<Message> = serde_json::from_str(&messages_json)
-                .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+        let mut stmt = conn.prepare(
+            "SELECT id, title, created_at, updated_at
+             FROM conversations
+             ORDER BY updated_at DESC
+             LIMIT ?1"
+        )?;
 
+        let rows = stmt.query_map([limit.unwrap_or(-1)], |row| {
             Ok(Conversation {
                 id: row.get(0)?,
                 title: row.get(1)?,
-                messages,
-                created_at: row.get(3)?,
-                updated_at: row.get(4)?,
+                messages: Vec::new(),
+                created_at: row.get(2)?,
+                updated_at: row.get(3)?,
             })
         })?;
 
-        let conversations: Result<Vec<_>, _> = rows.collect();
-        conversations.context("Failed to collect conversations")
+        let mut conversations = rows.collect::<Result<Vec<_>, _>>()
+            .context("Failed to collect conversations")?;
+
+        // Every listed conversation's messages in one query
+        let mut messages: std::collections::HashMap<String, Vec<Message>> = std::collections::HashMap::new();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {}, conversation_id FROM conversation_messages
+             WHERE conversation_id IN (SELECT id FROM conversations ORDER BY updated_at DESC LIMIT ?1)
+             ORDER BY conversation_id, position ASC",
+            MESSAGE_COLUMNS
+        )).context("Failed to prepare messages query")?;
+        let rows = stmt
+            .query_map([limit.unwrap_or(-1)], |row| Ok((row.get::<_, String>(9)?, message_from_row(row)?)))
+            .context("Failed to query messages")?;
+        for row in rows {
+            let (conversation_id, message) = row.context("Failed to collect messages")?;
+            messages.entry(conversation_id).or_default().push(message);
+        }
+
+        for conversation in &mut conversations {
+            conversation.messages = messages.remove(&conversation.id).unwrap_or_default();
+        }
+
+        Ok(conversations)
     }
 
-    pub async fn delete_conversation(&self, id: &str) -> Result<()> {
+    /// Lightweight listing for conversation pickers: no message bodies
+    pub async fn list_conversation_summaries(&self, limit: Option<i32>) -> Result<Vec<ConversationSummary>> {
         let conn = self.conn.lock().await;
 
-        let rows_affected = conn.execute(
+        let mut stmt = conn.prepare(
+            "SELECT c.id, c.title, c.created_at, c.updated_at,
+                    (SELECT COUNT(*) FROM conversation_messages m
+                     WHERE m.conversation_id = c.id AND m.role != 'summary'),
+                    last.role, substr(last.content, 1, ?2), last.timestamp
+             FROM conversations c
+             LEFT JOIN conversation_messages last
+               ON last.conversation_id = c.id
+              AND last.position = (SELECT MAX(position) FROM conversation_messages
+                                   WHERE conversation_id = c.id AND role != 'summary')
+             ORDER BY c.updated_at DESC
+             LIMIT ?1"
+        ).context("Failed to prepare conversation summaries query")?;
+
+        let summaries = stmt.query_map(params![limit.unwrap_or(-1), PREVIEW_LENGTH], |row| {
+            Ok(ConversationSummary {
+                id: row.get(0)?,
+                title: row.get(1)?,
+                created_at: row.get(2)?,
+                updated_at: row.get(3)?,
+                message_count: row.get(4)?,
+                last_message_role: row.get(5)?,
+                last_message_preview: row.get(6)?,
+                last_message_at: row.get(7)?,
+            })
+        })
+        .context("Failed to query conversation summaries")?
+        .collect::<Result<Vec<_>, _>>()
+        .context("Failed to collect conversation summaries")?;
+
+        Ok(summaries)
+    }
+
+    pub async fn delete_conversation(&self, id: &str) -> Result<()> {
+        let mut conn = self.conn.lock().await;
+        let tx = conn.transaction().context("Failed to start transaction")?;
+
+        let rows_affected = tx.execute(
             "DELETE FROM conversations WHERE id = ?1",
             [id],
         ).context("Failed to delete conversation")?;
@@ -565,8 +1247,19 @@ impl VaultService {
             anyhow::bail!("Conversation not found: {}", id);
         }
 
+        tx.execute(
+            "DELETE FROM conversation_messages WHERE conversation_id = ?1",
+            [id],
+        ).context("Failed to delete conversation messages")?;
+
+        tx.execute(
+            "UPDATE dork_provenance SET conversation_id = NULL, message_id = NULL
+             WHERE conversation_id = ?1",
+            [id],
+        ).context("Failed to detach dork provenance")?;
+
         // Update count
-        conn.execute(
+        tx.execute(
             "UPDATE usage_stats
              SET total_conversations = (SELECT COUNT(*) FROM conversations),
                  updated_at = datetime('now')
@@ -574,9 +1267,80 @@ impl VaultService {
             [],
         ).context("Failed to update conversation count")?;
 
+        tx.commit().context("Failed to commit conversation deletion")?;
+
         tracing::debug!("Conversation deleted: {}", id);
         Ok(())
     }
+
+    // ========================================================================
+    // SETTINGS METHODS
+    // ========================================================================
+
+    pub async fn get_setting(&self, key: &str) -> Result<Option<serde_json::Value>> {
+        let conn = self.conn.lock().await;
+
+        let result = conn.query_row(
+            "SELECT value FROM settings WHERE key = ?1",
+            [key],
+            |row| row.get::<_, String>(0),
+        );
+
+        match result {
+            Ok(value) => Ok(Some(
+                serde_json::from_str(&value).context("Failed to parse setting value")?,
+            )),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e).context("Failed to get setting"),
+        }
+    }
+
+    pub async fn set_setting(&self, key: &str, value: &serde_json::Value) -> Result<()> {
+        let conn = self.conn.lock().await;
+
+        let value_json = serde_json::to_string(value)
+            .context("Failed to serialize setting value")?;
+
+        conn.execute(
+            "INSERT OR REPLACE INTO settings (key, value, updated_at)
+             VALUES (?1, ?2, ?3)",
+            params![key, value_json, Utc::now().to_rfc3339()],
+        ).context("Failed to save setting")?;
+
+        tracing::debug!("Setting saved: {}", key);
+        Ok(())
+    }
+
+    pub async fn get_all_settings(&self) -> Result<serde_json::Map<String, serde_json::Value>> {
+        let conn = self.conn.lock().await;
+
+        let mut stmt = conn.prepare("SELECT key, value FROM settings ORDER BY key")
+            .context("Failed to prepare settings query")?;
+
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })
+        .context("Failed to query settings")?
+        .collect::<Result<Vec<_>, _>>()
+        .context("Failed to collect settings")?;
+
+        let mut settings = serde_json::Map::new();
+        for (key, value) in rows {
+            let value = serde_json::from_str(&value).unwrap_or(serde_json::Value::String(value));
+            settings.insert(key, value);
+        }
+
+        Ok(settings)
+    }
+
+    pub async fn delete_setting(&self, key: &str) -> Result<()> {
+        let conn = self.conn.lock().await;
+
+        conn.execute("DELETE FROM settings WHERE key = ?1", [key])
+            .context("Failed to delete setting")?;
+
+        Ok(())
+    }
 }
 
 impl Default for VaultService {
@@ -622,4 +1386,116 @@ mod tests {
         let deleted = service.get_dork_by_id(&test_dork.id).await.unwrap();
         assert!(deleted.is_none());
     }
+
+    fn message(id: &str, content: &str) -> Message {
+        Message {
+            id: id.to_string(),
+            role: "user".to_string(),
+            content: content.to_string(),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            dork: None,
+            dork_id: None,
+            prompt_version: None,
+            pinned: false,
+            summarizes_through: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_append_message_and_summaries() {
+        let service = VaultService::open_in_memory().unwrap();
+        let now = chrono::Utc::now().to_rfc3339();
+
+        service.save_conversation(&Conversation {
+            id: "c1".to_string(),
+            title: "Recon".to_string(),
+            messages: vec![message("m1", "first")],
+            created_at: now.clone(),
+            updated_at: now,
+        }).await.unwrap();
+
+        service.append_message("c1", &message("m2", "second")).await.unwrap();
+        assert!(service.append_message("missing", &message("m3", "x")).await.is_err());
+
+        let conversation = service.get_conversation("c1").await.unwrap();
+        let contents: Vec<_> = conversation.messages.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, vec!["first", "second"]);
+
+        let summaries = service.list_conversation_summaries(None).await.unwrap();
+        assert_eq!(summaries[0].message_count, 2);
+        assert_eq!(summaries[0].last_message_preview.as_deref(), Some("second"));
+
+        // A pin set elsewhere can be undone by saving the conversation
+        service.set_message_pinned("c1", "m1", true).await.unwrap();
+        let mut conversation = service.get_conversation("c1").await.unwrap();
+        assert!(conversation.messages[0].pinned);
+        conversation.messages[0].pinned = false;
+        service.save_conversation(&conversation).await.unwrap();
+        let listed = service.list_conversations(None).await.unwrap();
+        assert_eq!(listed[0].messages.len(), 2);
+        assert!(!listed[0].messages[0].pinned);
+    }
+
+    #[tokio::test]
+    async fn test_legacy_message_blobs_are_migrated() {
+        let path = std::env::temp_dir().join(format!("parallax-legacy-{}.db", Uuid::new_v4()));
+        {
+            let conn = Connection::open(&path).unwrap();
+            conn.execute(
+                "CREATE TABLE conversations (
+                    id TEXT PRIMARY KEY, title TEXT NOT NULL, messages TEXT NOT NULL,
+                    created_at TEXT NOT NULL, updated_at TEXT NOT NULL
+                )",
+                [],
+            ).unwrap();
+            let blob = serde_json::json!([
+                message("m1", "kept"),
+                { "id": "broken" },
+                message("m2", "also kept"),
+            ]);
+            conn.execute(
+                "INSERT INTO conversations VALUES ('c1', 'Old', ?1, datetime('now'), datetime('now'))",
+                [blob.to_string()],
+            ).unwrap();
+            conn.execute(
+                "INSERT INTO conversations VALUES ('c2', 'Corrupt', '[{\"id\": ', datetime('now'), datetime('now'))",
+                [],
+            ).unwrap();
+            let reused = serde_json::json!([message("m1", "same id, other chat")]);
+            conn.execute(
+                "INSERT INTO conversations VALUES ('c3', 'Reused', ?1, datetime('now'), datetime('now'))",
+                [reused.to_string()],
+            ).unwrap();
+        }
+
+        let service = VaultService::open(&path).unwrap();
+        let conversation = service.get_conversation("c1").await.unwrap();
+        assert_eq!(conversation.messages.len(), 2);
+        assert_eq!(conversation.messages[1].content, "also kept");
+        assert!(service.get_conversation("c2").await.unwrap().messages.is_empty());
+        // Ids were only unique per conversation; a collision gets a new id instead of being dropped
+        let reused = service.get_conversation("c3").await.unwrap().messages;
+        assert_eq!(reused.len(), 1);
+        assert_eq!(reused[0].content, "same id, other chat");
+        assert_ne!(reused[0].id, "m1");
+
+        // Unreadable messages are kept aside rather than dropped with the column
+        let quarantined: Vec<(String, String)> = service
+            .conn
+            .lock()
+            .await
+            .prepare("SELECT id, data FROM conversations_legacy ORDER BY id")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(quarantined, vec![
+            ("c1:1".to_string(), r#"{"id":"broken"}"#.to_string()),
+            ("c2:blob".to_string(), r#"[{"id": "#.to_string()),
+        ]);
+
+        drop(service);
+        std::fs::remove_file(&path).ok();
+    }
 }
@@ -0,0 +1,812 @@
+//! Full vault backup and restore.
+//!
+//! A backup is a JSON archive holding every vault table as rows keyed by
+//! column name, a manifest with per-table row counts and SHA-256 checksums,
+//! and an optional AES-256-GCM layer keyed from a passphrase. Plain archives
+//! keep the manifest readable for inspection before restoring. Encrypted
+//! ones seal it with the rows, since checksums and counts of the plaintext
+//! would let anyone confirm guessed contents; their clear manifest only
+//! holds the checksum of the ciphertext.
+
+use super::{split_legacy_blob, VaultService};
+use aes_gcm::{
+    aead::{Aead, KeyInit, OsRng, Payload},
+    Aes256Gcm, Nonce,
+};
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use rand::RngCore;
+use rusqlite::{params_from_iter, types::ValueRef, Connection};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashSet};
+
+pub const BACKUP_FORMAT: &str = "parallax-vault-backup";
+/// Version 2 stores conversation messages as rows instead of a JSON column.
+/// Version 3 moves an encrypted archive's table manifest into the ciphertext.
+pub const BACKUP_VERSION: u32 = 3;
+
+const PBKDF2_ROUNDS: u32 = 210_000;
+/// Iteration counts accepted from an archive header, so a crafted archive
+/// can neither stall a restore nor weaken the key
+const PBKDF2_ROUNDS_ACCEPTED: std::ops::RangeInclusive<u32> = PBKDF2_ROUNDS..=10 * PBKDF2_ROUNDS;
+
+/// Tables included in a backup, with their primary key column
+const BACKUP_TABLES: &[(&str, &str)] = &[
+    ("dorks", "id"),
+    ("dork_merges", "merged_id"),
+    ("dork_provenance", "dork_id"),
+    ("conversations", "id"),
+    ("conversation_messages", "id"),
+    ("conversations_legacy", "id"),
+    ("usage_stats", "id"),
+    ("settings", "key"),
+    ("prompt_overrides", "id"),
+    ("ai_usage_ledger", "id"),
+    ("graph_entities", "id"),
+    ("graph_relationships", "id"),
+    ("graph_provenance", "id"),
+];
+
+type TableRows = Vec<Map<String, Value>>;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupArchive {
+    pub format: String,
+    pub version: u32,
+    pub app_version: String,
+    pub created_at: String,
+    pub manifest: BackupManifest,
+    pub encryption: Option<BackupEncryption>,
+    /// Base64 of the table payload (ciphertext when `encryption` is set)
+    pub payload: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupManifest {
+    /// Empty for encrypted archives, whose table manifest is sealed with the rows
+    pub tables: Vec<TableManifest>,
+    /// Checksum of the payload as stored: the ciphertext when encrypted
+    pub payload_sha256: String,
+}
+
+/// The plaintext of an encrypted archive
+#[derive(Serialize, Deserialize)]
+struct SealedPayload {
+    tables: Vec<TableManifest>,
+    rows: BTreeMap<String, TableRows>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TableManifest {
+    pub name: String,
+    pub primary_key: String,
+    pub row_count: usize,
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupEncryption {
+    pub algorithm: String,
+    pub kdf: String,
+    pub iterations: u32,
+    pub salt: String,
+    pub nonce: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RestoreMode {
+    /// Keep local rows and only add rows that don't exist yet
+    Merge,
+    /// Wipe every table in the archive before restoring it
+    Replace,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestoreConflict {
+    pub table: String,
+    pub id: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TableRestoreSummary {
+    pub name: String,
+    pub inserted: usize,
+    pub unchanged: usize,
+    pub conflicts: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestoreReport {
+    pub mode: RestoreMode,
+    pub tables: Vec<TableRestoreSummary>,
+    pub conflicts: Vec<RestoreConflict>,
+}
+
+impl BackupArchive {
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).context("Failed to serialize backup archive")
+    }
+
+    /// Parse an archive and check its header, without decrypting the payload
+    pub fn from_json(json: &str) -> Result<Self> {
+        let archive: BackupArchive = serde_json::from_str(json)
+            .context("Failed to parse backup archive")?;
+
+        if archive.format != BACKUP_FORMAT {
+            anyhow::bail!("Not a Parallax vault backup (format: {})", archive.format);
+        }
+
+        if archive.version == 0 || archive.version > BACKUP_VERSION {
+            anyhow::bail!(
+                "Unsupported backup version {} (this build supports up to {})",
+                archive.version,
+                BACKUP_VERSION
+            );
+        }
+
+        check_table_names(&archive.manifest.tables)?;
+        Ok(archive)
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.encryption.is_some()
+    }
+
+    /// Decrypt (if needed) and verify the payload against the manifest
+    fn open_payload(&self, passphrase: Option<&str>) -> Result<BTreeMap<String, TableRows>> {
+        let raw = BASE64.decode(&self.payload)
+            .context("Backup payload is not valid base64")?;
+
+        let (entries, mut tables) = match &self.encryption {
+            Some(encryption) => {
+                let passphrase = passphrase
+                    .ok_or_else(|| anyhow::anyhow!("Backup is encrypted; a passphrase is required"))?;
+                if self.version >= 3 {
+                    verify_checksum(&raw, &self.manifest.payload_sha256)?;
+                    let plaintext = decrypt_payload(encryption, passphrase, &raw, &associated_data(self.version))?;
+                    let sealed: SealedPayload = serde_json::from_slice(&plaintext)
+                        .context("Failed to parse backup payload")?;
+                    check_table_names(&sealed.tables)?;
+                    (sealed.tables, sealed.rows)
+                } else {
+                    let plaintext = decrypt_payload(encryption, passphrase, &raw, &[])?;
+                    verify_checksum(&plaintext, &self.manifest.payload_sha256)?;
+                    (self.manifest.tables.clone(), parse_rows(&plaintext)?)
+                }
+            }
+            None => {
+                verify_checksum(&raw, &self.manifest.payload_sha256)?;
+                (self.manifest.tables.clone(), parse_rows(&raw)?)
+            }
+        };
+
+        if let Some(extra) = tables.keys().find(|name| !entries.iter().any(|t| &t.name == *name)) {
+            anyhow::bail!("Backup payload has table {} missing from the manifest", extra);
+        }
+
+        for entry in &entries {
+            let rows = tables.get(&entry.name)
+                .ok_or_else(|| anyhow::anyhow!("Backup payload is missing table {}", entry.name))?;
+
+            if rows.len() != entry.row_count {
+                anyhow::bail!(
+                    "Table {} has {} rows but the manifest lists {}",
+                    entry.name,
+                    rows.len(),
+                    entry.row_count
+                );
+            }
+
+            if table_checksum(rows)? != entry.sha256 {
+                anyhow::bail!("Checksum mismatch for table {}", entry.name);
+            }
+        }
+
+        if self.version < 2 {
+            upgrade_v1_conversations(&mut tables);
+        }
+
+        Ok(tables)
+    }
+}
+
+impl VaultService {
+    /// Snapshot every vault table into a backup archive
+    pub async fn create_backup(&self, passphrase: Option<&str>) -> Result<BackupArchive> {
+        let conn = self.conn.lock().await;
+
+        let mut tables = BTreeMap::new();
+        let mut manifest_tables = Vec::new();
+
+        for (table, primary_key) in BACKUP_TABLES {
+            let rows = dump_table(&conn, table, primary_key)?;
+
+            manifest_tables.push(TableManifest {
+                name: table.to_string(),
+                primary_key: primary_key.to_string(),
+                row_count: rows.len(),
+                sha256: table_checksum(&rows)?,
+            });
+            tables.insert(table.to_string(), rows);
+        }
+
+        drop(conn);
+
+        let table_count = manifest_tables.len();
+        let (encryption, manifest_tables, payload) = match passphrase {
+            Some(passphrase) => {
+                let sealed = SealedPayload {
+                    tables: manifest_tables,
+                    rows: tables,
+                };
+                let plaintext = serde_json::to_vec(&sealed)
+                    .context("Failed to serialize backup payload")?;
+                let (encryption, ciphertext) =
+                    encrypt_payload(passphrase, &plaintext, &associated_data(BACKUP_VERSION))?;
+                (Some(encryption), Vec::new(), ciphertext)
+            }
+            None => {
+                let plaintext = serde_json::to_vec(&tables)
+                    .context("Failed to serialize backup payload")?;
+                (None, manifest_tables, plaintext)
+            }
+        };
+        let payload_sha256 = sha256_hex(&payload);
+
+        tracing::info!(
+            "Vault backup created ({} tables, encrypted: {})",
+            table_count,
+            encryption.is_some()
+        );
+
+        Ok(BackupArchive {
+            format: BACKUP_FORMAT.to_string(),
+            version: BACKUP_VERSION,
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            created_at: chrono::Utc::now().to_rfc3339(),
+            manifest: BackupManifest {
+                tables: manifest_tables,
+                payload_sha256,
+            },
+            encryption,
+            payload: BASE64.encode(payload),
+        })
+    }
+
+    /// Restore a backup archive into the vault in a single transaction
+    pub async fn restore_backup(
+        &self,
+        archive: &BackupArchive,
+        passphrase: Option<&str>,
+        mode: RestoreMode,
+    ) -> Result<RestoreReport> {
+        let tables = archive.open_payload(passphrase)?;
+
+        let mut conn = self.conn.lock().await;
+        let tx = conn.transaction().context("Failed to start restore transaction")?;
+
+        let mut summaries = Vec::new();
+        let mut conflicts = Vec::new();
+
+        for (table, primary_key) in BACKUP_TABLES.iter().copied() {
+            let rows = match tables.get(table) {
+                Some(rows) => rows,
+                None => continue,
+            };
+
+            let columns = table_columns(&tx, table)?;
+            let mut summary = TableRestoreSummary {
+                name: table.to_string(),
+                ..Default::default()
+            };
+
+            if mode == RestoreMode::Replace {
+                tx.execute(&format!("DELETE FROM \"{}\"", table), [])
+                    .with_context(|| format!("Failed to clear table {}", table))?;
+            }
+
+            for row in rows {
+                for column in row.keys() {
+                    if !columns.contains(column) {
+                        anyhow::bail!("Backup has unknown column {}.{}", table, column);
+                    }
+                }
+
+                let id = row.get(primary_key)
+                    .map(json_key_to_string)
+                    .ok_or_else(|| anyhow::anyhow!("Row in {} is missing {}", table, primary_key))?;
+
+                if mode == RestoreMode::Merge {
+                    if let Some(existing) = fetch_row(&tx, table, primary_key, &row[primary_key])? {
+                        if &existing == row {
+                            summary.unchanged += 1;
+                        } else {
+                            summary.conflicts += 1;
+                            conflicts.push(RestoreConflict {
+                                table: table.to_string(),
+                                id,
+                            });
+                        }
+                        continue;
+                    }
+                }
+
+                insert_row(&tx, table, row)?;
+                summary.inserted += 1;
+            }
+
+            summaries.push(summary);
+        }
+
+        tx.execute(
+            "UPDATE usage_stats
+             SET total_dorks = (SELECT COUNT(*) FROM dorks),
+                 total_conversations = (SELECT COUNT(*) FROM conversations),
+                 updated_at = datetime('now')
+             WHERE id = 1",
+            [],
+        ).context("Failed to refresh usage counters")?;
+
+        tx.commit().context("Failed to commit restore")?;
+
+        tracing::info!(
+            "Vault backup restored ({:?}, {} conflicts)",
+            mode,
+            conflicts.len()
+        );
+
+        Ok(RestoreReport {
+            mode,
+            tables: summaries,
+            conflicts,
+        })
+    }
+}
+
+/// Split version 1 `conversations.messages` JSON blobs into message rows.
+/// Like the live migration, messages that can't be read are quarantined in
+/// `conversations_legacy` instead of failing the restore.
+fn upgrade_v1_conversations(tables: &mut BTreeMap<String, TableRows>) {
+    let mut messages = TableRows::new();
+    let mut quarantined = TableRows::new();
+    let mut ids = HashSet::new();
+    let now = chrono::Utc::now().to_rfc3339();
+
+    for conversation in tables.get_mut("conversations").into_iter().flatten() {
+        let conversation_id = conversation.get("id").map(json_key_to_string).unwrap_or_default();
+        let blob = match conversation.remove("messages") {
+            Some(Value::String(blob)) => blob,
+            _ => continue,
+        };
+
+        let mut position = 0;
+        for message in split_legacy_blob(&blob) {
+            let (rows, row) = match message {
+                Ok(message) => {
+                    // Blob ids were only unique within their conversation
+                    let id = if ids.insert(message.id.clone()) {
+                        message.id
+                    } else {
+                        uuid::Uuid::new_v4().to_string()
+                    };
+                    let row = serde_json::json!({
+                        "id": id,
+                        "conversation_id": conversation_id,
+                        "position": position,
+                        "role": message.role,
+                        "content": message.content,
+                        "timestamp": message.timestamp,
+                        "dork": message.dork,
+                    });
+                    position += 1;
+                    (&mut messages, row)
+                }
+                Err(unreadable) => {
+                    tracing::warn!(
+                        "Quarantining unreadable message in conversation {}: {}",
+                        conversation_id,
+                        unreadable.error
+                    );
+                    let row = serde_json::json!({
+                        "id": unreadable.id(&conversation_id),
+                        "conversation_id": conversation_id,
+                        "data": unreadable.data,
+                        "error": unreadable.error,
+                        "quarantined_at": now,
+                    });
+                    (&mut quarantined, row)
+                }
+            };
+            if let Value::Object(row) = row {
+                rows.push(row);
+            }
+        }
+    }
+
+    tables.insert("conversation_messages".to_string(), messages);
+    tables.insert("conversations_legacy".to_string(), quarantined);
+}
+
+fn dump_table(conn: &Connection, table: &str, primary_key: &str) -> Result<TableRows> {
+    let mut stmt = conn
+        .prepare(&format!("SELECT * FROM \"{}\" ORDER BY \"{}\"", table, primary_key))
+        .with_context(|| format!("Failed to prepare dump of {}", table))?;
+
+    let names: Vec<String> = stmt.column_names().iter().map(|n| n.to_string()).collect();
+
+    let rows = stmt
+        .query_map([], |row| row_to_json(row, &names))
+        .with_context(|| format!("Failed to dump table {}", table))?
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("Failed to collect rows of {}", table))?;
+
+    Ok(rows)
+}
+
+fn fetch_row(
+    conn: &Connection,
+    table: &str,
+    primary_key: &str,
+    id: &Value,
+) -> Result<Option<Map<String, Value>>> {
+    let mut stmt = conn
+        .prepare(&format!("SELECT * FROM \"{}\" WHERE \"{}\" = ?1", table, primary_key))
+        .with_context(|| format!("Failed to prepare lookup in {}", table))?;
+
+    let names: Vec<String> = stmt.column_names().iter().map(|n| n.to_string()).collect();
+
+    let result = stmt.query_row([json_to_sql(id)], |row| row_to_json(row, &names));
+
+    match result {
+        Ok(row) => Ok(Some(row)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("Failed to look up row in {}", table)),
+    }
+}
+
+fn insert_row(conn: &Connection, table: &str, row: &Map<String, Value>) -> Result<()> {
+    let columns: Vec<String> = row.keys().map(|c| format!("\"{}\"", c)).collect();
+    let placeholders: Vec<String> = (1..=row.len()).map(|i| format!("?{}", i)).collect();
+
+    conn.execute(
+        &format!(
+            "INSERT OR REPLACE INTO \"{}\" ({}) VALUES ({})",
+            table,
+            columns.join(", "),
+            placeholders.join(", ")
+        ),
+        params_from_iter(row.values().map(json_to_sql)),
+    )
+    .with_context(|| format!("Failed to restore row into {}", table))?;
+
+    Ok(())
+}
+
+fn table_columns(conn: &Connection, table: &str) -> Result<HashSet<String>> {
+    let mut stmt = conn
+        .prepare(&format!("PRAGMA table_info(\"{}\")", table))
+        .context("Failed to read table schema")?;
+
+    let columns = stmt
+        .query_map([], |row| row.get::<_, String>(1))
+        .context("Failed to read table columns")?
+        .collect::<Result<HashSet<_>, _>>()
+        .context("Failed to collect table columns")?;
+
+    Ok(columns)
+}
+
+fn row_to_json(row: &rusqlite::Row, names: &[String]) -> rusqlite::Result<Map<String, Value>> {
+    let mut map = Map::new();
+
+    for (i, name) in names.iter().enumerate() {
+        let value = match row.get_ref(i)? {
+            ValueRef::Null => Value::Null,
+            ValueRef::Integer(n) => Value::from(n),
+            ValueRef::Real(f) => serde_json::Number::from_f64(f)
+                .map(Value::Number)
+                .unwrap_or(Value::Null),
+            ValueRef::Text(t) => Value::String(String::from_utf8_lossy(t).into_owned()),
+            ValueRef::Blob(b) => serde_json::json!({ "$blob": BASE64.encode(b) }),
+        };
+        map.insert(name.clone(), value);
+    }
+
+    Ok(map)
+}
+
+fn json_to_sql(value: &Value) -> rusqlite::types::Value {
+    use rusqlite::types::Value as Sql;
+
+    match value {
+        Value::Null => Sql::Null,
+        Value::Bool(b) => Sql::Integer(*b as i64),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => Sql::Integer(i),
+            None => Sql::Real(n.as_f64().unwrap_or_default()),
+        },
+        Value::String(s) => Sql::Text(s.clone()),
+        Value::Object(obj) => match obj.get("$blob").and_then(|b| b.as_str()) {
+            Some(encoded) => BASE64
+                .decode(encoded)
+                .map(Sql::Blob)
+                .unwrap_or(Sql::Null),
+            None => Sql::Text(value.to_string()),
+        },
+        Value::Array(_) => Sql::Text(value.to_string()),
+    }
+}
+
+fn json_key_to_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn table_checksum(rows: &TableRows) -> Result<String> {
+    let bytes = serde_json::to_vec(rows).context("Failed to serialize table rows")?;
+    Ok(sha256_hex(&bytes))
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+fn verify_checksum(payload: &[u8], expected: &str) -> Result<()> {
+    if sha256_hex(payload) != expected {
+        anyhow::bail!("Backup payload checksum mismatch; archive is corrupted");
+    }
+    Ok(())
+}
+
+fn parse_rows(plaintext: &[u8]) -> Result<BTreeMap<String, TableRows>> {
+    serde_json::from_slice(plaintext).context("Failed to parse backup payload")
+}
+
+fn check_table_names(tables: &[TableManifest]) -> Result<()> {
+    for table in tables {
+        if !BACKUP_TABLES.iter().any(|(name, _)| *name == table.name) {
+            anyhow::bail!("Backup contains unknown table: {}", table.name);
+        }
+    }
+    Ok(())
+}
+
+/// Binds the ciphertext to its format and version, so the header can't be
+/// rewritten to have the payload read the wrong way
+fn associated_data(version: u32) -> Vec<u8> {
+    format!("{}:{}", BACKUP_FORMAT, version).into_bytes()
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], iterations: u32) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, iterations, &mut key);
+    key
+}
+
+fn encrypt_payload(passphrase: &str, plaintext: &[u8], aad: &[u8]) -> Result<(BackupEncryption, Vec<u8>)> {
+    let mut salt = [0u8; 16];
+    let mut nonce_bytes = [0u8; 12];
+    OsRng.fill_bytes(&mut salt);
+    OsRng.fill_bytes(&mut nonce_bytes);
+
+    let key = derive_key(passphrase, &salt, PBKDF2_ROUNDS);
+    let cipher = Aes256Gcm::new(&key.into());
+
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce_bytes), Payload { msg: plaintext, aad })
+        .map_err(|e| anyhow::anyhow!("Backup encryption failed: {}", e))?;
+
+    Ok((
+        BackupEncryption {
+            algorithm: "aes-256-gcm".to_string(),
+            kdf: "pbkdf2-sha256".to_string(),
+            iterations: PBKDF2_ROUNDS,
+            salt: BASE64.encode(salt),
+            nonce: BASE64.encode(nonce_bytes),
+        },
+        ciphertext,
+    ))
+}
+
+fn decrypt_payload(
+    encryption: &BackupEncryption,
+    passphrase: &str,
+    ciphertext: &[u8],
+    aad: &[u8],
+) -> Result<Vec<u8>> {
+    if encryption.algorithm != "aes-256-gcm" || encryption.kdf != "pbkdf2-sha256" {
+        anyhow::bail!(
+            "Unsupported backup encryption: {} / {}",
+            encryption.algorithm,
+            encryption.kdf
+        );
+    }
+
+    if !PBKDF2_ROUNDS_ACCEPTED.contains(&encryption.iterations) {
+        anyhow::bail!("Unsupported backup key derivation iterations: {}", encryption.iterations);
+    }
+
+    let salt = BASE64.decode(&encryption.salt).context("Invalid backup salt")?;
+    let nonce_bytes = BASE64.decode(&encryption.nonce).context("Invalid backup nonce")?;
+    if nonce_bytes.len() != 12 {
+        anyhow::bail!("Invalid backup nonce length");
+    }
+
+    let key = derive_key(passphrase, &salt, encryption.iterations);
+    let cipher = Aes256Gcm::new(&key.into());
+
+    cipher
+        .decrypt(Nonce::from_slice(&nonce_bytes), Payload { msg: ciphertext, aad })
+        .map_err(|_| anyhow::anyhow!("Failed to decrypt backup (wrong passphrase or corrupted archive)"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vault::{Conversation, DorkQuery, Message};
+
+    fn sample_dork(id: &str, name: &str) -> DorkQuery {
+        DorkQuery {
+            id: id.to_string(),
+            name: name.to_string(),
+            query: "site:example.com filetype:env".to_string(),
+            category: "config".to_string(),
+            tags: vec!["env".to_string()],
+            created_at: chrono::Utc::now().to_rfc3339(),
+            updated_at: None,
+        }
+    }
+
+    async fn seeded_vault() -> VaultService {
+        let vault = VaultService::open_in_memory().unwrap();
+        vault.save_dork(sample_dork("d1", "Env files")).await.unwrap();
+        vault.save_conversation(&Conversation {
+            id: "c1".to_string(),
+            title: "Recon".to_string(),
+            messages: vec![Message {
+                id: "m1".to_string(),
+                role: "user".to_string(),
+                content: "find env files".to_string(),
+                timestamp: chrono::Utc::now().to_rfc3339(),
+                dork: None,
+                dork_id: None,
+                prompt_version: None,
+                pinned: false,
+                summarizes_through: None,
+            }],
+            created_at: chrono::Utc::now().to_rfc3339(),
+            updated_at: chrono::Utc::now().to_rfc3339(),
+        }).await.unwrap();
+        vault.set_setting("theme", &serde_json::json!("dark")).await.unwrap();
+        vault
+    }
+
+    #[tokio::test]
+    async fn test_backup_round_trip_with_passphrase() {
+        let source = seeded_vault().await;
+        let archive = source.create_backup(Some("correct horse")).await.unwrap();
+        let json = archive.to_json().unwrap();
+        let archive = BackupArchive::from_json(&json).unwrap();
+        assert!(archive.is_encrypted());
+        // Nothing about the plaintext is readable without the passphrase
+        assert!(archive.manifest.tables.is_empty());
+        assert!(!json.contains("row_count"));
+
+        let mut downgraded = archive.clone();
+        downgraded.version = 2;
+        assert!(source.restore_backup(&downgraded, Some("correct horse"), RestoreMode::Merge).await.is_err());
+
+        let target = VaultService::open_in_memory().unwrap();
+        assert!(target.restore_backup(&archive, None, RestoreMode::Replace).await.is_err());
+        assert!(target.restore_backup(&archive, Some("wrong"), RestoreMode::Replace).await.is_err());
+
+        target.restore_backup(&archive, Some("correct horse"), RestoreMode::Replace).await.unwrap();
+
+        assert_eq!(target.get_all_dorks().await.unwrap().len(), 1);
+        assert_eq!(target.get_conversation("c1").await.unwrap().messages.len(), 1);
+        assert_eq!(target.get_setting("theme").await.unwrap(), Some(serde_json::json!("dark")));
+        assert_eq!(target.get_usage_stats().await.unwrap().total_conversations, 1);
+    }
+
+    #[tokio::test]
+    async fn test_merge_reports_conflicts_by_id() {
+        let source = seeded_vault().await;
+        source.save_dork(sample_dork("d2", "Backups")).await.unwrap();
+        let archive = source.create_backup(None).await.unwrap();
+
+        let target = VaultService::open_in_memory().unwrap();
+        target.save_dork(sample_dork("d1", "Renamed locally")).await.unwrap();
+
+        let report = target.restore_backup(&archive, None, RestoreMode::Merge).await.unwrap();
+
+        let dorks = report.tables.iter().find(|t| t.name == "dorks").unwrap();
+        assert_eq!(dorks.inserted, 1);
+        assert_eq!(dorks.conflicts, 1);
+        assert!(report.conflicts.iter().any(|c| c.table == "dorks" && c.id == "d1"));
+
+        let kept = target.get_dork_by_id("d1").await.unwrap().unwrap();
+        assert_eq!(kept.name, "Renamed locally");
+    }
+
+    #[tokio::test]
+    async fn test_tampered_archive_is_rejected() {
+        let source = seeded_vault().await;
+        let mut archive = source.create_backup(None).await.unwrap();
+        archive.manifest.tables[0].row_count += 1;
+
+        let target = VaultService::open_in_memory().unwrap();
+        let err = target.restore_backup(&archive, None, RestoreMode::Replace).await.unwrap_err();
+        assert!(err.to_string().contains("manifest"));
+        assert!(target.get_all_dorks().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_out_of_range_iterations_are_rejected() {
+        let source = seeded_vault().await;
+        let mut archive = source.create_backup(Some("correct horse")).await.unwrap();
+
+        let target = VaultService::open_in_memory().unwrap();
+        for iterations in [1, u32::MAX] {
+            archive.encryption.as_mut().unwrap().iterations = iterations;
+            let err = target
+                .restore_backup(&archive, Some("correct horse"), RestoreMode::Merge)
+                .await
+                .unwrap_err();
+            assert!(err.to_string().contains("iterations"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_version_1_archive_is_upgraded() {
+        let blob = serde_json::json!([
+            { "id": "m1", "role": "user", "content": "find env files", "timestamp": "2024-01-01T00:00:00Z" },
+            { "id": "broken" },
+        ]);
+        let conversation = serde_json::json!({
+            "id": "c1",
+            "title": "Old",
+            "messages": blob.to_string(),
+            "created_at": "2024-01-01T00:00:00Z",
+            "updated_at": "2024-01-01T00:00:00Z",
+        });
+        let Value::Object(conversation) = conversation else { unreachable!() };
+        let tables = BTreeMap::from([("conversations".to_string(), vec![conversation])]);
+        let plaintext = serde_json::to_vec(&tables).unwrap();
+        let archive = BackupArchive {
+            format: BACKUP_FORMAT.to_string(),
+            version: 1,
+            app_version: "1.0.0".to_string(),
+            created_at: "2024-01-02T00:00:00Z".to_string(),
+            manifest: BackupManifest {
+                tables: vec![TableManifest {
+                    name: "conversations".to_string(),
+                    primary_key: "id".to_string(),
+                    row_count: 1,
+                    sha256: table_checksum(&tables["conversations"]).unwrap(),
+                }],
+                payload_sha256: sha256_hex(&plaintext),
+            },
+            encryption: None,
+            payload: BASE64.encode(&plaintext),
+        };
+
+        let vault = VaultService::open_in_memory().unwrap();
+        vault.restore_backup(&archive, None, RestoreMode::Merge).await.unwrap();
+        let messages = vault.get_conversation("c1").await.unwrap().messages;
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].content, "find env files");
+
+        let quarantined: String = vault
+            .conn
+            .lock()
+            .await
+            .query_row("SELECT data FROM conversations_legacy WHERE id = 'c1:1'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(quarantined, r#"{"id":"broken"}"#);
+    }
+}
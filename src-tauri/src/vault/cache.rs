@@ -0,0 +1,241 @@
+//! Content-addressed cache of AI responses.
+//!
+//! Entries are keyed by a hash of everything that determines a reply
+//! (provider, model, prompt version and inputs), expire after a TTL and are
+//! evicted least recently used first once the cache outgrows its size
+//! limit. The cache is derived data and is not included in backups.
+
+use super::{load_setting, VaultService};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+/// Settings key holding the [`CacheConfig`]
+const CACHE_SETTING: &str = "ai.cache";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CacheConfig {
+    pub enabled: bool,
+    pub ttl_hours: u32,
+    /// Total size of cached values before the least recently used are evicted
+    pub max_bytes: u64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            ttl_hours: 24 * 7,
+            max_bytes: 5 * 1024 * 1024,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheStats {
+    pub entries: u64,
+    pub total_bytes: u64,
+    pub hits: u64,
+    pub misses: u64,
+    /// Hits over lookups, 0 when nothing has been looked up
+    pub hit_rate: f64,
+}
+
+fn timestamp(at: DateTime<Utc>) -> String {
+    at.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+fn lookup(conn: &Connection, key: &str, now: DateTime<Utc>) -> Result<Option<String>> {
+    let config: CacheConfig = load_setting(conn, CACHE_SETTING)?;
+    if !config.enabled {
+        return Ok(None);
+    }
+
+    let now = timestamp(now);
+    let entry: Option<(String, String)> = conn
+        .query_row(
+            "SELECT value, expires_at FROM ai_response_cache WHERE key = ?1",
+            [key],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()
+        .context("Failed to look up AI cache")?;
+
+    let value = match entry {
+        Some((_, expires_at)) if expires_at <= now => {
+            conn.execute("DELETE FROM ai_response_cache WHERE key = ?1", [key])
+                .context("Failed to drop expired AI cache entry")?;
+            None
+        }
+        Some((value, _)) => {
+            conn.execute(
+                "UPDATE ai_response_cache SET hits = hits + 1, last_used_at = ?2 WHERE key = ?1",
+                params![key, now],
+            ).context("Failed to touch AI cache entry")?;
+            Some(value)
+        }
+        None => None,
+    };
+
+    let counter = if value.is_some() { "ai_cache_hits" } else { "ai_cache_misses" };
+    conn.execute(
+        &format!("UPDATE usage_stats SET {0} = {0} + 1 WHERE id = 1", counter),
+        [],
+    ).context("Failed to count AI cache lookup")?;
+
+    Ok(value)
+}
+
+fn store(
+    conn: &mut Connection,
+    key: &str,
+    provider: &str,
+    model: &str,
+    prompt_version: &str,
+    value: &str,
+    now: DateTime<Utc>,
+) -> Result<()> {
+    let config: CacheConfig = load_setting(conn, CACHE_SETTING)?;
+    if !config.enabled || value.len() as u64 > config.max_bytes {
+        return Ok(());
+    }
+
+    let tx = conn.transaction().context("Failed to start transaction")?;
+    let created_at = timestamp(now);
+    let expires_at = timestamp(now + Duration::hours(i64::from(config.ttl_hours)));
+
+    tx.execute("DELETE FROM ai_response_cache WHERE expires_at <= ?1", [&created_at])
+        .context("Failed to purge expired AI cache entries")?;
+
+    tx.execute(
+        "INSERT OR REPLACE INTO ai_response_cache
+         (key, provider, model, prompt_version, value, size_bytes, hits, created_at, last_used_at, expires_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, 0, ?7, ?7, ?8)",
+        params![key, provider, model, prompt_version, value, value.len() as i64, created_at, expires_at],
+    ).context("Failed to store AI cache entry")?;
+
+    // Keep the most recently used entries that fit in the size limit
+    tx.execute(
+        "DELETE FROM ai_response_cache WHERE key IN (
+             SELECT key FROM (
+                 SELECT key, SUM(size_bytes) OVER (ORDER BY last_used_at DESC, key) AS running
+                 FROM ai_response_cache
+             ) WHERE running > ?1
+         )",
+        [config.max_bytes as i64],
+    ).context("Failed to evict AI cache entries")?;
+
+    tx.commit().context("Failed to commit AI cache entry")?;
+    Ok(())
+}
+
+impl VaultService {
+    /// The cached value for `key`, counting the lookup as a hit or miss
+    pub async fn cache_lookup(&self, key: &str) -> Result<Option<String>> {
+        let conn = self.conn.lock().await;
+        lookup(&conn, key, Utc::now())
+    }
+
+    pub async fn cache_store(
+        &self,
+        key: &str,
+        provider: &str,
+        model: &str,
+        prompt_version: &str,
+        value: &str,
+    ) -> Result<()> {
+        let mut conn = self.conn.lock().await;
+        store(&mut conn, key, provider, model, prompt_version, value, Utc::now())
+    }
+
+    pub async fn cache_stats(&self) -> Result<CacheStats> {
+        let conn = self.conn.lock().await;
+
+        let (entries, total_bytes): (i64, i64) = conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(size_bytes), 0) FROM ai_response_cache",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).context("Failed to measure AI cache")?;
+
+        let (hits, misses): (i64, i64) = conn.query_row(
+            "SELECT ai_cache_hits, ai_cache_misses FROM usage_stats WHERE id = 1",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).context("Failed to load AI cache counters")?;
+
+        let lookups = hits + misses;
+        Ok(CacheStats {
+            entries: entries as u64,
+            total_bytes: total_bytes as u64,
+            hits: hits as u64,
+            misses: misses as u64,
+            hit_rate: if lookups > 0 { hits as f64 / lookups as f64 } else { 0.0 },
+        })
+    }
+
+    /// Drop every entry and zero the hit counters
+    pub async fn clear_cache(&self) -> Result<u64> {
+        let conn = self.conn.lock().await;
+
+        let removed = conn
+            .execute("DELETE FROM ai_response_cache", [])
+            .context("Failed to clear AI cache")?;
+        conn.execute("UPDATE usage_stats SET ai_cache_hits = 0, ai_cache_misses = 0 WHERE id = 1", [])
+            .context("Failed to reset AI cache counters")?;
+
+        tracing::info!("AI cache cleared ({} entries)", removed);
+        Ok(removed as u64)
+    }
+
+    pub async fn cache_config(&self) -> Result<CacheConfig> {
+        let conn = self.conn.lock().await;
+        load_setting(&conn, CACHE_SETTING)
+    }
+
+    pub async fn set_cache_config(&self, config: &CacheConfig) -> Result<()> {
+        if config.ttl_hours == 0 {
+            anyhow::bail!("Cache TTL must be at least one hour");
+        }
+
+        let value = serde_json::to_value(config).context("Failed to serialize cache config")?;
+        self.set_setting(CACHE_SETTING, &value).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(rfc3339: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(rfc3339).unwrap().with_timezone(&Utc)
+    }
+
+    #[tokio::test]
+    async fn test_entries_expire_and_evict_least_recently_used() {
+        let vault = VaultService::open_in_memory().unwrap();
+        vault.set_cache_config(&CacheConfig { enabled: true, ttl_hours: 1, max_bytes: 10 }).await.unwrap();
+
+        let mut conn = vault.conn.lock().await;
+        store(&mut conn, "a", "mock", "m", "v@1", "aaaa", at("2026-05-01T10:00:00Z")).unwrap();
+        store(&mut conn, "b", "mock", "m", "v@1", "bbbb", at("2026-05-01T10:01:00Z")).unwrap();
+        assert_eq!(lookup(&conn, "a", at("2026-05-01T10:02:00Z")).unwrap().as_deref(), Some("aaaa"));
+
+        // "b" is now the least recently used and no longer fits
+        store(&mut conn, "c", "mock", "m", "v@1", "cccc", at("2026-05-01T10:03:00Z")).unwrap();
+        assert_eq!(lookup(&conn, "b", at("2026-05-01T10:04:00Z")).unwrap(), None);
+        assert!(lookup(&conn, "c", at("2026-05-01T10:04:00Z")).unwrap().is_some());
+
+        // Past the TTL
+        assert_eq!(lookup(&conn, "c", at("2026-05-01T11:03:00Z")).unwrap(), None);
+        drop(conn);
+
+        let stats = vault.cache_stats().await.unwrap();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (2, 2, 1));
+        assert_eq!(stats.hit_rate, 0.5);
+
+        vault.clear_cache().await.unwrap();
+        assert_eq!(vault.cache_stats().await.unwrap().entries, 0);
+    }
+}
@@ -0,0 +1,203 @@
+//! Duplicate dork detection and merging.
+//!
+//! Merging keeps one dork, folds the other dorks' tags into it and moves the
+//! merged rows into `dork_merges` so their original names and queries stay
+//! available as history.
+
+use super::{dork_from_row, DorkQuery, VaultService};
+use crate::dorks::{find_duplicate_clusters, DuplicateCluster};
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DorkMergeRecord {
+    pub merged_id: String,
+    pub into_id: String,
+    pub name: String,
+    pub query: String,
+    pub category: String,
+    pub tags: Vec<String>,
+    pub created_at: String,
+    pub merged_at: String,
+}
+
+impl VaultService {
+    /// Report clusters of duplicate and near-duplicate dorks across the vault
+    pub async fn find_duplicate_dorks(&self, threshold: f64) -> Result<Vec<DuplicateCluster>> {
+        let dorks = self.get_all_dorks().await?;
+        Ok(find_duplicate_clusters(&dorks, threshold.clamp(0.0, 1.0)))
+    }
+
+    /// Merge `merge_ids` into `keep_id`, combining tags and recording history
+    pub async fn merge_dorks(&self, keep_id: &str, merge_ids: &[String]) -> Result<DorkQuery> {
+        let mut conn = self.conn.lock().await;
+        let tx = conn.transaction().context("Failed to start merge transaction")?;
+
+        let mut kept = load_dork(&tx, keep_id)?
+            .ok_or_else(|| anyhow::anyhow!("Dork not found: {}", keep_id))?;
+        let merged_at = chrono::Utc::now().to_rfc3339();
+        let mut removed = 0;
+
+        for merge_id in merge_ids.iter().filter(|id| id.as_str() != keep_id) {
+            let merged = load_dork(&tx, merge_id)?
+                .ok_or_else(|| anyhow::anyhow!("Dork not found: {}", merge_id))?;
+
+            for tag in &merged.tags {
+                if !kept.tags.contains(tag) {
+                    kept.tags.push(tag.clone());
+                }
+            }
+
+            let tags_json = serde_json::to_string(&merged.tags)
+                .context("Failed to serialize tags")?;
+
+            tx.execute(
+                "INSERT OR REPLACE INTO dork_merges
+                 (merged_id, into_id, name, query, category, tags, created_at, merged_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    merged.id,
+                    keep_id,
+                    merged.name,
+                    merged.query,
+                    merged.category,
+                    tags_json,
+                    merged.created_at,
+                    merged_at,
+                ],
+            ).context("Failed to record merge history")?;
+
+            // Earlier merges into the removed dork now belong to the survivor
+            tx.execute(
+                "UPDATE dork_merges SET into_id = ?1 WHERE into_id = ?2",
+                params![keep_id, merged.id],
+            ).context("Failed to re-point merge history")?;
+
+            // Chat messages follow the survivor; provenance moves over unless
+            // the survivor already has its own
+            tx.execute(
+                "UPDATE conversation_messages SET dork_id = ?1 WHERE dork_id = ?2",
+                params![keep_id, merged.id],
+            ).context("Failed to re-link messages")?;
+
+            tx.execute(
+                "UPDATE OR IGNORE dork_provenance SET dork_id = ?1 WHERE dork_id = ?2",
+                params![keep_id, merged.id],
+            ).context("Failed to move dork provenance")?;
+
+            tx.execute("DELETE FROM dork_provenance WHERE dork_id = ?1", params![merged.id])
+                .context("Failed to delete merged dork provenance")?;
+
+            removed += tx.execute("DELETE FROM dorks WHERE id = ?1", params![merged.id])
+                .context("Failed to delete merged dork")?;
+        }
+
+        kept.updated_at = Some(merged_at);
+        let tags_json = serde_json::to_string(&kept.tags)
+            .context("Failed to serialize tags")?;
+
+        tx.execute(
+            "UPDATE dorks SET tags = ?1, updated_at = ?2 WHERE id = ?3",
+            params![tags_json, kept.updated_at, kept.id],
+        ).context("Failed to update merged dork")?;
+
+        tx.execute(
+            "UPDATE usage_stats
+             SET total_dorks = (SELECT COUNT(*) FROM dorks),
+                 updated_at = datetime('now')
+             WHERE id = 1",
+            [],
+        ).context("Failed to update dork count")?;
+
+        tx.commit().context("Failed to commit merge")?;
+
+        tracing::info!("Merged {} dorks into {}", removed, keep_id);
+        Ok(kept)
+    }
+
+    /// Dorks previously merged into `dork_id`, oldest first
+    pub async fn get_dork_merge_history(&self, dork_id: &str) -> Result<Vec<DorkMergeRecord>> {
+        let conn = self.conn.lock().await;
+
+        let mut stmt = conn.prepare(
+            "SELECT merged_id, into_id, name, query, category, tags, created_at, merged_at
+             FROM dork_merges
+             WHERE into_id = ?1
+             ORDER BY merged_at ASC"
+        ).context("Failed to prepare merge history query")?;
+
+        let records = stmt.query_map([dork_id], |row| {
+            let tags_json: String = row.get(5)?;
+
+            Ok(DorkMergeRecord {
+                merged_id: row.get(0)?,
+                into_id: row.get(1)?,
+                name: row.get(2)?,
+                query: row.get(3)?,
+                category: row.get(4)?,
+                tags: serde_json::from_str(&tags_json).unwrap_or_default(),
+                created_at: row.get(6)?,
+                merged_at: row.get(7)?,
+            })
+        })
+        .context("Failed to query merge history")?
+        .collect::<Result<Vec<_>, _>>()
+        .context("Failed to collect merge history")?;
+
+        Ok(records)
+    }
+}
+
+fn load_dork(conn: &Connection, id: &str) -> Result<Option<DorkQuery>> {
+    let result = conn.query_row(
+        "SELECT id, name, query, category, tags, created_at, updated_at
+         FROM dorks WHERE id = ?1",
+        [id],
+        dork_from_row,
+    );
+
+    match result {
+        Ok(dork) => Ok(Some(dork)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e).context("Failed to load dork"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dork(id: &str, query: &str, tags: &[&str]) -> DorkQuery {
+        DorkQuery {
+            id: id.to_string(),
+            name: format!("Dork {}", id),
+            query: query.to_string(),
+            category: "config".to_string(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            created_at: chrono::Utc::now().to_rfc3339(),
+            updated_at: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_find_and_merge_duplicates() {
+        let vault = VaultService::open_in_memory().unwrap();
+        vault.save_dork(dork("a", "site:example.com ext:env", &["env"])).await.unwrap();
+        vault.save_dork(dork("b", "FILETYPE:env site:example.com", &["secrets"])).await.unwrap();
+        vault.save_dork(dork("c", "inurl:admin", &[])).await.unwrap();
+
+        let clusters = vault.find_duplicate_dorks(1.0).await.unwrap();
+        assert_eq!(clusters.len(), 1);
+        assert!(clusters[0].exact);
+
+        let kept = vault.merge_dorks("a", &["b".to_string()]).await.unwrap();
+        assert_eq!(kept.tags, vec!["env", "secrets"]);
+        assert!(vault.get_dork_by_id("b").await.unwrap().is_none());
+        assert!(vault.find_duplicate_dorks(1.0).await.unwrap().is_empty());
+
+        let history = vault.get_dork_merge_history("a").await.unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].query, "FILETYPE:env site:example.com");
+    }
+}
@@ -0,0 +1,59 @@
+//! Cached responses of enrichment connectors.
+//!
+//! Entries are keyed by the connector and the request it made (never the
+//! credentials it sent) and expire after the connector's TTL. Like the AI
+//! cache, this is derived data and is not included in backups.
+
+use super::VaultService;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use rusqlite::{params, OptionalExtension};
+
+fn timestamp(at: DateTime<Utc>) -> String {
+    at.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+impl VaultService {
+    /// The unexpired cached response for `key`
+    pub async fn enrichment_cache_get(&self, key: &str) -> Result<Option<String>> {
+        let conn = self.conn.lock().await;
+        conn.query_row(
+            "SELECT body FROM enrichment_cache WHERE key = ?1 AND expires_at > ?2",
+            params![key, timestamp(Utc::now())],
+            |row| row.get(0),
+        )
+        .optional()
+        .context("Failed to look up enrichment cache")
+    }
+
+    pub async fn enrichment_cache_put(&self, key: &str, connector: &str, body: &str, ttl_hours: u32) -> Result<()> {
+        let conn = self.conn.lock().await;
+        let now = Utc::now();
+        conn.execute("DELETE FROM enrichment_cache WHERE expires_at <= ?1", [timestamp(now)])
+            .context("Failed to purge expired enrichment cache entries")?;
+        conn.execute(
+            "INSERT OR REPLACE INTO enrichment_cache (key, connector, body, fetched_at, expires_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                key,
+                connector,
+                body,
+                timestamp(now),
+                timestamp(now + Duration::hours(i64::from(ttl_hours)))
+            ],
+        )
+        .context("Failed to store enrichment cache entry")?;
+        Ok(())
+    }
+
+    /// Drop cached responses, of one connector or all of them
+    pub async fn clear_enrichment_cache(&self, connector: Option<&str>) -> Result<usize> {
+        let conn = self.conn.lock().await;
+        let removed = match connector {
+            Some(connector) => conn.execute("DELETE FROM enrichment_cache WHERE connector = ?1", [connector]),
+            None => conn.execute("DELETE FROM enrichment_cache", []),
+        }
+        .context("Failed to clear enrichment cache")?;
+        Ok(removed)
+    }
+}
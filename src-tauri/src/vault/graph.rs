@@ -0,0 +1,779 @@
+//! Persistent entity graph behind the Nexus graph view.
+//!
+//! Entities (domains, IPs, services, ...) and the typed relationships
+//! between them are kept per workspace. Upserting the same entity or
+//! relationship again merges its properties, widens its first/last seen
+//! window and records which dork, tool or import observed it.
+
+use super::VaultService;
+use anyhow::{Context, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntityType {
+    Domain,
+    Subdomain,
+    Ip,
+    Cidr,
+    Url,
+    Email,
+    Hash,
+    Technology,
+    Service,
+    Organization,
+    Person,
+}
+
+impl EntityType {
+    pub const ALL: &'static [EntityType] = &[
+        EntityType::Domain,
+        EntityType::Subdomain,
+        EntityType::Ip,
+        EntityType::Cidr,
+        EntityType::Url,
+        EntityType::Email,
+        EntityType::Hash,
+        EntityType::Technology,
+        EntityType::Service,
+        EntityType::Organization,
+        EntityType::Person,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EntityType::Domain => "domain",
+            EntityType::Subdomain => "subdomain",
+            EntityType::Ip => "ip",
+            EntityType::Cidr => "cidr",
+            EntityType::Url => "url",
+            EntityType::Email => "email",
+            EntityType::Hash => "hash",
+            EntityType::Technology => "technology",
+            EntityType::Service => "service",
+            EntityType::Organization => "organization",
+            EntityType::Person => "person",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|t| t.as_str() == value)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RelationType {
+    /// Subdomain to its parent domain
+    SubdomainOf,
+    /// Host name to an address (A/AAAA)
+    ResolvesTo,
+    /// Host name to its canonical name (CNAME)
+    AliasOf,
+    /// Domain to a mail exchanger (MX)
+    MailServer,
+    /// Domain to a name server (NS)
+    NameServer,
+    /// Address or host to a service running on it
+    Hosts,
+    /// Host or service to a technology it runs
+    Uses,
+    /// Network range to an address inside it
+    Contains,
+    LinksTo,
+    RelatedTo,
+}
+
+impl RelationType {
+    pub const ALL: &'static [RelationType] = &[
+        RelationType::SubdomainOf,
+        RelationType::ResolvesTo,
+        RelationType::AliasOf,
+        RelationType::MailServer,
+        RelationType::NameServer,
+        RelationType::Hosts,
+        RelationType::Uses,
+        RelationType::Contains,
+        RelationType::LinksTo,
+        RelationType::RelatedTo,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RelationType::SubdomainOf => "subdomain_of",
+            RelationType::ResolvesTo => "resolves_to",
+            RelationType::AliasOf => "alias_of",
+            RelationType::MailServer => "mail_server",
+            RelationType::NameServer => "name_server",
+            RelationType::Hosts => "hosts",
+            RelationType::Uses => "uses",
+            RelationType::Contains => "contains",
+            RelationType::LinksTo => "links_to",
+            RelationType::RelatedTo => "related_to",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|t| t.as_str() == value)
+    }
+}
+
+/// What kind of thing observed an entity or relationship
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceKind {
+    Dork,
+    Tool,
+    Import,
+    Ai,
+    Manual,
+}
+
+impl SourceKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            SourceKind::Dork => "dork",
+            SourceKind::Tool => "tool",
+            SourceKind::Import => "import",
+            SourceKind::Ai => "ai",
+            SourceKind::Manual => "manual",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        [SourceKind::Dork, SourceKind::Tool, SourceKind::Import, SourceKind::Ai, SourceKind::Manual]
+            .into_iter()
+            .find(|k| k.as_str() == value)
+    }
+}
+
+/// The dork id, tool name, import file, ... behind an observation
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct GraphSource {
+    pub kind: SourceKind,
+    pub reference: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Provenance {
+    pub kind: SourceKind,
+    pub reference: String,
+    pub first_seen: String,
+    pub last_seen: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Entity {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub entity_type: EntityType,
+    pub value: String,
+    pub properties: Map<String, Value>,
+    pub sources: Vec<Provenance>,
+    pub first_seen: String,
+    pub last_seen: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Relationship {
+    pub id: String,
+    pub source_id: String,
+    pub target_id: String,
+    #[serde(rename = "type")]
+    pub relation_type: RelationType,
+    pub properties: Map<String, Value>,
+    pub sources: Vec<Provenance>,
+    pub first_seen: String,
+    pub last_seen: String,
+}
+
+/// Entities and the relationships among them
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Subgraph {
+    pub entities: Vec<Entity>,
+    pub relationships: Vec<Relationship>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct EntityKey {
+    #[serde(rename = "type")]
+    pub entity_type: EntityType,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntityInput {
+    #[serde(rename = "type")]
+    pub entity_type: EntityType,
+    pub value: String,
+    #[serde(default)]
+    pub properties: Map<String, Value>,
+    /// Overrides the batch's observation time, e.g. for imported items
+    #[serde(default)]
+    pub seen: Option<Seen>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RelationshipInput {
+    pub source: EntityKey,
+    pub target: EntityKey,
+    #[serde(rename = "type")]
+    pub relation_type: RelationType,
+    #[serde(default)]
+    pub properties: Map<String, Value>,
+    /// Overrides the batch's observation time, e.g. for imported items
+    #[serde(default)]
+    pub seen: Option<Seen>,
+}
+
+/// When an item was first and last observed (RFC 3339)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Seen {
+    pub first: String,
+    pub last: String,
+}
+
+/// Observations from one source, written together
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphBatch {
+    pub source: GraphSource,
+    /// RFC 3339; defaults to now
+    #[serde(default)]
+    pub observed_at: Option<String>,
+    #[serde(default)]
+    pub entities: Vec<EntityInput>,
+    /// Endpoints are created if they don't exist yet
+    #[serde(default)]
+    pub relationships: Vec<RelationshipInput>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GraphUpsertReport {
+    pub entities_created: usize,
+    pub entities_updated: usize,
+    pub relationships_created: usize,
+    pub relationships_updated: usize,
+}
+
+impl std::ops::AddAssign for GraphUpsertReport {
+    fn add_assign(&mut self, other: Self) {
+        self.entities_created += other.entities_created;
+        self.entities_updated += other.entities_updated;
+        self.relationships_created += other.relationships_created;
+        self.relationships_updated += other.relationships_updated;
+    }
+}
+
+impl EntityKey {
+    pub fn new(entity_type: EntityType, value: impl Into<String>) -> Self {
+        Self {
+            entity_type,
+            value: value.into(),
+        }
+    }
+}
+
+/// Canonical form of a value, so the same entity isn't stored twice
+pub fn normalize_value(entity_type: EntityType, value: &str) -> String {
+    let value = value.trim();
+    match entity_type {
+        EntityType::Domain | EntityType::Subdomain => crate::indicators::normalize_domain(value)
+            .unwrap_or_else(|| value.trim_end_matches('.').to_lowercase()),
+        EntityType::Ip => value.parse::<std::net::IpAddr>().map_or_else(|_| value.to_string(), |ip| ip.to_string()),
+        EntityType::Email | EntityType::Hash => value.to_lowercase(),
+        _ => value.to_string(),
+    }
+}
+
+fn timestamp(observed_at: Option<&str>) -> Result<String> {
+    let at = match observed_at {
+        Some(at) => DateTime::parse_from_rfc3339(at)
+            .with_context(|| format!("Invalid observation time: {}", at))?
+            .with_timezone(&Utc),
+        None => Utc::now(),
+    };
+    Ok(at.to_rfc3339_opts(SecondsFormat::Micros, true))
+}
+
+/// Normalized first and last observation times of an item
+fn seen_range(seen: Option<&Seen>, at: &str) -> Result<(String, String)> {
+    let Some(seen) = seen else {
+        return Ok((at.to_string(), at.to_string()));
+    };
+    let (first, last) = (timestamp(Some(&seen.first))?, timestamp(Some(&seen.last))?);
+    Ok(if first <= last { (first, last) } else { (last, first) })
+}
+
+fn merge_properties(existing: &str, update: &Map<String, Value>) -> Result<String> {
+    let mut properties: Map<String, Value> =
+        serde_json::from_str(existing).context("Failed to parse stored graph properties")?;
+    properties.extend(update.iter().map(|(k, v)| (k.clone(), v.clone())));
+    serde_json::to_string(&properties).context("Failed to serialize graph properties")
+}
+
+fn record_source(
+    conn: &Connection,
+    workspace: &str,
+    subject_id: &str,
+    source: &GraphSource,
+    (first, last): (&str, &str),
+) -> Result<()> {
+    conn.execute(
+        "INSERT INTO graph_provenance (id, workspace, subject_id, kind, reference, first_seen, last_seen)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+         ON CONFLICT(subject_id, kind, reference) DO UPDATE SET
+            first_seen = MIN(first_seen, excluded.first_seen),
+            last_seen = MAX(last_seen, excluded.last_seen)",
+        params![
+            uuid::Uuid::new_v4().to_string(),
+            workspace,
+            subject_id,
+            source.kind.as_str(),
+            source.reference,
+            first,
+            last,
+        ],
+    ).context("Failed to record graph provenance")?;
+    Ok(())
+}
+
+/// Insert or merge an entity; returns its id and whether it was created
+fn upsert_entity(
+    conn: &Connection,
+    workspace: &str,
+    input: &EntityInput,
+    source: &GraphSource,
+    at: &str,
+) -> Result<(String, bool)> {
+    let (first, last) = seen_range(input.seen.as_ref(), at)?;
+    let value = normalize_value(input.entity_type, &input.value);
+    if value.is_empty() {
+        anyhow::bail!("A {} entity needs a value", input.entity_type.as_str());
+    }
+
+    let existing: Option<(String, String)> = conn
+        .query_row(
+            "SELECT id, properties FROM graph_entities WHERE workspace = ?1 AND entity_type = ?2 AND value = ?3",
+            params![workspace, input.entity_type.as_str(), value],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()
+        .context("Failed to look up graph entity")?;
+
+    let (id, created) = match existing {
+        Some((id, properties)) => {
+            conn.execute(
+                "UPDATE graph_entities
+                 SET properties = ?2, first_seen = MIN(first_seen, ?3), last_seen = MAX(last_seen, ?4)
+                 WHERE id = ?1",
+                params![id, merge_properties(&properties, &input.properties)?, first, last],
+            ).context("Failed to update graph entity")?;
+            (id, false)
+        }
+        None => {
+            let id = uuid::Uuid::new_v4().to_string();
+            conn.execute(
+                "INSERT INTO graph_entities (id, workspace, entity_type, value, properties, first_seen, last_seen)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    id,
+                    workspace,
+                    input.entity_type.as_str(),
+                    value,
+                    serde_json::to_string(&input.properties).context("Failed to serialize graph properties")?,
+                    first,
+                    last,
+                ],
+            ).context("Failed to insert graph entity")?;
+            (id, true)
+        }
+    };
+
+    record_source(conn, workspace, &id, source, (&first, &last))?;
+    Ok((id, created))
+}
+
+fn upsert_relationship(
+    conn: &Connection,
+    workspace: &str,
+    source_id: &str,
+    target_id: &str,
+    input: &RelationshipInput,
+    source: &GraphSource,
+    at: &str,
+) -> Result<bool> {
+    let (first, last) = seen_range(input.seen.as_ref(), at)?;
+    let existing: Option<(String, String)> = conn
+        .query_row(
+            "SELECT id, properties FROM graph_relationships
+             WHERE workspace = ?1 AND source_id = ?2 AND target_id = ?3 AND relation_type = ?4",
+            params![workspace, source_id, target_id, input.relation_type.as_str()],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()
+        .context("Failed to look up graph relationship")?;
+
+    let (id, created) = match existing {
+        Some((id, properties)) => {
+            conn.execute(
+                "UPDATE graph_relationships
+                 SET properties = ?2, first_seen = MIN(first_seen, ?3), last_seen = MAX(last_seen, ?4)
+                 WHERE id = ?1",
+                params![id, merge_properties(&properties, &input.properties)?, first, last],
+            ).context("Failed to update graph relationship")?;
+            (id, false)
+        }
+        None => {
+            let id = uuid::Uuid::new_v4().to_string();
+            conn.execute(
+                "INSERT INTO graph_relationships
+                 (id, workspace, source_id, target_id, relation_type, properties, first_seen, last_seen)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    id,
+                    workspace,
+                    source_id,
+                    target_id,
+                    input.relation_type.as_str(),
+                    serde_json::to_string(&input.properties).context("Failed to serialize graph properties")?,
+                    first,
+                    last,
+                ],
+            ).context("Failed to insert graph relationship")?;
+            (id, true)
+        }
+    };
+
+    record_source(conn, workspace, &id, source, (&first, &last))?;
+    Ok(created)
+}
+
+fn parse_properties(json: String) -> rusqlite::Result<Map<String, Value>> {
+    serde_json::from_str(&json)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, Box::new(e)))
+}
+
+fn unknown_type(value: &str) -> rusqlite::Error {
+    rusqlite::Error::FromSqlConversionFailure(
+        0,
+        rusqlite::types::Type::Text,
+        format!("Unknown graph type: {}", value).into(),
+    )
+}
+
+/// The whole workspace graph, or only `around` and its direct neighbors
+fn load_subgraph(conn: &Connection, workspace: &str, around: Option<&str>) -> Result<Subgraph> {
+    let mut sources: HashMap<String, Vec<Provenance>> = HashMap::new();
+    // Only the sources of the entities and relationships loaded below
+    let mut stmt = conn.prepare(
+        "SELECT subject_id, kind, reference, first_seen, last_seen
+         FROM graph_provenance
+         WHERE workspace = ?1
+           AND (?2 IS NULL OR subject_id = ?2
+                OR subject_id IN (SELECT id FROM graph_relationships WHERE source_id = ?2 OR target_id = ?2)
+                OR subject_id IN (SELECT target_id FROM graph_relationships WHERE source_id = ?2)
+                OR subject_id IN (SELECT source_id FROM graph_relationships WHERE target_id = ?2))
+         ORDER BY first_seen, kind, reference"
+    ).context("Failed to prepare graph provenance query")?;
+    let rows = stmt.query_map(params![workspace, around], |row| {
+        let kind: String = row.get(1)?;
+        Ok((
+            row.get::<_, String>(0)?,
+            Provenance {
+                kind: SourceKind::parse(&kind).ok_or_else(|| unknown_type(&kind))?,
+                reference: row.get(2)?,
+                first_seen: row.get(3)?,
+                last_seen: row.get(4)?,
+            },
+        ))
+    }).context("Failed to query graph provenance")?;
+    for row in rows {
+        let (subject_id, provenance) = row.context("Failed to read graph provenance")?;
+        sources.entry(subject_id).or_default().push(provenance);
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT id, entity_type, value, properties, first_seen, last_seen
+         FROM graph_entities
+         WHERE workspace = ?1
+           AND (?2 IS NULL OR id = ?2
+                OR id IN (SELECT target_id FROM graph_relationships WHERE source_id = ?2)
+                OR id IN (SELECT source_id FROM graph_relationships WHERE target_id = ?2))
+         ORDER BY entity_type, value"
+    ).context("Failed to prepare graph entities query")?;
+    let entities = stmt.query_map(params![workspace, around], |row| {
+        let entity_type: String = row.get(1)?;
+        Ok(Entity {
+            id: row.get(0)?,
+            entity_type: EntityType::parse(&entity_type).ok_or_else(|| unknown_type(&entity_type))?,
+            value: row.get(2)?,
+            properties: parse_properties(row.get(3)?)?,
+            sources: Vec::new(),
+            first_seen: row.get(4)?,
+            last_seen: row.get(5)?,
+        })
+    })
+    .context("Failed to query graph entities")?
+    .collect::<Result<Vec<_>, _>>()
+    .context("Failed to collect graph entities")?;
+
+    let mut stmt = conn.prepare(
+        "SELECT id, source_id, target_id, relation_type, properties, first_seen, last_seen
+         FROM graph_relationships
+         WHERE workspace = ?1 AND (?2 IS NULL OR source_id = ?2 OR target_id = ?2)
+         ORDER BY first_seen, id"
+    ).context("Failed to prepare graph relationships query")?;
+    let relationships = stmt.query_map(params![workspace, around], |row| {
+        let relation_type: String = row.get(3)?;
+        Ok(Relationship {
+            id: row.get(0)?,
+            source_id: row.get(1)?,
+            target_id: row.get(2)?,
+            relation_type: RelationType::parse(&relation_type).ok_or_else(|| unknown_type(&relation_type))?,
+            properties: parse_properties(row.get(4)?)?,
+            sources: Vec::new(),
+            first_seen: row.get(5)?,
+            last_seen: row.get(6)?,
+        })
+    })
+    .context("Failed to query graph relationships")?
+    .collect::<Result<Vec<_>, _>>()
+    .context("Failed to collect graph relationships")?;
+
+    let mut subgraph = Subgraph { entities, relationships };
+    for entity in &mut subgraph.entities {
+        entity.sources = sources.remove(&entity.id).unwrap_or_default();
+    }
+    for relationship in &mut subgraph.relationships {
+        relationship.sources = sources.remove(&relationship.id).unwrap_or_default();
+    }
+    Ok(subgraph)
+}
+
+impl VaultService {
+    /// Merge a batch of observations into a workspace graph
+    pub async fn upsert_graph(&self, workspace: &str, batch: &GraphBatch) -> Result<GraphUpsertReport> {
+        if batch.source.reference.trim().is_empty() {
+            anyhow::bail!("Graph observations need a source reference");
+        }
+        let at = timestamp(batch.observed_at.as_deref())?;
+
+        let mut conn = self.conn.lock().await;
+        let tx = conn.transaction().context("Failed to start transaction")?;
+        let mut report = GraphUpsertReport::default();
+        let mut ids: HashMap<EntityKey, String> = HashMap::new();
+
+        let endpoints = batch.relationships.iter().flat_map(|r| {
+            [&r.source, &r.target].map(|key| EntityInput {
+                entity_type: key.entity_type,
+                value: key.value.clone(),
+                properties: Map::new(),
+                seen: None,
+            })
+        });
+        for input in batch.entities.iter().cloned().chain(endpoints) {
+            let key = EntityKey::new(input.entity_type, normalize_value(input.entity_type, &input.value));
+            if ids.contains_key(&key) {
+                continue;
+            }
+            let (id, created) = upsert_entity(&tx, workspace, &input, &batch.source, &at)?;
+            if created {
+                report.entities_created += 1;
+            } else {
+                report.entities_updated += 1;
+            }
+            ids.insert(key, id);
+        }
+
+        for relationship in &batch.relationships {
+            let id_of = |key: &EntityKey| {
+                ids[&EntityKey::new(key.entity_type, normalize_value(key.entity_type, &key.value))].clone()
+            };
+            let (source_id, target_id) = (id_of(&relationship.source), id_of(&relationship.target));
+            if source_id == target_id {
+                anyhow::bail!("A relationship can't connect {} to itself", relationship.source.value);
+            }
+
+            if upsert_relationship(&tx, workspace, &source_id, &target_id, relationship, &batch.source, &at)? {
+                report.relationships_created += 1;
+            } else {
+                report.relationships_updated += 1;
+            }
+        }
+
+        tx.commit().context("Failed to commit graph batch")?;
+
+        tracing::debug!("Graph batch from {} merged into {}: {:?}", batch.source.reference, workspace, report);
+        Ok(report)
+    }
+
+    /// Every entity and relationship in a workspace
+    pub async fn load_graph(&self, workspace: &str) -> Result<Subgraph> {
+        let conn = self.conn.lock().await;
+        load_subgraph(&conn, workspace, None)
+    }
+
+    /// An entity with its direct neighbors, optionally only along some
+    /// relationship types
+    pub async fn entity_neighbors(
+        &self,
+        workspace: &str,
+        entity_id: &str,
+        relation_types: &[RelationType],
+    ) -> Result<Subgraph> {
+        let conn = self.conn.lock().await;
+        let mut subgraph = load_subgraph(&conn, workspace, Some(entity_id))?;
+        if !subgraph.entities.iter().any(|e| e.id == entity_id) {
+            anyhow::bail!("Entity not found: {}", entity_id);
+        }
+
+        if !relation_types.is_empty() {
+            subgraph.relationships.retain(|r| relation_types.contains(&r.relation_type));
+            let connected: HashSet<&str> = subgraph
+                .relationships
+                .iter()
+                .flat_map(|r| [r.source_id.as_str(), r.target_id.as_str()])
+                .collect();
+            let keep: HashSet<String> = subgraph
+                .entities
+                .iter()
+                .filter(|e| e.id == entity_id || connected.contains(e.id.as_str()))
+                .map(|e| e.id.clone())
+                .collect();
+            subgraph.entities.retain(|e| keep.contains(&e.id));
+        }
+        Ok(subgraph)
+    }
+
+    pub async fn find_entity(&self, workspace: &str, key: &EntityKey) -> Result<Option<Entity>> {
+        let conn = self.conn.lock().await;
+        let id: Option<String> = conn
+            .query_row(
+                "SELECT id FROM graph_entities WHERE workspace = ?1 AND entity_type = ?2 AND value = ?3",
+                params![workspace, key.entity_type.as_str(), normalize_value(key.entity_type, &key.value)],
+                |row| row.get(0),
+            )
+            .optional()
+            .context("Failed to look up graph entity")?;
+
+        let Some(id) = id else {
+            return Ok(None);
+        };
+        Ok(load_subgraph(&conn, workspace, Some(&id))?.entities.into_iter().find(|e| e.id == id))
+    }
+
+    /// Delete a workspace's graph; returns how many entities were removed
+    pub async fn clear_graph(&self, workspace: &str) -> Result<usize> {
+        let mut conn = self.conn.lock().await;
+        let tx = conn.transaction().context("Failed to start transaction")?;
+
+        tx.execute("DELETE FROM graph_provenance WHERE workspace = ?1", [workspace])
+            .context("Failed to clear graph provenance")?;
+        tx.execute("DELETE FROM graph_relationships WHERE workspace = ?1", [workspace])
+            .context("Failed to clear graph relationships")?;
+        let removed = tx
+            .execute("DELETE FROM graph_entities WHERE workspace = ?1", [workspace])
+            .context("Failed to clear graph entities")?;
+
+        tx.commit().context("Failed to commit graph clear")?;
+
+        tracing::info!("Graph for workspace {} cleared ({} entities)", workspace, removed);
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn batch(reference: &str, observed_at: &str) -> GraphBatch {
+        GraphBatch {
+            source: GraphSource { kind: SourceKind::Tool, reference: reference.to_string() },
+            observed_at: Some(observed_at.to_string()),
+            entities: vec![],
+            relationships: vec![],
+        }
+    }
+
+    fn resolves(host: &str, ip: &str) -> RelationshipInput {
+        RelationshipInput {
+            source: EntityKey::new(EntityType::Subdomain, host),
+            target: EntityKey::new(EntityType::Ip, ip),
+            relation_type: RelationType::ResolvesTo,
+            properties: Map::new(),
+            seen: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_upserts_merge_observations() {
+        let vault = VaultService::open_in_memory().unwrap();
+
+        let mut first = batch("dns", "2026-05-02T10:00:00Z");
+        first.entities.push(EntityInput {
+            entity_type: EntityType::Subdomain,
+            value: "WWW.Example.com.".to_string(),
+            properties: serde_json::json!({"status": 200}).as_object().unwrap().clone(),
+            seen: None,
+        });
+        first.relationships.push(resolves("www.example.com", "93.184.216.34"));
+        let report = vault.upsert_graph("default", &first).await.unwrap();
+        assert_eq!(report, GraphUpsertReport { entities_created: 2, relationships_created: 1, ..Default::default() });
+
+        let mut earlier = batch("crt.sh", "2026-04-01T08:00:00+02:00");
+        earlier.relationships.push(resolves("www.example.com", "93.184.216.34"));
+        earlier.relationships[0].properties.insert("ttl".to_string(), 300.into());
+        let report = vault.upsert_graph("default", &earlier).await.unwrap();
+        assert_eq!(report.entities_updated, 2);
+        assert_eq!(report.relationships_updated, 1);
+
+        let graph = vault.load_graph("default").await.unwrap();
+        assert_eq!((graph.entities.len(), graph.relationships.len()), (2, 1));
+        let host = vault
+            .find_entity("default", &EntityKey::new(EntityType::Subdomain, "www.example.com"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(host.first_seen, "2026-04-01T06:00:00.000000Z");
+        assert_eq!(host.last_seen, "2026-05-02T10:00:00.000000Z");
+        assert_eq!(host.properties["status"], 200);
+        let references: Vec<&str> = host.sources.iter().map(|s| s.reference.as_str()).collect();
+        assert_eq!(references, vec!["crt.sh", "dns"]);
+        assert_eq!(graph.relationships[0].properties["ttl"], 300);
+
+        assert!(vault.load_graph("other").await.unwrap().entities.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_neighbors_filter_by_relation() {
+        let vault = VaultService::open_in_memory().unwrap();
+        let mut observations = batch("dns", "2026-05-02T10:00:00Z");
+        observations.relationships.push(resolves("a.example.com", "10.0.0.1"));
+        observations.relationships.push(resolves("b.example.com", "10.0.0.1"));
+        observations.relationships.push(RelationshipInput {
+            source: EntityKey::new(EntityType::Ip, "10.0.0.1"),
+            target: EntityKey::new(EntityType::Service, "https/443"),
+            relation_type: RelationType::Hosts,
+            properties: Map::new(),
+            seen: None,
+        });
+        vault.upsert_graph("default", &observations).await.unwrap();
+
+        let ip = vault.find_entity("default", &EntityKey::new(EntityType::Ip, "10.0.0.1")).await.unwrap().unwrap();
+        let all = vault.entity_neighbors("default", &ip.id, &[]).await.unwrap();
+        assert_eq!((all.entities.len(), all.relationships.len()), (4, 3));
+        assert!(all.entities.iter().all(|e| e.sources.len() == 1));
+        assert!(all.relationships.iter().all(|r| r.sources.len() == 1));
+
+        let resolved = vault.entity_neighbors("default", &ip.id, &[RelationType::ResolvesTo]).await.unwrap();
+        assert_eq!(resolved.entities.len(), 3);
+        assert!(resolved.entities.iter().all(|e| e.entity_type != EntityType::Service));
+
+        assert_eq!(vault.clear_graph("default").await.unwrap(), 4);
+        assert!(vault.entity_neighbors("default", &ip.id, &[]).await.is_err());
+    }
+}
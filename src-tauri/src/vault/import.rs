@@ -0,0 +1,492 @@
+//! Dork import from external sources.
+//!
+//! Supports Exploit-DB Google Hacking Database dumps (CSV and XML), generic
+//! CSV with a caller-supplied column mapping, and newline-delimited query
+//! lists. Every row is checked against the vault (and earlier rows of the
+//! same import) by normalized query text, and problems are reported per row
+//! instead of aborting the whole import.
+
+use super::{insert_dork, DorkQuery, VaultService};
+use crate::dorks::normalize_query;
+use anyhow::{Context, Result};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const GHDB_QUERY_COLUMNS: &[&str] = &["query", "dork", "google dork", "url_title"];
+const GHDB_NAME_COLUMNS: &[&str] = &["short_description", "shortdescription", "description", "title"];
+const GHDB_CATEGORY_COLUMNS: &[&str] = &["category"];
+const GHDB_ID_COLUMNS: &[&str] = &["id", "ghdb_id", "ghdb id"];
+const GHDB_DATE_COLUMNS: &[&str] = &["date", "date_published"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ImportFormat {
+    GhdbCsv,
+    GhdbXml,
+    Csv { mapping: CsvColumnMapping },
+    Text,
+}
+
+/// Column names (matched case-insensitively) for a generic CSV import
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CsvColumnMapping {
+    pub query: String,
+    pub name: Option<String>,
+    pub category: Option<String>,
+    pub tags: Option<String>,
+    /// Separator used inside the tags column (defaults to `,`)
+    pub tag_separator: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImportOptions {
+    /// Parse and deduplicate without writing anything
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Category for rows that don't provide one
+    pub default_category: Option<String>,
+    /// Tags added to every imported dork
+    #[serde(default)]
+    pub extra_tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportRowError {
+    pub row: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportDuplicate {
+    pub row: usize,
+    pub query: String,
+    /// Existing vault dork, or `None` when the duplicate is within the import itself
+    pub existing_id: Option<String>,
+    pub duplicate_of_row: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub total_rows: usize,
+    pub imported: Vec<DorkQuery>,
+    pub duplicates: Vec<ImportDuplicate>,
+    pub errors: Vec<ImportRowError>,
+}
+
+/// A row as read from the source, before validation
+#[derive(Debug, Default)]
+struct RawRow {
+    row: usize,
+    query: Option<String>,
+    name: Option<String>,
+    category: Option<String>,
+    tags: Vec<String>,
+    created_at: Option<String>,
+}
+
+impl VaultService {
+    /// Import dorks from an external source, skipping duplicates
+    pub async fn import_dorks(
+        &self,
+        content: &str,
+        format: &ImportFormat,
+        options: &ImportOptions,
+    ) -> Result<ImportReport> {
+        let mut errors = Vec::new();
+        let rows = match format {
+            ImportFormat::GhdbCsv => parse_ghdb_csv(content, &mut errors)?,
+            ImportFormat::GhdbXml => parse_ghdb_xml(content)?,
+            ImportFormat::Csv { mapping } => parse_mapped_csv(content, mapping, &mut errors)?,
+            ImportFormat::Text => parse_text(content),
+        };
+
+        // Checked and written under one transaction, so a dork saved meanwhile can't slip past
+        // the duplicate check. All or nothing, so a failure can't leave half an import behind.
+        let mut conn = self.conn.lock().await;
+        let tx = conn.transaction().context("Failed to start import transaction")?;
+        let existing = existing_queries(&tx)?;
+
+        let total_rows = rows.len() + errors.len();
+        let mut seen: HashMap<String, usize> = HashMap::new();
+        let mut imported = Vec::new();
+        let mut duplicates = Vec::new();
+
+        for raw in rows {
+            let query = match raw.query.as_deref().map(str::trim) {
+                Some(q) if !q.is_empty() => q.to_string(),
+                _ => {
+                    errors.push(ImportRowError {
+                        row: raw.row,
+                        message: "Missing query".to_string(),
+                    });
+                    continue;
+                }
+            };
+
+            let normalized = normalize_query(&query);
+
+            if let Some(existing_id) = existing.get(&normalized) {
+                duplicates.push(ImportDuplicate {
+                    row: raw.row,
+                    query,
+                    existing_id: Some(existing_id.clone()),
+                    duplicate_of_row: None,
+                });
+                continue;
+            }
+
+            if let Some(first_row) = seen.get(&normalized) {
+                duplicates.push(ImportDuplicate {
+                    row: raw.row,
+                    query,
+                    existing_id: None,
+                    duplicate_of_row: Some(*first_row),
+                });
+                continue;
+            }
+            seen.insert(normalized, raw.row);
+
+            let mut tags = raw.tags;
+            for tag in &options.extra_tags {
+                if !tags.contains(tag) {
+                    tags.push(tag.clone());
+                }
+            }
+
+            imported.push(DorkQuery {
+                id: uuid::Uuid::new_v4().to_string(),
+                name: raw
+                    .name
+                    .filter(|n| !n.trim().is_empty())
+                    .unwrap_or_else(|| truncate_name(&query)),
+                category: raw
+                    .category
+                    .filter(|c| !c.trim().is_empty())
+                    .or_else(|| options.default_category.clone())
+                    .unwrap_or_else(|| "Imported".to_string()),
+                query,
+                tags,
+                created_at: raw.created_at.unwrap_or_else(|| chrono::Utc::now().to_rfc3339()),
+                updated_at: None,
+            });
+        }
+
+        errors.sort_by_key(|e| e.row);
+
+        if !options.dry_run {
+            for dork in &imported {
+                insert_dork(&tx, dork)?;
+            }
+            tx.commit().context("Failed to commit import")?;
+            tracing::info!(
+                "Imported {} dorks ({} duplicates, {} errors)",
+                imported.len(),
+                duplicates.len(),
+                errors.len()
+            );
+        }
+
+        Ok(ImportReport {
+            dry_run: options.dry_run,
+            total_rows,
+            imported,
+            duplicates,
+            errors,
+        })
+    }
+}
+
+/// Ids of the vault's dorks, keyed by normalized query
+fn existing_queries(conn: &Connection) -> Result<HashMap<String, String>> {
+    let mut stmt = conn.prepare("SELECT id, query FROM dorks")?;
+    let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+
+    let mut existing = HashMap::new();
+    for row in rows {
+        let (id, query) = row.context("Failed to read existing dorks")?;
+        existing.insert(normalize_query(&query), id);
+    }
+    Ok(existing)
+}
+
+fn parse_ghdb_csv(content: &str, errors: &mut Vec<ImportRowError>) -> Result<Vec<RawRow>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(content.as_bytes());
+
+    let headers = normalized_headers(&mut reader)?;
+    let query_col = find_column(&headers, GHDB_QUERY_COLUMNS)
+        .ok_or_else(|| anyhow::anyhow!("GHDB CSV has no query/dork column"))?;
+    let name_col = find_column(&headers, GHDB_NAME_COLUMNS).filter(|c| *c != query_col);
+    let category_col = find_column(&headers, GHDB_CATEGORY_COLUMNS);
+    let id_col = find_column(&headers, GHDB_ID_COLUMNS);
+    let date_col = find_column(&headers, GHDB_DATE_COLUMNS);
+
+    let mut rows = Vec::new();
+    for (index, record) in reader.records().enumerate() {
+        let row = index + 2;
+        let record = match record {
+            Ok(r) => r,
+            Err(e) => {
+                errors.push(ImportRowError { row, message: e.to_string() });
+                continue;
+            }
+        };
+
+        let field = |col: Option<usize>| col.and_then(|c| record.get(c)).map(str::to_string);
+
+        rows.push(RawRow {
+            row,
+            query: field(Some(query_col)),
+            name: field(name_col),
+            category: field(category_col),
+            tags: ghdb_tags(field(id_col).as_deref()),
+            created_at: field(date_col).and_then(|d| parse_date(&d)),
+        });
+    }
+
+    Ok(rows)
+}
+
+fn parse_ghdb_xml(content: &str) -> Result<Vec<RawRow>> {
+    let doc = roxmltree::Document::parse(content).context("Failed to parse GHDB XML")?;
+
+    let rows = doc
+        .descendants()
+        .filter(|n| n.has_tag_name("entry"))
+        .enumerate()
+        .map(|(index, entry)| {
+            let child = |name: &str| {
+                entry
+                    .children()
+                    .find(|c| c.tag_name().name().eq_ignore_ascii_case(name))
+                    .and_then(|c| c.text())
+                    .map(|t| t.trim().to_string())
+                    .filter(|t| !t.is_empty())
+            };
+
+            RawRow {
+                row: index + 1,
+                query: child("query"),
+                name: child("shortDescription").or_else(|| child("title")),
+                category: child("category"),
+                tags: ghdb_tags(child("id").as_deref()),
+                created_at: child("date").and_then(|d| parse_date(&d)),
+            }
+        })
+        .collect();
+
+    Ok(rows)
+}
+
+fn parse_mapped_csv(
+    content: &str,
+    mapping: &CsvColumnMapping,
+    errors: &mut Vec<ImportRowError>,
+) -> Result<Vec<RawRow>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(content.as_bytes());
+
+    let headers = normalized_headers(&mut reader)?;
+    let column = |name: &Option<String>| -> Result<Option<usize>> {
+        match name {
+            Some(name) => find_column(&headers, &[name.to_lowercase().as_str()])
+                .map(Some)
+                .ok_or_else(|| anyhow::anyhow!("CSV has no column named {:?}", name)),
+            None => Ok(None),
+        }
+    };
+
+    let query_col = column(&Some(mapping.query.clone()))?.unwrap_or_default();
+    let name_col = column(&mapping.name)?;
+    let category_col = column(&mapping.category)?;
+    let tags_col = column(&mapping.tags)?;
+    let separator = mapping.tag_separator.as_deref().unwrap_or(",");
+
+    let mut rows = Vec::new();
+    for (index, record) in reader.records().enumerate() {
+        let row = index + 2;
+        let record = match record {
+            Ok(r) => r,
+            Err(e) => {
+                errors.push(ImportRowError { row, message: e.to_string() });
+                continue;
+            }
+        };
+
+        let field = |col: Option<usize>| col.and_then(|c| record.get(c)).map(str::to_string);
+
+        rows.push(RawRow {
+            row,
+            query: field(Some(query_col)),
+            name: field(name_col),
+            category: field(category_col),
+            tags: field(tags_col)
+                .map(|t| {
+                    t.split(separator)
+                        .map(|s| s.trim().to_string())
+                        .filter(|s| !s.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            created_at: None,
+        });
+    }
+
+    Ok(rows)
+}
+
+/// One query per line; blank lines and `#` comments are skipped
+fn parse_text(content: &str) -> Vec<RawRow> {
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| {
+            let line = line.trim();
+            !line.is_empty() && !line.starts_with('#')
+        })
+        .map(|(index, line)| RawRow {
+            row: index + 1,
+            query: Some(line.trim().to_string()),
+            ..Default::default()
+        })
+        .collect()
+}
+
+fn normalized_headers<R: std::io::Read>(reader: &mut csv::Reader<R>) -> Result<Vec<String>> {
+    Ok(reader
+        .headers()
+        .context("Failed to read CSV header row")?
+        .iter()
+        .map(|h| h.trim().trim_start_matches('\u{feff}').to_lowercase())
+        .collect())
+}
+
+fn find_column(headers: &[String], candidates: &[&str]) -> Option<usize> {
+    candidates
+        .iter()
+        .find_map(|candidate| headers.iter().position(|h| h == candidate))
+}
+
+fn ghdb_tags(id: Option<&str>) -> Vec<String> {
+    let mut tags = vec!["ghdb".to_string()];
+    if let Some(id) = id.map(str::trim).filter(|id| !id.is_empty()) {
+        tags.push(format!("ghdb:{}", id));
+    }
+    tags
+}
+
+fn parse_date(date: &str) -> Option<String> {
+    let date = chrono::NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d").ok()?;
+    Some(date.and_hms_opt(0, 0, 0)?.and_utc().to_rfc3339())
+}
+
+fn truncate_name(query: &str) -> String {
+    if query.chars().count() > 60 {
+        format!("{}...", query.chars().take(57).collect::<String>())
+    } else {
+        query.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_ghdb_xml_import_with_dedup() {
+        let vault = VaultService::open_in_memory().unwrap();
+        vault.import_dorks(
+            "intitle:\"index of\" .env",
+            &ImportFormat::Text,
+            &ImportOptions::default(),
+        ).await.unwrap();
+
+        let xml = r#"<?xml version="1.0"?>
+            <ghdb>
+              <entry>
+                <id>101</id>
+                <category>Files Containing Juicy Info</category>
+                <shortDescription>Exposed env files</shortDescription>
+                <query>INTITLE:"index of"   .env</query>
+                <date>2020-01-02</date>
+              </entry>
+              <entry>
+                <id>102</id>
+                <category>Pages Containing Login Portals</category>
+                <shortDescription>Admin login</shortDescription>
+                <query>inurl:admin/login.php</query>
+              </entry>
+              <entry><id>103</id><query></query></entry>
+            </ghdb>"#;
+
+        let options = ImportOptions { dry_run: true, ..Default::default() };
+        let preview = vault.import_dorks(xml, &ImportFormat::GhdbXml, &options).await.unwrap();
+        assert_eq!(preview.total_rows, 3);
+        assert_eq!(preview.imported.len(), 1);
+        assert_eq!(preview.duplicates.len(), 1);
+        assert_eq!(preview.errors[0].row, 3);
+        assert_eq!(vault.get_all_dorks().await.unwrap().len(), 1);
+
+        vault.import_dorks(xml, &ImportFormat::GhdbXml, &ImportOptions::default()).await.unwrap();
+        let login = vault.search_dorks("Admin login").await.unwrap();
+        assert_eq!(login[0].category, "Pages Containing Login Portals");
+        assert!(login[0].tags.contains(&"ghdb:102".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_mapped_csv_import() {
+        let vault = VaultService::open_in_memory().unwrap();
+        let csv = "Dork,Label,Tags\n\
+                   site:example.com filetype:sql,SQL dumps,db;dump\n\
+                   site:example.com  filetype:sql,Again,\n\
+                   ,Empty,\n";
+        let format = ImportFormat::Csv {
+            mapping: CsvColumnMapping {
+                query: "dork".to_string(),
+                name: Some("label".to_string()),
+                category: None,
+                tags: Some("tags".to_string()),
+                tag_separator: Some(";".to_string()),
+            },
+        };
+        let options = ImportOptions {
+            default_category: Some("Databases".to_string()),
+            ..Default::default()
+        };
+
+        let report = vault.import_dorks(csv, &format, &options).await.unwrap();
+        assert_eq!(report.imported.len(), 1);
+        assert_eq!(report.imported[0].tags, vec!["db", "dump"]);
+        assert_eq!(report.imported[0].category, "Databases");
+        assert_eq!(report.duplicates[0].duplicate_of_row, Some(2));
+        assert_eq!(report.errors[0].row, 4);
+    }
+
+    #[tokio::test]
+    async fn test_failed_import_writes_nothing() {
+        let vault = VaultService::open_in_memory().unwrap();
+        vault.conn.lock().await.execute_batch(
+            "CREATE TRIGGER reject_boom BEFORE INSERT ON dorks WHEN NEW.query = 'boom'
+             BEGIN SELECT RAISE(ABORT, 'rejected'); END",
+        ).unwrap();
+
+        let result = vault.import_dorks("site:example.com\nboom", &ImportFormat::Text, &ImportOptions::default()).await;
+        assert!(result.is_err());
+        assert!(vault.get_all_dorks().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_ghdb_title_is_not_a_query_column() {
+        let vault = VaultService::open_in_memory().unwrap();
+
+        // A title is a name, never the query
+        let csv = "title,category\nExposed env files,Files\n";
+        assert!(vault.import_dorks(csv, &ImportFormat::GhdbCsv, &ImportOptions::default()).await.is_err());
+    }
+}
@@ -0,0 +1,127 @@
+//! User overrides of the built-in AI prompt templates.
+//!
+//! Every saved override is kept as a numbered revision so messages can be
+//! traced back to the exact text that produced them. The newest revision is
+//! active until the override is reset.
+
+use super::VaultService;
+use anyhow::{Context, Result};
+use chrono::Utc;
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptOverride {
+    /// `<name>@user-<revision>`, the version recorded on messages
+    pub id: String,
+    pub name: String,
+    pub revision: u32,
+    pub template: String,
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u32>,
+    pub active: bool,
+    pub created_at: String,
+}
+
+const OVERRIDE_COLUMNS: &str = "id, name, revision, template, temperature, max_tokens, active, created_at";
+
+fn override_from_row(row: &rusqlite::Row) -> rusqlite::Result<PromptOverride> {
+    Ok(PromptOverride {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        revision: row.get(2)?,
+        template: row.get(3)?,
+        temperature: row.get::<_, Option<f64>>(4)?.map(|t| t as f32),
+        max_tokens: row.get(5)?,
+        active: row.get(6)?,
+        created_at: row.get(7)?,
+    })
+}
+
+impl VaultService {
+    /// Store a new override revision for `name` and make it the active one
+    pub async fn save_prompt_override(
+        &self,
+        name: &str,
+        template: &str,
+        temperature: Option<f32>,
+        max_tokens: Option<u32>,
+    ) -> Result<PromptOverride> {
+        let mut conn = self.conn.lock().await;
+        let tx = conn.transaction().context("Failed to start transaction")?;
+
+        let revision: u32 = tx.query_row(
+            "SELECT COALESCE(MAX(revision), 0) + 1 FROM prompt_overrides WHERE name = ?1",
+            [name],
+            |row| row.get(0),
+        ).context("Failed to get next prompt revision")?;
+
+        tx.execute("UPDATE prompt_overrides SET active = 0 WHERE name = ?1", [name])
+            .context("Failed to deactivate previous prompt override")?;
+
+        let saved = PromptOverride {
+            id: format!("{}@user-{}", name, revision),
+            name: name.to_string(),
+            revision,
+            template: template.to_string(),
+            temperature,
+            max_tokens,
+            active: true,
+            created_at: Utc::now().to_rfc3339(),
+        };
+        tx.execute(
+            "INSERT INTO prompt_overrides (id, name, revision, template, temperature, max_tokens, active, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, 1, ?7)",
+            params![
+                saved.id,
+                saved.name,
+                saved.revision,
+                saved.template,
+                saved.temperature.map(f64::from),
+                saved.max_tokens,
+                saved.created_at,
+            ],
+        ).context("Failed to save prompt override")?;
+
+        tx.commit().context("Failed to commit prompt override")?;
+
+        tracing::info!("Prompt override saved: {}", saved.id);
+        Ok(saved)
+    }
+
+    pub async fn active_prompt_override(&self, name: &str) -> Result<Option<PromptOverride>> {
+        let conn = self.conn.lock().await;
+
+        conn.query_row(
+            &format!("SELECT {} FROM prompt_overrides WHERE name = ?1 AND active = 1", OVERRIDE_COLUMNS),
+            [name],
+            override_from_row,
+        )
+        .optional()
+        .context("Failed to load prompt override")
+    }
+
+    /// A specific revision, active or not
+    pub async fn prompt_override_revision(&self, name: &str, revision: u32) -> Result<Option<PromptOverride>> {
+        let conn = self.conn.lock().await;
+
+        conn.query_row(
+            &format!("SELECT {} FROM prompt_overrides WHERE name = ?1 AND revision = ?2", OVERRIDE_COLUMNS),
+            params![name, revision],
+            override_from_row,
+        )
+        .optional()
+        .context("Failed to load prompt override revision")
+    }
+
+    /// Go back to the built-in template. Earlier revisions are kept.
+    pub async fn reset_prompt_override(&self, name: &str) -> Result<bool> {
+        let conn = self.conn.lock().await;
+
+        let rows_affected = conn
+            .execute("UPDATE prompt_overrides SET active = 0 WHERE name = ?1 AND active = 1", [name])
+            .context("Failed to reset prompt override")?;
+
+        Ok(rows_affected > 0)
+    }
+}
@@ -0,0 +1,183 @@
+//! Provenance linking saved dorks to the AI conversation messages that
+//! produced them.
+
+use super::{insert_dork, DorkQuery, VaultService};
+use anyhow::{Context, Result};
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+
+/// Characters of the source message kept with the provenance record
+const EXCERPT_LENGTH: usize = 200;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DorkProvenance {
+    pub dork_id: String,
+    /// `None` once the source conversation has been deleted
+    pub conversation_id: Option<String>,
+    pub message_id: Option<String>,
+    pub conversation_title: String,
+    pub message_excerpt: String,
+    pub recorded_at: String,
+}
+
+impl VaultService {
+    /// Save a dork generated in a conversation and record where it came from
+    pub async fn save_dork_from_message(
+        &self,
+        conversation_id: &str,
+        message_id: &str,
+        dork: DorkQuery,
+    ) -> Result<DorkProvenance> {
+        let mut conn = self.conn.lock().await;
+        let tx = conn.transaction().context("Failed to start transaction")?;
+
+        let (conversation_title, content): (String, String) = tx.query_row(
+            "SELECT c.title, m.content
+             FROM conversation_messages m
+             JOIN conversations c ON c.id = m.conversation_id
+             WHERE m.conversation_id = ?1 AND m.id = ?2",
+            params![conversation_id, message_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => anyhow::anyhow!(
+                "Message {} not found in conversation {}",
+                message_id,
+                conversation_id
+            ),
+            e => anyhow::Error::new(e).context("Failed to look up source message"),
+        })?;
+
+        insert_dork(&tx, &dork)?;
+
+        let provenance = DorkProvenance {
+            dork_id: dork.id.clone(),
+            conversation_id: Some(conversation_id.to_string()),
+            message_id: Some(message_id.to_string()),
+            conversation_title,
+            message_excerpt: content.chars().take(EXCERPT_LENGTH).collect(),
+            recorded_at: chrono::Utc::now().to_rfc3339(),
+        };
+
+        tx.execute(
+            "INSERT OR REPLACE INTO dork_provenance
+             (dork_id, conversation_id, message_id, conversation_title, message_excerpt, recorded_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                provenance.dork_id,
+                provenance.conversation_id,
+                provenance.message_id,
+                provenance.conversation_title,
+                provenance.message_excerpt,
+                provenance.recorded_at,
+            ],
+        ).context("Failed to record dork provenance")?;
+
+        tx.execute(
+            "UPDATE conversation_messages
+             SET dork_id = ?1, dork = COALESCE(dork, ?2)
+             WHERE conversation_id = ?3 AND id = ?4",
+            params![dork.id, dork.query, conversation_id, message_id],
+        ).context("Failed to link message to dork")?;
+
+        tx.execute(
+            "UPDATE usage_stats
+             SET total_dorks = (SELECT COUNT(*) FROM dorks),
+                 updated_at = datetime('now')
+             WHERE id = 1",
+            [],
+        ).context("Failed to update dork count")?;
+
+        tx.commit().context("Failed to commit dork provenance")?;
+
+        tracing::debug!("Dork {} saved from conversation {}", dork.id, conversation_id);
+        Ok(provenance)
+    }
+
+    pub async fn get_dork_provenance(&self, dork_id: &str) -> Result<Option<DorkProvenance>> {
+        let provenance = self.list_dork_provenance(Some(dork_id)).await?;
+        Ok(provenance.into_iter().next())
+    }
+
+    /// All provenance records (or just one dork's), newest first
+    pub async fn list_dork_provenance(&self, dork_id: Option<&str>) -> Result<Vec<DorkProvenance>> {
+        let conn = self.conn.lock().await;
+
+        let mut stmt = conn.prepare(
+            "SELECT dork_id, conversation_id, message_id, conversation_title,
+                    message_excerpt, recorded_at
+             FROM dork_provenance
+             WHERE ?1 IS NULL OR dork_id = ?1
+             ORDER BY recorded_at DESC"
+        ).context("Failed to prepare provenance query")?;
+
+        let records = stmt.query_map([dork_id], |row| {
+            Ok(DorkProvenance {
+                dork_id: row.get(0)?,
+                conversation_id: row.get(1)?,
+                message_id: row.get(2)?,
+                conversation_title: row.get(3)?,
+                message_excerpt: row.get(4)?,
+                recorded_at: row.get(5)?,
+            })
+        })
+        .context("Failed to query provenance")?
+        .collect::<Result<Vec<_>, _>>()
+        .context("Failed to collect provenance")?;
+
+        Ok(records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vault::{Conversation, Message};
+
+    #[tokio::test]
+    async fn test_provenance_survives_conversation_delete() {
+        let vault = VaultService::open_in_memory().unwrap();
+        let now = chrono::Utc::now().to_rfc3339();
+
+        vault.save_conversation(&Conversation {
+            id: "c1".to_string(),
+            title: "Acme recon".to_string(),
+            messages: vec![Message {
+                id: "m1".to_string(),
+                role: "assistant".to_string(),
+                content: "Try site:acme.com filetype:env".to_string(),
+                timestamp: now.clone(),
+                dork: None,
+                dork_id: None,
+                prompt_version: None,
+                pinned: false,
+                summarizes_through: None,
+            }],
+            created_at: now.clone(),
+            updated_at: now.clone(),
+        }).await.unwrap();
+
+        let dork = DorkQuery {
+            id: "d1".to_string(),
+            name: "Acme env".to_string(),
+            query: "site:acme.com filetype:env".to_string(),
+            category: "config".to_string(),
+            tags: vec![],
+            created_at: now,
+            updated_at: None,
+        };
+        assert!(vault.save_dork_from_message("c1", "missing", dork.clone()).await.is_err());
+        vault.save_dork_from_message("c1", "m1", dork).await.unwrap();
+
+        let message = &vault.get_conversation("c1").await.unwrap().messages[0];
+        assert_eq!(message.dork_id.as_deref(), Some("d1"));
+        assert_eq!(message.dork.as_deref(), Some("site:acme.com filetype:env"));
+
+        vault.delete_conversation("c1").await.unwrap();
+        let provenance = vault.get_dork_provenance("d1").await.unwrap().unwrap();
+        assert_eq!(provenance.conversation_id, None);
+        assert_eq!(provenance.conversation_title, "Acme recon");
+
+        vault.delete_dork("d1").await.unwrap();
+        assert!(vault.get_dork_provenance("d1").await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,420 @@
+//! AI generation quota.
+//!
+//! Each consumed generation is stored as an event. The limit check and the
+//! event insert run in one `IMMEDIATE` transaction, so concurrent requests,
+//! even from another connection to the same vault, can't overshoot. The
+//! window is either the calendar day in a configurable time zone or a
+//! rolling number of hours.
+
+use super::{is_unlimited_tier, load_setting, VaultService, FREE_TIER_DAILY_AI_LIMIT};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, NaiveTime, SecondsFormat, TimeZone, Utc};
+use chrono_tz::Tz;
+use rusqlite::{Connection, OptionalExtension, TransactionBehavior};
+use serde::{Deserialize, Serialize};
+
+/// Settings key holding the [`QuotaConfig`]
+const QUOTA_SETTING: &str = "ai.quota";
+
+/// Longest rolling window, and how long quota events are kept
+const MAX_WINDOW_HOURS: u32 = 24 * 30;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuotaConfig {
+    /// IANA time zone whose midnight starts a new day, e.g. "Europe/Berlin"
+    pub timezone: String,
+    pub window: QuotaWindow,
+}
+
+impl Default for QuotaConfig {
+    fn default() -> Self {
+        Self {
+            timezone: "UTC".to_string(),
+            window: QuotaWindow::CalendarDay,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum QuotaWindow {
+    /// Resets at midnight in the configured time zone
+    CalendarDay,
+    /// Counts generations in the last `hours`
+    Rolling { hours: u32 },
+}
+
+/// One consumed generation, handed back to refund exactly that event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuotaGrant {
+    pub event_id: i64,
+    /// Generations used in the window, this one included
+    pub used: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuotaStatus {
+    pub used: i32,
+    /// `None` for unlimited tiers
+    pub limit: Option<i32>,
+    pub remaining: Option<i32>,
+    /// When the next generation frees up (UTC), if the window is in use
+    pub resets_at: Option<String>,
+}
+
+impl QuotaConfig {
+    fn validate(&self) -> Result<Tz> {
+        if let QuotaWindow::Rolling { hours } = self.window {
+            if hours == 0 || hours > MAX_WINDOW_HOURS {
+                anyhow::bail!("Rolling window must be between 1 and {} hours", MAX_WINDOW_HOURS);
+            }
+        }
+        self.timezone
+            .parse::<Tz>()
+            .map_err(|_| anyhow::anyhow!("Unknown time zone: {}", self.timezone))
+    }
+
+    /// Start of the window containing `now`
+    fn window_start(&self, now: DateTime<Utc>) -> Result<DateTime<Utc>> {
+        match self.window {
+            QuotaWindow::CalendarDay => Ok(local_midnight(self.validate()?, now)),
+            QuotaWindow::Rolling { hours } => Ok(now - Duration::hours(i64::from(hours))),
+        }
+    }
+}
+
+/// Midnight starting the local day of `now`, in UTC. On days where
+/// midnight is skipped by a DST change the day starts at the first valid
+/// local time.
+fn local_midnight(tz: Tz, now: DateTime<Utc>) -> DateTime<Utc> {
+    let date = now.with_timezone(&tz).date_naive();
+
+    (0..24)
+        .find_map(|hour| {
+            let time = NaiveTime::from_hms_opt(hour, 0, 0)?;
+            tz.from_local_datetime(&date.and_time(time)).earliest()
+        })
+        .map(|start| start.with_timezone(&Utc))
+        .unwrap_or(now)
+}
+
+/// Timestamps are stored in one fixed format so they compare as strings
+fn timestamp(at: DateTime<Utc>) -> String {
+    at.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+fn load_config(conn: &Connection) -> Result<QuotaConfig> {
+    load_setting(conn, QUOTA_SETTING)
+}
+
+/// Zero the per-day counters once the local day in the configured time
+/// zone has changed
+pub(super) fn reset_daily_usage_if_stale(conn: &Connection) -> Result<()> {
+    let tz = load_config(conn)?.validate()?;
+    let today = Utc::now().with_timezone(&tz).format("%Y-%m-%d").to_string();
+
+    let last_reset: String = conn.query_row(
+        "SELECT last_reset_date FROM usage_stats WHERE id = 1",
+        [],
+        |row| row.get(0),
+    ).context("Failed to get last reset date")?;
+
+    if last_reset != today {
+        conn.execute(
+            "UPDATE usage_stats
+             SET ai_generations_today = 0,
+                 prompt_tokens_today = 0,
+                 completion_tokens_today = 0,
+                 last_reset_date = ?1,
+                 updated_at = datetime('now')
+             WHERE id = 1",
+            [&today],
+        ).context("Failed to reset daily usage")?;
+
+        tracing::info!("Daily usage counter reset for new day: {}", today);
+    }
+
+    Ok(())
+}
+
+fn used_since(conn: &Connection, since: DateTime<Utc>) -> Result<i32> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM ai_quota_events WHERE consumed_at >= ?1",
+        [timestamp(since)],
+        |row| row.get(0),
+    ).context("Failed to count AI generations")
+}
+
+/// Check the limit and record one generation in a single transaction.
+/// Returns `None` if the limit was hit.
+fn consume(conn: &mut Connection, license_tier: &str, now: DateTime<Utc>) -> Result<Option<QuotaGrant>> {
+    let tx = conn
+        .transaction_with_behavior(TransactionBehavior::Immediate)
+        .context("Failed to start quota transaction")?;
+    reset_daily_usage_if_stale(&tx)?;
+
+    let since = load_config(&tx)?.window_start(now)?;
+    let used = used_since(&tx, since)?;
+    if !is_unlimited_tier(license_tier) && used >= FREE_TIER_DAILY_AI_LIMIT {
+        return Ok(None);
+    }
+
+    tx.execute(
+        "DELETE FROM ai_quota_events WHERE consumed_at < ?1",
+        [timestamp(now - Duration::hours(i64::from(MAX_WINDOW_HOURS)))],
+    ).context("Failed to prune quota events")?;
+    tx.execute("INSERT INTO ai_quota_events (consumed_at) VALUES (?1)", [timestamp(now)])
+        .context("Failed to consume AI generation")?;
+    let event_id = tx.last_insert_rowid();
+    tx.execute(
+        "UPDATE usage_stats
+         SET ai_generations_today = ai_generations_today + 1,
+             updated_at = datetime('now')
+         WHERE id = 1",
+        [],
+    ).context("Failed to count AI generation")?;
+
+    tx.commit().context("Failed to commit quota transaction")?;
+
+    tracing::debug!("AI generations in window: {}", used + 1);
+    Ok(Some(QuotaGrant {
+        event_id,
+        used: used + 1,
+    }))
+}
+
+/// Delete the grant's event. The day's counter only goes down if the event
+/// was counted today; after a reset it belongs to a day already closed.
+fn refund(conn: &mut Connection, grant: QuotaGrant, now: DateTime<Utc>) -> Result<()> {
+    let tx = conn
+        .transaction_with_behavior(TransactionBehavior::Immediate)
+        .context("Failed to start quota transaction")?;
+    reset_daily_usage_if_stale(&tx)?;
+
+    let consumed_at: Option<String> = tx
+        .query_row(
+            "DELETE FROM ai_quota_events WHERE id = ?1 RETURNING consumed_at",
+            [grant.event_id],
+            |row| row.get(0),
+        )
+        .optional()
+        .context("Failed to refund AI generation")?;
+
+    let day_start = timestamp(local_midnight(load_config(&tx)?.validate()?, now));
+    if consumed_at.is_some_and(|consumed_at| consumed_at >= day_start) {
+        tx.execute(
+            "UPDATE usage_stats
+             SET ai_generations_today = MAX(ai_generations_today - 1, 0),
+                 updated_at = datetime('now')
+             WHERE id = 1",
+            [],
+        ).context("Failed to refund AI generation")?;
+    }
+
+    tx.commit().context("Failed to commit quota refund")?;
+    Ok(())
+}
+
+fn status(conn: &Connection, license_tier: &str, now: DateTime<Utc>) -> Result<QuotaStatus> {
+    let config = load_config(conn)?;
+    let since = config.window_start(now)?;
+    let used = used_since(conn, since)?;
+
+    if is_unlimited_tier(license_tier) {
+        return Ok(QuotaStatus { used, limit: None, remaining: None, resets_at: None });
+    }
+
+    let resets_at = match config.window {
+        QuotaWindow::CalendarDay => {
+            let tz = config.validate()?;
+            Some(local_midnight(tz, local_midnight(tz, now) + Duration::hours(36)))
+        }
+        // The oldest generation in the window is the next to expire
+        QuotaWindow::Rolling { hours } => conn
+            .query_row(
+                "SELECT MIN(consumed_at) FROM ai_quota_events WHERE consumed_at >= ?1",
+                [timestamp(since)],
+                |row| row.get::<_, Option<String>>(0),
+            )
+            .context("Failed to find oldest AI generation")?
+            .and_then(|oldest| DateTime::parse_from_rfc3339(&oldest).ok())
+            .map(|oldest| oldest.with_timezone(&Utc) + Duration::hours(i64::from(hours))),
+    };
+
+    Ok(QuotaStatus {
+        used,
+        limit: Some(FREE_TIER_DAILY_AI_LIMIT),
+        remaining: Some((FREE_TIER_DAILY_AI_LIMIT - used).max(0)),
+        resets_at: resets_at.filter(|_| used > 0).map(timestamp),
+    })
+}
+
+impl VaultService {
+    /// Atomically check the tier's limit and count one AI generation.
+    /// Returns `None` when the limit has been reached.
+    pub async fn try_consume_ai_generation(&self, license_tier: &str) -> Result<Option<QuotaGrant>> {
+        let mut conn = self.conn.lock().await;
+        consume(&mut conn, license_tier, Utc::now())
+    }
+
+    /// Give back a generation consumed for a request that failed
+    pub async fn refund_ai_generation(&self, grant: QuotaGrant) -> Result<()> {
+        let mut conn = self.conn.lock().await;
+        refund(&mut conn, grant, Utc::now())
+    }
+
+    pub async fn can_generate_ai(&self, license_tier: &str) -> Result<bool> {
+        Ok(self.quota_status(license_tier).await?.remaining.map_or(true, |r| r > 0))
+    }
+
+    pub async fn quota_status(&self, license_tier: &str) -> Result<QuotaStatus> {
+        let conn = self.conn.lock().await;
+        status(&conn, license_tier, Utc::now())
+    }
+
+    pub async fn quota_config(&self) -> Result<QuotaConfig> {
+        let conn = self.conn.lock().await;
+        load_config(&conn)
+    }
+
+    pub async fn set_quota_config(&self, config: &QuotaConfig) -> Result<()> {
+        config.validate()?;
+
+        let value = serde_json::to_value(config).context("Failed to serialize quota config")?;
+        self.set_setting(QUOTA_SETTING, &value).await?;
+
+        tracing::info!("AI quota set to {:?}", config);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn at(rfc3339: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(rfc3339).unwrap().with_timezone(&Utc)
+    }
+
+    #[tokio::test]
+    async fn test_windows_follow_configured_time_zone() {
+        let vault = VaultService::open_in_memory().unwrap();
+        assert!(vault.set_quota_config(&QuotaConfig {
+            timezone: "Mars/Olympus".to_string(),
+            window: QuotaWindow::CalendarDay,
+        }).await.is_err());
+
+        // Midnight in Los Angeles is 07:00 UTC during daylight saving time
+        vault.set_quota_config(&QuotaConfig {
+            timezone: "America/Los_Angeles".to_string(),
+            window: QuotaWindow::CalendarDay,
+        }).await.unwrap();
+        {
+            let mut conn = vault.conn.lock().await;
+            for _ in 0..FREE_TIER_DAILY_AI_LIMIT {
+                assert!(consume(&mut conn, "free", at("2026-06-01T20:00:00Z")).unwrap().is_some());
+            }
+            assert!(consume(&mut conn, "free", at("2026-06-02T06:59:00Z")).unwrap().is_none());
+            assert!(consume(&mut conn, "professional", at("2026-06-02T06:59:00Z")).unwrap().is_some());
+
+            let status = status(&conn, "free", at("2026-06-02T06:59:00Z")).unwrap();
+            assert_eq!(status.remaining, Some(0));
+            assert_eq!(status.resets_at.as_deref(), Some("2026-06-02T07:00:00.000000Z"));
+
+            assert_eq!(consume(&mut conn, "free", at("2026-06-02T07:00:00Z")).unwrap().map(|g| g.used), Some(1));
+        }
+
+        vault.set_quota_config(&QuotaConfig {
+            timezone: "UTC".to_string(),
+            window: QuotaWindow::Rolling { hours: 2 },
+        }).await.unwrap();
+        let mut conn = vault.conn.lock().await;
+        assert_eq!(consume(&mut conn, "free", at("2026-06-02T10:00:00Z")).unwrap().map(|g| g.used), Some(1));
+        for _ in 1..FREE_TIER_DAILY_AI_LIMIT {
+            consume(&mut conn, "free", at("2026-06-02T10:30:00Z")).unwrap().unwrap();
+        }
+        assert!(consume(&mut conn, "free", at("2026-06-02T11:59:00Z")).unwrap().is_none());
+        let status = status(&conn, "free", at("2026-06-02T11:59:00Z")).unwrap();
+        assert_eq!(status.resets_at.as_deref(), Some("2026-06-02T12:00:00.000000Z"));
+        assert!(consume(&mut conn, "free", at("2026-06-02T12:00:01Z")).unwrap().is_some());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_consumers_never_overshoot() {
+        let vault = Arc::new(VaultService::open_in_memory().unwrap());
+        let tasks: Vec<_> = (0..64)
+            .map(|_| {
+                let vault = vault.clone();
+                tokio::spawn(async move { vault.try_consume_ai_generation("free").await.unwrap() })
+            })
+            .collect();
+
+        let mut granted = Vec::new();
+        for task in tasks {
+            granted.extend(task.await.unwrap());
+        }
+        assert_eq!(granted.len() as i32, FREE_TIER_DAILY_AI_LIMIT);
+
+        // Refunds remove the request's own event, not the newest one
+        let oldest = *granted.iter().min_by_key(|g| g.event_id).unwrap();
+        vault.refund_ai_generation(oldest).await.unwrap();
+        assert_eq!(vault.quota_status("free").await.unwrap().remaining, Some(1));
+        assert!(vault.can_generate_ai("free").await.unwrap());
+        let conn = vault.conn.lock().await;
+        let remaining: i64 = conn
+            .query_row("SELECT COUNT(*) FROM ai_quota_events WHERE id = ?1", [oldest.event_id], |row| row.get(0))
+            .unwrap();
+        assert_eq!(remaining, 0);
+    }
+
+    #[tokio::test]
+    async fn test_refund_after_rollover_keeps_new_day_count() {
+        let vault = VaultService::open_in_memory().unwrap();
+        let mut conn = vault.conn.lock().await;
+        let before_midnight = local_midnight(chrono_tz::UTC, Utc::now()) - Duration::hours(1);
+        let yesterday = consume(&mut conn, "free", before_midnight).unwrap().unwrap();
+        let today = consume(&mut conn, "free", Utc::now()).unwrap().unwrap();
+        conn.execute("UPDATE usage_stats SET ai_generations_today = 1 WHERE id = 1", []).unwrap();
+
+        let generations_today = |conn: &Connection| -> i32 {
+            conn.query_row("SELECT ai_generations_today FROM usage_stats WHERE id = 1", [], |row| row.get(0))
+                .unwrap()
+        };
+        refund(&mut conn, yesterday, Utc::now()).unwrap();
+        assert_eq!(generations_today(&conn), 1);
+        refund(&mut conn, today, Utc::now()).unwrap();
+        assert_eq!(generations_today(&conn), 0);
+        // A second refund of the same grant changes nothing
+        conn.execute("UPDATE usage_stats SET ai_generations_today = 1 WHERE id = 1", []).unwrap();
+        refund(&mut conn, today, Utc::now()).unwrap();
+        assert_eq!(generations_today(&conn), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_separate_connections_share_the_limit() {
+        let path = std::env::temp_dir().join(format!("parallax-quota-{}.db", uuid::Uuid::new_v4()));
+        let vaults = [
+            Arc::new(VaultService::open(&path).unwrap()),
+            Arc::new(VaultService::open(&path).unwrap()),
+        ];
+
+        let tasks: Vec<_> = (0..40)
+            .map(|i| {
+                let vault = vaults[i % 2].clone();
+                tokio::spawn(async move { vault.try_consume_ai_generation("free").await.unwrap() })
+            })
+            .collect();
+
+        let mut granted = 0;
+        for task in tasks {
+            if task.await.unwrap().is_some() {
+                granted += 1;
+            }
+        }
+        assert_eq!(granted, FREE_TIER_DAILY_AI_LIMIT);
+
+        drop(vaults);
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,301 @@
+//! Per-request AI usage ledger with daily and monthly rollups.
+//!
+//! Every provider call is recorded with its token counts, latency and
+//! outcome. Reports group the ledger by period, provider, model and feature
+//! and price tokens with the per-model cost table kept in settings.
+
+use super::quota::reset_daily_usage_if_stale;
+use super::VaultService;
+use anyhow::{Context, Result};
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// Settings key holding the `Vec<ModelCost>` price table
+const MODEL_COSTS_SETTING: &str = "ai.model_costs";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UsageOutcome {
+    Success,
+    Error,
+    Cancelled,
+}
+
+impl UsageOutcome {
+    fn as_str(self) -> &'static str {
+        match self {
+            UsageOutcome::Success => "success",
+            UsageOutcome::Error => "error",
+            UsageOutcome::Cancelled => "cancelled",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageRecord {
+    pub id: String,
+    /// RFC 3339, UTC
+    pub timestamp: String,
+    pub provider: String,
+    pub model: String,
+    /// What the request was for, e.g. "dork_generation"
+    pub feature: String,
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    /// True when the token counts were estimated
+    pub estimated: bool,
+    pub latency_ms: u64,
+    pub outcome: UsageOutcome,
+}
+
+/// Price per million tokens for a model. `provider` narrows the match when
+/// the same model name is served by several providers.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelCost {
+    #[serde(default)]
+    pub provider: Option<String>,
+    pub model: String,
+    pub prompt_per_million: f64,
+    pub completion_per_million: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UsagePeriod {
+    Day,
+    Month,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageRollup {
+    /// `YYYY-MM-DD` or `YYYY-MM`
+    pub period: String,
+    pub provider: String,
+    pub model: String,
+    pub feature: String,
+    pub requests: u64,
+    pub failed_requests: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub avg_latency_ms: f64,
+    /// `None` when the model has no entry in the cost table
+    pub cost: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageReport {
+    pub period: UsagePeriod,
+    pub rollups: Vec<UsageRollup>,
+    pub total_requests: u64,
+    pub total_prompt_tokens: u64,
+    pub total_completion_tokens: u64,
+    /// Sum over priced rollups only
+    pub total_cost: f64,
+    /// Models that appear in the report without a price
+    pub unpriced_models: Vec<String>,
+}
+
+impl ModelCost {
+    fn matches(&self, provider: &str, model: &str) -> bool {
+        self.model == model && self.provider.as_deref().map_or(true, |p| p == provider)
+    }
+
+    fn price(&self, prompt_tokens: u64, completion_tokens: u64) -> f64 {
+        (prompt_tokens as f64 * self.prompt_per_million + completion_tokens as f64 * self.completion_per_million)
+            / 1_000_000.0
+    }
+}
+
+impl VaultService {
+    /// Append a request to the ledger and add its tokens to the counters
+    pub async fn record_ai_usage(&self, record: &UsageRecord) -> Result<()> {
+        let mut conn = self.conn.lock().await;
+        let tx = conn.transaction().context("Failed to start transaction")?;
+        reset_daily_usage_if_stale(&tx)?;
+
+        tx.execute(
+            "INSERT INTO ai_usage_ledger
+             (id, timestamp, provider, model, feature, prompt_tokens, completion_tokens, estimated, latency_ms, outcome)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                record.id,
+                record.timestamp,
+                record.provider,
+                record.model,
+                record.feature,
+                record.prompt_tokens,
+                record.completion_tokens,
+                record.estimated,
+                record.latency_ms as i64,
+                record.outcome.as_str(),
+            ],
+        ).context("Failed to record AI usage")?;
+
+        tx.execute(
+            "UPDATE usage_stats
+             SET prompt_tokens_today = prompt_tokens_today + ?1,
+                 completion_tokens_today = completion_tokens_today + ?2,
+                 total_prompt_tokens = total_prompt_tokens + ?1,
+                 total_completion_tokens = total_completion_tokens + ?2,
+                 updated_at = datetime('now')
+             WHERE id = 1",
+            params![record.prompt_tokens, record.completion_tokens],
+        ).context("Failed to record token usage")?;
+
+        tx.commit().context("Failed to commit AI usage")?;
+        Ok(())
+    }
+
+    pub async fn model_costs(&self) -> Result<Vec<ModelCost>> {
+        match self.get_setting(MODEL_COSTS_SETTING).await? {
+            Some(value) => serde_json::from_value(value).context("Failed to parse model cost table"),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    pub async fn set_model_costs(&self, costs: &[ModelCost]) -> Result<()> {
+        if let Some(cost) = costs.iter().find(|c| c.prompt_per_million < 0.0 || c.completion_per_million < 0.0) {
+            anyhow::bail!("Price for model {} must not be negative", cost.model);
+        }
+
+        let value = serde_json::to_value(costs).context("Failed to serialize model cost table")?;
+        self.set_setting(MODEL_COSTS_SETTING, &value).await
+    }
+
+    /// Roll the ledger up by `period` between `since` and `until` (inclusive
+    /// `YYYY-MM-DD` dates, UTC) and price it with the cost table
+    pub async fn usage_report(
+        &self,
+        period: UsagePeriod,
+        since: Option<&str>,
+        until: Option<&str>,
+    ) -> Result<UsageReport> {
+        let costs = self.model_costs().await?;
+        let conn = self.conn.lock().await;
+
+        let bucket_length = match period {
+            UsagePeriod::Day => 10,
+            UsagePeriod::Month => 7,
+        };
+
+        let mut stmt = conn.prepare(
+            "SELECT substr(timestamp, 1, ?1) AS bucket, provider, model, feature,
+                    COUNT(*),
+                    SUM(CASE WHEN outcome = 'error' THEN 1 ELSE 0 END),
+                    SUM(prompt_tokens),
+                    SUM(completion_tokens),
+                    AVG(latency_ms)
+             FROM ai_usage_ledger
+             WHERE (?2 IS NULL OR substr(timestamp, 1, 10) >= ?2)
+               AND (?3 IS NULL OR substr(timestamp, 1, 10) <= ?3)
+             GROUP BY bucket, provider, model, feature
+             ORDER BY bucket, provider, model, feature"
+        ).context("Failed to prepare usage report query")?;
+
+        let rollups = stmt.query_map(params![bucket_length, since, until], |row| {
+            Ok(UsageRollup {
+                period: row.get(0)?,
+                provider: row.get(1)?,
+                model: row.get(2)?,
+                feature: row.get(3)?,
+                requests: row.get::<_, i64>(4)? as u64,
+                failed_requests: row.get::<_, i64>(5)? as u64,
+                prompt_tokens: row.get::<_, i64>(6)? as u64,
+                completion_tokens: row.get::<_, i64>(7)? as u64,
+                avg_latency_ms: row.get(8)?,
+                cost: None,
+            })
+        })
+        .context("Failed to query usage report")?
+        .collect::<Result<Vec<_>, _>>()
+        .context("Failed to collect usage report")?;
+
+        let mut report = UsageReport {
+            period,
+            rollups: Vec::with_capacity(rollups.len()),
+            total_requests: 0,
+            total_prompt_tokens: 0,
+            total_completion_tokens: 0,
+            total_cost: 0.0,
+            unpriced_models: Vec::new(),
+        };
+        let mut unpriced = BTreeSet::new();
+
+        for mut rollup in rollups {
+            rollup.cost = costs
+                .iter()
+                .find(|c| c.matches(&rollup.provider, &rollup.model))
+                .map(|c| c.price(rollup.prompt_tokens, rollup.completion_tokens));
+
+            match rollup.cost {
+                Some(cost) => report.total_cost += cost,
+                None => {
+                    unpriced.insert(format!("{}/{}", rollup.provider, rollup.model));
+                }
+            }
+            report.total_requests += rollup.requests;
+            report.total_prompt_tokens += rollup.prompt_tokens;
+            report.total_completion_tokens += rollup.completion_tokens;
+            report.rollups.push(rollup);
+        }
+        report.unpriced_models = unpriced.into_iter().collect();
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(timestamp: &str, model: &str, prompt_tokens: u32, outcome: UsageOutcome) -> UsageRecord {
+        UsageRecord {
+            id: uuid::Uuid::new_v4().to_string(),
+            timestamp: timestamp.to_string(),
+            provider: "openai_compatible".to_string(),
+            model: model.to_string(),
+            feature: "dork_generation".to_string(),
+            prompt_tokens,
+            completion_tokens: 100,
+            estimated: false,
+            latency_ms: 200,
+            outcome,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_usage_report_rollups_and_costs() {
+        let vault = VaultService::open_in_memory().unwrap();
+        for entry in [
+            record("2026-03-30T10:00:00+00:00", "gpt-4o-mini", 1000, UsageOutcome::Success),
+            record("2026-03-30T11:00:00+00:00", "gpt-4o-mini", 3000, UsageOutcome::Error),
+            record("2026-03-31T09:00:00+00:00", "llama3.1", 500, UsageOutcome::Success),
+            record("2026-04-01T09:00:00+00:00", "gpt-4o-mini", 1000, UsageOutcome::Success),
+        ] {
+            vault.record_ai_usage(&entry).await.unwrap();
+        }
+        assert_eq!(vault.get_usage_stats().await.unwrap().total_prompt_tokens, 5500);
+
+        vault.set_model_costs(&[ModelCost {
+            provider: None,
+            model: "gpt-4o-mini".to_string(),
+            prompt_per_million: 0.15,
+            completion_per_million: 0.60,
+        }]).await.unwrap();
+
+        let daily = vault.usage_report(UsagePeriod::Day, Some("2026-03-30"), Some("2026-03-31")).await.unwrap();
+        assert_eq!(daily.rollups.len(), 2);
+        assert_eq!(daily.rollups[0].period, "2026-03-30");
+        assert_eq!(daily.rollups[0].requests, 2);
+        assert_eq!(daily.rollups[0].failed_requests, 1);
+        assert_eq!(daily.unpriced_models, vec!["openai_compatible/llama3.1"]);
+        // 4000 prompt and 200 completion tokens
+        assert!((daily.total_cost - (4000.0 * 0.15 + 200.0 * 0.60) / 1e6).abs() < 1e-12);
+
+        let monthly = vault.usage_report(UsagePeriod::Month, None, None).await.unwrap();
+        let periods: Vec<&str> = monthly.rollups.iter().map(|r| r.period.as_str()).collect();
+        assert_eq!(periods, vec!["2026-03", "2026-03", "2026-04"]);
+        assert_eq!(monthly.total_requests, 4);
+    }
+}
@@ -20,7 +20,6 @@ import FirstRun from './pages/FirstRun';
 
 // Services
 import { getAppConfig } from './services/tauri';
-import { initializeGemini } from './services/gemini';
 
 // Styles
 import './styles/globals.css';
@@ -68,16 +67,6 @@ function App() {
       // Check if this is first run (no API key or license)
       if (!config.api_key_configured && config.license_status === 'free') {
         setIsFirstRun(true);
-      } else {
-        // Initialize Gemini if API key is configured
-        if (config.api_key_configured) {
-          try {
-            await initializeGemini();
-          } catch (error) {
-            console.error('Failed to initialize Gemini:', error);
-            // Non-fatal error - user can configure later
-          }
-        }
       }
 
       setIsLoading(false);
@@ -6,18 +6,23 @@ import {
   BookmarkIcon,
   MagnifyingGlassIcon,
   SparklesIcon,
-  ExclamationTriangleIcon
+  ExclamationTriangleIcon,
+  StopIcon,
 } from '@heroicons/react/24/outline';
-import { generateDork } from '../services/gemini';
 import {
-  saveDork,
+  streamGenerateDork,
+  generateDorkOffline,
+  cancelGeneration,
+  onAIStream,
+  saveDorkFromMessage,
   getUsageStats,
-  incrementAIUsage,
   canGenerateAI,
   getRemainingAIGenerations,
-  saveConversation as saveConversationBackend,
-  listConversations,
-  type Conversation,
+  saveConversation,
+  getConversation,
+  appendMessage,
+  deleteConversation,
+  listConversationSummaries,
   type Message as BackendMessage,
 } from '../services/tauri';
 import { useLicense } from '../hooks/useLicense';
@@ -26,6 +31,8 @@ import { v4 as uuidv4 } from 'uuid';
 // Use backend Message type but extend with error flag for UI
 interface Message extends BackendMessage {
   error?: boolean;
+  /** Validation warnings for a freshly generated dork */
+  warnings?: string[];
 }
 
 const EXAMPLE_PROMPTS = [
@@ -51,6 +58,9 @@ export default function AIGenerator() {
   const [showUpgrade, setShowUpgrade] = useState(false);
   const messagesEndRef = useRef<HTMLDivElement>(null);
   const inputRef = useRef<HTMLTextAreaElement>(null);
+  const activeRequestRef = useRef<string | null>(null);
+  // The conversation is created with its first message; later turns are appended
+  const conversationCreatedRef = useRef(false);
 
   // Use license hook for tier information
   const { tier, isPro, isLoading: licenseLoading } = useLicense();
@@ -68,27 +78,33 @@ export default function AIGenerator() {
 
   const loadConversation = async () => {
     try {
-      const conversations = await listConversations(1);
-      if (conversations.length > 0) {
-        setMessages(conversations[0].messages as Message[]);
+      const summaries = await listConversationSummaries();
+      if (summaries.some((c) => c.id === ACTIVE_CONVERSATION_ID)) {
+        const conversation = await getConversation(ACTIVE_CONVERSATION_ID);
+        conversationCreatedRef.current = true;
+        setMessages(conversation.messages as Message[]);
       }
     } catch (error) {
       console.error('Failed to load conversation:', error);
     }
   };
 
-  const saveConversation = async (msgs: Message[]) => {
+  const persistMessage = async (message: Message) => {
     try {
-      const conversation: Conversation = {
-        id: ACTIVE_CONVERSATION_ID,
-        title: msgs.length > 0 ? msgs[0].content.substring(0, 50) : 'New Conversation',
-        messages: msgs,
-        created_at: msgs[0]?.timestamp || new Date().toISOString(),
-        updated_at: new Date().toISOString(),
-      };
-      await saveConversationBackend(conversation);
+      if (conversationCreatedRef.current) {
+        await appendMessage(ACTIVE_CONVERSATION_ID, message);
+      } else {
+        await saveConversation({
+          id: ACTIVE_CONVERSATION_ID,
+          title: message.content.substring(0, 50),
+          messages: [message],
+          created_at: message.timestamp,
+          updated_at: new Date().toISOString(),
+        });
+        conversationCreatedRef.current = true;
+      }
     } catch (error) {
-      console.error('Failed to save conversation:', error);
+      console.error('Failed to save message:', error);
     }
   };
 
@@ -111,6 +127,25 @@ export default function AIGenerator() {
     }
   };
 
+  // Rule-based answer from the template library, used when AI is unavailable
+  const offlineReply = async (description: string, reason: string): Promise<Message | null> => {
+    try {
+      const result = await generateDorkOffline({ description });
+      return {
+        id: uuidv4(),
+        role: 'assistant',
+        content: `${reason} Here's a dork from the template library instead:\n\n${result.explanation}`,
+        timestamp: new Date().toISOString(),
+        dork: result.query,
+        prompt_version: result.prompt_version,
+        warnings: [...result.warnings, ...(result.alternatives ?? []).map((q) => `Alternative: ${q}`)],
+      };
+    } catch (error) {
+      console.error('Offline generation error:', error);
+      return null;
+    }
+  };
+
   const handleSend = async () => {
     if (!input.trim() || loading) return;
 
@@ -118,6 +153,20 @@ export default function AIGenerator() {
     const canUse = await checkCanUseAI();
     if (!canUse) {
       setShowUpgrade(true);
+
+      const userMessage: Message = {
+        id: uuidv4(),
+        role: 'user',
+        content: input.trim(),
+        timestamp: new Date().toISOString(),
+      };
+      const reply = await offlineReply(userMessage.content, 'Daily AI limit reached.');
+      if (reply) {
+        setMessages([...messages, userMessage, reply]);
+        setInput('');
+        await persistMessage(userMessage);
+        await persistMessage(reply);
+      }
       return;
     }
 
@@ -128,50 +177,111 @@ export default function AIGenerator() {
       timestamp: new Date().toISOString(),
     };
 
-    const newMessages = [...messages, userMessage];
-    setMessages(newMessages);
+    setMessages([...messages, userMessage]);
     setInput('');
     setLoading(true);
 
-    try {
-      // Generate dork using AI
-      const response = await generateDork(input.trim());
-
-      // Increment usage counter in backend
-      const newCount = await incrementAIUsage();
-      setRemainingGenerations(isPro ? Infinity : Math.max(0, FREE_TIER_DAILY_LIMIT - newCount));
-
-      const assistantMessage: Message = {
-        id: uuidv4(),
-        role: 'assistant',
-        content: response.explanation || `Here's a dork for that:\n\n${response.query}`,
-        dork: response.query,
-        timestamp: new Date().toISOString(),
-      };
+    // Persist the user turn first; the backend appends the streamed reply
+    await persistMessage(userMessage);
+
+    const requestId = uuidv4();
+    activeRequestRef.current = requestId;
+    let streamedMessageId: string | null = null;
+
+    const unlisten = await onAIStream((event) => {
+      if (event.request_id !== requestId) return;
+
+      switch (event.kind) {
+        case 'started':
+          streamedMessageId = event.message_id;
+          setMessages((prev) => [
+            ...prev,
+            { id: event.message_id, role: 'assistant', content: '', timestamp: new Date().toISOString() },
+          ]);
+          break;
+        case 'chunk':
+          setMessages((prev) =>
+            prev.map((m) => (m.id === streamedMessageId ? { ...m, content: m.content + event.delta } : m))
+          );
+          break;
+        case 'done':
+          setMessages((prev) =>
+            prev.map((m) =>
+              m.id === event.message_id
+                ? {
+                    ...m,
+                    content: event.result.explanation || `Here's a dork for that:\n\n${event.result.query}`,
+                    dork: event.result.query,
+                    warnings: event.result.warnings,
+                  }
+                : m
+            )
+          );
+          break;
+        case 'cancelled':
+          // Keep whatever arrived before the stop; drop a reply that never started
+          setMessages((prev) =>
+            prev.filter((m) => m.id !== event.message_id || m.content.trim() !== '')
+          );
+          setLoading(false);
+          break;
+        case 'error':
+          setMessages((prev) =>
+            prev.map((m) =>
+              m.id === streamedMessageId
+                ? {
+                    ...m,
+                    content: [m.content.trim(), `Sorry, I encountered an error: ${event.message}`]
+                      .filter(Boolean)
+                      .join('\n\n'),
+                    error: true,
+                  }
+                : m
+            )
+          );
+          setLoading(false);
+          break;
+      }
+    });
 
-      const updatedMessages = [...newMessages, assistantMessage];
-      setMessages(updatedMessages);
-      await saveConversation(updatedMessages);
+    try {
+      await streamGenerateDork(requestId, ACTIVE_CONVERSATION_ID, { description: userMessage.content });
     } catch (error: any) {
       console.error('AI generation error:', error);
 
-      const errorMessage: Message = {
-        id: uuidv4(),
-        role: 'assistant',
-        content: `Sorry, I encountered an error: ${error.message || 'Failed to generate dork'}`,
-        timestamp: new Date().toISOString(),
-        error: true,
-      };
-
-      const updatedMessages = [...newMessages, errorMessage];
-      setMessages(updatedMessages);
-      await saveConversation(updatedMessages);
+      // Fall back to the template library when the provider can't be reached.
+      // A reply that already started streaming was stored by the backend, so
+      // the fallback is a message of its own.
+      const fallback = await offlineReply(userMessage.content, 'AI generation is unavailable.');
+      if (fallback) {
+        setMessages((prev) => [...prev, fallback]);
+        await persistMessage(fallback);
+      } else {
+        const errorMessage: Message = {
+          id: streamedMessageId ?? uuidv4(),
+          role: 'assistant',
+          content: `Sorry, I encountered an error: ${error?.message || String(error) || 'Failed to generate dork'}`,
+          timestamp: new Date().toISOString(),
+          error: true,
+        };
+        setMessages((prev) => [...prev.filter((m) => m.id !== errorMessage.id), errorMessage]);
+      }
     } finally {
+      unlisten();
+      activeRequestRef.current = null;
+      // The backend counts generations; refresh the remaining quota
+      await loadUsageStats();
       setLoading(false);
       inputRef.current?.focus();
     }
   };
 
+  const handleStop = async () => {
+    if (activeRequestRef.current) {
+      await cancelGeneration(activeRequestRef.current);
+    }
+  };
+
   const handleKeyPress = (e: React.KeyboardEvent) => {
     if (e.key === 'Enter' && !e.shiftKey) {
       e.preventDefault();
@@ -187,7 +297,14 @@ export default function AIGenerator() {
   const handleClear = async () => {
     if (confirm('Are you sure you want to clear the conversation?')) {
       setMessages([]);
-      await saveConversation([]);
+      if (conversationCreatedRef.current) {
+        conversationCreatedRef.current = false;
+        try {
+          await deleteConversation(ACTIVE_CONVERSATION_ID);
+        } catch (error) {
+          console.error('Failed to clear conversation:', error);
+        }
+      }
     }
   };
 
@@ -199,16 +316,20 @@ export default function AIGenerator() {
     }
   };
 
-  const handleSaveDork = async (dork: string, description: string) => {
+  const handleSaveDork = async (messageId: string, dork: string, description: string) => {
     try {
-      await saveDork({
-        id: uuidv4(),
+      const dorkId = uuidv4();
+      await saveDorkFromMessage(ACTIVE_CONVERSATION_ID, messageId, {
+        id: dorkId,
         name: description.substring(0, 50) + (description.length > 50 ? '...' : ''),
         query: dork,
         category: 'AI Generated',
         tags: ['AI Generated'],
         created_at: new Date().toISOString(),
       });
+      setMessages((prev) =>
+        prev.map((m) => (m.id === messageId ? { ...m, dork_id: dorkId } : m))
+      );
     } catch (error) {
       console.error('Failed to save dork:', error);
     }
@@ -300,7 +421,7 @@ export default function AIGenerator() {
         ) : (
           /* Messages */
           <div className="max-w-4xl mx-auto space-y-4">
-            {messages.map((message) => (
+            {messages.filter((m) => m.role !== 'summary').map((message) => (
               <div
                 key={message.id}
                 className={`flex ${message.role === 'user' ? 'justify-end' : 'justify-start'}`}
@@ -333,6 +454,13 @@ export default function AIGenerator() {
                           {message.dork}
                         </code>
                       </div>
+                      {message.warnings && message.warnings.length > 0 && (
+                        <ul className="mb-3 text-xs text-yellow-700 dark:text-yellow-400 list-disc list-inside">
+                          {message.warnings.map((warning) => (
+                            <li key={warning}>{warning}</li>
+                          ))}
+                        </ul>
+                      )}
                       <div className="flex gap-2">
                         <button
                           onClick={() => handleCopyDork(message.dork!)}
@@ -342,7 +470,7 @@ export default function AIGenerator() {
                           Copy
                         </button>
                         <button
-                          onClick={() => handleSaveDork(message.dork!, message.content)}
+                          onClick={() => handleSaveDork(message.id, message.dork!, message.content)}
                           className="flex items-center gap-1 px-3 py-1.5 text-xs bg-gray-100 dark:bg-gray-700 text-gray-700 dark:text-gray-300 rounded hover:bg-gray-200 dark:hover:bg-gray-600 transition-colors"
                         >
                           <BookmarkIcon className="w-4 h-4" />
@@ -413,14 +541,24 @@ export default function AIGenerator() {
               rows={2}
               disabled={loading}
             />
-            <button
-              onClick={handleSend}
-              disabled={!input.trim() || loading}
-              className="px-6 py-3 bg-purple-600 text-white rounded-lg hover:bg-purple-700 disabled:opacity-50 disabled:cursor-not-allowed transition-colors flex items-center gap-2"
-            >
-              <PaperAirplaneIcon className="w-5 h-5" />
-              Send
-            </button>
+            {loading ? (
+              <button
+                onClick={handleStop}
+                className="px-6 py-3 bg-gray-600 text-white rounded-lg hover:bg-gray-700 transition-colors flex items-center gap-2"
+              >
+                <StopIcon className="w-5 h-5" />
+                Stop
+              </button>
+            ) : (
+              <button
+                onClick={handleSend}
+                disabled={!input.trim()}
+                className="px-6 py-3 bg-purple-600 text-white rounded-lg hover:bg-purple-700 disabled:opacity-50 disabled:cursor-not-allowed transition-colors flex items-center gap-2"
+              >
+                <PaperAirplaneIcon className="w-5 h-5" />
+                Send
+              </button>
+            )}
           </div>
           <p className="text-xs text-gray-500 dark:text-gray-400 mt-2">
             Press Enter to send, Shift+Enter for new line
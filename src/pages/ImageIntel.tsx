@@ -1,17 +1,25 @@
 import { useState } from 'react';
 import { PhotoIcon, DocumentMagnifyingGlassIcon, MagnifyingGlassIcon } from '@heroicons/react/24/outline';
+import {
+  extractImageMetadata,
+  scrubImageMetadata,
+  type ImageMetadata,
+  type SanitizedImage,
+} from '../services/tauri';
 
-interface ImageMetadata {
+interface ImageDetails {
+  file: File;
   filename: string;
-  size: number;
-  dimensions: { width: number; height: number } | null;
-  format: string;
-  exif: Record<string, any>;
+  report: ImageMetadata;
 }
 
 export default function ImageIntel() {
   const [selectedImage, setSelectedImage] = useState<string | null>(null);
-  const [metadata, setMetadata] = useState<ImageMetadata | null>(null);
+  const [metadata, setMetadata] = useState<ImageDetails | null>(null);
+  const [metadataError, setMetadataError] = useState('');
+  const [sanitized, setSanitized] = useState<SanitizedImage | null>(null);
+  const [keepColorProfile, setKeepColorProfile] = useState(false);
+  const [scrubbing, setScrubbing] = useState(false);
   const [ocrText, setOcrText] = useState<string>('');
   const [loading, setLoading] = useState(false);
   const [activeTab, setActiveTab] = useState<'metadata' | 'ocr' | 'reverse'>('metadata');
@@ -23,46 +31,66 @@ export default function ImageIntel() {
     // Display image
     const reader = new FileReader();
     reader.onload = (e) => {
-      const result = e.target?.result as string;
-      setSelectedImage(result);
-      extractMetadata(file, result);
+      setSelectedImage(e.target?.result as string);
     };
     reader.readAsDataURL(file);
+    extractMetadata(file);
   };
 
-  const extractMetadata = async (file: File, dataUrl: string) => {
+  const extractMetadata = async (file: File) => {
     setLoading(true);
+    setMetadata(null);
+    setMetadataError('');
+    setSanitized(null);
 
-    // Create an image to get dimensions
-    const img = new Image();
-    img.onload = () => {
-      const meta: ImageMetadata = {
-        filename: file.name,
-        size: file.size,
-        dimensions: { width: img.width, height: img.height },
-        format: file.type,
-        exif: extractEXIF(file),
-      };
-      setMetadata(meta);
+    try {
+      const report = await extractImageMetadata(new Uint8Array(await file.arrayBuffer()));
+      setMetadata({ file, filename: file.name, report });
+    } catch (err) {
+      setMetadataError(err instanceof Error ? err.message : String(err));
+    } finally {
       setLoading(false);
-    };
-    img.src = dataUrl;
+    }
   };
 
-  const extractEXIF = (file: File): Record<string, any> => {
-    // Requires exif-js or equivalent library for real EXIF extraction
-    return {
-      'Camera Make': 'Demo Camera',
-      'Camera Model': 'DC-X1000',
-      'Date Taken': new Date().toISOString().split('T')[0],
-      'ISO': '400',
-      'Exposure': '1/125',
-      'F-Number': 'f/2.8',
-      'Focal Length': '50mm',
-      'GPS Latitude': '40.7128° N',
-      'GPS Longitude': '74.0060° W',
-      'Software': 'Adobe Photoshop 2024',
-    };
+  const exifSummary = (report: ImageMetadata): Record<string, string> => {
+    const exif = report.exif;
+    if (!exif) return {};
+    const lens = [exif.lens_make, exif.lens_model].filter(Boolean).join(' ');
+    const entries: [string, string | null][] = [
+      ['Camera Make', exif.make],
+      ['Camera Model', exif.model],
+      ['Serial Number', exif.serial_number],
+      ['Lens', lens || null],
+      ['Date Taken', exif.date_time_original],
+      ['Date Digitized', exif.date_time_digitized],
+      ['Date Modified', exif.date_time_modified],
+      ['Software', exif.software],
+      ['Artist', exif.artist],
+      ['Copyright', exif.copyright],
+      ['Exposure', exif.exposure_time],
+      ['F-Number', exif.f_number],
+      ['ISO', exif.iso],
+      ['Focal Length', exif.focal_length],
+    ];
+    return Object.fromEntries(entries.filter((entry): entry is [string, string] => entry[1] !== null));
+  };
+
+  const exportSanitized = async () => {
+    if (!metadata) return;
+    setScrubbing(true);
+    setMetadataError('');
+
+    try {
+      const data = new Uint8Array(await metadata.file.arrayBuffer());
+      setSanitized(
+        await scrubImageMetadata(data, metadata.filename, { keep_color_profile: keepColorProfile })
+      );
+    } catch (err) {
+      setMetadataError(err instanceof Error ? err.message : String(err));
+    } finally {
+      setScrubbing(false);
+    }
   };
 
   const performOCR = async () => {
@@ -122,7 +150,7 @@ export default function ImageIntel() {
               <p className="text-sm text-gray-600 dark:text-gray-400">
                 <span className="font-semibold">Click to upload</span> or drag and drop
               </p>
-              <p className="text-xs text-gray-500 dark:text-gray-500">PNG, JPG, GIF up to 10MB</p>
+              <p className="text-xs text-gray-500 dark:text-gray-500">JPG, PNG, WebP, TIFF, HEIC up to 10MB</p>
             </div>
             <input
               id="file-upload"
@@ -194,9 +222,15 @@ export default function ImageIntel() {
         )}
 
         {/* Tab Content */}
-        {selectedImage && metadata && (
+        {selectedImage && (
           <div className="space-y-4">
-            {activeTab === 'metadata' && (
+            {activeTab === 'metadata' && metadataError && (
+              <div className="p-3 bg-red-50 dark:bg-red-900/20 text-red-600 dark:text-red-400 rounded-lg text-sm">
+                {metadataError}
+              </div>
+            )}
+
+            {activeTab === 'metadata' && metadata && (
               <div className="space-y-3">
                 <div className="bg-gray-100 dark:bg-gray-700 rounded-lg p-4">
                   <h3 className="font-semibold text-gray-900 dark:text-white mb-3">File Information</h3>
@@ -210,44 +244,102 @@ export default function ImageIntel() {
                     <div className="flex justify-between">
                       <span className="text-gray-600 dark:text-gray-400">Size:</span>
                       <span className="font-medium text-gray-900 dark:text-white">
-                        {formatFileSize(metadata.size)}
+                        {formatFileSize(metadata.report.file_size)}
                       </span>
                     </div>
                     <div className="flex justify-between">
                       <span className="text-gray-600 dark:text-gray-400">Format:</span>
-                      <span className="font-medium text-gray-900 dark:text-white">{metadata.format}</span>
+                      <span className="font-medium text-gray-900 dark:text-white">
+                        {metadata.report.format.toUpperCase()}
+                      </span>
                     </div>
-                    {metadata.dimensions && (
+                    {metadata.report.width !== null && metadata.report.height !== null && (
                       <div className="flex justify-between">
                         <span className="text-gray-600 dark:text-gray-400">Dimensions:</span>
                         <span className="font-medium text-gray-900 dark:text-white">
-                          {metadata.dimensions.width} × {metadata.dimensions.height}
+                          {metadata.report.width} × {metadata.report.height}
                         </span>
                       </div>
                     )}
+                    <div className="flex justify-between">
+                      <span className="text-gray-600 dark:text-gray-400">SHA-256:</span>
+                      <button
+                        onClick={() => copyToClipboard(metadata.report.sha256)}
+                        className="font-mono text-xs text-gray-900 dark:text-white truncate ml-2 hover:underline"
+                        title={metadata.report.sha256}
+                      >
+                        {metadata.report.sha256.slice(0, 16)}…
+                      </button>
+                    </div>
+                  </div>
+                </div>
+
+                <div className="bg-gray-100 dark:bg-gray-700 rounded-lg p-4">
+                  <h3 className="font-semibold text-gray-900 dark:text-white mb-3">Sanitized Copy</h3>
+                  <div className="space-y-2 text-sm">
+                    <label className="flex items-center gap-2 text-gray-600 dark:text-gray-400">
+                      <input
+                        type="checkbox"
+                        checked={keepColorProfile}
+                        onChange={(e) => setKeepColorProfile(e.target.checked)}
+                      />
+                      Keep color profile
+                    </label>
+                    <button
+                      onClick={exportSanitized}
+                      disabled={scrubbing}
+                      className="btn-secondary w-full disabled:opacity-50"
+                    >
+                      {scrubbing ? 'Removing metadata...' : 'Save Copy Without Metadata'}
+                    </button>
+                    {sanitized && (
+                      <div className="space-y-1">
+                        <p className="text-gray-900 dark:text-white break-all">Saved to {sanitized.path}</p>
+                        <p className="text-gray-600 dark:text-gray-400">
+                          Removed {sanitized.removed.length} block(s),{' '}
+                          {formatFileSize(sanitized.original_size - sanitized.sanitized_size)}
+                          {sanitized.removed_gps && ', including GPS location'}
+                        </p>
+                        {sanitized.removed.map((block) => (
+                          <div key={block.offset} className="flex justify-between">
+                            <span className="text-gray-600 dark:text-gray-400">
+                              {block.kind}
+                              {block.label && ` (${block.label})`}
+                            </span>
+                            <span className="font-medium text-gray-900 dark:text-white">
+                              {formatFileSize(block.length)}
+                            </span>
+                          </div>
+                        ))}
+                      </div>
+                    )}
                   </div>
                 </div>
 
                 <div className="bg-gray-100 dark:bg-gray-700 rounded-lg p-4">
                   <h3 className="font-semibold text-gray-900 dark:text-white mb-3">EXIF Data</h3>
                   <div className="space-y-2 text-sm">
-                    {Object.entries(metadata.exif).map(([key, value]) => (
+                    {Object.entries(exifSummary(metadata.report)).map(([key, value]) => (
                       <div key={key} className="flex justify-between">
                         <span className="text-gray-600 dark:text-gray-400">{key}:</span>
-                        <span className="font-medium text-gray-900 dark:text-white">{value}</span>
+                        <span className="font-medium text-gray-900 dark:text-white text-right ml-2">{value}</span>
                       </div>
                     ))}
+                    {!metadata.report.exif && (
+                      <p className="text-gray-500 dark:text-gray-400">No EXIF data</p>
+                    )}
                   </div>
                 </div>
 
-                {metadata.exif['GPS Latitude'] && metadata.exif['GPS Longitude'] && (
+                {metadata.report.gps && (
                   <div className="bg-blue-50 dark:bg-blue-900/20 border border-blue-200 dark:border-blue-800 rounded-lg p-4">
                     <h3 className="font-semibold text-blue-900 dark:text-blue-200 mb-2">GPS Location</h3>
                     <p className="text-sm text-blue-800 dark:text-blue-300 mb-2">
-                      {metadata.exif['GPS Latitude']}, {metadata.exif['GPS Longitude']}
+                      {metadata.report.gps.latitude.toFixed(6)}, {metadata.report.gps.longitude.toFixed(6)}
+                      {metadata.report.gps.altitude !== null && ` · ${metadata.report.gps.altitude.toFixed(1)} m`}
                     </p>
                     <a
-                      href={`https://www.google.com/maps?q=${metadata.exif['GPS Latitude']},${metadata.exif['GPS Longitude']}`}
+                      href={`https://www.google.com/maps?q=${metadata.report.gps.latitude},${metadata.report.gps.longitude}`}
                       target="_blank"
                       rel="noopener noreferrer"
                       className="text-sm text-blue-600 dark:text-blue-400 hover:underline"
@@ -256,6 +348,85 @@ export default function ImageIntel() {
                     </a>
                   </div>
                 )}
+
+                {Object.keys(metadata.report.iptc).length > 0 && (
+                  <div className="bg-gray-100 dark:bg-gray-700 rounded-lg p-4">
+                    <h3 className="font-semibold text-gray-900 dark:text-white mb-3">IPTC</h3>
+                    <div className="space-y-2 text-sm">
+                      {Object.entries(metadata.report.iptc).map(([key, values]) => (
+                        <div key={key} className="flex justify-between">
+                          <span className="text-gray-600 dark:text-gray-400">{key}:</span>
+                          <span className="font-medium text-gray-900 dark:text-white text-right ml-2">
+                            {values.join(', ')}
+                          </span>
+                        </div>
+                      ))}
+                    </div>
+                  </div>
+                )}
+
+                {metadata.report.xmp && Object.keys(metadata.report.xmp.properties).length > 0 && (
+                  <div className="bg-gray-100 dark:bg-gray-700 rounded-lg p-4">
+                    <h3 className="font-semibold text-gray-900 dark:text-white mb-3">XMP</h3>
+                    <div className="space-y-2 text-sm">
+                      {Object.entries(metadata.report.xmp.properties).map(([key, value]) => (
+                        <div key={key} className="flex justify-between">
+                          <span className="text-gray-600 dark:text-gray-400">{key}:</span>
+                          <span className="font-medium text-gray-900 dark:text-white text-right ml-2 break-all">
+                            {value}
+                          </span>
+                        </div>
+                      ))}
+                    </div>
+                  </div>
+                )}
+
+                {metadata.report.icc && (
+                  <div className="bg-gray-100 dark:bg-gray-700 rounded-lg p-4">
+                    <h3 className="font-semibold text-gray-900 dark:text-white mb-3">ICC Profile</h3>
+                    <div className="space-y-2 text-sm">
+                      <div className="flex justify-between">
+                        <span className="text-gray-600 dark:text-gray-400">Description:</span>
+                        <span className="font-medium text-gray-900 dark:text-white text-right ml-2">
+                          {metadata.report.icc.description ?? 'Unnamed'}
+                        </span>
+                      </div>
+                      <div className="flex justify-between">
+                        <span className="text-gray-600 dark:text-gray-400">Color Space:</span>
+                        <span className="font-medium text-gray-900 dark:text-white">
+                          {metadata.report.icc.color_space ?? 'Unknown'} · v{metadata.report.icc.version}
+                        </span>
+                      </div>
+                    </div>
+                  </div>
+                )}
+
+                {metadata.report.thumbnails.length > 0 && (
+                  <div className="bg-gray-100 dark:bg-gray-700 rounded-lg p-4">
+                    <h3 className="font-semibold text-gray-900 dark:text-white mb-3">Embedded Thumbnails</h3>
+                    <div className="flex flex-wrap gap-2">
+                      {metadata.report.thumbnails.map((thumbnail, index) => (
+                        <img
+                          key={index}
+                          src={thumbnail.data_url}
+                          alt={`Embedded ${thumbnail.source} thumbnail`}
+                          title={`${thumbnail.source.toUpperCase()} · ${thumbnail.width ?? '?'} × ${thumbnail.height ?? '?'}`}
+                          className="max-h-24 rounded border border-gray-300 dark:border-gray-600"
+                        />
+                      ))}
+                    </div>
+                  </div>
+                )}
+
+                {metadata.report.warnings.length > 0 && (
+                  <div className="bg-yellow-50 dark:bg-yellow-900/20 border border-yellow-200 dark:border-yellow-800 rounded-lg p-3">
+                    {metadata.report.warnings.map((warning) => (
+                      <p key={warning} className="text-sm text-yellow-800 dark:text-yellow-300">
+                        {warning}
+                      </p>
+                    ))}
+                  </div>
+                )}
               </div>
             )}
 
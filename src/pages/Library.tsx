@@ -12,10 +12,18 @@ import {
   CheckIcon,
   FunnelIcon,
   ArrowsUpDownIcon,
+  ChatBubbleLeftRightIcon,
 } from '@heroicons/react/24/outline';
 import { BookOpenIcon } from '@heroicons/react/24/solid';
 import { DORK_TEMPLATES, TEMPLATE_CATEGORIES, type DorkTemplate } from '../data/dorkTemplates';
-import { getAllDorks, saveDork, deleteDork, type DorkQuery } from '../services/tauri';
+import {
+  getAllDorks,
+  saveDork,
+  deleteDork,
+  listDorkProvenance,
+  type DorkQuery,
+  type DorkProvenance,
+} from '../services/tauri';
 import { useNavigate } from 'react-router-dom';
 
 type ViewMode = 'grid' | 'list';
@@ -24,6 +32,12 @@ type SortOption = 'recent' | 'name-asc' | 'name-desc' | 'category';
 interface SavedDork extends DorkTemplate {
   saved_at: string;
   custom?: boolean;
+  provenance?: DorkProvenance;
+}
+
+function provenanceLabel(provenance: DorkProvenance): string {
+  const source = provenance.conversation_id ? 'conversation' : 'deleted conversation';
+  return `Generated in ${source} "${provenance.conversation_title}"`;
 }
 
 export default function Library() {
@@ -46,6 +60,12 @@ export default function Library() {
     try {
       // Load custom saved dorks from Rust backend vault
       const savedFromVault = await getAllDorks();
+      // Provenance is optional context, so a failed lookup still shows the dorks
+      const provenance = await listDorkProvenance().catch((error) => {
+        console.error('Failed to load dork provenance:', error);
+        return [] as DorkProvenance[];
+      });
+      const provenanceById = new Map(provenance.map(p => [p.dork_id, p]));
       const customDorks: SavedDork[] = savedFromVault.map(d => ({
         id: d.id,
         name: d.name,
@@ -55,6 +75,7 @@ export default function Library() {
         tags: d.tags,
         saved_at: d.created_at,
         custom: true,
+        provenance: provenanceById.get(d.id),
       }));
 
       // Combine templates with custom dorks
@@ -471,6 +492,16 @@ export default function Library() {
                   {dork.description}
                 </p>
 
+                {dork.provenance && (
+                  <p
+                    className="flex items-center gap-1 text-xs text-gray-500 dark:text-gray-400 mb-3"
+                    title={dork.provenance.message_excerpt}
+                  >
+                    <ChatBubbleLeftRightIcon className="w-4 h-4 flex-shrink-0" />
+                    <span className="truncate">{provenanceLabel(dork.provenance)}</span>
+                  </p>
+                )}
+
                 <div className="bg-gray-50 dark:bg-gray-900 rounded p-2 mb-3">
                   <code className="text-xs text-purple-600 dark:text-purple-400 break-all line-clamp-2">
                     {dork.query}
@@ -571,6 +602,15 @@ export default function Library() {
                       <div>
                         <p className="font-medium text-gray-900 dark:text-white">{dork.name}</p>
                         <p className="text-sm text-gray-600 dark:text-gray-400 line-clamp-1">{dork.description}</p>
+                        {dork.provenance && (
+                          <p
+                            className="flex items-center gap-1 text-xs text-gray-500 dark:text-gray-400"
+                            title={dork.provenance.message_excerpt}
+                          >
+                            <ChatBubbleLeftRightIcon className="w-3 h-3 flex-shrink-0" />
+                            <span className="truncate">{provenanceLabel(dork.provenance)}</span>
+                          </p>
+                        )}
                       </div>
                     </td>
                     <td className="px-4 py-3 text-sm text-gray-600 dark:text-gray-400">{dork.category}</td>
@@ -1,4 +1,4 @@
-import { useCallback, useState } from 'react';
+import { useCallback, useEffect, useState } from 'react';
 import {
   ReactFlow,
   Node,
@@ -13,10 +13,21 @@ import {
   MarkerType,
 } from '@xyflow/react';
 import '@xyflow/react/dist/style.css';
+import {
+  enrichDns,
+  enrichCertificateTransparency,
+  loadGraph,
+  clearGraph as clearStoredGraph,
+  computeGraphLayout,
+  type EntityType,
+  type LayoutAlgorithm,
+  type NodePosition,
+  type Subgraph,
+} from '../services/tauri';
 
 interface NodeData {
   label: string;
-  type: 'domain' | 'subdomain' | 'ip' | 'technology' | 'service';
+  type: EntityType;
   info?: string;
 }
 
@@ -40,12 +51,85 @@ const initialNodes: Node<NodeData>[] = [
 
 const initialEdges: Edge[] = [];
 
+const NODE_COLORS: Partial<Record<EntityType, { background: string; border: string }>> = {
+  domain: { background: '#6366f1', border: '#4f46e5' },
+  subdomain: { background: '#8b5cf6', border: '#7c3aed' },
+  ip: { background: '#10b981', border: '#059669' },
+  technology: { background: '#06b6d4', border: '#06b6d4' },
+  service: { background: '#06b6d4', border: '#0891b2' },
+};
+
+/**
+ * Turn a stored graph and its computed layout into React Flow nodes and edges
+ */
+function toFlow(graph: Subgraph, layout: NodePosition[]): { nodes: Node<NodeData>[]; edges: Edge[] } {
+  const positions = new Map(layout.map((position) => [position.id, position]));
+  const nodes = graph.entities.map((entity): Node<NodeData> => {
+    const position = positions.get(entity.id) ?? { x: 0, y: 0 };
+    const colors = NODE_COLORS[entity.type] ?? { background: '#6b7280', border: '#4b5563' };
+    const sources = entity.sources.map((source) => `${source.kind}: ${source.reference}`).join(', ');
+
+    return {
+      id: entity.id,
+      type: 'default',
+      position: { x: position.x, y: position.y },
+      data: {
+        label: entity.value,
+        type: entity.type,
+        info: `Seen ${new Date(entity.last_seen).toLocaleString()} via ${sources}`,
+      },
+      style: {
+        background: colors.background,
+        color: 'white',
+        border: `${entity.type === 'domain' ? 2 : 1}px solid ${colors.border}`,
+        borderRadius: entity.type === 'domain' ? '8px' : '6px',
+        padding: entity.type === 'domain' ? '12px' : '10px',
+        fontSize: entity.type === 'domain' ? '16px' : '12px',
+        fontWeight: entity.type === 'domain' ? 'bold' : undefined,
+      },
+    };
+  });
+
+  const edges = graph.relationships.map((relationship): Edge => ({
+    id: relationship.id,
+    source: relationship.source_id,
+    target: relationship.target_id,
+    label: relationship.type.replace(/_/g, ' '),
+    type: 'smoothstep',
+    animated: relationship.type === 'subdomain_of',
+    markerEnd: { type: MarkerType.ArrowClosed },
+  }));
+
+  return { nodes, edges };
+}
+
 export default function NexusGraph() {
   const [nodes, setNodes, onNodesChange] = useNodesState(initialNodes);
   const [edges, setEdges, onEdgesChange] = useEdgesState(initialEdges);
   const [domain, setDomain] = useState('');
   const [loading, setLoading] = useState(false);
   const [selectedNode, setSelectedNode] = useState<Node<NodeData> | null>(null);
+  const [layout, setLayout] = useState<LayoutAlgorithm>('hierarchical');
+
+  const showGraph = useCallback(
+    async (graph: Subgraph) => {
+      if (graph.entities.length === 0) {
+        setNodes(initialNodes);
+        setEdges(initialEdges);
+        return;
+      }
+      const flow = toFlow(graph, await computeGraphLayout({ algorithm: layout }));
+      setNodes(flow.nodes);
+      setEdges(flow.edges);
+    },
+    [layout, setNodes, setEdges]
+  );
+
+  useEffect(() => {
+    loadGraph()
+      .then(showGraph)
+      .catch((error) => console.error('Failed to load graph:', error));
+  }, [showGraph]);
 
   const onConnect = useCallback(
     (params: Connection) => setEdges((eds) => addEdge(params, eds)),
@@ -56,163 +140,54 @@ export default function NexusGraph() {
     if (!domain.trim()) return;
 
     setLoading(true);
+    const target = domain.trim();
 
-    // Clear existing nodes and edges
-    const newNodes: Node<NodeData>[] = [];
-    const newEdges: Edge[] = [];
-    let nodeId = 0;
-
-    // Create domain node
-    const domainNode: Node<NodeData> = {
-      id: String(nodeId++),
-      type: 'default',
-      position: { x: 400, y: 50 },
-      data: { label: domain, type: 'domain', info: `Primary domain: ${domain}` },
-      style: {
-        background: '#6366f1',
-        color: 'white',
-        border: '2px solid #4f46e5',
-        borderRadius: '8px',
-        padding: '12px',
-        fontSize: '16px',
-        fontWeight: 'bold',
-      },
-    };
-    newNodes.push(domainNode);
+    // The apex plus common host names; ones that don't resolve are dropped
+    const names = [target, ...['www', 'api', 'mail', 'ftp', 'admin'].map((name) => `${name}.${target}`)];
 
-    // Simulate DNS lookup and subdomain discovery
-    setTimeout(() => {
-      // Add subdomains
-      const subdomains = ['www', 'api', 'mail', 'ftp', 'admin'];
-      const subdomainY = 200;
-      subdomains.forEach((subdomain, index) => {
-        const subdomainNode: Node<NodeData> = {
-          id: String(nodeId++),
-          type: 'default',
-          position: { x: 100 + index * 150, y: subdomainY },
-          data: {
-            label: `${subdomain}.${domain}`,
-            type: 'subdomain',
-            info: `Subdomain discovered: ${subdomain}`,
-          },
-          style: {
-            background: '#8b5cf6',
-            color: 'white',
-            border: '1px solid #7c3aed',
-            borderRadius: '6px',
-            padding: '10px',
-            fontSize: '12px',
-          },
-        };
-        newNodes.push(subdomainNode);
+    try {
+      try {
+        const ct = await enrichCertificateTransparency(target);
+        names.push(...ct.subdomains.map((subdomain) => subdomain.name));
+      } catch (error) {
+        console.warn('Certificate transparency search failed:', error);
+      }
 
-        // Create edge from domain to subdomain
-        newEdges.push({
-          id: `e${domainNode.id}-${subdomainNode.id}`,
-          source: domainNode.id,
-          target: subdomainNode.id,
-          type: 'smoothstep',
-          animated: true,
-          markerEnd: { type: MarkerType.ArrowClosed },
-          style: { stroke: '#8b5cf6' },
-        });
-      });
-
-      // Add IP addresses
-      const ips = ['192.168.1.1', '10.0.0.1'];
-      const ipY = 350;
-      ips.forEach((ip, index) => {
-        const ipNode: Node<NodeData> = {
-          id: String(nodeId++),
-          type: 'default',
-          position: { x: 250 + index * 200, y: ipY },
-          data: {
-            label: ip,
-            type: 'ip',
-            info: `Resolved IP address: ${ip}`,
-          },
-          style: {
-            background: '#10b981',
-            color: 'white',
-            border: '1px solid #059669',
-            borderRadius: '6px',
-            padding: '10px',
-            fontSize: '12px',
-          },
-        };
-        newNodes.push(ipNode);
-
-        // Create edges from subdomains to IPs
-        const sourceSubdomain = newNodes[1 + index % subdomains.length];
-        newEdges.push({
-          id: `e${sourceSubdomain.id}-${ipNode.id}`,
-          source: sourceSubdomain.id,
-          target: ipNode.id,
-          type: 'smoothstep',
-          markerEnd: { type: MarkerType.ArrowClosed },
-          style: { stroke: '#10b981' },
-        });
-      });
-
-      // Add technologies/services
-      const technologies = [
-        { name: 'nginx', color: '#06b6d4' },
-        { name: 'Node.js', color: '#06b6d4' },
-        { name: 'PostgreSQL', color: '#06b6d4' },
-      ];
-      const techY = 500;
-      technologies.forEach((tech, index) => {
-        const techNode: Node<NodeData> = {
-          id: String(nodeId++),
-          type: 'default',
-          position: { x: 200 + index * 200, y: techY },
-          data: {
-            label: tech.name,
-            type: 'technology',
-            info: `Technology detected: ${tech.name}`,
-          },
-          style: {
-            background: tech.color,
-            color: 'white',
-            border: `1px solid ${tech.color}`,
-            borderRadius: '6px',
-            padding: '8px',
-            fontSize: '11px',
-          },
-        };
-        newNodes.push(techNode);
-
-        // Create edges from IPs to technologies
-        const sourceIp = newNodes[newNodes.length - technologies.length - ips.length + index % ips.length];
-        newEdges.push({
-          id: `e${sourceIp.id}-${techNode.id}`,
-          source: sourceIp.id,
-          target: techNode.id,
-          type: 'smoothstep',
-          markerEnd: { type: MarkerType.ArrowClosed },
-          style: { stroke: tech.color, strokeDasharray: '5,5' },
-        });
-      });
-
-      setNodes(newNodes);
-      setEdges(newEdges);
+      const report = await enrichDns([...new Set(names)].slice(0, 500));
+      if (report.errors.length > 0) {
+        console.warn('DNS lookups failed:', report.errors);
+      }
+      await showGraph(await loadGraph());
+    } catch (error) {
+      console.error('Failed to resolve domain:', error);
+    } finally {
       setLoading(false);
-    }, 1000);
+    }
   };
 
-  const autoLayout = () => {
-    // Simple grid layout
-    const nodesCopy = nodes.map((node, index) => ({
-      ...node,
-      position: {
-        x: (index % 5) * 200 + 50,
-        y: Math.floor(index / 5) * 150 + 50,
-      },
-    }));
-    setNodes(nodesCopy);
+  const autoLayout = async () => {
+    try {
+      const positions = new Map(
+        (await computeGraphLayout({ algorithm: layout })).map((position) => [position.id, position])
+      );
+      setNodes((current) =>
+        current.map((node) => {
+          const position = positions.get(node.id);
+          return position ? { ...node, position: { x: position.x, y: position.y } } : node;
+        })
+      );
+    } catch (error) {
+      console.error('Failed to compute layout:', error);
+    }
   };
 
-  const clearGraph = () => {
+  const clearGraph = async () => {
+    try {
+      await clearStoredGraph();
+    } catch (error) {
+      console.error('Failed to clear graph:', error);
+      return;
+    }
     setNodes(initialNodes);
     setEdges(initialEdges);
     setDomain('');
@@ -261,6 +236,15 @@ export default function NexusGraph() {
         </div>
 
         {/* Controls */}
+        <select
+          value={layout}
+          onChange={(e) => setLayout(e.target.value as LayoutAlgorithm)}
+          className="w-full mb-2 px-3 py-2 border border-gray-300 dark:border-gray-600 rounded-lg bg-white dark:bg-gray-700 text-gray-900 dark:text-white text-sm"
+        >
+          <option value="hierarchical">Hierarchical</option>
+          <option value="radial">Radial</option>
+          <option value="force_directed">Force-directed</option>
+        </select>
         <div className="flex gap-2 mb-6">
           <button
             onClick={autoLayout}
@@ -1,4 +1,5 @@
 import { invoke } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
 
 export interface AppConfig {
   version: string;
@@ -43,16 +44,72 @@ export interface UsageStats {
   last_reset_date: string;
   total_dorks: number;
   total_conversations: number;
+  prompt_tokens_today: number;
+  completion_tokens_today: number;
+  total_prompt_tokens: number;
+  total_completion_tokens: number;
   created_at: string;
   updated_at: string;
 }
 
+export interface DorkGenerationRequest {
+  description: string;
+  searchEngine?: 'google' | 'bing' | 'duckduckgo';
+  category?: string;
+  advanced?: boolean;
+  /** Always ask the provider, even if an identical request was cached */
+  bypassCache?: boolean;
+}
+
+export type DorkSeverity = 'Low' | 'Medium' | 'High' | 'Critical';
+
+export interface DorkGenerationResult {
+  query: string;
+  explanation: string;
+  category: string;
+  severity: DorkSeverity;
+  tags: string[];
+  alternatives?: string[];
+  /** Lint findings and repairs that didn't make the result unusable */
+  warnings: string[];
+  /** Prompt template version (`<name>@<version>`) that produced this result */
+  prompt_version: string;
+  /** Served from the response cache */
+  cached: boolean;
+}
+
+export interface TokenUsage {
+  prompt_tokens: number;
+  completion_tokens: number;
+  estimated: boolean;
+}
+
+export type AIStreamEvent =
+  | { kind: 'started'; request_id: string; message_id: string }
+  | { kind: 'chunk'; request_id: string; delta: string }
+  | { kind: 'done'; request_id: string; message_id: string; result: DorkGenerationResult; usage: TokenUsage }
+  | { kind: 'cancelled'; request_id: string; message_id: string; usage: TokenUsage }
+  | { kind: 'error'; request_id: string; message: string };
+
+export type AIProviderConfig =
+  | { type: 'gemini'; model?: string }
+  | { type: 'openai_compatible'; base_url: string; model: string }
+  | { type: 'ollama'; base_url?: string; model: string };
+
 export interface Message {
   id: string;
-  role: 'user' | 'assistant';
+  /** `summary` messages stand in for older turns when replaying context */
+  role: 'user' | 'assistant' | 'summary';
   content: string;
   timestamp: string;
   dork?: string;
+  dork_id?: string;
+  /** Prompt template version that produced an AI message */
+  prompt_version?: string;
+  /** Always included in the context sent to the model */
+  pinned?: boolean;
+  /** On a summary, the id of the last message it covers */
+  summarizes_through?: string;
 }
 
 export interface Conversation {
@@ -63,6 +120,110 @@ export interface Conversation {
   updated_at: string;
 }
 
+export interface ConversationSummary {
+  id: string;
+  title: string;
+  message_count: number;
+  last_message_role?: 'user' | 'assistant';
+  last_message_preview?: string;
+  last_message_at?: string;
+  created_at: string;
+  updated_at: string;
+}
+
+export interface DorkProvenance {
+  dork_id: string;
+  conversation_id?: string;
+  message_id?: string;
+  conversation_title: string;
+  message_excerpt: string;
+  recorded_at: string;
+}
+
+export interface DuplicateCluster {
+  canonical_query: string;
+  exact: boolean;
+  min_similarity: number;
+  dorks: DorkQuery[];
+}
+
+export interface DorkMergeRecord {
+  merged_id: string;
+  into_id: string;
+  name: string;
+  query: string;
+  category: string;
+  tags: string[];
+  created_at: string;
+  merged_at: string;
+}
+
+export type ImportFormat =
+  | { type: 'ghdb_csv' }
+  | { type: 'ghdb_xml' }
+  | {
+      type: 'csv';
+      mapping: {
+        query: string;
+        name?: string;
+        category?: string;
+        tags?: string;
+        tag_separator?: string;
+      };
+    }
+  | { type: 'text' };
+
+export interface ImportOptions {
+  dry_run?: boolean;
+  default_category?: string;
+  extra_tags?: string[];
+}
+
+export interface ImportReport {
+  dry_run: boolean;
+  total_rows: number;
+  imported: DorkQuery[];
+  duplicates: {
+    row: number;
+    query: string;
+    existing_id?: string;
+    duplicate_of_row?: number;
+  }[];
+  errors: { row: number; message: string }[];
+}
+
+export type RestoreMode = 'merge' | 'replace';
+
+export interface BackupTableManifest {
+  name: string;
+  primary_key: string;
+  row_count: number;
+  sha256: string;
+}
+
+export interface BackupInfo {
+  version: number;
+  app_version: string;
+  created_at: string;
+  encrypted: boolean;
+  manifest: {
+    // Empty for encrypted backups, whose table list is sealed with the data
+    tables: BackupTableManifest[];
+    payload_sha256: string;
+  };
+}
+
+export interface RestoreReport {
+  mode: RestoreMode;
+  tables: {
+    name: string;
+    inserted: number;
+    unchanged: number;
+    conflicts: number;
+  }[];
+  conflicts: { table: string; id: string }[];
+}
+
 /**
  * Get current application configuration and status
  */
@@ -77,13 +238,6 @@ export async function storeGeminiApiKey(apiKey: string): Promise<void> {
   await invoke('store_gemini_api_key', { apiKey });
 }
 
-/**
- * Retrieve Gemini API key from OS keyring
- */
-export async function getGeminiApiKey(): Promise<string> {
-  return await invoke<string>('get_gemini_api_key');
-}
-
 /**
  * Delete Gemini API key from OS keyring
  */
@@ -137,6 +291,60 @@ export async function deleteDork(id: string): Promise<void> {
   await invoke('delete_dork', { id });
 }
 
+/**
+ * Save a dork generated in a conversation, recording its source message
+ */
+export async function saveDorkFromMessage(
+  conversationId: string,
+  messageId: string,
+  dork: DorkQuery
+): Promise<DorkProvenance> {
+  return await invoke<DorkProvenance>('save_dork_from_message', {
+    conversationId,
+    messageId,
+    dork,
+  });
+}
+
+/**
+ * Get provenance for one dork, or for every dork when no id is given
+ */
+export async function listDorkProvenance(dorkId?: string): Promise<DorkProvenance[]> {
+  return await invoke<DorkProvenance[]>('list_dork_provenance', { dorkId });
+}
+
+/**
+ * Find clusters of duplicate / near-duplicate dorks (threshold 0..1, default 0.75)
+ */
+export async function findDuplicateDorks(threshold?: number): Promise<DuplicateCluster[]> {
+  return await invoke<DuplicateCluster[]>('find_duplicate_dorks', { threshold });
+}
+
+/**
+ * Merge duplicate dorks into one, combining tags and keeping history
+ */
+export async function mergeDorks(keepId: string, mergeIds: string[]): Promise<DorkQuery> {
+  return await invoke<DorkQuery>('merge_dorks', { keepId, mergeIds });
+}
+
+/**
+ * Get dorks previously merged into the given dork
+ */
+export async function getDorkMergeHistory(id: string): Promise<DorkMergeRecord[]> {
+  return await invoke<DorkMergeRecord[]>('get_dork_merge_history', { id });
+}
+
+/**
+ * Import dorks from GHDB dumps, mapped CSV or plain text (use dry_run to preview)
+ */
+export async function importDorks(
+  content: string,
+  format: ImportFormat,
+  options: ImportOptions = {}
+): Promise<ImportReport> {
+  return await invoke<ImportReport>('import_dorks', { content, format, options });
+}
+
 /**
  * Export data in specified format
  */
@@ -197,13 +405,6 @@ export async function getUsageStats(): Promise<UsageStats> {
   return await invoke<UsageStats>('get_usage_stats');
 }
 
-/**
- * Increment AI generation counter and return new count
- */
-export async function incrementAIUsage(): Promise<number> {
-  return await invoke<number>('increment_ai_usage');
-}
-
 /**
  * Check if user can generate AI dork (respects tier limits)
  */
@@ -225,6 +426,300 @@ export async function getRemainingAIGenerations(): Promise<number> {
   return await invoke<number>('get_remaining_ai_generations');
 }
 
+export interface QuotaStatus {
+  used: number;
+  /** Null for unlimited tiers */
+  limit: number | null;
+  remaining: number | null;
+  /** When the next generation frees up (UTC) */
+  resets_at: string | null;
+}
+
+export type QuotaWindow = { type: 'calendar_day' } | { type: 'rolling'; hours: number };
+
+export interface QuotaConfig {
+  /** IANA time zone whose midnight starts a new day */
+  timezone: string;
+  window: QuotaWindow;
+}
+
+/**
+ * Get AI generations used and remaining in the current quota window
+ */
+export async function getAIQuotaStatus(): Promise<QuotaStatus> {
+  return await invoke<QuotaStatus>('get_ai_quota_status');
+}
+
+/**
+ * Get the quota window settings
+ */
+export async function getAIQuotaConfig(): Promise<QuotaConfig> {
+  return await invoke<QuotaConfig>('get_ai_quota_config');
+}
+
+/**
+ * Set the quota reset time zone and window
+ */
+export async function setAIQuotaConfig(config: QuotaConfig): Promise<void> {
+  await invoke('set_ai_quota_config', { config });
+}
+
+export interface CacheConfig {
+  enabled: boolean;
+  ttl_hours: number;
+  /** Size limit before least recently used entries are evicted */
+  max_bytes: number;
+}
+
+export interface CacheStats {
+  entries: number;
+  total_bytes: number;
+  hits: number;
+  misses: number;
+  hit_rate: number;
+}
+
+/**
+ * Get AI response cache size and hit statistics
+ */
+export async function getAICacheStats(): Promise<CacheStats> {
+  return await invoke<CacheStats>('get_ai_cache_stats');
+}
+
+/**
+ * Get the AI response cache settings
+ */
+export async function getAICacheConfig(): Promise<CacheConfig> {
+  return await invoke<CacheConfig>('get_ai_cache_config');
+}
+
+/**
+ * Set the AI response cache TTL, size limit and on/off switch
+ */
+export async function setAICacheConfig(config: CacheConfig): Promise<void> {
+  await invoke('set_ai_cache_config', { config });
+}
+
+/**
+ * Drop every cached AI response
+ */
+export async function clearAICache(): Promise<number> {
+  return await invoke<number>('clear_ai_cache');
+}
+
+export type UsagePeriod = 'day' | 'month';
+
+export interface UsageRollup {
+  /** `YYYY-MM-DD` or `YYYY-MM` */
+  period: string;
+  provider: string;
+  model: string;
+  feature: string;
+  requests: number;
+  failed_requests: number;
+  prompt_tokens: number;
+  completion_tokens: number;
+  avg_latency_ms: number;
+  /** Null when the model has no price in the cost table */
+  cost: number | null;
+}
+
+export interface UsageReport {
+  period: UsagePeriod;
+  rollups: UsageRollup[];
+  total_requests: number;
+  total_prompt_tokens: number;
+  total_completion_tokens: number;
+  total_cost: number;
+  unpriced_models: string[];
+}
+
+export interface ModelCost {
+  provider?: string | null;
+  model: string;
+  prompt_per_million: number;
+  completion_per_million: number;
+}
+
+/**
+ * Get AI usage rolled up by day or month (dates are inclusive YYYY-MM-DD)
+ */
+export async function getUsageReport(period: UsagePeriod, since?: string, until?: string): Promise<UsageReport> {
+  return await invoke<UsageReport>('get_usage_report', { period, since, until });
+}
+
+/**
+ * Get the per-model token price table
+ */
+export async function getModelCosts(): Promise<ModelCost[]> {
+  return await invoke<ModelCost[]>('get_model_costs');
+}
+
+/**
+ * Replace the per-model token price table
+ */
+export async function setModelCosts(costs: ModelCost[]): Promise<void> {
+  await invoke('set_model_costs', { costs });
+}
+
+// ============================================================================
+// AI GENERATION
+// ============================================================================
+
+/**
+ * Generate a dork server-side; counts against the daily AI quota
+ */
+export async function generateDork(
+  request: DorkGenerationRequest,
+  workspace?: string
+): Promise<DorkGenerationResult> {
+  return await invoke<DorkGenerationResult>('generate_dork', { request, workspace });
+}
+
+/**
+ * Build a dork from the template library without AI; works offline and is not metered
+ */
+export async function generateDorkOffline(request: DorkGenerationRequest): Promise<DorkGenerationResult> {
+  return await invoke<DorkGenerationResult>('generate_dork_offline', { request });
+}
+
+export interface ContextOptions {
+  /** Token budget for the assembled context */
+  max_tokens?: number;
+  /** Summarize turns that no longer fit instead of dropping them */
+  summarize?: boolean;
+}
+
+export interface ConversationContext {
+  messages: Message[];
+  estimated_tokens: number;
+  /** Turns left out without being covered by a summary */
+  omitted: number;
+  summarized: boolean;
+}
+
+/**
+ * Get the token-bounded context a conversation is replayed to the model with
+ */
+export async function getConversationContext(
+  conversationId: string,
+  options?: ContextOptions,
+  workspace?: string
+): Promise<ConversationContext> {
+  return await invoke<ConversationContext>('get_conversation_context', { conversationId, options, workspace });
+}
+
+/**
+ * Pin or unpin a message so it is always part of the conversation context
+ */
+export async function setMessagePinned(conversationId: string, messageId: string, pinned: boolean): Promise<void> {
+  await invoke('set_message_pinned', { conversationId, messageId, pinned });
+}
+
+/**
+ * Stream a dork generation into a conversation; progress arrives via onAIStream.
+ * Resolves to null if the generation was cancelled.
+ */
+export async function streamGenerateDork(
+  requestId: string,
+  conversationId: string,
+  request: DorkGenerationRequest,
+  workspace?: string
+): Promise<DorkGenerationResult | null> {
+  return await invoke<DorkGenerationResult | null>('stream_generate_dork', {
+    requestId,
+    conversationId,
+    request,
+    workspace,
+  });
+}
+
+/**
+ * Cancel a streaming generation; returns false if it already finished
+ */
+export async function cancelGeneration(requestId: string): Promise<boolean> {
+  return await invoke<boolean>('cancel_generation', { requestId });
+}
+
+/**
+ * Subscribe to streaming generation events for all requests
+ */
+export async function onAIStream(handler: (event: AIStreamEvent) => void): Promise<UnlistenFn> {
+  return await listen<AIStreamEvent>('ai-stream', (event) => handler(event.payload));
+}
+
+/**
+ * Get the AI provider a workspace generates with (Gemini by default)
+ */
+export async function getAIProviderConfig(workspace?: string): Promise<AIProviderConfig> {
+  return await invoke<AIProviderConfig>('get_ai_provider_config', { workspace });
+}
+
+/**
+ * Select the AI provider for a workspace
+ */
+export async function setAIProviderConfig(config: AIProviderConfig, workspace?: string): Promise<void> {
+  await invoke('set_ai_provider_config', { workspace, config });
+}
+
+/**
+ * Store the API key for a workspace's OpenAI-compatible endpoint
+ */
+export async function storeAIProviderKey(apiKey: string, workspace?: string): Promise<void> {
+  await invoke('store_ai_provider_key', { workspace, apiKey });
+}
+
+/**
+ * Delete the API key for a workspace's OpenAI-compatible endpoint
+ */
+export async function deleteAIProviderKey(workspace?: string): Promise<void> {
+  await invoke('delete_ai_provider_key', { workspace });
+}
+
+export interface PromptTemplate {
+  name: string;
+  /** Built-in version number, or `user-<revision>` for an override */
+  version: string;
+  description: string;
+  variables: string[];
+  template: string;
+  temperature: number | null;
+  max_tokens: number | null;
+}
+
+/**
+ * List every prompt template as currently in effect
+ */
+export async function listPromptTemplates(): Promise<PromptTemplate[]> {
+  return await invoke<PromptTemplate[]>('list_prompt_templates');
+}
+
+/**
+ * Get the active template, or a specific version of it
+ */
+export async function getPromptTemplate(name: string, version?: string): Promise<PromptTemplate> {
+  return await invoke<PromptTemplate>('get_prompt_template', { name, version });
+}
+
+/**
+ * Save a new override revision of a prompt template
+ */
+export async function setPromptOverride(
+  name: string,
+  template: string,
+  temperature?: number,
+  maxTokens?: number
+): Promise<PromptTemplate> {
+  return await invoke<PromptTemplate>('set_prompt_override', { name, template, temperature, maxTokens });
+}
+
+/**
+ * Go back to the built-in version of a prompt template
+ */
+export async function resetPromptOverride(name: string): Promise<void> {
+  await invoke('reset_prompt_override', { name });
+}
+
 // ============================================================================
 // CONVERSATION PERSISTENCE
 // ============================================================================
@@ -250,6 +745,20 @@ export async function listConversations(limit?: number): Promise<Conversation[]>
   return await invoke<Conversation[]>('list_conversations', { limit });
 }
 
+/**
+ * Append a single message to an existing conversation
+ */
+export async function appendMessage(conversationId: string, message: Message): Promise<void> {
+  await invoke('append_message', { conversationId, message });
+}
+
+/**
+ * List conversation titles, message counts and last-message previews
+ */
+export async function listConversationSummaries(limit?: number): Promise<ConversationSummary[]> {
+  return await invoke<ConversationSummary[]>('list_conversation_summaries', { limit });
+}
+
 /**
  * Delete conversation from vault
  */
@@ -257,6 +766,640 @@ export async function deleteConversation(id: string): Promise<void> {
   await invoke('delete_conversation', { id });
 }
 
+// ============================================================================
+// ENTITY GRAPH
+// ============================================================================
+
+export type EntityType =
+  | 'domain'
+  | 'subdomain'
+  | 'ip'
+  | 'cidr'
+  | 'url'
+  | 'email'
+  | 'hash'
+  | 'technology'
+  | 'service'
+  | 'organization'
+  | 'person';
+
+export type RelationType =
+  | 'subdomain_of'
+  | 'resolves_to'
+  | 'alias_of'
+  | 'mail_server'
+  | 'name_server'
+  | 'hosts'
+  | 'uses'
+  | 'contains'
+  | 'links_to'
+  | 'related_to';
+
+export type SourceKind = 'dork' | 'tool' | 'import' | 'ai' | 'manual';
+
+export interface GraphSource {
+  kind: SourceKind;
+  reference: string;
+}
+
+export interface Provenance extends GraphSource {
+  first_seen: string;
+  last_seen: string;
+}
+
+export interface GraphEntity {
+  id: string;
+  type: EntityType;
+  value: string;
+  properties: Record<string, unknown>;
+  sources: Provenance[];
+  first_seen: string;
+  last_seen: string;
+}
+
+export interface GraphRelationship {
+  id: string;
+  source_id: string;
+  target_id: string;
+  type: RelationType;
+  properties: Record<string, unknown>;
+  sources: Provenance[];
+  first_seen: string;
+  last_seen: string;
+}
+
+export interface Subgraph {
+  entities: GraphEntity[];
+  relationships: GraphRelationship[];
+}
+
+export interface EntityKey {
+  type: EntityType;
+  value: string;
+}
+
+export interface GraphBatch {
+  source: GraphSource;
+  observed_at?: string;
+  entities?: (EntityKey & { properties?: Record<string, unknown> })[];
+  relationships?: {
+    source: EntityKey;
+    target: EntityKey;
+    type: RelationType;
+    properties?: Record<string, unknown>;
+  }[];
+}
+
+export interface GraphUpsertReport {
+  entities_created: number;
+  entities_updated: number;
+  relationships_created: number;
+  relationships_updated: number;
+}
+
+/**
+ * Merge entities and relationships observed by one source into the graph
+ */
+export async function upsertGraph(batch: GraphBatch, workspace?: string): Promise<GraphUpsertReport> {
+  return await invoke<GraphUpsertReport>('upsert_graph', { batch, workspace });
+}
+
+/**
+ * Narrows what graph queries walk through; empty lists allow every type
+ */
+export interface GraphFilter {
+  entity_types?: EntityType[];
+  relation_types?: RelationType[];
+  since?: string;
+  until?: string;
+}
+
+/**
+ * Load a workspace graph, optionally only what a filter allows
+ */
+export async function loadGraph(filter?: GraphFilter, workspace?: string): Promise<Subgraph> {
+  return await invoke<Subgraph>('load_graph', { filter, workspace });
+}
+
+/**
+ * Entities within `hops` of an entity, e.g. every IP within two hops of a domain
+ */
+export async function getGraphNeighborhood(
+  entityId: string,
+  hops: number,
+  filter?: GraphFilter,
+  resultTypes?: EntityType[],
+  workspace?: string
+): Promise<Subgraph> {
+  return await invoke<Subgraph>('get_graph_neighborhood', { entityId, hops, filter, resultTypes, workspace });
+}
+
+/**
+ * The fewest-hop path between two entities, in path order, or null if they aren't connected
+ */
+export async function findGraphPath(
+  fromId: string,
+  toId: string,
+  filter?: GraphFilter,
+  workspace?: string
+): Promise<Subgraph | null> {
+  return await invoke<Subgraph | null>('find_graph_path', { fromId, toId, filter, workspace });
+}
+
+/**
+ * Groups of connected entities, largest first
+ */
+export async function getGraphComponents(filter?: GraphFilter, workspace?: string): Promise<Subgraph[]> {
+  return await invoke<Subgraph[]>('get_graph_components', { filter, workspace });
+}
+
+export type LayoutAlgorithm = 'force_directed' | 'hierarchical' | 'radial';
+
+export interface LayoutOptions {
+  algorithm?: LayoutAlgorithm;
+  /** Entity id at the top or centre of tree layouts */
+  root?: string;
+  seed?: number;
+  iterations?: number;
+  spacing?: number;
+}
+
+export interface NodePosition {
+  id: string;
+  x: number;
+  y: number;
+}
+
+/**
+ * Compute node positions for the workspace graph; the same seed gives the same layout
+ */
+export async function computeGraphLayout(
+  options?: LayoutOptions,
+  filter?: GraphFilter,
+  workspace?: string
+): Promise<NodePosition[]> {
+  return await invoke<NodePosition[]>('compute_graph_layout', { options, filter, workspace });
+}
+
+export type GraphFormat = 'graphml' | 'gexf' | 'maltego_csv';
+
+/** A GraphML or GEXF file, or Maltego's entity and link tables */
+export type GraphDocument =
+  | { format: 'graphml'; content: string }
+  | { format: 'gexf'; content: string }
+  | { format: 'maltego_csv'; entities: string; links: string };
+
+export interface GraphImportReport extends GraphUpsertReport {
+  /** Nodes and edges that were skipped */
+  warnings: string[];
+}
+
+/**
+ * Export the workspace graph for Gephi (GraphML, GEXF) or Maltego (CSV tables)
+ */
+export async function exportGraph(
+  format: GraphFormat,
+  filter?: GraphFilter,
+  workspace?: string
+): Promise<GraphDocument> {
+  return await invoke<GraphDocument>('export_graph', { format, filter, workspace });
+}
+
+/**
+ * Merge an exported graph into the workspace; `name` is recorded as the source
+ */
+export async function importGraph(
+  document: GraphDocument,
+  name: string,
+  workspace?: string
+): Promise<GraphImportReport> {
+  return await invoke<GraphImportReport>('import_graph', { document, name, workspace });
+}
+
+/**
+ * An entity and its direct neighbors, optionally along some relationship types
+ */
+export async function getEntityNeighbors(
+  entityId: string,
+  relationTypes?: RelationType[],
+  workspace?: string
+): Promise<Subgraph> {
+  return await invoke<Subgraph>('get_entity_neighbors', { entityId, relationTypes, workspace });
+}
+
+/**
+ * Look up an entity by type and value
+ */
+export async function findGraphEntity(key: EntityKey, workspace?: string): Promise<GraphEntity | null> {
+  return await invoke<GraphEntity | null>('find_graph_entity', { key, workspace });
+}
+
+/**
+ * Delete a workspace's graph, returns how many entities were removed
+ */
+export async function clearGraph(workspace?: string): Promise<number> {
+  return await invoke<number>('clear_graph', { workspace });
+}
+
+// ============================================================================
+// INDICATOR EXTRACTION
+// ============================================================================
+
+export interface Indicator {
+  type: EntityType;
+  value: string;
+  registrable_domain: string | null;
+  unicode: string | null;
+}
+
+/**
+ * Extract domains, URLs, IPs, emails and hashes from free text (defanged or not)
+ */
+export async function extractIndicators(text: string): Promise<Indicator[]> {
+  return await invoke<Indicator[]>('extract_indicators', { text });
+}
+
+/**
+ * Add the indicators found in a message to the entity graph
+ */
+export async function recordIndicators(
+  text: string,
+  conversationId: string,
+  workspace?: string
+): Promise<GraphUpsertReport> {
+  return await invoke<GraphUpsertReport>('record_indicators', { text, conversationId, workspace });
+}
+
+// ============================================================================
+// ENRICHMENT
+// ============================================================================
+
+export type DnsRecordType = 'a' | 'aaaa' | 'cname' | 'mx' | 'ns' | 'txt';
+
+export interface DnsConfig {
+  /** `ip` or `ip:port`, tried in order */
+  resolvers: string[];
+  timeout_ms: number;
+  attempts: number;
+  concurrency: number;
+  record_types: DnsRecordType[];
+  detect_wildcards: boolean;
+}
+
+export interface DnsRecord {
+  name: string;
+  type: DnsRecordType;
+  value: string;
+  ttl: number;
+  priority: number | null;
+  /** Answer matches the parent zone's wildcard rather than a real host */
+  wildcard: boolean;
+}
+
+export interface DnsReport {
+  records: DnsRecord[];
+  wildcard_zones: string[];
+  errors: string[];
+  graph: GraphUpsertReport;
+}
+
+/**
+ * Get the DNS resolver settings
+ */
+export async function getDnsConfig(): Promise<DnsConfig> {
+  return await invoke<DnsConfig>('get_dns_config');
+}
+
+/**
+ * Save the DNS resolver settings
+ */
+export async function setDnsConfig(config: DnsConfig): Promise<void> {
+  return await invoke('set_dns_config', { config });
+}
+
+/**
+ * Resolve domains and record the results in the entity graph
+ */
+export async function enrichDns(names: string[], workspace?: string): Promise<DnsReport> {
+  return await invoke<DnsReport>('enrich_dns', { names, workspace });
+}
+
+export interface CertTransparencyConfig {
+  base_url: string;
+  timeout_secs: number;
+  include_expired: boolean;
+}
+
+export interface CtSubdomain {
+  name: string;
+  /** Covered by a `*.` certificate name */
+  wildcard: boolean;
+  certificates: number;
+  first_issued: string | null;
+  last_issued: string | null;
+  expires: string | null;
+  issuers: string[];
+}
+
+export interface CtReport {
+  domain: string;
+  certificates: number;
+  subdomains: CtSubdomain[];
+  graph: GraphUpsertReport;
+}
+
+/**
+ * Get the certificate transparency search settings
+ */
+export async function getCtConfig(): Promise<CertTransparencyConfig> {
+  return await invoke<CertTransparencyConfig>('get_ct_config');
+}
+
+/**
+ * Save the certificate transparency search settings
+ */
+export async function setCtConfig(config: CertTransparencyConfig): Promise<void> {
+  return await invoke('set_ct_config', { config });
+}
+
+/**
+ * Discover subdomains from certificate transparency logs and record them in the entity graph
+ */
+export async function enrichCertificateTransparency(domain: string, workspace?: string): Promise<CtReport> {
+  return await invoke<CtReport>('enrich_certificate_transparency', { domain, workspace });
+}
+
+export interface ConnectorSettings {
+  enabled: boolean;
+  /** Overrides the connector's API root */
+  base_url: string | null;
+  /** 0 turns caching off */
+  cache_ttl_hours: number;
+  requests_per_minute: number | null;
+}
+
+export interface ConnectorInfo {
+  id: string;
+  name: string;
+  description: string;
+  input_types: EntityType[];
+  requires_key: boolean;
+  default_base_url: string;
+  requests_per_minute: number;
+  has_key: boolean;
+  settings: ConnectorSettings;
+}
+
+export interface EnrichmentJob {
+  connector: string;
+  entities: EntityKey[];
+  bypass_cache?: boolean;
+}
+
+export interface JobProgress {
+  job_id: string;
+  completed: number;
+  total: number;
+  entity: EntityKey;
+  error: string | null;
+}
+
+export interface JobReport {
+  connector: string;
+  enriched: number;
+  /** Entities of a type the connector doesn't accept */
+  skipped: number;
+  errors: string[];
+  requests: number;
+  cache_hits: number;
+  graph: GraphUpsertReport;
+}
+
+/**
+ * List the enrichment connectors with their settings and whether a key is stored
+ */
+export async function listEnrichmentConnectors(): Promise<ConnectorInfo[]> {
+  return await invoke<ConnectorInfo[]>('list_enrichment_connectors');
+}
+
+/**
+ * Get a connector's settings
+ */
+export async function getConnectorSettings(connector: string): Promise<ConnectorSettings> {
+  return await invoke<ConnectorSettings>('get_connector_settings', { connector });
+}
+
+/**
+ * Save a connector's settings
+ */
+export async function setConnectorSettings(connector: string, settings: ConnectorSettings): Promise<void> {
+  return await invoke('set_connector_settings', { connector, settings });
+}
+
+/**
+ * Store a connector's API key in the OS keyring (write-only)
+ */
+export async function storeConnectorKey(connector: string, apiKey: string): Promise<void> {
+  return await invoke('store_connector_key', { connector, apiKey });
+}
+
+/**
+ * Delete a connector's API key
+ */
+export async function deleteConnectorKey(connector: string): Promise<void> {
+  return await invoke('delete_connector_key', { connector });
+}
+
+/**
+ * Run entities through a connector; progress arrives through onEnrichmentProgress
+ */
+export async function runEnrichmentJob(jobId: string, job: EnrichmentJob, workspace?: string): Promise<JobReport> {
+  return await invoke<JobReport>('run_enrichment_job', { jobId, job, workspace });
+}
+
+/**
+ * Subscribe to progress of running enrichment jobs
+ */
+export async function onEnrichmentProgress(handler: (progress: JobProgress) => void): Promise<UnlistenFn> {
+  return await listen<JobProgress>('enrichment-progress', (event) => handler(event.payload));
+}
+
+/**
+ * Drop cached connector responses, of one connector or all; returns how many were removed
+ */
+export async function clearEnrichmentCache(connector?: string): Promise<number> {
+  return await invoke<number>('clear_enrichment_cache', { connector });
+}
+
+// ============================================================================
+// IMAGE FORENSICS
+// ============================================================================
+
+export type ImageFormat = 'jpeg' | 'png' | 'webp' | 'tiff' | 'heif';
+
+export type MetadataBlockKind =
+  | 'exif'
+  | 'xmp'
+  | 'photoshop'
+  | 'icc'
+  | 'comment'
+  | 'text'
+  | 'thumbnail'
+  | 'other'
+  | 'trailer';
+
+export interface ExifField {
+  ifd: number;
+  group: string;
+  tag: string;
+  value: string;
+}
+
+export interface ExifData {
+  make: string | null;
+  model: string | null;
+  serial_number: string | null;
+  lens_make: string | null;
+  lens_model: string | null;
+  lens_serial_number: string | null;
+  software: string | null;
+  artist: string | null;
+  copyright: string | null;
+  description: string | null;
+  date_time_original: string | null;
+  date_time_digitized: string | null;
+  date_time_modified: string | null;
+  exposure_time: string | null;
+  f_number: string | null;
+  iso: string | null;
+  focal_length: string | null;
+  orientation: number | null;
+  fields: ExifField[];
+}
+
+export interface GpsPosition {
+  latitude: number;
+  longitude: number;
+  altitude: number | null;
+  timestamp: string | null;
+  source: MetadataBlockKind;
+}
+
+export interface ImageThumbnail {
+  source: MetadataBlockKind;
+  width: number | null;
+  height: number | null;
+  size: number;
+  data_url: string;
+}
+
+export interface IccProfile {
+  size: number;
+  version: string;
+  cmm: string | null;
+  device_class: string | null;
+  color_space: string | null;
+  connection_space: string | null;
+  created: string | null;
+  platform: string | null;
+  manufacturer: string | null;
+  creator: string | null;
+  description: string | null;
+  copyright: string | null;
+}
+
+export interface MetadataBlock {
+  kind: MetadataBlockKind;
+  offset: number;
+  length: number;
+  label: string | null;
+}
+
+export interface ImageMetadata {
+  format: ImageFormat;
+  file_size: number;
+  sha256: string;
+  width: number | null;
+  height: number | null;
+  exif: ExifData | null;
+  gps: GpsPosition | null;
+  xmp: { properties: Record<string, string>; packet: string } | null;
+  iptc: Record<string, string[]>;
+  thumbnails: ImageThumbnail[];
+  icc: IccProfile | null;
+  text: { keyword: string | null; text: string }[];
+  blocks: MetadataBlock[];
+  warnings: string[];
+}
+
+/**
+ * Read EXIF, GPS, XMP, IPTC, thumbnails and ICC profile from image bytes
+ */
+export async function extractImageMetadata(data: Uint8Array): Promise<ImageMetadata> {
+  return await invoke<ImageMetadata>('extract_image_metadata', { data: Array.from(data) });
+}
+
+export interface ScrubOptions {
+  keep_color_profile?: boolean;
+}
+
+export interface SanitizedImage {
+  path: string;
+  format: ImageFormat;
+  original_size: number;
+  sanitized_size: number;
+  original_sha256: string;
+  sanitized_sha256: string;
+  removed: MetadataBlock[];
+  kept: MetadataBlock[];
+  removed_gps: boolean;
+  removed_thumbnails: number;
+}
+
+/**
+ * Write a copy of a JPEG, PNG or WebP image with its metadata removed
+ */
+export async function scrubImageMetadata(
+  data: Uint8Array,
+  fileName: string,
+  options?: ScrubOptions
+): Promise<SanitizedImage> {
+  return await invoke<SanitizedImage>('scrub_image_metadata', {
+    data: Array.from(data),
+    fileName,
+    options,
+  });
+}
+
+// ============================================================================
+// BACKUP & RESTORE
+// ============================================================================
+
+/**
+ * Create a full vault backup (optionally passphrase-encrypted), returns file path
+ */
+export async function createBackup(passphrase?: string): Promise<string> {
+  return await invoke<string>('create_backup', { passphrase });
+}
+
+/**
+ * Read a backup's manifest without restoring it
+ */
+export async function inspectBackup(path: string): Promise<BackupInfo> {
+  return await invoke<BackupInfo>('inspect_backup', { path });
+}
+
+/**
+ * Restore a vault backup, merging with or replacing existing data
+ */
+export async function restoreBackup(
+  path: string,
+  mode: RestoreMode,
+  passphrase?: string
+): Promise<RestoreReport> {
+  return await invoke<RestoreReport>('restore_backup', { path, passphrase, mode });
+}
+
 // ============================================================================
 // ENHANCED LICENSE FUNCTIONS
 // ============================================================================